@@ -0,0 +1,94 @@
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, Criterion};
+
+// the game is a binary crate, so the rules engine is pulled in directly
+#[allow(unused)]
+#[path = "../src/api/mod.rs"]
+mod api;
+
+use api::{
+    chessmove::{ChessMove, ChessboardLocation, File, Rank},
+    chessstate::ChessState,
+    selfplay,
+};
+
+fn chess_move(from: (Rank, File), to: (Rank, File)) -> ChessMove {
+    ChessMove {
+        from: ChessboardLocation::new(from.0, from.1),
+        to: ChessboardLocation::new(to.0, to.1),
+    }
+}
+
+/// a position after 1. e4 e5 2. Nf3 Nc6 3. Bc4 Bc5 with pieces on both sides able to castle
+fn italian_game() -> ChessState {
+    let mut state = ChessState::default();
+    for chess_move in [
+        chess_move((Rank::Two, File::E), (Rank::Four, File::E)),
+        chess_move((Rank::Seven, File::E), (Rank::Five, File::E)),
+        chess_move((Rank::One, File::G), (Rank::Three, File::F)),
+        chess_move((Rank::Eight, File::B), (Rank::Six, File::C)),
+        chess_move((Rank::One, File::F), (Rank::Four, File::C)),
+        chess_move((Rank::Eight, File::F), (Rank::Five, File::C)),
+    ] {
+        state.move_piece(chess_move).unwrap();
+    }
+    state
+}
+
+fn is_valid_move(c: &mut Criterion) {
+    let start = ChessState::default();
+    let italian = italian_game();
+    let pawn_push = chess_move((Rank::Two, File::E), (Rank::Four, File::E));
+    let castle = chess_move((Rank::One, File::E), (Rank::One, File::G));
+    c.bench_function("is_valid_move pawn push", |b| {
+        b.iter(|| black_box(&start).is_valid_move(black_box(pawn_push)))
+    });
+    c.bench_function("is_valid_move castle", |b| {
+        b.iter(|| black_box(&italian).is_valid_move(black_box(castle)))
+    });
+    c.bench_function("legal_moves", |b| {
+        b.iter(|| black_box(&italian).legal_moves())
+    });
+}
+
+fn is_attacked(c: &mut Criterion) {
+    let italian = italian_game();
+    let f7 = ChessboardLocation::new(Rank::Seven, File::F);
+    let a4 = ChessboardLocation::new(Rank::Four, File::A);
+    c.bench_function("is_attacked hit", |b| {
+        b.iter(|| black_box(&italian).is_attacked(black_box(f7)))
+    });
+    c.bench_function("is_attacked miss", |b| {
+        b.iter(|| black_box(&italian).is_attacked(black_box(a4)))
+    });
+}
+
+fn check_game_end(c: &mut Criterion) {
+    let start = ChessState::default();
+    let italian = italian_game();
+    c.bench_function("check_game_end start", |b| {
+        b.iter(|| black_box(&start).check_game_end(&[]))
+    });
+    c.bench_function("check_game_end italian", |b| {
+        b.iter(|| black_box(&italian).check_game_end(&[]))
+    });
+}
+
+fn full_game(c: &mut Criterion) {
+    let mut group = c.benchmark_group("selfplay");
+    group.sample_size(10);
+    group.bench_function("full game", |b| {
+        b.iter(|| selfplay::play_game(black_box(1)))
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    is_valid_move,
+    is_attacked,
+    check_game_end,
+    full_game
+);
+criterion_main!(benches);
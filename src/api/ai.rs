@@ -0,0 +1,201 @@
+use super::{
+    chessmove::{ChessColor, ChessMove, ChessPieceType, ChessboardLocation, PlayedMove, Rank},
+    chessstate::ChessState,
+};
+
+/// score of a checkmate, mates found closer to the root score a bit higher
+pub const MATE: i32 = 1_000_000;
+
+/// how many captures deep the quiescence search looks
+const QUIESCENCE_DEPTH: u8 = 4;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchResult {
+    /// centipawns from the perspective of the player whose turn it is
+    pub score: i32,
+    /// the expected continuation, starting with the best move
+    pub line: Vec<PlayedMove>,
+}
+
+impl SearchResult {
+    /// Some(plies) if the search found a forced mate for the player to move
+    pub fn mate_in(&self) -> Option<usize> {
+        (self.score > MATE - 1000).then(|| (MATE - self.score) as usize)
+    }
+}
+
+pub fn piece_value(piece: ChessPieceType) -> i32 {
+    match piece {
+        ChessPieceType::King => 0,
+        ChessPieceType::Queen => 900,
+        ChessPieceType::Rook => 500,
+        ChessPieceType::Bishop => 330,
+        ChessPieceType::Knight => 320,
+        ChessPieceType::Pawn => 100,
+    }
+}
+
+/// static evaluation in centipawns from the perspective of the player whose turn it is
+pub fn evaluate(state: &ChessState) -> i32 {
+    let mut score = 0;
+    for (rank, row) in state.board.iter().enumerate() {
+        for (file, piece) in row.iter().enumerate() {
+            let Some(piece) = piece else {
+                continue;
+            };
+            // 0 in the corners up to 12 in the middle
+            let centrality = 14 - (2 * file as i32 - 7).abs() - (2 * rank as i32 - 7).abs();
+            let advancement = match piece.color {
+                ChessColor::White => rank as i32,
+                ChessColor::Black => 7 - rank as i32,
+            };
+            let value = piece_value(piece.piece_type)
+                + match piece.piece_type {
+                    ChessPieceType::Pawn => advancement * 5,
+                    ChessPieceType::King => 0,
+                    _ => centrality * 2,
+                };
+            if piece.color == state.turn {
+                score += value;
+            } else {
+                score -= value;
+            }
+        }
+    }
+    score
+}
+
+/// legal moves with every promotion choice spelled out, captures first
+pub fn candidate_moves(state: &ChessState) -> Vec<PlayedMove> {
+    let last_rank = match state.turn {
+        ChessColor::White => Rank::Eight,
+        ChessColor::Black => Rank::One,
+    };
+    let mut moves = Vec::new();
+    for chess_move in state.legal_moves() {
+        if chess_move.to.rank == last_rank
+            && state
+                .get_location(chess_move.from)
+                .is_some_and(|p| p.piece_type == ChessPieceType::Pawn)
+        {
+            for piece in [
+                ChessPieceType::Queen,
+                ChessPieceType::Knight,
+                ChessPieceType::Rook,
+                ChessPieceType::Bishop,
+            ] {
+                moves.push(PlayedMove {
+                    chess_move,
+                    promotion: Some(piece),
+                });
+            }
+        } else {
+            moves.push(chess_move.into());
+        }
+    }
+    // most valuable victim first, promotions count as capturing the new piece
+    moves.sort_by_key(|m| {
+        -state
+            .get_location(m.chess_move.to)
+            .map_or(0, |p| piece_value(p.piece_type))
+            - m.promotion.map_or(0, piece_value)
+    });
+    moves
+}
+
+/// legal captures only (ignoring en passant), pawns capturing onto the last rank become queens
+fn captures(state: &ChessState) -> Vec<PlayedMove> {
+    let mut own = Vec::new();
+    let mut targets = Vec::new();
+    for x in 0..8 {
+        for y in 0..8 {
+            let location = ChessboardLocation::new(x, y);
+            match state.get_location(location) {
+                Some(piece) if piece.color == state.turn => own.push((location, piece)),
+                Some(piece) => targets.push((location, piece)),
+                None => {}
+            }
+        }
+    }
+    // most valuable victim first
+    targets.sort_by_key(|(_, piece)| -piece_value(piece.piece_type));
+    let mut moves = Vec::new();
+    for &(to, _) in &targets {
+        for &(from, piece) in &own {
+            let chess_move = ChessMove { from, to };
+            if !state.is_valid_move(chess_move) {
+                continue;
+            }
+            let promotion = (piece.piece_type == ChessPieceType::Pawn
+                && (to.rank == Rank::One || to.rank == Rank::Eight))
+                .then_some(ChessPieceType::Queen);
+            moves.push(PlayedMove {
+                chess_move,
+                promotion,
+            });
+        }
+    }
+    moves
+}
+
+/// searches `depth` plies deep (plus captures) for the best move
+pub fn search(state: &ChessState, depth: u8) -> SearchResult {
+    negamax(state, depth, 0, -MATE - 1, MATE + 1)
+}
+
+fn negamax(state: &ChessState, depth: u8, ply: i32, mut alpha: i32, beta: i32) -> SearchResult {
+    let moves = candidate_moves(state);
+    if moves.is_empty() {
+        return SearchResult {
+            score: if state.in_check() { ply - MATE } else { 0 },
+            line: Vec::new(),
+        };
+    }
+    if depth == 0 {
+        return SearchResult {
+            score: quiescence(state, alpha, beta, QUIESCENCE_DEPTH),
+            line: Vec::new(),
+        };
+    }
+    let mut best = SearchResult {
+        score: -MATE - 1,
+        line: Vec::new(),
+    };
+    for played_move in moves {
+        let mut copy = *state;
+        if copy.play_move(played_move).is_err() {
+            continue;
+        }
+        let mut result = negamax(&copy, depth - 1, ply + 1, -beta, -alpha);
+        result.score = -result.score;
+        if result.score > best.score {
+            result.line.insert(0, played_move);
+            best = result;
+        }
+        alpha = alpha.max(best.score);
+        if alpha >= beta {
+            break;
+        }
+    }
+    best
+}
+
+fn quiescence(state: &ChessState, mut alpha: i32, beta: i32, depth: u8) -> i32 {
+    let stand_pat = evaluate(state);
+    if depth == 0 || stand_pat >= beta {
+        return stand_pat;
+    }
+    alpha = alpha.max(stand_pat);
+    for played_move in captures(state) {
+        let mut copy = *state;
+        if copy.play_move(played_move).is_err() {
+            continue;
+        }
+        let score = -quiescence(&copy, -beta, -alpha, depth - 1);
+        if score >= beta {
+            return score;
+        }
+        alpha = alpha.max(score);
+    }
+    alpha
+}
@@ -0,0 +1,117 @@
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ai,
+    chessmove::{ChessColor, PlayedMove},
+    chessstate::ChessState,
+};
+
+/// how deep every position gets searched
+const DEPTH: u8 = 3;
+/// losses are capped so a single missed mate doesn't drown out the rest of the game
+const MAX_LOSS: i32 = 1000;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveQuality {
+    Best,
+    Good,
+    Inaccuracy,
+    Mistake,
+    Blunder,
+}
+
+impl MoveQuality {
+    pub fn from_centipawn_loss(loss: i32) -> Self {
+        match loss {
+            ..=0 => MoveQuality::Best,
+            1..=49 => MoveQuality::Good,
+            50..=99 => MoveQuality::Inaccuracy,
+            100..=299 => MoveQuality::Mistake,
+            _ => MoveQuality::Blunder,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnnotatedMove {
+    pub played_move: PlayedMove,
+    /// what the engine would have played instead
+    pub best_move: Option<PlayedMove>,
+    pub centipawn_loss: i32,
+    pub quality: MoveQuality,
+    /// how the engine sees the position after the move from white's side, in centipawns,
+    /// one point of the eval graph
+    pub eval: i32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct GameAnalysis {
+    /// every move of the game, white's moves on the even indices
+    pub moves: Vec<AnnotatedMove>,
+}
+
+impl GameAnalysis {
+    pub fn moves_of(&self, color: ChessColor) -> impl Iterator<Item = &AnnotatedMove> {
+        let skip = match color {
+            ChessColor::White => 0,
+            ChessColor::Black => 1,
+        };
+        self.moves.iter().skip(skip).step_by(2)
+    }
+
+    pub fn average_centipawn_loss(&self, color: ChessColor) -> f32 {
+        let (total, count) = self.moves_of(color).fold((0, 0), |(total, count), m| {
+            (total + m.centipawn_loss, count + 1)
+        });
+        if count == 0 {
+            return 0.0;
+        }
+        total as f32 / count as f32
+    }
+
+    /// how many moves of `color` were this bad, like the blunders of a player
+    pub fn count(&self, color: ChessColor, quality: MoveQuality) -> usize {
+        self.moves_of(color)
+            .filter(|m| m.quality == quality)
+            .count()
+    }
+
+    /// 100% for a game without any centipawn loss, 50% at an average loss of a pawn per move
+    pub fn accuracy(&self, color: ChessColor) -> f32 {
+        100.0 / (1.0 + self.average_centipawn_loss(color) / 100.0)
+    }
+}
+
+/// compares every move of a game from `start` against the engine
+pub fn analyze(start: ChessState, moves: &[PlayedMove]) -> GameAnalysis {
+    let mut analysis = GameAnalysis::default();
+    let mut state = start;
+    let mut best = ai::search(&state, DEPTH);
+    for &played_move in moves {
+        if state.play_move(played_move).is_err() {
+            break;
+        }
+        let after = ai::search(&state, DEPTH);
+        let best_score = best.score.clamp(-MAX_LOSS, MAX_LOSS);
+        let played_score = (-after.score).clamp(-MAX_LOSS, MAX_LOSS);
+        let best_move = best.line.first().copied();
+        let centipawn_loss = if best_move == Some(played_move) {
+            0
+        } else {
+            (best_score - played_score).max(0)
+        };
+        analysis.moves.push(AnnotatedMove {
+            played_move,
+            best_move,
+            centipawn_loss,
+            quality: MoveQuality::from_centipawn_loss(centipawn_loss),
+            eval: match state.turn {
+                ChessColor::White => after.score,
+                ChessColor::Black => -after.score,
+            }
+            .clamp(-MAX_LOSS, MAX_LOSS),
+        });
+        best = after;
+    }
+    analysis
+}
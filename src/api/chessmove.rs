@@ -5,12 +5,28 @@ use serde::{Deserialize, Serialize};
 
 pub type Chessboard = [[Option<ChessPiece>; 8]; 8];
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ChessMove {
     pub from: ChessboardLocation,
     pub to: ChessboardLocation,
 }
 
+/// a move as it was played, with the piece a pawn got promoted to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayedMove {
+    pub chess_move: ChessMove,
+    pub promotion: Option<ChessPieceType>,
+}
+
+impl From<ChessMove> for PlayedMove {
+    fn from(chess_move: ChessMove) -> Self {
+        Self {
+            chess_move,
+            promotion: None,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum Rank {
@@ -3,7 +3,7 @@ use std::{error::Error, fmt::Display};
 use super::{
     chessmove::{
         compress_chessboard, ChessColor, ChessMove, ChessPiece, ChessPieceType, Chessboard,
-        ChessboardLocation, CompressedChessboard, File, Rank,
+        ChessboardLocation, CompressedChessboard, File, PlayedMove, Rank,
     },
     EndReason, GameEnd,
 };
@@ -16,7 +16,7 @@ pub struct ChessState {
     pub turn: ChessColor,
     /// Some(File) if a pawn pushed 2 squares on that file as the last move.
     pub en_passant: Option<File>,
-    /// moves since last pawn move or capture
+    /// plies since last pawn move or capture
     pub fifty_move_rule: u8,
     /// player should make a promotion
     pub should_promote: bool,
@@ -28,6 +28,11 @@ pub struct ChessState {
     pub black_h_rook_moved: bool,
 }
 
+/// plies without a pawn move or capture after which either player can claim a draw
+pub const FIFTY_MOVES: u8 = 100;
+/// plies without a pawn move or capture after which the game is drawn automatically
+pub const SEVENTY_FIVE_MOVES: u8 = 150;
+
 #[derive(Debug)]
 pub struct InvalidMoveError;
 
@@ -104,6 +109,11 @@ impl ChessState {
     }
 
     pub fn is_valid_move(&self, chess_move: ChessMove) -> bool {
+        self.follows_piece_rules(chess_move) && !self.leaves_king_attacked(chess_move)
+    }
+
+    /// whether the piece moves like that, without looking at the king of the player moving it
+    fn follows_piece_rules(&self, chess_move: ChessMove) -> bool {
         if self.should_promote || chess_move.to == chess_move.from {
             return false;
         }
@@ -113,16 +123,22 @@ impl ChessState {
         if piece.color != self.turn {
             return false;
         }
-        if !match piece.piece_type {
+        match piece.piece_type {
             ChessPieceType::King => moves::king(self, chess_move),
             ChessPieceType::Queen => moves::queen(self, chess_move),
             ChessPieceType::Rook => moves::rook(self, chess_move),
             ChessPieceType::Knight => moves::knight(self, chess_move),
             ChessPieceType::Bishop => moves::bishop(self, chess_move),
             ChessPieceType::Pawn => moves::pawn(self, chess_move),
-        } {
-            return false;
-        };
+        }
+    }
+
+    /// the piece could move like that, but it would leave its own king in check
+    pub fn exposes_king(&self, chess_move: ChessMove) -> bool {
+        self.follows_piece_rules(chess_move) && self.leaves_king_attacked(chess_move)
+    }
+
+    fn leaves_king_attacked(&self, chess_move: ChessMove) -> bool {
         let mut copy = *self;
         copy.move_piece_unchecked(chess_move);
         copy.turn = self.turn;
@@ -132,11 +148,43 @@ impl ChessState {
                 if copy.get_location(location)
                     == Some(ChessPiece::new(copy.turn, ChessPieceType::King))
                 {
-                    return !copy.is_attacked(location);
+                    return copy.is_attacked(location);
                 }
             }
         }
-        false
+        // a board without a king has no legal moves
+        true
+    }
+
+    /// all legal moves for the player whose turn it is
+    pub fn legal_moves(&self) -> Vec<ChessMove> {
+        let mut out = Vec::new();
+        if self.should_promote {
+            return out;
+        }
+        for x in 0..8 {
+            for y in 0..8 {
+                let from = ChessboardLocation::new(x, y);
+                if !self
+                    .get_location(from)
+                    .is_some_and(|p| p.color == self.turn)
+                {
+                    continue;
+                }
+                for x2 in 0..8 {
+                    for y2 in 0..8 {
+                        let chess_move = ChessMove {
+                            from,
+                            to: ChessboardLocation::new(x2, y2),
+                        };
+                        if self.is_valid_move(chess_move) {
+                            out.push(chess_move);
+                        }
+                    }
+                }
+            }
+        }
+        out
     }
 
     /// moves piece if move is valid, returns an Error when piece didn't move, returns Ok(true) if a redraw needs to happen
@@ -147,6 +195,15 @@ impl ChessState {
         Ok(self.move_piece_unchecked(chess_move))
     }
 
+    /// plays a move and its promotion, used for replaying games
+    pub fn play_move(&mut self, played_move: PlayedMove) -> Result<(), InvalidMoveError> {
+        self.move_piece(played_move.chess_move)?;
+        if let Some(piece) = played_move.promotion {
+            self.promote(piece)?;
+        }
+        Ok(())
+    }
+
     /// moves the piece, assumes it is valid
     fn move_piece_unchecked(&mut self, chess_move: ChessMove) -> bool {
         let piece = self.take_piece(chess_move.from);
@@ -173,7 +230,7 @@ impl ChessState {
         {
             self.fifty_move_rule = 0;
         } else {
-            self.fifty_move_rule += 1;
+            self.fifty_move_rule = self.fifty_move_rule.saturating_add(1);
         }
 
         self.set_location(chess_move.to, piece);
@@ -278,6 +335,49 @@ impl ChessState {
         Err(InvalidMoveError)
     }
 
+    pub fn king_location(&self, color: ChessColor) -> Option<ChessboardLocation> {
+        for x in 0..8 {
+            for y in 0..8 {
+                let location = ChessboardLocation::new(x, y);
+                if self.get_location(location) == Some(ChessPiece::new(color, ChessPieceType::King))
+                {
+                    return Some(location);
+                }
+            }
+        }
+        None
+    }
+
+    /// whether no series of moves can checkmate anymore, which is only the case with the kings
+    /// and a single knight, or with bishops that all stand on squares of one color
+    pub fn is_dead(&self) -> bool {
+        let mut knights = 0;
+        let mut bishop_squares = [false; 2];
+        for (x, column) in self.board.iter().enumerate() {
+            for (y, piece) in column.iter().enumerate() {
+                match piece.map(|piece| piece.piece_type) {
+                    None | Some(ChessPieceType::King) => {}
+                    Some(ChessPieceType::Knight) => knights += 1,
+                    Some(ChessPieceType::Bishop) => bishop_squares[(x + y) % 2] = true,
+                    Some(ChessPieceType::Pawn | ChessPieceType::Rook | ChessPieceType::Queen) => {
+                        return false
+                    }
+                }
+            }
+        }
+        match knights {
+            0 => !(bishop_squares[0] && bishop_squares[1]),
+            1 => bishop_squares == [false; 2],
+            _ => false,
+        }
+    }
+
+    /// returns true if the king of the player whose turn it is is attacked
+    pub fn in_check(&self) -> bool {
+        self.king_location(self.turn)
+            .is_some_and(|location| self.is_attacked(location))
+    }
+
     /// returns true if a square is attacked by the opponent
     pub fn is_attacked(&self, location: ChessboardLocation) -> bool {
         let mut copy = *self;
@@ -314,17 +414,30 @@ impl ChessState {
         false
     }
 
-    // checks if the game should end
+    /// how often the current position appears in the history
+    fn repetitions(&self, move_history: &[CompressedChessboard]) -> usize {
+        let board = compress_chessboard(&self.board);
+        move_history.iter().filter(|&b| b == &board).count()
+    }
+
+    /// the draw a player can claim in this position, after fifty moves or a threefold repetition
+    pub fn claimable_draw(&self, move_history: &[CompressedChessboard]) -> Option<GameEnd> {
+        if self.fifty_move_rule >= FIFTY_MOVES {
+            Some(GameEnd::Draw(EndReason::FiftyMoveRule))
+        } else if self.repetitions(move_history) >= 3 {
+            Some(GameEnd::Draw(EndReason::RepetitionOfMoves))
+        } else {
+            None
+        }
+    }
+
+    // checks if the game should end, the fifty move rule and threefold repetition
+    // have to be claimed, only seventy five moves and fivefold repetition end it
     pub fn check_game_end(&self, move_history: &[CompressedChessboard]) -> Option<GameEnd> {
-        if self.fifty_move_rule == 50 {
+        if self.fifty_move_rule >= SEVENTY_FIVE_MOVES {
             return Some(GameEnd::Draw(EndReason::FiftyMoveRule));
         }
-        if move_history
-            .iter()
-            .filter(|&b| b == &compress_chessboard(&self.board))
-            .count()
-            == 3
-        {
+        if self.repetitions(move_history) >= 5 {
             return Some(GameEnd::Draw(EndReason::RepetitionOfMoves));
         }
         if self
@@ -0,0 +1,79 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::{chessmove::ChessColor, matchmaking::TimeControl};
+
+/// the most latency a move gets refunded, so a slow connection can't buy much time
+pub const MAX_LAG_COMPENSATION: Duration = Duration::from_millis(500);
+
+/// the clocks as the server saw them, the server is the only one keeping time
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Clock {
+    /// milliseconds white has left
+    pub white: u64,
+    /// milliseconds black has left
+    pub black: u64,
+    /// when the server took this reading, in milliseconds since the unix epoch,
+    /// for a reading sent with a move this is when the server received it
+    pub server_time: u64,
+    /// milliseconds of latency the last move wasn't charged for
+    pub compensation: u64,
+}
+
+impl Clock {
+    /// fresh clocks started at `now`, white and black may get different time
+    pub fn new(white: TimeControl, black: TimeControl, now: u64) -> Self {
+        Self {
+            white: white.initial.saturating_mul(1000),
+            black: black.initial.saturating_mul(1000),
+            server_time: now,
+            compensation: 0,
+        }
+    }
+
+    pub fn remaining(&self, color: ChessColor) -> Duration {
+        Duration::from_millis(match color {
+            ChessColor::White => self.white,
+            ChessColor::Black => self.black,
+        })
+    }
+
+    /// time `color` has left at `now` when their clock is the one running
+    pub fn left(&self, color: ChessColor, now: u64) -> Duration {
+        self.remaining(color)
+            .saturating_sub(Duration::from_millis(now.saturating_sub(self.server_time)))
+    }
+
+    /// charges `color` for the time since the last reading, minus the half round-trip the move spent on its way,
+    /// then gives back the (Bronstein) delay or less if the move was quicker and adds the increment,
+    /// correspondence clocks get reset instead
+    pub fn charge(
+        &mut self,
+        color: ChessColor,
+        time_control: TimeControl,
+        now: u64,
+        round_trip: Duration,
+    ) {
+        self.compensation = (round_trip / 2).min(MAX_LAG_COMPENSATION).as_millis() as u64;
+        let spent = now
+            .saturating_sub(self.server_time)
+            .saturating_sub(self.compensation);
+        let refund = spent
+            .min(time_control.delay.saturating_mul(1000))
+            .saturating_add(time_control.increment.saturating_mul(1000));
+        let remaining = match color {
+            ChessColor::White => &mut self.white,
+            ChessColor::Black => &mut self.black,
+        };
+        // a player who ran out of time doesn't get saved by the refund
+        if *remaining <= spent {
+            *remaining = 0;
+        } else if time_control.is_correspondence() {
+            *remaining = time_control.per_move.saturating_mul(1000);
+        } else {
+            *remaining = remaining.saturating_sub(spent).saturating_add(refund);
+        }
+        self.server_time = now;
+    }
+}
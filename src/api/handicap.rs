@@ -0,0 +1,81 @@
+use std::fmt::Display;
+
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    chessmove::{ChessColor, ChessboardLocation, File, Rank},
+    chessstate::ChessState,
+};
+
+/// odds a player gives by starting without a piece
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default, Resource)]
+pub enum Handicap {
+    #[default]
+    None,
+    /// the f pawn
+    Pawn,
+    /// the queen side knight
+    Knight,
+    /// the queen side rook
+    Rook,
+    Queen,
+}
+
+impl Handicap {
+    pub const ALL: [Handicap; 5] = [
+        Handicap::None,
+        Handicap::Pawn,
+        Handicap::Knight,
+        Handicap::Rook,
+        Handicap::Queen,
+    ];
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&h| h == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// removes the piece from `color`s side of the board
+    pub fn apply(self, state: &mut ChessState, color: ChessColor) {
+        let (back_rank, pawn_rank) = match color {
+            ChessColor::White => (Rank::One, Rank::Two),
+            ChessColor::Black => (Rank::Eight, Rank::Seven),
+        };
+        let location = match self {
+            Handicap::None => return,
+            Handicap::Pawn => ChessboardLocation::new(pawn_rank, File::F),
+            Handicap::Knight => ChessboardLocation::new(back_rank, File::B),
+            Handicap::Rook => ChessboardLocation::new(back_rank, File::A),
+            Handicap::Queen => ChessboardLocation::new(back_rank, File::D),
+        };
+        state.board[location.rank as usize][location.file as usize] = None;
+        // can't castle with a rook that isn't there
+        if self == Handicap::Rook {
+            match color {
+                ChessColor::White => state.white_a_rook_moved = true,
+                ChessColor::Black => state.black_a_rook_moved = true,
+            }
+        }
+    }
+}
+
+impl Display for Handicap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Handicap::None => "none",
+            Handicap::Pawn => "pawn",
+            Handicap::Knight => "knight",
+            Handicap::Rook => "rook",
+            Handicap::Queen => "queen",
+        })
+    }
+}
+
+/// the starting position with both players' odds taken off the board
+pub fn starting_position(white: Handicap, black: Handicap) -> ChessState {
+    let mut state = ChessState::default();
+    white.apply(&mut state, ChessColor::White);
+    black.apply(&mut state, ChessColor::Black);
+    state
+}
@@ -0,0 +1,120 @@
+use serde::{Deserialize, Serialize};
+
+use super::{chessmove::ChessColor, matchmaking::Seek, GameEnd, PlayerId, PlayerInfo};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LeagueId(pub u32);
+
+/// how a league game ended
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub enum LeagueResult {
+    Played(GameEnd),
+    /// the color that showed up in time wins, None when neither did
+    Forfeit(Option<ChessColor>),
+}
+
+impl LeagueResult {
+    /// what white and black scored, in half points
+    pub fn half_points(&self) -> [u32; 2] {
+        match self {
+            LeagueResult::Played(GameEnd::White(_))
+            | LeagueResult::Forfeit(Some(ChessColor::White)) => [2, 0],
+            LeagueResult::Played(GameEnd::Black(_))
+            | LeagueResult::Forfeit(Some(ChessColor::Black)) => [0, 2],
+            LeagueResult::Played(GameEnd::Draw(_)) => [1, 1],
+            LeagueResult::Forfeit(None) => [0, 0],
+        }
+    }
+}
+
+/// one game of the schedule
+#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+pub struct Pairing {
+    /// starting from 1
+    pub round: u32,
+    pub white: PlayerId,
+    pub black: PlayerId,
+    /// None until it is played or forfeited
+    pub result: Option<LeagueResult>,
+}
+
+/// everything the players of a league get to see
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LeagueSchedule {
+    pub id: LeagueId,
+    pub seek: Seek,
+    /// everyone plays everyone twice, the second time with the other color
+    pub double: bool,
+    pub players: Vec<PlayerInfo>,
+    /// the round being played, 0 while players can still join
+    pub round: u32,
+    pub rounds: u32,
+    pub pairings: Vec<Pairing>,
+}
+
+impl LeagueSchedule {
+    pub fn is_finished(&self) -> bool {
+        self.round != 0 && self.pairings.iter().all(|p| p.result.is_some())
+    }
+
+    /// every player with their score in half points, the leader first
+    pub fn standings(&self) -> Vec<(&PlayerInfo, u32)> {
+        let mut standings: Vec<_> = self
+            .players
+            .iter()
+            .map(|player| {
+                let score = self
+                    .pairings
+                    .iter()
+                    .filter_map(|pairing| {
+                        let points = pairing.result?.half_points();
+                        if pairing.white == player.id {
+                            Some(points[0])
+                        } else if pairing.black == player.id {
+                            Some(points[1])
+                        } else {
+                            None
+                        }
+                    })
+                    .sum::<u32>();
+                (player, score)
+            })
+            .collect();
+        standings.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+        standings
+    }
+}
+
+/// pairs every player with every other with the circle method, with an odd number of players
+/// one of them sits out each round. returns the round, white and black as player indices
+pub fn round_robin(players: usize, double: bool) -> Vec<(u32, usize, usize)> {
+    // an odd number of players gets a dummy, whoever is paired with it sits the round out
+    let n = players + players % 2;
+    let mut circle: Vec<usize> = (0..n).collect();
+    let mut schedule = Vec::new();
+    for round in 0..n.saturating_sub(1) {
+        for board in 0..n / 2 {
+            let (a, b) = (circle[board], circle[n - 1 - board]);
+            if a >= players || b >= players {
+                continue;
+            }
+            // alternates colors so nobody gets white every round
+            let (white, black) = if (round + board).is_multiple_of(2) {
+                (a, b)
+            } else {
+                (b, a)
+            };
+            schedule.push((round as u32 + 1, white, black));
+        }
+        circle[1..].rotate_right(1);
+    }
+    if double {
+        let rounds = n.saturating_sub(1) as u32;
+        let second_half: Vec<_> = schedule
+            .iter()
+            .map(|&(round, white, black)| (round + rounds, black, white))
+            .collect();
+        schedule.extend(second_half);
+    }
+    schedule
+}
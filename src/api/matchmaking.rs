@@ -0,0 +1,263 @@
+use std::fmt::Display;
+
+use bevy::prelude::Resource;
+use serde::{Deserialize, Serialize};
+
+use super::chessmove::ChessColor;
+
+/// the rules a game is played with
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Variant {
+    #[default]
+    Standard,
+}
+
+/// a day in seconds
+const DAY: u64 = 24 * 60 * 60;
+/// the most time a clock can start with or be reset to, in seconds
+const MAX_TIME: u64 = 14 * DAY;
+/// the most increment or delay a move can get, in seconds
+const MAX_BONUS: u64 = 10 * 60;
+
+/// how much time a player gets, all in seconds
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimeControl {
+    /// for the whole game
+    pub initial: u64,
+    /// added after every move
+    pub increment: u64,
+    /// given back after every move, or less if the move took less time
+    pub delay: u64,
+    /// what the clock gets reset to after every move, only correspondence games have it
+    pub per_move: u64,
+}
+
+impl TimeControl {
+    /// the time controls players pick from, None plays without a clock
+    pub const ALL: [Option<TimeControl>; 10] = [
+        None,
+        Some(TimeControl::new(60, 0, 0)),
+        Some(TimeControl::new(3 * 60, 0, 0)),
+        Some(TimeControl::new(3 * 60, 2, 0)),
+        Some(TimeControl::new(5 * 60, 0, 0)),
+        Some(TimeControl::new(5 * 60, 0, 3)),
+        Some(TimeControl::new(10 * 60, 0, 0)),
+        Some(TimeControl::new(10 * 60, 5, 0)),
+        Some(TimeControl::correspondence(1)),
+        Some(TimeControl::correspondence(3)),
+    ];
+
+    pub const fn new(initial: u64, increment: u64, delay: u64) -> Self {
+        Self {
+            initial,
+            increment,
+            delay,
+            per_move: 0,
+        }
+    }
+
+    /// `days` for every move, the game goes on while the players are offline
+    pub const fn correspondence(days: u64) -> Self {
+        Self {
+            initial: days.saturating_mul(DAY),
+            increment: 0,
+            delay: 0,
+            per_move: days.saturating_mul(DAY),
+        }
+    }
+
+    pub fn is_correspondence(&self) -> bool {
+        self.per_move != 0
+    }
+
+    /// whether the clock stays within MAX_TIME and MAX_BONUS, anything from a client is checked
+    /// before it gets near a clock
+    pub fn is_valid(&self) -> bool {
+        self.initial <= MAX_TIME
+            && self.per_move <= MAX_TIME
+            && self.increment <= MAX_BONUS
+            && self.delay <= MAX_BONUS
+    }
+
+    /// how fast the game is, from the time a game of 40 moves gets
+    pub fn class(&self) -> TimeClass {
+        if self.is_correspondence() {
+            return TimeClass::Correspondence;
+        }
+        let bonus = self.increment.saturating_add(self.delay);
+        match self.initial.saturating_add(bonus.saturating_mul(40)) {
+            ..180 => TimeClass::Bullet,
+            180..480 => TimeClass::Blitz,
+            480..1500 => TimeClass::Rapid,
+            _ => TimeClass::Classical,
+        }
+    }
+
+    pub fn next(time_control: Option<TimeControl>) -> Option<TimeControl> {
+        let index = Self::ALL
+            .iter()
+            .position(|&t| t == time_control)
+            .unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+impl Display for TimeControl {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_correspondence() {
+            return write!(f, "{} days per move", self.per_move / DAY);
+        }
+        write!(f, "{} min", self.initial / 60)?;
+        if self.increment != 0 {
+            write!(f, " +{}s", self.increment)?;
+        }
+        if self.delay != 0 {
+            write!(f, " delay {}s", self.delay)?;
+        }
+        Ok(())
+    }
+}
+
+/// time controls that make for similarly fast games, slowest last
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum TimeClass {
+    Bullet,
+    Blitz,
+    Rapid,
+    Classical,
+    Correspondence,
+}
+
+/// the kind of game a player in the queue is looking for
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Seek {
+    pub variant: Variant,
+    /// None for games without a clock
+    pub time_control: Option<TimeControl>,
+    /// the time the opponent gets when it differs from yours, only for unrated games with a clock
+    pub time_odds: Option<TimeControl>,
+    pub rated: bool,
+}
+
+impl Seek {
+    /// whether two players looking for these games can be paired
+    pub fn compatible(&self, other: &Seek) -> bool {
+        self.variant == other.variant
+            && self.rated == other.rated
+            && self.time_control == other.opponent_time_control()
+            && other.time_control == self.opponent_time_control()
+    }
+
+    /// the time control the opponent plays with
+    pub fn opponent_time_control(&self) -> Option<TimeControl> {
+        self.time_odds.or(self.time_control)
+    }
+
+    /// the seek of the opponent this seek asks for
+    pub fn mirrored(&self) -> Seek {
+        let time_control = self.opponent_time_control();
+        Seek {
+            time_control,
+            time_odds: self.time_control.filter(|&own| Some(own) != time_control),
+            ..*self
+        }
+    }
+
+    /// drops odds the seek can't have, and odds that give the opponent the same clock
+    pub fn without_invalid_odds(mut self) -> Seek {
+        if self.time_odds == self.time_control || !self.is_valid() {
+            self.time_odds = None;
+        }
+        self
+    }
+
+    /// both clocks have to be valid, odds need a clock to give time on, and ratings would be
+    /// meaningless with them, a correspondence game can't give odds in minutes
+    pub fn is_valid(&self) -> bool {
+        let mut clocks = self.time_control.into_iter().chain(self.time_odds);
+        if !clocks.all(|time_control| time_control.is_valid()) {
+            return false;
+        }
+        match (self.time_control, self.time_odds) {
+            (_, None) => true,
+            (Some(own), Some(odds)) => {
+                !self.rated && own.is_correspondence() == odds.is_correspondence()
+            }
+            (None, Some(_)) => false,
+        }
+    }
+}
+
+/// the color a player in the queue wants to play
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Resource)]
+pub enum ColorPreference {
+    #[default]
+    Random,
+    White,
+    Black,
+}
+
+impl ColorPreference {
+    pub const ALL: [ColorPreference; 3] = [
+        ColorPreference::Random,
+        ColorPreference::White,
+        ColorPreference::Black,
+    ];
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&c| c == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// whether both players can get what they asked for, only two players wanting the same color can't
+    pub fn compatible(self, other: ColorPreference) -> bool {
+        self == ColorPreference::Random || self != other
+    }
+
+    /// the color the player with this preference gets against `other`, None leaves it to chance
+    pub fn resolve(self, other: ColorPreference) -> Option<ChessColor> {
+        match (self, other) {
+            (ColorPreference::White, _) | (_, ColorPreference::Black) => Some(ChessColor::White),
+            (ColorPreference::Black, _) | (_, ColorPreference::White) => Some(ChessColor::Black),
+            (ColorPreference::Random, ColorPreference::Random) => None,
+        }
+    }
+}
+
+impl Display for ColorPreference {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ColorPreference::Random => "random",
+            ColorPreference::White => "white",
+            ColorPreference::Black => "black",
+        })
+    }
+}
+
+/// the characters lobby codes are made of, leaving out the ones that are easy to mix up
+pub const LOBBY_CODE_CHARACTERS: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+
+/// the code a friend joins a private lobby with, like K7QX2M
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LobbyCode(pub [u8; 6]);
+
+impl Display for LobbyCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&String::from_utf8_lossy(&self.0))
+    }
+}
+
+impl std::str::FromStr for LobbyCode {
+    type Err = String;
+
+    /// ignores case, codes get typed over
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_uppercase();
+        let code = <[u8; 6]>::try_from(s.as_bytes())
+            .map_err(|_| format!("lobby codes are 6 characters, not {}", s.len()))?;
+        if let Some(&c) = code.iter().find(|c| !LOBBY_CODE_CHARACTERS.contains(c)) {
+            return Err(format!("{} isn't in lobby codes", c as char));
+        }
+        Ok(Self(code))
+    }
+}
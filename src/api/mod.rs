@@ -1,56 +1,101 @@
 #![cfg_attr(not(all(feature = "client", feature = "server")), allow(dead_code))]
 
+use bevy::prelude::{Component, Resource};
+#[cfg(not(any(feature = "websocket", target_arch = "wasm32")))]
+use bevy_slinet::protocols::tcp::TcpProtocol;
+#[cfg(not(target_arch = "wasm32"))]
 use bevy_slinet::{
-    packet_length_serializer::LittleEndian,
-    protocols::tcp::TcpProtocol,
-    serializer::{ReadOnlySerializer, SerializerAdapter},
-    serializers::bincode::{BincodeSerializer, DefaultOptions},
-    ClientConfig, ServerConfig,
+    packet_length_serializer::LittleEndian, serializer::SerializerAdapter, ClientConfig,
+    ServerConfig,
 };
 use serde::{Deserialize, Serialize};
+#[cfg(not(target_arch = "wasm32"))]
 use std::sync::Arc;
 
+pub mod ai;
+pub mod analysis;
 pub mod chessmove;
 pub mod chessstate;
+pub mod clock;
+pub mod handicap;
+pub mod league;
+pub mod matchmaking;
+pub mod notation;
+pub mod openings;
+pub mod pgn;
+pub mod ping;
+pub mod puzzle;
+pub mod selfplay;
+pub mod simul;
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+pub mod transport;
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+pub mod websocket;
+pub mod wire;
 
+/// longest chat message in characters, longer messages get dropped by the server
+pub const MAX_CHAT_LENGTH: usize = 200;
+
+/// plies a player has to wait after offering a draw before they can offer another one
+pub const DRAW_OFFER_INTERVAL: u32 = 10;
+/// longest player name in characters
+pub const MAX_NAME_LENGTH: usize = 20;
+/// how many hints a player gets in a game that isn't rated
+pub const HINTS: u8 = 3;
+/// how many plies the engine looks ahead for a hint
+pub const HINT_DEPTH: u8 = 3;
+
+/// the transports both sides use, plain tcp unless built with the websocket feature,
+/// then it is picked at startup, see transport. browsers always use their own websocket, see
+/// client::networking::browser
+#[cfg(not(any(feature = "websocket", target_arch = "wasm32")))]
+type ClientProtocol = TcpProtocol;
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+type ClientProtocol = transport::AnyProtocol;
+#[cfg(not(any(feature = "websocket", target_arch = "wasm32")))]
+type ServerProtocol = TcpProtocol;
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+type ServerProtocol = transport::AnyProtocol;
+
+/// how the client talks to the server
 #[derive(Debug)]
-pub struct Config;
+pub struct ClientNetConfig;
 
-impl ClientConfig for Config {
+#[cfg(not(target_arch = "wasm32"))]
+impl ClientConfig for ClientNetConfig {
     type ClientPacket = ClientPacket;
     type ServerPacket = ServerPacket;
-    type Protocol = TcpProtocol;
+    type Protocol = ClientProtocol;
     type LengthSerializer = LittleEndian<u32>;
 
-    type SerializerError = <BincodeSerializer<DefaultOptions> as ReadOnlySerializer<
-        ServerPacket,
-        ClientPacket,
-    >>::Error;
+    type SerializerError = wire::WireError;
 
     fn build_serializer(
     ) -> SerializerAdapter<Self::ServerPacket, Self::ClientPacket, Self::SerializerError> {
-        SerializerAdapter::ReadOnly(Arc::new(BincodeSerializer::<DefaultOptions>::default()))
+        SerializerAdapter::ReadOnly(Arc::new(wire::VersionedSerializer::default()))
     }
 }
 
-impl ServerConfig for Config {
+/// how the server talks to its clients
+#[derive(Debug)]
+pub struct ServerNetConfig;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ServerConfig for ServerNetConfig {
     type ClientPacket = ClientPacket;
     type ServerPacket = ServerPacket;
-    type Protocol = TcpProtocol;
+    type Protocol = ServerProtocol;
     type LengthSerializer = LittleEndian<u32>;
 
-    type SerializerError = <BincodeSerializer<DefaultOptions> as ReadOnlySerializer<
-        ServerPacket,
-        ClientPacket,
-    >>::Error;
+    type SerializerError = wire::WireError;
 
     fn build_serializer(
     ) -> SerializerAdapter<Self::ClientPacket, Self::ServerPacket, Self::SerializerError> {
-        SerializerAdapter::ReadOnly(Arc::new(BincodeSerializer::<DefaultOptions>::default()))
+        SerializerAdapter::ReadOnly(Arc::new(wire::VersionedSerializer::default()))
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum GameEnd {
     White(EndReason),
     Black(EndReason),
@@ -63,27 +108,589 @@ pub enum EndReason {
     Stalemate,
     Resignation,
     Agreement,
-    // Timeout, // maybe later
+    /// the player to move ran out of time
+    Timeout,
     InsufficientMaterial,
     FiftyMoveRule,
     RepetitionOfMoves,
+    /// ended before both players moved or called off by the server, it doesn't count for anything
+    Aborted,
+}
+
+/// why the server refused a packet
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServerError {
+    /// too many packets of one kind, keep going and you get disconnected
+    RateLimited,
+    /// someone registered with that name already
+    NameTaken,
+    /// no account with that name and password
+    WrongPassword,
+    /// the server doesn't keep accounts, or its database broke
+    AccountsUnavailable,
+    /// no finished game with that id, or the server couldn't read it
+    NoSuchGame,
+    /// no open lobby with that code, it may have been joined or closed already
+    NoSuchLobby,
+    /// the seek was accepted by someone else or taken back
+    NoSuchSeek,
+    /// no league with that id, or it started without you
+    NoSuchLeague,
+    /// the server doesn't allow games with that clock
+    TimeControlNotAllowed,
+    /// the server isn't pairing players from the queue right now
+    QueueClosed,
+    /// the server has as many players, games, players waiting or games waiting for analysis as
+    /// it takes
+    ServerFull,
+    /// no simul with that id, or it started without you
+    NoSuchSimul,
+    /// you offered a draw less than DRAW_OFFER_INTERVAL plies ago
+    DrawOfferTooSoon,
+    /// you don't play in the game the packet was about, it may have ended already
+    NotInGame,
+    /// no account with that name
+    NoSuchPlayer,
+    /// you or the player who challenged you are in a game already
+    InGame,
+    /// rated games are only for players that registered and logged in with a password
+    AccountRequired,
+    /// too many wrong passwords for that account, try again in a few minutes
+    TooManyAttempts,
+    /// the server doesn't play games itself, you keep waiting for an opponent in the queue
+    NoBots,
+    /// rated games have no hints, and the others only HINTS
+    NoHints,
+    /// the server is older than you and doesn't know the packet
+    Unsupported,
+    /// the name belongs to a registered account, only logging into it gets you the name
+    NameRegistered,
+}
+
+/// identifies a game, every packet belonging to a game starts with one
+#[derive(
+    Serialize,
+    Deserialize,
+    Resource,
+    Component,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Debug,
+)]
+pub struct GameId(pub u32);
+
+/// identifies a player for as long as they stay connected
+#[derive(
+    Serialize,
+    Deserialize,
+    Resource,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Default,
+    Debug,
+)]
+pub struct PlayerId(pub u32);
+
+/// logs a connection back into the account it got the token for
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct SessionToken(pub u64);
+
+/// proves a connection owns a seat in a game, a new connection can take the seat back with it
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct GameToken {
+    pub game: GameId,
+    pub key: u64,
+}
+
+/// a correspondence game you have a seat in, the server lists them after logging in
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OngoingGame {
+    pub token: GameToken,
+    pub opponent: PlayerInfo,
+    pub your_move: bool,
+}
+
+/// identifies a finished game in the server's history
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct ReplayId(pub u64);
+
+/// a finished game in a player's history, FetchReplay gets its moves
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct GameSummary {
+    pub id: ReplayId,
+    pub white: String,
+    pub black: String,
+    pub result: GameEnd,
+    /// plies played
+    pub moves: u32,
+    pub rated: bool,
+    /// milliseconds since the unix epoch
+    pub ended: u64,
+}
+
+/// a finished game seen from one of its players, FetchReplay gets its moves if you played it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct HistoryEntry {
+    pub id: ReplayId,
+    pub color: chessmove::ChessColor,
+    pub opponent: PlayerId,
+    pub opponent_name: String,
+    pub result: GameEnd,
+    /// plies played
+    pub moves: u32,
+    pub rated: bool,
+    /// milliseconds since the unix epoch
+    pub ended: u64,
+}
+
+/// identifies an open seek
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct SeekId(pub u32);
+
+/// a game someone is offering, anyone can accept it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OpenSeek {
+    pub id: SeekId,
+    /// with their rating
+    pub player: PlayerInfo,
+    /// the odds the player gives
+    pub handicap: handicap::Handicap,
+    pub seek: matchmaking::Seek,
+    /// the color the player wants, you get the other one
+    pub color: matchmaking::ColorPreference,
+}
+
+/// what players get to know about each other
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct PlayerInfo {
+    pub id: PlayerId,
+    pub name: String,
+    /// only sent at the start of a game
+    pub rating: Option<Rating>,
+    /// a computer plays for this player
+    pub bot: bool,
+}
+
+/// a player's elo, it moves faster while it is provisional
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rating {
+    pub elo: u32,
+    pub provisional: bool,
+}
+
+impl std::fmt::Display for Rating {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.elo)?;
+        if self.provisional {
+            write!(f, "?")?;
+        }
+        Ok(())
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+/// names can't be empty, too long or contain things like newlines
+pub fn valid_name(name: &str) -> bool {
+    !name.trim().is_empty()
+        && name.chars().count() <= MAX_NAME_LENGTH
+        && !name.chars().any(char::is_control)
+}
+
+/// new packets go at the end before Unsupported, see wire
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum ClientPacket {
-    Reconnect,
-    RequestDraw,
-    Move(chessmove::ChessMove),
-    Promotion(chessmove::ChessPieceType),
+    /// enter the matchmaking queue, giving the opponent odds,
+    /// only players asking for the same kind of game get paired
+    JoinQueue {
+        handicap: handicap::Handicap,
+        variant: matchmaking::Variant,
+        time_control: Option<matchmaking::TimeControl>,
+        /// the time your opponent gets when it differs from yours
+        time_odds: Option<matchmaking::TimeControl>,
+        rated: bool,
+        color: matchmaking::ColorPreference,
+    },
+    /// stop waiting for an opponent
+    LeaveQueue,
+    /// asks for the moves after this ply, when the client lost track of the game
+    Resync(GameId, u32),
+    RequestDraw(GameId),
+    DeclineDraw(GameId),
+    /// the ply is the number of moves played before this one
+    Move(GameId, u32, chessmove::ChessMove),
+    Promotion(GameId, chessmove::ChessPieceType),
+    RequestPuzzle,
+    OfferRematch(GameId),
+    AcceptRematch(GameId),
+    /// a message for the opponent
+    Chat(GameId, String),
+    /// take back a seat with the token from MatchFound, from a new connection after losing the old one
+    Resume(GameToken),
+    /// the timestamp gets echoed back in a Pong
+    Ping(u64),
+    Pong(u64),
+    /// ends the game without a result, only before both players moved
+    Abort(GameId),
+    /// asks the opponent to take back your last move
+    RequestTakeback(GameId),
+    /// true accepts the takeback the opponent asked for
+    RespondTakeback(GameId, bool),
+    /// watch a game, the server answers with SpectateState and then forwards every move
+    Spectate(GameId),
+    /// the name the opponent sees, players who don't say hello get a generated one,
+    /// engines say they are a bot so players know and they get Position after every move
+    Hello {
+        name: String,
+        bot: bool,
+    },
+    /// logs into the account of the token, None (or an unknown token) creates a new account,
+    /// the server answers with a Session either way
+    Login {
+        token: Option<SessionToken>,
+    },
+    /// ends the game in a draw if the fifty move rule or a threefold repetition allows it
+    ClaimDraw(GameId),
+    /// asks the player with this name for a game, skipping the queue
+    Challenge {
+        target: String,
+        variant: matchmaking::Variant,
+        time_control: Option<matchmaking::TimeControl>,
+    },
+    /// true accepts the challenge with this id and starts the game
+    AnswerChallenge(u32, bool),
+    /// asks how busy the server is, works without joining anything
+    ServerStats,
+    /// creates an account with a password, the server answers with a Session like Login
+    Register {
+        name: String,
+        password: String,
+    },
+    /// logs into a registered account from anywhere, the server answers with a Session
+    PasswordLogin {
+        name: String,
+        password: String,
+    },
+    /// lists the last games you finished, works without joining anything
+    RecentGames,
+    /// asks for the moves of a finished game you played, the server answers with a Replay
+    FetchReplay(ReplayId),
+    /// opens a private game only the player with the code from LobbyCreated can join,
+    /// LeaveQueue closes it again
+    CreateLobby {
+        handicap: handicap::Handicap,
+        variant: matchmaking::Variant,
+        time_control: Option<matchmaking::TimeControl>,
+        color: matchmaking::ColorPreference,
+    },
+    /// plays the game of the lobby with this code
+    JoinLobby(matchmaking::LobbyCode),
+    /// offers a game anyone can accept from the seek list, LeaveQueue takes it back
+    CreateSeek {
+        handicap: handicap::Handicap,
+        variant: matchmaking::Variant,
+        time_control: Option<matchmaking::TimeControl>,
+        time_odds: Option<matchmaking::TimeControl>,
+        rated: bool,
+        color: matchmaking::ColorPreference,
+    },
+    /// asks for the seeks of everyone else, the server answers with Seeks
+    ListSeeks,
+    /// plays the game someone offered
+    AcceptSeek(SeekId),
+    /// opens a round-robin league others can join until you start it
+    CreateLeague {
+        time_control: Option<matchmaking::TimeControl>,
+        rated: bool,
+        /// play everyone twice, once with each color
+        double: bool,
+    },
+    JoinLeague(league::LeagueId),
+    /// only the player who created the league can start it
+    StartLeague(league::LeagueId),
+    /// opens a simul, you play everyone who joins at once from this connection
+    CreateSimul {
+        time_control: Option<matchmaking::TimeControl>,
+    },
+    JoinSimul(simul::SimulId),
+    /// only the host can start the simul, every opponent gets a game against them
+    StartSimul(simul::SimulId),
+    /// the last `count` games the player with this name finished, or your own without a name,
+    /// `before` pages back from an earlier answer. the server answers with a History
+    History {
+        player: Option<String>,
+        count: u32,
+        before: Option<ReplayId>,
+    },
+    /// the engine's report on a finished game you played, the server answers with a
+    /// ReplayAnalysis once it is done, games nobody asked about before get analyzed first
+    FetchAnalysis(ReplayId),
+    /// plays the server's bot right away instead of waiting in the queue you joined, a server
+    /// without a bot answers with NoBots
+    PlayComputer,
+    /// asks the engine for a move in a game you play that isn't rated, on your turn, the server
+    /// answers with a Hint or NoHints
+    Hint(GameId),
+    /// gives the registered account you logged into a new name, the server answers with a
+    /// Session or NameTaken. anonymous players pick theirs with Hello
+    Rename(String),
+    /// a packet from a newer client, never sent, see wire
+    #[serde(other, skip_serializing)]
+    Unsupported,
+}
+
+impl ClientPacket {
+    /// the game this packet belongs to
+    pub fn game(&self) -> Option<GameId> {
+        match *self {
+            ClientPacket::Resync(game, _)
+            | ClientPacket::RequestDraw(game)
+            | ClientPacket::DeclineDraw(game)
+            | ClientPacket::Move(game, _, _)
+            | ClientPacket::Promotion(game, _)
+            | ClientPacket::OfferRematch(game)
+            | ClientPacket::AcceptRematch(game)
+            | ClientPacket::Chat(game, _)
+            | ClientPacket::Abort(game)
+            | ClientPacket::RequestTakeback(game)
+            | ClientPacket::RespondTakeback(game, _)
+            | ClientPacket::Spectate(game)
+            | ClientPacket::ClaimDraw(game)
+            | ClientPacket::Hint(game)
+            | ClientPacket::Resume(GameToken { game, .. }) => Some(game),
+            ClientPacket::JoinQueue { .. }
+            | ClientPacket::LeaveQueue
+            | ClientPacket::PlayComputer
+            | ClientPacket::Hello { .. }
+            | ClientPacket::Rename(_)
+            | ClientPacket::Login { .. }
+            | ClientPacket::Register { .. }
+            | ClientPacket::PasswordLogin { .. }
+            | ClientPacket::Challenge { .. }
+            | ClientPacket::AnswerChallenge(_, _)
+            | ClientPacket::ServerStats
+            | ClientPacket::RecentGames
+            | ClientPacket::FetchReplay(_)
+            | ClientPacket::CreateLobby { .. }
+            | ClientPacket::JoinLobby(_)
+            | ClientPacket::CreateSeek { .. }
+            | ClientPacket::ListSeeks
+            | ClientPacket::AcceptSeek(_)
+            | ClientPacket::CreateLeague { .. }
+            | ClientPacket::JoinLeague(_)
+            | ClientPacket::StartLeague(_)
+            | ClientPacket::CreateSimul { .. }
+            | ClientPacket::JoinSimul(_)
+            | ClientPacket::StartSimul(_)
+            | ClientPacket::History { .. }
+            | ClientPacket::FetchAnalysis(_)
+            | ClientPacket::RequestPuzzle
+            | ClientPacket::Ping(_)
+            | ClientPacket::Pong(_)
+            | ClientPacket::Unsupported => None,
+        }
+    }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Copy)]
+/// new packets go at the end before Unsupported, see wire
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum ServerPacket {
-    MatchFound(chessmove::ChessColor),
-    InvalidMove(chessstate::ChessState),
-    StateReminder(chessstate::ChessState),
-    Move(chessmove::ChessMove),
-    Promotion(chessmove::ChessPieceType),
-    EndGame(GameEnd),
-    DrawRequested,
+    MatchFound {
+        game: GameId,
+        color: chessmove::ChessColor,
+        white: PlayerInfo,
+        black: PlayerInfo,
+        /// rated games change the ratings of both players and have no hints
+        rated: bool,
+        /// the position the game starts in, differs from the default with handicaps
+        start: chessstate::ChessState,
+        /// proves who you are when resuming after a lost connection
+        token: GameToken,
+        /// every move played so far, empty unless the game is being resumed
+        moves: Vec<chessmove::PlayedMove>,
+        /// None for games without a time control
+        clock: Option<clock::Clock>,
+    },
+    /// rewind to ply `since` and replay `moves` from there, undoes illegal moves and catches up on missed ones
+    Resync {
+        game: GameId,
+        since: u32,
+        moves: Vec<chessmove::PlayedMove>,
+        clock: Option<clock::Clock>,
+    },
+    /// the ply is the number of moves played before this one, the clock is read right after the move
+    Move(GameId, u32, chessmove::ChessMove, Option<clock::Clock>),
+    Promotion(GameId, chessmove::ChessPieceType),
+    EndGame(GameId, GameEnd),
+    DrawRequested(GameId),
+    DrawDeclined(GameId),
+    RematchOffered(GameId),
+    /// the game waits this many seconds for the opponent to come back
+    OpponentDisconnected {
+        game: GameId,
+        grace: u64,
+    },
+    OpponentReconnected(GameId),
+    Chat {
+        game: GameId,
+        from: chessmove::ChessColor,
+        message: String,
+    },
+    /// the timestamp gets echoed back in a Pong
+    Ping(u64),
+    Pong(u64),
+    /// None if the server hasn't found any puzzles yet
+    DailyPuzzle(Option<puzzle::Puzzle>),
+    /// send after EndGame once the server is done looking at the game
+    Analysis(GameId, analysis::GameAnalysis),
+    /// send regularly while waiting in the queue, position 1 is next in line
+    QueueStatus {
+        position: u32,
+        players_waiting: u32,
+    },
+    /// the message of the day on connect or a message from the server operator
+    Announcement(String),
+    /// an accepted takeback rewinds the game with a Resync
+    TakebackRequested(GameId),
+    TakebackDeclined(GameId),
+    /// the game so far for a new spectator, after that they get the
+    /// Move, Promotion, Resync and EndGame packets the players get
+    SpectateState {
+        game: GameId,
+        white: PlayerInfo,
+        black: PlayerInfo,
+        start: chessstate::ChessState,
+        moves: Vec<chessmove::PlayedMove>,
+        clock: Option<clock::Clock>,
+    },
+    /// the account the connection is logged into, keep the token to log in again later
+    Session {
+        token: SessionToken,
+        player: PlayerInfo,
+    },
+    /// someone wants to play you, answer with AnswerChallenge
+    ChallengeReceived {
+        challenge: u32,
+        from: PlayerInfo,
+        variant: matchmaking::Variant,
+        time_control: Option<matchmaking::TimeControl>,
+    },
+    /// the player you challenged said no or isn't online
+    ChallengeDeclined {
+        target: String,
+    },
+    /// your clock after the server charged you for your move
+    Clock(GameId, clock::Clock),
+    Error(ServerError),
+    ServerStats {
+        /// games in progress
+        games: u32,
+        players_online: u32,
+        queue_length: u32,
+        /// seconds since the server started
+        uptime: u64,
+        /// the opening played most often since then
+        opening: Option<String>,
+    },
+    /// your correspondence games, sent after logging in
+    OngoingGames(Vec<OngoingGame>),
+    /// the games you finished last, the newest first
+    RecentGames(Vec<GameSummary>),
+    /// a finished game from the start, for watching it again
+    Replay {
+        id: ReplayId,
+        white: String,
+        black: String,
+        start: chessstate::ChessState,
+        moves: Vec<chessmove::PlayedMove>,
+        result: GameEnd,
+    },
+    /// the lobby is open, the game starts once someone joins with the code
+    LobbyCreated(matchmaking::LobbyCode),
+    /// the games other players are offering, the oldest first
+    Seeks(Vec<OpenSeek>),
+    /// how many people are watching your game, sent whenever it changes
+    Spectators(GameId, u32),
+    /// the schedule and results of a league you play in, sent whenever they change
+    League(league::LeagueSchedule),
+    /// every move of the game so far and the clock, sent to bots after every move
+    /// so they don't have to keep track of the game themselves
+    Position {
+        game: GameId,
+        moves: Vec<chessmove::PlayedMove>,
+        clock: Option<clock::Clock>,
+    },
+    /// who is in the simul you host or joined, sent whenever it changes until it starts
+    Simul(simul::SimulInfo),
+    /// the draw offer ran out of time or the player it was made to moved instead of answering
+    DrawExpired(GameId),
+    /// the games a player finished, the newest first
+    History {
+        player: PlayerInfo,
+        games: Vec<HistoryEntry>,
+    },
+    /// the report on a finished game asked for with FetchAnalysis
+    ReplayAnalysis(ReplayId, analysis::GameAnalysis),
+    /// the move the engine suggests for the position after this many plies, None when there is none
+    Hint(GameId, u32, Option<chessmove::PlayedMove>),
+    /// a packet from a newer server, never sent, see wire
+    #[serde(other, skip_serializing)]
+    Unsupported,
+}
+
+impl ServerPacket {
+    /// the game this packet belongs to, MatchFound starts a new one so it doesn't count
+    pub fn game(&self) -> Option<GameId> {
+        match *self {
+            ServerPacket::Resync { game, .. }
+            | ServerPacket::Move(game, _, _, _)
+            | ServerPacket::Promotion(game, _)
+            | ServerPacket::EndGame(game, _)
+            | ServerPacket::DrawRequested(game)
+            | ServerPacket::DrawDeclined(game)
+            | ServerPacket::DrawExpired(game)
+            | ServerPacket::RematchOffered(game)
+            | ServerPacket::TakebackRequested(game)
+            | ServerPacket::TakebackDeclined(game)
+            | ServerPacket::SpectateState { game, .. }
+            | ServerPacket::OpponentDisconnected { game, .. }
+            | ServerPacket::OpponentReconnected(game)
+            | ServerPacket::Chat { game, .. }
+            | ServerPacket::Analysis(game, _)
+            | ServerPacket::Spectators(game, _)
+            | ServerPacket::Position { game, .. }
+            | ServerPacket::Hint(game, _, _)
+            | ServerPacket::Clock(game, _) => Some(game),
+            ServerPacket::MatchFound { .. }
+            | ServerPacket::Ping(_)
+            | ServerPacket::Pong(_)
+            | ServerPacket::DailyPuzzle(_)
+            | ServerPacket::QueueStatus { .. }
+            | ServerPacket::Session { .. }
+            | ServerPacket::ChallengeReceived { .. }
+            | ServerPacket::ChallengeDeclined { .. }
+            | ServerPacket::Error(_)
+            | ServerPacket::ServerStats { .. }
+            | ServerPacket::OngoingGames(_)
+            | ServerPacket::RecentGames(_)
+            | ServerPacket::Replay { .. }
+            | ServerPacket::History { .. }
+            | ServerPacket::ReplayAnalysis(..)
+            | ServerPacket::LobbyCreated(_)
+            | ServerPacket::Seeks(_)
+            | ServerPacket::League(_)
+            | ServerPacket::Simul(_)
+            | ServerPacket::Announcement(_)
+            | ServerPacket::Unsupported => None,
+        }
+    }
 }
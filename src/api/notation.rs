@@ -0,0 +1,348 @@
+use std::fmt::Display;
+
+use super::{
+    chessmove::{
+        ChessColor, ChessMove, ChessPiece, ChessPieceType, ChessboardLocation, File, PlayedMove,
+        Rank,
+    },
+    chessstate::{ChessState, SEVENTY_FIVE_MOVES},
+};
+
+impl ChessPieceType {
+    /// the (uppercase) letter used for this piece in FEN and algebraic notation
+    pub fn letter(self) -> char {
+        match self {
+            ChessPieceType::King => 'K',
+            ChessPieceType::Queen => 'Q',
+            ChessPieceType::Rook => 'R',
+            ChessPieceType::Knight => 'N',
+            ChessPieceType::Bishop => 'B',
+            ChessPieceType::Pawn => 'P',
+        }
+    }
+
+    /// the piece of an uppercase letter from FEN or algebraic notation
+    pub fn from_letter(letter: char) -> Option<Self> {
+        match letter {
+            'K' => Some(ChessPieceType::King),
+            'Q' => Some(ChessPieceType::Queen),
+            'R' => Some(ChessPieceType::Rook),
+            'N' => Some(ChessPieceType::Knight),
+            'B' => Some(ChessPieceType::Bishop),
+            'P' => Some(ChessPieceType::Pawn),
+            _ => None,
+        }
+    }
+}
+
+impl ChessState {
+    /// the position in Forsyth-Edwards Notation, the state doesn't know how many moves were played so the caller passes it in
+    pub fn fen(&self, fullmove_number: usize) -> String {
+        let mut fen = String::new();
+        for row in self.board.iter().rev() {
+            let mut empty = 0;
+            for piece in row {
+                let Some(piece) = piece else {
+                    empty += 1;
+                    continue;
+                };
+                if empty != 0 {
+                    fen.push_str(&empty.to_string());
+                    empty = 0;
+                }
+                fen.push(match piece.color {
+                    ChessColor::White => piece.piece_type.letter(),
+                    ChessColor::Black => piece.piece_type.letter().to_ascii_lowercase(),
+                });
+            }
+            if empty != 0 {
+                fen.push_str(&empty.to_string());
+            }
+            fen.push('/');
+        }
+        fen.pop();
+
+        fen.push_str(match self.turn {
+            ChessColor::White => " w ",
+            ChessColor::Black => " b ",
+        });
+
+        let castling: String = [
+            (!self.white_king_moved && !self.white_h_rook_moved, 'K'),
+            (!self.white_king_moved && !self.white_a_rook_moved, 'Q'),
+            (!self.black_king_moved && !self.black_h_rook_moved, 'k'),
+            (!self.black_king_moved && !self.black_a_rook_moved, 'q'),
+        ]
+        .into_iter()
+        .filter_map(|(allowed, c)| allowed.then_some(c))
+        .collect();
+        if castling.is_empty() {
+            fen.push('-');
+        } else {
+            fen.push_str(&castling);
+        }
+
+        // the square behind the pawn that just moved 2 squares
+        match self.en_passant {
+            Some(file) => fen.push_str(&format!(
+                " {}{} ",
+                file_letter(file),
+                match self.turn {
+                    ChessColor::White => 6,
+                    ChessColor::Black => 3,
+                }
+            )),
+            None => fen.push_str(" - "),
+        }
+
+        fen.push_str(&format!("{} {}", self.fifty_move_rule, fullmove_number));
+        fen
+    }
+
+    /// reads a position in Forsyth-Edwards Notation, the move counters at the end may be left out
+    pub fn from_fen(fen: &str) -> Option<Self> {
+        let mut fields = fen.split_whitespace();
+        let mut state = Self {
+            board: [[None; 8]; 8],
+            ..Self::default()
+        };
+        let rows: Vec<_> = fields.next()?.split('/').collect();
+        if rows.len() != 8 {
+            return None;
+        }
+        for (rank, row) in rows.into_iter().rev().enumerate() {
+            let mut file = 0;
+            for c in row.chars() {
+                if let Some(empty) = c.to_digit(10) {
+                    file += empty as usize;
+                    continue;
+                }
+                let piece_type = ChessPieceType::from_letter(c.to_ascii_uppercase())?;
+                let color = if c.is_ascii_uppercase() {
+                    ChessColor::White
+                } else {
+                    ChessColor::Black
+                };
+                *state.board[rank].get_mut(file)? = Some(ChessPiece::new(color, piece_type));
+                file += 1;
+            }
+            if file != 8 {
+                return None;
+            }
+        }
+        state.turn = match fields.next().unwrap_or("w") {
+            "w" => ChessColor::White,
+            "b" => ChessColor::Black,
+            _ => return None,
+        };
+        let castling = fields.next().unwrap_or("-");
+        // a right only counts while the king and that rook are still on the squares they started on
+        let at_home = |square: &str, piece: ChessPiece| {
+            parse_square(square).and_then(|square| state.get_location(square)) == Some(piece)
+        };
+        let white_king = at_home(
+            "e1",
+            ChessPiece::new(ChessColor::White, ChessPieceType::King),
+        );
+        let black_king = at_home(
+            "e8",
+            ChessPiece::new(ChessColor::Black, ChessPieceType::King),
+        );
+        let white_rook = ChessPiece::new(ChessColor::White, ChessPieceType::Rook);
+        let black_rook = ChessPiece::new(ChessColor::Black, ChessPieceType::Rook);
+        let white_h = white_king && castling.contains('K') && at_home("h1", white_rook);
+        let white_a = white_king && castling.contains('Q') && at_home("a1", white_rook);
+        let black_h = black_king && castling.contains('k') && at_home("h8", black_rook);
+        let black_a = black_king && castling.contains('q') && at_home("a8", black_rook);
+        state.white_king_moved = !(white_h || white_a);
+        state.white_h_rook_moved = !white_h;
+        state.white_a_rook_moved = !white_a;
+        state.black_king_moved = !(black_h || black_a);
+        state.black_h_rook_moved = !black_h;
+        state.black_a_rook_moved = !black_a;
+        state.en_passant = match fields.next().unwrap_or("-") {
+            "-" => None,
+            square => Some(parse_square(square)?.file),
+        };
+        state.fifty_move_rule = fields.next().map_or(Some(0), |n| n.parse().ok())?;
+        // the game would have ended at 75 moves already
+        if state.fifty_move_rule > SEVENTY_FIVE_MOVES {
+            return None;
+        }
+        // both kings have to be there, the rules look for them every move
+        if state.king_location(ChessColor::White).is_none()
+            || state.king_location(ChessColor::Black).is_none()
+        {
+            return None;
+        }
+        Some(state)
+    }
+
+    /// the legal move written as `san` in standard algebraic notation, the check marks and
+    /// annotations like ! and ? after it don't matter
+    pub fn parse_san(&self, san: &str) -> Option<PlayedMove> {
+        let bare = |san: &str| san.trim_end_matches(['+', '#', '!', '?']).replace('0', "O");
+        let wanted = bare(san);
+        self.legal_moves()
+            .into_iter()
+            .flat_map(|chess_move| {
+                let promotes = self.get_location(chess_move.from).is_some_and(|piece| {
+                    piece.piece_type == ChessPieceType::Pawn
+                        && matches!(chess_move.to.rank, Rank::One | Rank::Eight)
+                });
+                let promotions = if promotes {
+                    vec![
+                        Some(ChessPieceType::Queen),
+                        Some(ChessPieceType::Rook),
+                        Some(ChessPieceType::Bishop),
+                        Some(ChessPieceType::Knight),
+                    ]
+                } else {
+                    vec![None]
+                };
+                promotions.into_iter().map(move |promotion| PlayedMove {
+                    chess_move,
+                    promotion,
+                })
+            })
+            .find(|&played_move| bare(&self.san(played_move)) == wanted)
+    }
+
+    /// the move in standard algebraic notation like Nbd7, exd6 or e8=Q+, played from this position
+    pub fn san(&self, played_move: PlayedMove) -> String {
+        let ChessMove { from, to } = played_move.chess_move;
+        let Some(piece) = self.get_location(from) else {
+            return played_move.to_string();
+        };
+        let mut san = String::new();
+        if piece.piece_type == ChessPieceType::King
+            && (from.file as u8).abs_diff(to.file as u8) == 2
+        {
+            san.push_str(if to.file == File::G { "O-O" } else { "O-O-O" });
+        } else {
+            // a pawn moving sideways always captures, even when the square it lands on is empty
+            let capture = self.get_location(to).is_some()
+                || piece.piece_type == ChessPieceType::Pawn && from.file != to.file;
+            if piece.piece_type == ChessPieceType::Pawn {
+                if capture {
+                    san.push(file_letter(from.file));
+                }
+            } else {
+                san.push(piece.piece_type.letter());
+                // the same kind of piece that could go there too
+                let others: Vec<_> = self
+                    .legal_moves()
+                    .into_iter()
+                    .filter(|m| {
+                        m.to == to && m.from != from && self.get_location(m.from) == Some(piece)
+                    })
+                    .collect();
+                if !others.is_empty() {
+                    if others.iter().all(|m| m.from.file != from.file) {
+                        san.push(file_letter(from.file));
+                    } else if others.iter().all(|m| m.from.rank != from.rank) {
+                        san.push_str(&(from.rank as u8 + 1).to_string());
+                    } else {
+                        san.push_str(&square(from));
+                    }
+                }
+            }
+            if capture {
+                san.push('x');
+            }
+            san.push_str(&square(to));
+            if let Some(promotion) = played_move.promotion {
+                san.push('=');
+                san.push(promotion.letter());
+            }
+        }
+
+        let mut after = *self;
+        if after.play_move(played_move).is_ok() && after.in_check() {
+            san.push(if after.legal_moves().is_empty() {
+                '#'
+            } else {
+                '+'
+            });
+        }
+        san
+    }
+}
+
+/// a square like e4
+fn square(location: ChessboardLocation) -> String {
+    format!("{}{}", file_letter(location.file), location.rank as u8 + 1)
+}
+
+/// reads a square like e4
+fn parse_square(square: &str) -> Option<ChessboardLocation> {
+    let &[file @ b'a'..=b'h', rank @ b'1'..=b'8'] = square.as_bytes() else {
+        return None;
+    };
+    Some(ChessboardLocation::new(rank - b'1', file - b'a'))
+}
+
+pub fn file_letter(file: File) -> char {
+    (b'a' + file as u8) as char
+}
+
+/// long algebraic notation like e2e4 or e7e8q
+impl Display for PlayedMove {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ChessMove { from, to } = self.chess_move;
+        write!(f, "{}{}", square(from), square(to))?;
+        if let Some(piece) = self.promotion {
+            write!(f, "{}", piece.letter().to_ascii_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// reads `fen` and writes it back out
+    fn round_trip(fen: &str) -> Option<String> {
+        let fullmove = fen.split_whitespace().last()?.parse().ok()?;
+        Some(ChessState::from_fen(fen)?.fen(fullmove))
+    }
+
+    #[test]
+    fn fen_survives_a_round_trip() {
+        for fen in [
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1",
+            "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq e3 0 1",
+            "r3k2r/8/8/8/8/8/8/R3K2R w Kq - 12 30",
+            "8/5k2/8/3pP3/8/8/8/4K3 w - d6 0 45",
+            "4k3/8/8/8/8/8/8/4K3 b - - 150 120",
+        ] {
+            assert_eq!(round_trip(fen).as_deref(), Some(fen));
+        }
+    }
+
+    #[test]
+    fn castling_needs_the_king_and_rook_at_home() {
+        // no rooks at all, and a king that left its square
+        assert_eq!(
+            round_trip("4k3/8/8/8/8/8/8/4K3 w KQkq - 0 1").as_deref(),
+            Some("4k3/8/8/8/8/8/8/4K3 w - - 0 1")
+        );
+        assert_eq!(
+            round_trip("r3k2r/8/8/8/8/8/8/R4K1R w KQkq - 0 1").as_deref(),
+            Some("r3k2r/8/8/8/8/8/8/R4K1R w kq - 0 1")
+        );
+        // only the rook that is still there keeps its right
+        assert_eq!(
+            round_trip("4k2r/8/8/8/8/8/8/R3K3 w KQkq - 0 1").as_deref(),
+            Some("4k2r/8/8/8/8/8/8/R3K3 w Qk - 0 1")
+        );
+    }
+
+    #[test]
+    fn halfmove_clock_past_75_moves_is_rejected() {
+        assert!(ChessState::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 151 1").is_none());
+        assert!(ChessState::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 255 1").is_none());
+        assert!(ChessState::from_fen("4k3/8/8/8/8/8/8/4K3 w - - 300 1").is_none());
+    }
+}
@@ -0,0 +1,97 @@
+use super::chessmove::PlayedMove;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Opening {
+    pub eco: &'static str,
+    pub name: &'static str,
+    /// the moves defining the opening in long algebraic notation
+    pub moves: &'static str,
+}
+
+macro_rules! openings {
+    ($(($eco:literal, $name:literal, $moves:literal),)*) => {
+        &[$(Opening { eco: $eco, name: $name, moves: $moves },)*]
+    };
+}
+
+#[rustfmt::skip]
+pub const OPENINGS: &[Opening] = openings![
+    ("A00", "Polish Opening", "b2b4"),
+    ("A00", "Grob's Attack", "g2g4"),
+    ("A01", "Nimzo-Larsen Attack", "b2b3"),
+    ("A02", "Bird's Opening", "f2f4"),
+    ("A04", "Reti Opening", "g1f3"),
+    ("A10", "English Opening", "c2c4"),
+    ("A20", "English Opening: King's English", "c2c4 e7e5"),
+    ("A40", "Queen's Pawn Game", "d2d4"),
+    ("A45", "Indian Defence", "d2d4 g8f6"),
+    ("A50", "Indian Defence: Normal Variation", "d2d4 g8f6 c2c4"),
+    ("A56", "Benoni Defence", "d2d4 g8f6 c2c4 c7c5"),
+    ("A57", "Benko Gambit", "d2d4 g8f6 c2c4 c7c5 d4d5 b7b5"),
+    ("A80", "Dutch Defence", "d2d4 f7f5"),
+    ("B00", "King's Pawn Opening", "e2e4"),
+    ("B01", "Scandinavian Defence", "e2e4 d7d5"),
+    ("B02", "Alekhine's Defence", "e2e4 g8f6"),
+    ("B06", "Modern Defence", "e2e4 g7g6"),
+    ("B07", "Pirc Defence", "e2e4 d7d6 d2d4 g8f6"),
+    ("B10", "Caro-Kann Defence", "e2e4 c7c6"),
+    ("B20", "Sicilian Defence", "e2e4 c7c5"),
+    ("B22", "Sicilian Defence: Alapin Variation", "e2e4 c7c5 c2c3"),
+    ("B23", "Sicilian Defence: Closed", "e2e4 c7c5 b1c3"),
+    ("B30", "Sicilian Defence: Old Sicilian", "e2e4 c7c5 g1f3 b8c6"),
+    ("B40", "Sicilian Defence: French Variation", "e2e4 c7c5 g1f3 e7e6"),
+    ("B50", "Sicilian Defence: Modern Variations", "e2e4 c7c5 g1f3 d7d6"),
+    ("B54", "Sicilian Defence: Open", "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4"),
+    ("B70", "Sicilian Defence: Dragon Variation", "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 g7g6"),
+    ("B90", "Sicilian Defence: Najdorf Variation", "e2e4 c7c5 g1f3 d7d6 d2d4 c5d4 f3d4 g8f6 b1c3 a7a6"),
+    ("C00", "French Defence", "e2e4 e7e6"),
+    ("C01", "French Defence: Exchange Variation", "e2e4 e7e6 d2d4 d7d5 e4d5"),
+    ("C02", "French Defence: Advance Variation", "e2e4 e7e6 d2d4 d7d5 e4e5"),
+    ("C03", "French Defence: Tarrasch Variation", "e2e4 e7e6 d2d4 d7d5 b1d2"),
+    ("C10", "French Defence: Paulsen Variation", "e2e4 e7e6 d2d4 d7d5 b1c3"),
+    ("C20", "King's Pawn Game", "e2e4 e7e5"),
+    ("C21", "Center Game", "e2e4 e7e5 d2d4 e5d4"),
+    ("C23", "Bishop's Opening", "e2e4 e7e5 f1c4"),
+    ("C25", "Vienna Game", "e2e4 e7e5 b1c3"),
+    ("C30", "King's Gambit", "e2e4 e7e5 f2f4"),
+    ("C33", "King's Gambit Accepted", "e2e4 e7e5 f2f4 e5f4"),
+    ("C40", "King's Knight Opening", "e2e4 e7e5 g1f3"),
+    ("C41", "Philidor Defence", "e2e4 e7e5 g1f3 d7d6"),
+    ("C42", "Petrov's Defence", "e2e4 e7e5 g1f3 g8f6"),
+    ("C44", "King's Knight Opening: Normal Variation", "e2e4 e7e5 g1f3 b8c6"),
+    ("C44", "Scotch Game", "e2e4 e7e5 g1f3 b8c6 d2d4"),
+    ("C46", "Three Knights Opening", "e2e4 e7e5 g1f3 b8c6 b1c3"),
+    ("C47", "Four Knights Game", "e2e4 e7e5 g1f3 b8c6 b1c3 g8f6"),
+    ("C50", "Italian Game", "e2e4 e7e5 g1f3 b8c6 f1c4"),
+    ("C50", "Italian Game: Giuoco Piano", "e2e4 e7e5 g1f3 b8c6 f1c4 f8c5"),
+    ("C51", "Italian Game: Evans Gambit", "e2e4 e7e5 g1f3 b8c6 f1c4 f8c5 b2b4"),
+    ("C55", "Italian Game: Two Knights Defence", "e2e4 e7e5 g1f3 b8c6 f1c4 g8f6"),
+    ("C57", "Italian Game: Two Knights Defence, Knight Attack", "e2e4 e7e5 g1f3 b8c6 f1c4 g8f6 f3g5"),
+    ("C60", "Ruy Lopez", "e2e4 e7e5 g1f3 b8c6 f1b5"),
+    ("C65", "Ruy Lopez: Berlin Defence", "e2e4 e7e5 g1f3 b8c6 f1b5 g8f6"),
+    ("C68", "Ruy Lopez: Exchange Variation", "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6 b5c6"),
+    ("C70", "Ruy Lopez: Morphy Defence", "e2e4 e7e5 g1f3 b8c6 f1b5 a7a6"),
+    ("D00", "Queen's Pawn Game", "d2d4 d7d5"),
+    ("D00", "Queen's Pawn Game: London System", "d2d4 d7d5 c1f4"),
+    ("D02", "Queen's Pawn Game: Zukertort Variation", "d2d4 d7d5 g1f3"),
+    ("D06", "Queen's Gambit", "d2d4 d7d5 c2c4"),
+    ("D10", "Slav Defence", "d2d4 d7d5 c2c4 c7c6"),
+    ("D20", "Queen's Gambit Accepted", "d2d4 d7d5 c2c4 d5c4"),
+    ("D30", "Queen's Gambit Declined", "d2d4 d7d5 c2c4 e7e6"),
+    ("D80", "Grunfeld Defence", "d2d4 g8f6 c2c4 g7g6 b1c3 d7d5"),
+    ("E12", "Queen's Indian Defence", "d2d4 g8f6 c2c4 e7e6 g1f3 b7b6"),
+    ("E20", "Nimzo-Indian Defence", "d2d4 g8f6 c2c4 e7e6 b1c3 f8b4"),
+    ("E60", "King's Indian Defence", "d2d4 g8f6 c2c4 g7g6"),
+];
+
+/// finds the most specific opening the game started with
+pub fn classify(moves: &[PlayedMove]) -> Option<&'static Opening> {
+    let played: Vec<String> = moves.iter().map(ToString::to_string).collect();
+    OPENINGS
+        .iter()
+        .filter(|opening| {
+            let line: Vec<_> = opening.moves.split_whitespace().collect();
+            line.len() <= played.len() && line.iter().zip(&played).all(|(a, b)| a == b)
+        })
+        .max_by_key(|opening| opening.moves.split_whitespace().count())
+}
@@ -0,0 +1,292 @@
+//! portable game notation, the format other chess programs open games in
+
+use super::{
+    chessmove::{ChessColor, PlayedMove},
+    chessstate::ChessState,
+    openings, EndReason, GameEnd,
+};
+
+/// a game read from pgn
+#[derive(Debug, Clone)]
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub start: ChessState,
+    pub moves: Vec<PlayedMove>,
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags
+            .iter()
+            .find(|(tag, _)| tag == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// how the game ended, None while it still goes on
+    pub fn end(&self) -> Option<GameEnd> {
+        let reason = self
+            .tag("Termination")
+            .and_then(reason)
+            .unwrap_or(EndReason::Agreement);
+        match self.tag("Result")? {
+            "1-0" => Some(GameEnd::White(reason)),
+            "0-1" => Some(GameEnd::Black(reason)),
+            "1/2-1/2" => Some(GameEnd::Draw(reason)),
+            _ => None,
+        }
+    }
+}
+
+/// the result the way pgn writes it, * for a game that goes on
+pub fn result(end: Option<GameEnd>) -> &'static str {
+    match end {
+        Some(GameEnd::White(_)) => "1-0",
+        Some(GameEnd::Black(_)) => "0-1",
+        Some(GameEnd::Draw(_)) => "1/2-1/2",
+        None => "*",
+    }
+}
+
+pub fn termination(reason: EndReason) -> &'static str {
+    match reason {
+        EndReason::Checkmate => "checkmate",
+        EndReason::Stalemate => "stalemate",
+        EndReason::Resignation => "resignation",
+        EndReason::Agreement => "agreement",
+        EndReason::Timeout => "time forfeit",
+        EndReason::InsufficientMaterial => "insufficient material",
+        EndReason::FiftyMoveRule => "fifty move rule",
+        EndReason::RepetitionOfMoves => "repetition",
+        EndReason::Aborted => "abandoned",
+    }
+}
+
+/// the reason of a termination tag this game wrote
+fn reason(termination: &str) -> Option<EndReason> {
+    [
+        EndReason::Checkmate,
+        EndReason::Stalemate,
+        EndReason::Resignation,
+        EndReason::Agreement,
+        EndReason::Timeout,
+        EndReason::InsufficientMaterial,
+        EndReason::FiftyMoveRule,
+        EndReason::RepetitionOfMoves,
+        EndReason::Aborted,
+    ]
+    .into_iter()
+    .find(|&reason| self::termination(reason) == termination)
+}
+
+/// the utc date and time of a timestamp in milliseconds, the way pgn writes them
+pub fn date_time(timestamp: u64) -> (String, String) {
+    let seconds = timestamp / 1000;
+    let time = seconds % 86400;
+    // days since 1970 to a calendar date, from Howard Hinnant's civil_from_days
+    let days = seconds / 86400 + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + u64::from(month <= 2);
+    (
+        format!("{:04}.{:02}.{:02}", year, month, day),
+        format!("{:02}:{:02}:{:02}", time / 3600, time / 60 % 60, time % 60),
+    )
+}
+
+/// the tags and moves of a game in portable game notation, with an empty line after it.
+/// games that don't start from the usual position get a FEN tag unless they have one, the others the opening they played
+pub fn write(
+    mut tags: Vec<(&str, String)>,
+    start: ChessState,
+    moves: &[PlayedMove],
+    end: Option<GameEnd>,
+) -> String {
+    // games with odds don't start from the usual position, a game read before has the tag already
+    let start_fen = start.fen(1);
+    if start_fen != ChessState::default().fen(1) {
+        if !tags.iter().any(|(name, _)| *name == "FEN") {
+            tags.push(("SetUp", "1".to_string()));
+            tags.push(("FEN", start_fen));
+        }
+    } else if let Some(opening) = openings::classify(moves) {
+        if !tags.iter().any(|(name, _)| *name == "ECO") {
+            tags.push(("ECO", opening.eco.to_string()));
+            tags.push(("Opening", opening.name.to_string()));
+        }
+    }
+
+    let mut pgn = String::new();
+    for (name, value) in tags {
+        pgn.push_str(&format!(
+            "[{} \"{}\"]\n",
+            name,
+            value.replace('\\', "\\\\").replace('"', "\\\"")
+        ));
+    }
+    pgn.push('\n');
+
+    let mut tokens = Vec::new();
+    let mut state = start;
+    for (ply, &played_move) in moves.iter().enumerate() {
+        let fullmove = (ply + start.turn as usize) / 2 + 1;
+        if state.turn == ChessColor::White {
+            tokens.push(format!("{}.", fullmove));
+        } else if ply == 0 {
+            tokens.push(format!("{}...", fullmove));
+        }
+        tokens.push(state.san(played_move));
+        if state.play_move(played_move).is_err() {
+            break;
+        }
+    }
+    tokens.push(result(end).to_string());
+
+    // export format keeps lines under 80 characters
+    let mut line = String::new();
+    for token in tokens {
+        if !line.is_empty() && line.len() + 1 + token.len() > 79 {
+            pgn.push_str(&line);
+            pgn.push('\n');
+            line.clear();
+        }
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        line.push_str(&token);
+    }
+    pgn.push_str(&line);
+    pgn.push_str("\n\n");
+    pgn
+}
+
+/// the first game in `text`, None when its tags, start position or moves don't make sense.
+/// comments and variations are skipped
+pub fn read(text: &str) -> Option<PgnGame> {
+    let mut tags = Vec::new();
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .skip_while(|line| line.is_empty());
+    let mut movetext = String::new();
+    for line in lines.by_ref() {
+        let Some(tag) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) else {
+            movetext.push_str(line);
+            movetext.push('\n');
+            break;
+        };
+        let (name, value) = tag.split_once(' ')?;
+        let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+        tags.push((
+            name.to_string(),
+            value.replace("\\\"", "\"").replace("\\\\", "\\"),
+        ));
+    }
+    for line in lines {
+        // the next game starts with its tags
+        if line.starts_with('[') && !movetext.trim().is_empty() {
+            break;
+        }
+        movetext.push_str(line);
+        movetext.push('\n');
+    }
+
+    let start = match tags.iter().find(|(name, _)| name == "FEN") {
+        Some((_, fen)) => ChessState::from_fen(fen)?,
+        None => ChessState::default(),
+    };
+    let mut state = start;
+    let mut moves = Vec::new();
+    let mut depth = 0;
+    let mut comment = false;
+    let mut token = String::new();
+    // a space at the end ends the last token
+    for c in movetext.chars().chain([' ']) {
+        match c {
+            _ if comment => comment = c != '}',
+            '{' => comment = true,
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ if depth > 0 => {}
+            c if c.is_whitespace() => {
+                let word = std::mem::take(&mut token);
+                if result_token(&word) {
+                    continue;
+                }
+                // move numbers like 12. or 12... can stick to the move after them
+                let word = word.trim_start_matches(|c: char| c.is_ascii_digit() || c == '.');
+                if word.is_empty() || word.starts_with('$') {
+                    continue;
+                }
+                let played_move = state.parse_san(word)?;
+                state.play_move(played_move).ok()?;
+                moves.push(played_move);
+            }
+            c => token.push(c),
+        }
+    }
+    Some(PgnGame { tags, start, moves })
+}
+
+fn result_token(word: &str) -> bool {
+    matches!(word, "1-0" | "0-1" | "1/2-1/2" | "*")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// plays `sans` from `start`
+    fn moves(start: ChessState, sans: &str) -> Vec<PlayedMove> {
+        let mut state = start;
+        sans.split_whitespace()
+            .map(|san| {
+                let played_move = state.parse_san(san).unwrap();
+                state.play_move(played_move).unwrap();
+                played_move
+            })
+            .collect()
+    }
+
+    #[test]
+    fn a_game_survives_a_round_trip() {
+        let start = ChessState::default();
+        // long enough to be split over a few lines
+        let played = moves(
+            start,
+            "e4 e5 Nf3 Nc6 Bb5 a6 Ba4 Nf6 O-O Be7 Re1 b5 Bb3 d6 c3 O-O h3 Nb8 d4 Nbd7",
+        );
+        let end = Some(GameEnd::White(EndReason::Resignation));
+        let tags = vec![
+            ("White", "a \"quoted\" \\ name".to_string()),
+            ("Black", "someone".to_string()),
+            ("Result", result(end).to_string()),
+        ];
+        let game = read(&write(tags, start, &played, end)).unwrap();
+        assert_eq!(game.moves, played);
+        assert_eq!(game.tag("White"), Some("a \"quoted\" \\ name"));
+        assert_eq!(game.tag("Black"), Some("someone"));
+        assert!(matches!(game.end(), Some(GameEnd::White(_))));
+    }
+
+    #[test]
+    fn a_game_from_a_set_up_position_survives_a_round_trip() {
+        let fen = "8/P6k/8/8/8/8/6p1/K7 b - - 0 40";
+        let start = ChessState::from_fen(fen).unwrap();
+        let played = moves(start, "g1=N a8=Q Kg6 Qb8");
+        let game = read(&write(Vec::new(), start, &played, None)).unwrap();
+        assert_eq!(game.tag("FEN"), Some("8/P6k/8/8/8/8/6p1/K7 b - - 0 1"));
+        assert_eq!(game.start.fen(1), start.fen(1));
+        assert_eq!(game.moves, played);
+        assert_eq!(game.end(), None);
+    }
+}
@@ -0,0 +1,28 @@
+use std::time::Duration;
+
+// the one from std panics in a browser, this one asks the browser for the time there
+use bevy::{prelude::*, utils::SystemTime};
+
+/// how often both sides ping each other
+pub const PING_INTERVAL: Duration = Duration::from_secs(2);
+
+#[derive(Resource, Debug)]
+pub struct PingTimer(pub Timer);
+
+impl Default for PingTimer {
+    fn default() -> Self {
+        Self(Timer::new(PING_INTERVAL, TimerMode::Repeating))
+    }
+}
+
+/// milliseconds since the unix epoch, only ever compared with the clock that made it
+pub fn timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// time since `sent`, a timestamp made on this side of the connection
+pub fn round_trip(sent: u64) -> Duration {
+    Duration::from_millis(timestamp().saturating_sub(sent))
+}
@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+use super::{ai, chessmove::PlayedMove, chessstate::ChessState};
+
+/// how deep positions get searched while looking for tactics
+const DEPTH: u8 = 3;
+/// centipawns a move has to throw away before the position afterwards counts as a puzzle
+const SWING: i32 = 250;
+/// longest solution a puzzle can have, in plies
+const MAX_SOLUTION: usize = 5;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct Puzzle {
+    /// the position the solver starts in, it's their turn
+    pub fen: String,
+    /// the moves of the solver and the replies of the opponent, always starts and ends with a solver move
+    pub solution: Vec<PlayedMove>,
+}
+
+/// scans a finished game from `start` for positions after a big mistake or with a forced mate
+pub fn find_puzzles(start: ChessState, moves: &[PlayedMove]) -> Vec<Puzzle> {
+    let mut puzzles = Vec::new();
+    let mut state = start;
+    // score before the move from the perspective of the player making it
+    let mut before = ai::search(&state, DEPTH).score;
+    for (plies, &played_move) in (1..).zip(moves) {
+        if state.play_move(played_move).is_err() {
+            break;
+        }
+        let after = ai::search(&state, DEPTH);
+        // a good move keeps `after` close to `-before`
+        let swing = after.score + before;
+        if !after.line.is_empty()
+            && (after.mate_in().is_some() || swing >= SWING && after.score >= SWING)
+        {
+            let mut solution = after.line.clone();
+            solution.truncate(MAX_SOLUTION);
+            // don't end on an opponent move
+            if solution.len().is_multiple_of(2) {
+                solution.pop();
+            }
+            puzzles.push(Puzzle {
+                fen: state.fen((plies + start.turn as usize) / 2 + 1),
+                solution,
+            });
+        }
+        before = after.score;
+    }
+    puzzles
+}
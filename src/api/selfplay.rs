@@ -0,0 +1,92 @@
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+use super::{
+    chessmove::{compress_chessboard, ChessPieceType},
+    chessstate::ChessState,
+    GameEnd,
+};
+
+/// games that take longer than this are cut off
+pub const MAX_PLIES: usize = 1000;
+
+/// tiny xorshift rng so selfplay games are reproducible and don't need the rand crate
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // xorshift gets stuck on 0
+        Self(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+}
+
+/// plays a game of random legal moves (always promoting to a queen),
+/// returns how the game ended (None if it hit MAX_PLIES) and the amount of plies played
+pub fn play_game(seed: u64) -> (Option<GameEnd>, usize) {
+    let mut rng = XorShift::new(seed);
+    let mut state = ChessState::default();
+    let mut move_history = Vec::new();
+    for ply in 0..MAX_PLIES {
+        let moves = state.legal_moves();
+        let chess_move = moves[rng.next() as usize % moves.len()];
+        state
+            .move_piece(chess_move)
+            .expect("legal_moves returned an invalid move");
+        if state.should_promote {
+            state
+                .promote(ChessPieceType::Queen)
+                .expect("couldn't promote after a promotion move");
+        }
+        move_history.push(compress_chessboard(&state.board));
+        if let Some(end) = state.check_game_end(&move_history) {
+            return (Some(end), ply + 1);
+        }
+    }
+    (None, MAX_PLIES)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SelfplayReport {
+    pub games: u64,
+    pub plies: usize,
+    pub elapsed: Duration,
+}
+
+impl SelfplayReport {
+    pub fn moves_per_second(&self) -> f64 {
+        self.plies as f64 / self.elapsed.as_secs_f64()
+    }
+}
+
+impl Display for SelfplayReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "played {} games ({} moves) in {:.2?}, {:.1} moves/second",
+            self.games,
+            self.plies,
+            self.elapsed,
+            self.moves_per_second()
+        )
+    }
+}
+
+/// plays `games` selfplay games back to back and measures how fast the rules engine is
+pub fn run(games: u64) -> SelfplayReport {
+    let start = Instant::now();
+    let plies = (0..games).map(|seed| play_game(seed).1).sum();
+    SelfplayReport {
+        games,
+        plies,
+        elapsed: start.elapsed(),
+    }
+}
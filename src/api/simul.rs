@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+use super::{matchmaking::TimeControl, PlayerInfo};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SimulId(pub u32);
+
+/// a simultaneous exhibition that hasn't started yet, the host plays white on every board
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SimulInfo {
+    pub id: SimulId,
+    pub host: PlayerInfo,
+    /// None for games without a clock
+    pub time_control: Option<TimeControl>,
+    /// the host's opponents, one board each
+    pub players: Vec<PlayerInfo>,
+}
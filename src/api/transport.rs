@@ -0,0 +1,178 @@
+//! picks tcp or websockets at startup instead of at compile time
+//!
+//! slinet wants the protocol as a type, so AnyProtocol forwards to whichever transport
+//! the side it runs on was told to use before it connected or bound
+
+use std::{io, net::SocketAddr, sync::OnceLock};
+
+use async_trait::async_trait;
+use bevy_slinet::{
+    protocol::{
+        ClientStream, Listener, NetworkStream, Protocol, ReadStream, ServerStream, WriteStream,
+    },
+    protocols::tcp::TcpProtocol,
+};
+
+use super::websocket::WebSocketProtocol;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Transport {
+    #[cfg_attr(not(target_arch = "wasm32"), default)]
+    Tcp,
+    /// the only one a browser can open
+    #[cfg_attr(target_arch = "wasm32", default)]
+    WebSocket,
+}
+
+impl std::str::FromStr for Transport {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "tcp" => Ok(Transport::Tcp),
+            "websocket" | "ws" => Ok(Transport::WebSocket),
+            _ => Err(format!("unknown transport {s}, expected tcp or websocket")),
+        }
+    }
+}
+
+static CLIENT_TRANSPORT: OnceLock<Transport> = OnceLock::new();
+static SERVER_TRANSPORT: OnceLock<Transport> = OnceLock::new();
+
+/// sets the transport the client connects with, only the first call counts
+pub fn set_client_transport(transport: Transport) {
+    let _ = CLIENT_TRANSPORT.set(transport);
+}
+
+/// sets the transport the server listens with, only the first call counts
+pub fn set_server_transport(transport: Transport) {
+    let _ = SERVER_TRANSPORT.set(transport);
+}
+
+fn client_transport() -> Transport {
+    CLIENT_TRANSPORT.get().copied().unwrap_or_default()
+}
+
+fn server_transport() -> Transport {
+    SERVER_TRANSPORT.get().copied().unwrap_or_default()
+}
+
+/// a value of one of the two transports
+pub enum Either<T, W> {
+    Tcp(T),
+    WebSocket(W),
+}
+
+pub struct AnyProtocol;
+
+#[async_trait]
+impl Protocol for AnyProtocol {
+    type Listener =
+        Either<<TcpProtocol as Protocol>::Listener, <WebSocketProtocol as Protocol>::Listener>;
+    type ServerStream = Either<
+        <TcpProtocol as Protocol>::ServerStream,
+        <WebSocketProtocol as Protocol>::ServerStream,
+    >;
+    type ClientStream = Either<
+        <TcpProtocol as Protocol>::ClientStream,
+        <WebSocketProtocol as Protocol>::ClientStream,
+    >;
+
+    async fn bind(addr: SocketAddr) -> io::Result<Self::Listener> {
+        Ok(match server_transport() {
+            Transport::Tcp => Either::Tcp(TcpProtocol::bind(addr).await?),
+            Transport::WebSocket => Either::WebSocket(WebSocketProtocol::bind(addr).await?),
+        })
+    }
+}
+
+#[async_trait]
+impl<T: Listener + Sync, W: Listener + Sync> Listener for Either<T, W> {
+    type Stream = Either<T::Stream, W::Stream>;
+
+    async fn accept(&self) -> io::Result<Self::Stream> {
+        Ok(match self {
+            Either::Tcp(listener) => Either::Tcp(listener.accept().await?),
+            Either::WebSocket(listener) => Either::WebSocket(listener.accept().await?),
+        })
+    }
+
+    fn address(&self) -> SocketAddr {
+        match self {
+            Either::Tcp(listener) => listener.address(),
+            Either::WebSocket(listener) => listener.address(),
+        }
+    }
+
+    fn handle_disconnection(&self, peer_addr: SocketAddr) {
+        match self {
+            Either::Tcp(listener) => listener.handle_disconnection(peer_addr),
+            Either::WebSocket(listener) => listener.handle_disconnection(peer_addr),
+        }
+    }
+}
+
+#[async_trait]
+impl<T: ClientStream, W: ClientStream> ClientStream for Either<T, W> {
+    async fn connect(addr: SocketAddr) -> io::Result<Self> {
+        Ok(match client_transport() {
+            Transport::Tcp => Either::Tcp(T::connect(addr).await?),
+            Transport::WebSocket => Either::WebSocket(W::connect(addr).await?),
+        })
+    }
+}
+
+impl<T: ServerStream, W: ServerStream> ServerStream for Either<T, W> {}
+
+#[async_trait]
+impl<T: NetworkStream, W: NetworkStream> NetworkStream for Either<T, W> {
+    type ReadHalf = Either<T::ReadHalf, W::ReadHalf>;
+    type WriteHalf = Either<T::WriteHalf, W::WriteHalf>;
+
+    async fn into_split(self) -> io::Result<(Self::ReadHalf, Self::WriteHalf)> {
+        Ok(match self {
+            Either::Tcp(stream) => {
+                let (read, write) = stream.into_split().await?;
+                (Either::Tcp(read), Either::Tcp(write))
+            }
+            Either::WebSocket(stream) => {
+                let (read, write) = stream.into_split().await?;
+                (Either::WebSocket(read), Either::WebSocket(write))
+            }
+        })
+    }
+
+    fn peer_addr(&self) -> SocketAddr {
+        match self {
+            Either::Tcp(stream) => stream.peer_addr(),
+            Either::WebSocket(stream) => stream.peer_addr(),
+        }
+    }
+
+    fn local_addr(&self) -> SocketAddr {
+        match self {
+            Either::Tcp(stream) => stream.local_addr(),
+            Either::WebSocket(stream) => stream.local_addr(),
+        }
+    }
+}
+
+#[async_trait]
+impl<T: ReadStream, W: ReadStream> ReadStream for Either<T, W> {
+    async fn read_exact(&mut self, buffer: &mut [u8]) -> io::Result<()> {
+        match self {
+            Either::Tcp(stream) => stream.read_exact(buffer).await,
+            Either::WebSocket(stream) => stream.read_exact(buffer).await,
+        }
+    }
+}
+
+#[async_trait]
+impl<T: WriteStream, W: WriteStream> WriteStream for Either<T, W> {
+    async fn write_all(&mut self, buffer: &[u8]) -> io::Result<()> {
+        match self {
+            Either::Tcp(stream) => stream.write_all(buffer).await,
+            Either::WebSocket(stream) => stream.write_all(buffer).await,
+        }
+    }
+}
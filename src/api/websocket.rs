@@ -0,0 +1,164 @@
+//! a websocket transport for bevy_slinet, so browsers and reverse proxies can connect
+//!
+//! slinet reads length prefixed packets out of a byte stream, every write becomes a binary
+//! message and received messages get buffered until the reader asked for enough bytes
+
+use std::{collections::VecDeque, io, net::SocketAddr};
+
+use async_trait::async_trait;
+use bevy_slinet::protocol::{
+    ClientStream, Listener, NetworkStream, Protocol, ReadStream, ServerStream, WriteStream,
+};
+use futures_util::{
+    stream::{SplitSink, SplitStream},
+    SinkExt, StreamExt,
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::{
+    accept_async, client_async,
+    tungstenite::{Error, Message},
+    MaybeTlsStream, WebSocketStream,
+};
+
+pub struct WebSocketProtocol;
+
+#[async_trait]
+impl Protocol for WebSocketProtocol {
+    type Listener = WebSocketListener;
+    type ServerStream = WebSocketNetworkStream;
+    type ClientStream = WebSocketNetworkStream;
+
+    async fn bind(addr: SocketAddr) -> io::Result<Self::Listener> {
+        Ok(WebSocketListener(TcpListener::bind(addr).await?))
+    }
+}
+
+pub struct WebSocketListener(TcpListener);
+
+#[async_trait]
+impl Listener for WebSocketListener {
+    type Stream = WebSocketNetworkStream;
+
+    async fn accept(&self) -> io::Result<Self::Stream> {
+        let (stream, peer_addr) = self.0.accept().await?;
+        let local_addr = stream.local_addr()?;
+        let socket = accept_async(MaybeTlsStream::Plain(stream))
+            .await
+            .map_err(to_io_error)?;
+        Ok(WebSocketNetworkStream {
+            socket,
+            peer_addr,
+            local_addr,
+        })
+    }
+
+    fn address(&self) -> SocketAddr {
+        self.0
+            .local_addr()
+            .expect("a bound listener has a local address")
+    }
+
+    fn handle_disconnection(&self, _peer_addr: SocketAddr) {}
+}
+
+pub struct WebSocketNetworkStream {
+    socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    peer_addr: SocketAddr,
+    local_addr: SocketAddr,
+}
+
+#[async_trait]
+impl ClientStream for WebSocketNetworkStream {
+    async fn connect(addr: SocketAddr) -> io::Result<Self> {
+        let stream = TcpStream::connect(addr).await?;
+        let local_addr = stream.local_addr()?;
+        let (socket, _) = client_async(format!("ws://{addr}/"), MaybeTlsStream::Plain(stream))
+            .await
+            .map_err(to_io_error)?;
+        Ok(Self {
+            socket,
+            peer_addr: addr,
+            local_addr,
+        })
+    }
+}
+
+impl ServerStream for WebSocketNetworkStream {}
+
+#[async_trait]
+impl NetworkStream for WebSocketNetworkStream {
+    type ReadHalf = WebSocketReadHalf;
+    type WriteHalf = WebSocketWriteHalf;
+
+    async fn into_split(self) -> io::Result<(Self::ReadHalf, Self::WriteHalf)> {
+        let (sink, stream) = self.socket.split();
+        Ok((
+            WebSocketReadHalf {
+                stream,
+                buffer: VecDeque::new(),
+            },
+            WebSocketWriteHalf(sink),
+        ))
+    }
+
+    fn peer_addr(&self) -> SocketAddr {
+        self.peer_addr
+    }
+
+    fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+pub struct WebSocketReadHalf {
+    stream: SplitStream<WebSocketStream<MaybeTlsStream<TcpStream>>>,
+    /// bytes of received messages that weren't read yet
+    buffer: VecDeque<u8>,
+}
+
+#[async_trait]
+impl ReadStream for WebSocketReadHalf {
+    async fn read_exact(&mut self, buffer: &mut [u8]) -> io::Result<()> {
+        while self.buffer.len() < buffer.len() {
+            match self.stream.next().await {
+                Some(Ok(Message::Binary(data))) => self.buffer.extend(data),
+                // pings get answered by tungstenite itself
+                Some(Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_))) => {}
+                Some(Ok(Message::Text(_))) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "expected a binary message",
+                    ))
+                }
+                Some(Ok(Message::Close(_))) | None => {
+                    return Err(io::ErrorKind::UnexpectedEof.into())
+                }
+                Some(Err(err)) => return Err(to_io_error(err)),
+            }
+        }
+        let len = buffer.len();
+        for (byte, buffered) in buffer.iter_mut().zip(self.buffer.drain(..len)) {
+            *byte = buffered;
+        }
+        Ok(())
+    }
+}
+
+pub struct WebSocketWriteHalf(SplitSink<WebSocketStream<MaybeTlsStream<TcpStream>>, Message>);
+
+#[async_trait]
+impl WriteStream for WebSocketWriteHalf {
+    async fn write_all(&mut self, buffer: &[u8]) -> io::Result<()> {
+        self.0
+            .send(Message::Binary(buffer.to_vec()))
+            .await
+            .map_err(to_io_error)
+    }
+}
+
+fn to_io_error(err: Error) -> io::Error {
+    match err {
+        Error::Io(err) => err,
+        err => io::Error::other(err),
+    }
+}
@@ -0,0 +1,137 @@
+//! every packet goes over the wire as a version byte followed by the bincode encoded packet
+//!
+//! bincode isn't self-describing, it encodes enum variants by their position and fields in the
+//! order they are declared, without names. the position of a packet is its tag, so packets are
+//! never reordered or removed and new ones go at the end, right before Unsupported. a peer that
+//! gets a tag it doesn't know decodes it as Unsupported and skips its fields, so a new packet
+//! doesn't break older peers and doesn't bump PROTOCOL_VERSION. every other change to a packet,
+//! or to a type sent inside one, does, new variants of ServerError or any other enum inside a
+//! packet too: only whole packets can be skipped
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::marker::PhantomData;
+use std::{error::Error, fmt};
+
+#[cfg(not(target_arch = "wasm32"))]
+use bevy_slinet::serializer::ReadOnlySerializer;
+use bincode::Options;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// bump after any change to the packets but a new one, see the module docs
+pub const PROTOCOL_VERSION: u8 = 25;
+
+/// the bincode options of both sides, the fields of a packet decoded as Unsupported are left over
+fn options() -> impl Options {
+    bincode::DefaultOptions::new().allow_trailing_bytes()
+}
+
+#[derive(Debug)]
+pub enum WireError {
+    /// the other side speaks a different version of the protocol
+    Version(u8),
+    Empty,
+    Packet(bincode::Error),
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WireError::Version(version) => write!(
+                f,
+                "got a packet with protocol version {version}, expected {PROTOCOL_VERSION}"
+            ),
+            WireError::Empty => write!(f, "got an empty packet"),
+            WireError::Packet(err) => write!(f, "invalid packet: {err}"),
+        }
+    }
+}
+
+impl Error for WireError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            WireError::Packet(err) => Some(err),
+            WireError::Version(_) | WireError::Empty => None,
+        }
+    }
+}
+
+pub fn encode(packet: &impl Serialize) -> Result<Vec<u8>, WireError> {
+    let mut buffer = vec![PROTOCOL_VERSION];
+    options()
+        .serialize_into(&mut buffer, packet)
+        .map_err(WireError::Packet)?;
+    Ok(buffer)
+}
+
+pub fn decode<P: DeserializeOwned>(buffer: &[u8]) -> Result<P, WireError> {
+    match buffer.split_first() {
+        Some((&PROTOCOL_VERSION, packet)) => {
+            options().deserialize(packet).map_err(WireError::Packet)
+        }
+        Some((&version, _)) => Err(WireError::Version(version)),
+        None => Err(WireError::Empty),
+    }
+}
+
+/// the versioned envelope for bevy_slinet
+#[cfg(not(target_arch = "wasm32"))]
+pub struct VersionedSerializer<R, S>(PhantomData<fn(S) -> R>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<R, S> Default for VersionedSerializer<R, S> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<R, S> ReadOnlySerializer<R, S> for VersionedSerializer<R, S>
+where
+    R: DeserializeOwned + 'static,
+    S: Serialize + 'static,
+{
+    type Error = WireError;
+
+    fn serialize(&self, packet: S) -> Result<Vec<u8>, Self::Error> {
+        encode(&packet)
+    }
+
+    fn deserialize(&self, buffer: &[u8]) -> Result<R, Self::Error> {
+        decode(buffer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::{ClientPacket, GameId, ServerPacket};
+
+    #[test]
+    fn unknown_packets_decode_as_unsupported() {
+        // a tag far past the last packet, followed by fields this build knows nothing about
+        let mut buffer = vec![PROTOCOL_VERSION, 200, 7, 1, 2, 3];
+        assert!(matches!(
+            decode::<ClientPacket>(&buffer),
+            Ok(ClientPacket::Unsupported)
+        ));
+        assert!(matches!(
+            decode::<ServerPacket>(&buffer),
+            Ok(ServerPacket::Unsupported)
+        ));
+        buffer[0] = PROTOCOL_VERSION.wrapping_sub(1);
+        assert!(matches!(
+            decode::<ClientPacket>(&buffer),
+            Err(WireError::Version(_))
+        ));
+    }
+
+    #[test]
+    fn packets_round_trip() {
+        let buffer = encode(&ClientPacket::Hint(GameId(9))).unwrap();
+        assert!(matches!(
+            decode::<ClientPacket>(&buffer),
+            Ok(ClientPacket::Hint(GameId(9)))
+        ));
+        assert!(encode(&ClientPacket::Unsupported).is_err());
+    }
+}
@@ -0,0 +1,76 @@
+use bevy::{color::palettes::css as color, prelude::*};
+
+use super::FONT;
+
+/// how long an announcement stays on screen
+const DURATION: f32 = 10.0;
+
+pub struct AnnouncementPlugin;
+
+impl Plugin for AnnouncementPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<AnnouncementEvent>()
+            .add_systems(Update, (spawn_banner, despawn_banner));
+    }
+}
+
+/// a message from the server, shown on top of whatever screen is open
+#[derive(Event, Debug)]
+pub struct AnnouncementEvent(pub String);
+
+#[derive(Component)]
+struct Banner(Timer);
+
+fn spawn_banner(
+    mut commands: Commands,
+    mut events: EventReader<AnnouncementEvent>,
+    query: Query<Entity, With<Banner>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(AnnouncementEvent(message)) = events.read().last() else {
+        return;
+    };
+    // only the newest announcement is shown
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.0),
+                    top: Val::Px(0.0),
+                    justify_content: JustifyContent::Center,
+                    padding: UiRect::all(Val::Px(5.0)),
+                    ..default()
+                },
+                background_color: Color::BLACK.with_alpha(0.7).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            Banner(Timer::from_seconds(DURATION, TimerMode::Once)),
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                message,
+                TextStyle {
+                    font: asset_server.load(FONT),
+                    font_size: 30.0,
+                    color: color::GOLD.into(),
+                },
+            ));
+        });
+}
+
+fn despawn_banner(
+    mut commands: Commands,
+    mut query: Query<(Entity, &mut Banner)>,
+    time: Res<Time>,
+) {
+    for (entity, mut banner) in query.iter_mut() {
+        if banner.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
@@ -0,0 +1,30 @@
+//! the clipboard of the system, browsers don't hand it to the game so there it stays empty
+
+#[cfg(not(target_arch = "wasm32"))]
+use copypasta::{ClipboardContext, ClipboardProvider};
+
+/// the text on the clipboard, None when it can't be read
+#[cfg(not(target_arch = "wasm32"))]
+pub fn get() -> Option<String> {
+    ClipboardContext::new()
+        .and_then(|mut ctx| ctx.get_contents())
+        .ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn get() -> Option<String> {
+    None
+}
+
+/// puts `text` on the clipboard, false when that didn't work
+#[cfg(not(target_arch = "wasm32"))]
+pub fn set(text: String) -> bool {
+    ClipboardContext::new()
+        .and_then(|mut ctx| ctx.set_contents(text))
+        .is_ok()
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn set(_text: String) -> bool {
+    false
+}
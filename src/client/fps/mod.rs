@@ -0,0 +1,93 @@
+use bevy::{
+    color::palettes::css as color,
+    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
+    prelude::*,
+};
+
+use super::{settings::Settings, FONT};
+
+pub struct FpsPlugin;
+
+impl Plugin for FpsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup)
+            .add_systems(Update, (toggle_fps, show_fps, update_fps));
+    }
+}
+
+/// the frames per second, shown on top of whatever screen is open
+#[derive(Component)]
+struct FpsText;
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                width: Val::Percent(100.0),
+                top: Val::Px(0.0),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            z_index: ZIndex::Global(9),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_sections([
+                    TextSection::new(
+                        "FPS: ",
+                        TextStyle {
+                            font: asset_server.load(FONT),
+                            font_size: 30.0,
+                            color: color::WHITE.into(),
+                        },
+                    ),
+                    TextSection::from_style(TextStyle {
+                        font: asset_server.load(FONT),
+                        font_size: 30.0,
+                        color: color::GOLD.into(),
+                    }),
+                ]),
+                FpsText,
+            ));
+        });
+}
+
+fn toggle_fps(keyboard_input: Res<ButtonInput<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keyboard_input.just_pressed(KeyCode::F3) {
+        settings.show_fps = !settings.show_fps;
+    }
+}
+
+fn show_fps(settings: Res<Settings>, mut query: Query<&mut Visibility, With<FpsText>>) {
+    if !settings.is_changed() {
+        return;
+    }
+    for mut visibility in query.iter_mut() {
+        *visibility = if settings.show_fps {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
+fn update_fps(
+    diagnostics: Res<DiagnosticsStore>,
+    settings: Res<Settings>,
+    mut query: Query<&mut Text, With<FpsText>>,
+) {
+    if !settings.show_fps {
+        return;
+    }
+    let Some(value) = diagnostics
+        .get(&FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+    else {
+        return;
+    };
+    for mut text in query.iter_mut() {
+        text.sections[1].value = format!("{value:.2}");
+    }
+}
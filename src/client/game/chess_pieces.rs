@@ -1,13 +1,42 @@
 use crate::api::chessmove::{ChessColor, ChessPiece, ChessPieceType, ChessboardLocation};
 
-use super::{ChessBoardComponent, GameWindow, MoveEvent, OpponentMoveEvent, RedrawBoardEvent};
-use crate::api::chessstate::ChessState;
+use super::{
+    GameWindow, MoveEvent, MoveHistory, OpponentMoveEvent, RedrawBoardEvent, Review, Slide,
+};
+use crate::{
+    api::chessstate::ChessState,
+    client::{networking::GameStart, settings::Settings},
+};
 use bevy::prelude::*;
 
 #[derive(Component)]
 pub struct ChessPieceComponent;
 
-fn chess_piece_to_bundle(chess_piece: ChessPiece, asset_server: &AssetServer) -> SpriteBundle {
+/// the image of a piece in a piece set, every set is a folder in assets/chess
+pub fn piece_texture(set: &str, color: ChessColor, piece: ChessPieceType) -> String {
+    let name = match (color, piece) {
+        (ChessColor::White, ChessPieceType::Pawn) => "white_pawn.png",
+        (ChessColor::Black, ChessPieceType::Pawn) => "black_pawn.png",
+        (ChessColor::White, ChessPieceType::King) => "white_king.png",
+        (ChessColor::Black, ChessPieceType::King) => "black_king.png",
+        (ChessColor::White, ChessPieceType::Knight) => "white_knight.png",
+        (ChessColor::Black, ChessPieceType::Knight) => "black_knight.png",
+        (ChessColor::White, ChessPieceType::Bishop) => "white_bishop.png",
+        (ChessColor::Black, ChessPieceType::Bishop) => "black_bishop.png",
+        (ChessColor::White, ChessPieceType::Rook) => "white_rook.png",
+        (ChessColor::Black, ChessPieceType::Rook) => "black_rook.png",
+        (ChessColor::White, ChessPieceType::Queen) => "white_queen.png",
+        (ChessColor::Black, ChessPieceType::Queen) => "black_queen.png",
+    };
+    format!("chess/{set}/{name}")
+}
+
+fn chess_piece_to_bundle(
+    chess_piece: ChessPiece,
+    asset_server: &AssetServer,
+    settings: &Settings,
+) -> SpriteBundle {
+    let (color, piece) = chess_piece.into();
     SpriteBundle {
         sprite: Sprite {
             custom_size: Some(Vec2::splat(1.0)),
@@ -17,20 +46,7 @@ fn chess_piece_to_bundle(chess_piece: ChessPiece, asset_server: &AssetServer) ->
             translation: Vec3::new(0.0, 0.0, 1.0),
             ..default()
         },
-        texture: asset_server.load(match chess_piece.into() {
-            (ChessColor::White, ChessPieceType::Pawn) => "chess/white_pawn.png",
-            (ChessColor::Black, ChessPieceType::Pawn) => "chess/black_pawn.png",
-            (ChessColor::White, ChessPieceType::King) => "chess/white_king.png",
-            (ChessColor::Black, ChessPieceType::King) => "chess/black_king.png",
-            (ChessColor::White, ChessPieceType::Knight) => "chess/white_knight.png",
-            (ChessColor::Black, ChessPieceType::Knight) => "chess/black_knight.png",
-            (ChessColor::White, ChessPieceType::Bishop) => "chess/white_bishop.png",
-            (ChessColor::Black, ChessPieceType::Bishop) => "chess/black_bishop.png",
-            (ChessColor::White, ChessPieceType::Rook) => "chess/white_rook.png",
-            (ChessColor::Black, ChessPieceType::Rook) => "chess/black_rook.png",
-            (ChessColor::White, ChessPieceType::Queen) => "chess/white_queen.png",
-            (ChessColor::Black, ChessPieceType::Queen) => "chess/black_queen.png",
-        }),
+        texture: asset_server.load(piece_texture(&settings.piece_set, color, piece)),
         ..default()
     }
 }
@@ -39,12 +55,22 @@ pub fn spawn_chess_pieces(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     board_state: Res<ChessState>,
+    settings: Res<Settings>,
+) {
+    spawn_pieces(&mut commands, &board_state, &asset_server, &settings);
+}
+
+fn spawn_pieces(
+    commands: &mut Commands,
+    board_state: &ChessState,
+    asset_server: &AssetServer,
+    settings: &Settings,
 ) {
     for (y, row) in board_state.board.iter().enumerate() {
         for (x, piece) in row.iter().copied().enumerate() {
             if let Some(piece) = piece {
                 commands.spawn((
-                    chess_piece_to_bundle(piece, &asset_server),
+                    chess_piece_to_bundle(piece, asset_server, settings),
                     ChessboardLocation {
                         file: (x as u8).into(),
                         rank: (y as u8).into(),
@@ -57,25 +83,62 @@ pub fn spawn_chess_pieces(
     }
 }
 
+/// puts the pieces of the position being reviewed on the board, the game itself stays as it ended
+pub fn show_review(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+    review: Res<Review>,
+    start: Res<GameStart>,
+    history: Res<MoveHistory>,
+    chess_pieces: Query<Entity, With<ChessPieceComponent>>,
+) {
+    if !review.is_changed() {
+        return;
+    }
+    let Some(ply) = review.0 else {
+        return;
+    };
+    let mut state = start.state;
+    for &played_move in history.iter().take(ply) {
+        if state.play_move(played_move).is_err() {
+            break;
+        }
+    }
+    for piece in chess_pieces.iter() {
+        commands.entity(piece).despawn_recursive();
+    }
+    spawn_pieces(&mut commands, &state, &asset_server, &settings);
+}
+
 /// moves the chess piece visually
 pub fn move_chess_piece(
     mut commands: Commands,
     mut event_reader: EventReader<MoveEvent>,
     mut event_reader2: EventReader<OpponentMoveEvent>,
-    mut query: Query<(&mut ChessboardLocation, Entity), Without<ChessBoardComponent>>,
+    // the highlights, hints and check marker sit on squares too
+    mut query: Query<(&mut ChessboardLocation, Entity, &Transform), With<ChessPieceComponent>>,
+    settings: Res<Settings>,
 ) {
     for chess_move in event_reader
         .read()
         .map(|x| x.0)
         .chain(event_reader2.read().map(|x| x.0))
     {
-        if let Some((_, ent)) = query.iter_mut().find(|x| x.0.as_ref() == &chess_move.to) {
+        if let Some((_, ent, _)) = query.iter_mut().find(|x| x.0.as_ref() == &chess_move.to) {
             commands.entity(ent).despawn_recursive();
         }
-        if let Some((mut location, _)) = query.iter_mut().find(|x| x.0.as_ref() == &chess_move.from)
+        if let Some((mut location, entity, transform)) =
+            query.iter_mut().find(|x| x.0.as_ref() == &chess_move.from)
         {
             info!("moving {:?} to {:?}", chess_move.from, chess_move.to);
             *location = chess_move.to;
+            if let Some(duration) = settings.animation_speed.duration() {
+                commands.entity(entity).insert(Slide {
+                    from: transform.translation.truncate(),
+                    timer: Timer::new(duration, TimerMode::Once),
+                });
+            }
         }
     }
 }
@@ -84,6 +147,7 @@ pub fn respawn_chess_pieces(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     board_state: Res<ChessState>,
+    settings: Res<Settings>,
     chess_pieces: Query<Entity, With<ChessPieceComponent>>,
     mut redraw: EventReader<RedrawBoardEvent>,
 ) {
@@ -92,6 +156,6 @@ pub fn respawn_chess_pieces(
         for piece in chess_pieces.iter() {
             commands.entity(piece).despawn_recursive();
         }
-        spawn_chess_pieces(commands, asset_server, board_state);
+        spawn_chess_pieces(commands, asset_server, board_state, settings);
     }
 }
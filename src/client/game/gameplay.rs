@@ -1,47 +1,87 @@
-use bevy::{prelude::*, window::PrimaryWindow};
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool};
+use bevy::{
+    ecs::system::SystemParam,
+    input::{
+        keyboard::{Key, KeyboardInput},
+        mouse::{MouseScrollUnit, MouseWheel},
+    },
+    prelude::*,
+    sprite::{Anchor, MaterialMesh2dBundle, Mesh2dHandle},
+    window::PrimaryWindow,
+};
 
-use crate::api::{
-    chessmove::{ChessColor, ChessMove, ChessPieceType, ChessboardLocation},
-    chessstate::ChessState,
+use crate::{
+    api::{
+        ai,
+        chessmove::{ChessColor, ChessMove, ChessPieceType, ChessboardLocation},
+        chessstate::ChessState,
+        HINTS, HINT_DEPTH, MAX_CHAT_LENGTH,
+    },
+    client::{
+        clipboard, library::GameRecord, networking::GameStart, settings::Settings, VictoryEvent,
+    },
 };
 
 use super::{
-    ui::{DrawButton, PromotionMenu, PromotionPiece, ResignButton},
-    Highlight, MoveEvent, PromotionEvent, PromotionMoveEvent, RedrawBoardEvent, RequestDrawEvent,
-    ResignEvent, SelectedPiece, TileSize,
+    ui::{
+        hint_label, AbortButton, AcceptDrawButton, AcceptTakebackButton, CopyFenButton,
+        CopyPgnButton, DeclineDrawButton, DeclineTakebackButton, DrawButton, FlipButton,
+        HintButton, MenuButton, MoveListEntry, PasteFenButton, PromotionMenu, PromotionPiece,
+        RematchButton, ResetBoardButton, ResignButton, SavePgnButton, TakebackButton,
+    },
+    AbortEvent, AnalysisBoard, Arrow, ArrowStart, AskHintEvent, BoardOrientation, ChatInput,
+    CheckIndicator, DeclineDrawEvent, GameWindow, Highlight, HintEvent, Hints, KingExposedEvent,
+    LeaveGameEvent, MarkColor, MoveEvent, MoveHint, MoveHistory, OpponentMoveEvent, PromotionEvent,
+    PromotionMoveEvent, RedrawBoardEvent, RematchEvent, RequestDrawEvent, RequestTakebackEvent,
+    ResignEvent, RespondTakebackEvent, Review, SelectedPiece, SendChatEvent, TileSize,
 };
 
+/// the square under the mouse, None when it is off the board
+fn cursor_square(
+    window: &Window,
+    tile_size: f32,
+    orientation: ChessColor,
+) -> Option<ChessboardLocation> {
+    let mut pos = window.cursor_position()?;
+    pos.x -= window.width() / 2.0;
+    pos.y -= window.height() / 2.0;
+    pos = (pos / tile_size + 4.0).floor();
+    if orientation == ChessColor::White {
+        pos.y = 7.0 - pos.y;
+    } else {
+        pos.x = 7.0 - pos.x;
+    }
+    let range = 0.0..7.5;
+    (range.contains(&pos.x) && range.contains(&pos.y))
+        .then(|| ChessboardLocation::new(pos.y as u8, pos.x as u8))
+}
+
 pub fn select_piece(
     window: Query<&Window, With<PrimaryWindow>>,
     mouse_input: Res<ButtonInput<MouseButton>>,
     tile_size: Res<TileSize>,
     color: Res<ChessColor>,
+    orientation: Res<BoardOrientation>,
     mut state: ResMut<ChessState>,
     mut selected_piece: ResMut<SelectedPiece>,
     mut writer: EventWriter<MoveEvent>,
     mut redraw_writer: EventWriter<RedrawBoardEvent>,
     mut promotion_writer: EventWriter<PromotionEvent>,
+    mut exposed_writer: EventWriter<KingExposedEvent>,
+    mut promotion_move_writer: EventWriter<PromotionMoveEvent>,
+    settings: Res<Settings>,
+    review: Res<Review>,
 ) {
-    if !mouse_input.just_pressed(MouseButton::Left) {
+    // the board shows an older position
+    if !mouse_input.just_pressed(MouseButton::Left) || review.is_some() {
         return;
     }
     let window = window.single();
-    let Some(mut pos) = window.cursor_position() else {
+    if window.cursor_position().is_none() {
         return;
-    };
-    pos.x -= window.width() / 2.0;
-    pos.y -= window.height() / 2.0;
-    pos = (pos / tile_size.0 + 4.0).floor();
-    if *color == ChessColor::White {
-        pos.y = 7.0 - pos.y;
-    } else {
-        pos.x = 7.0 - pos.x;
     }
-    let x = pos.x;
-    let y = pos.y;
-    let range = 0.0..7.5;
-    if range.contains(&x) && range.contains(&y) {
-        let location = ChessboardLocation::new(y as u8, x as u8);
+    if let Some(location) = cursor_square(window, tile_size.0, orientation.0) {
         if let Some(piece) = state.get_location(location) {
             if piece.color == *color {
                 // selected square with our piece
@@ -54,13 +94,22 @@ pub fn select_piece(
             // a square was selected before
             if state.turn == *color {
                 let chess_move = ChessMove { from, to: location };
+                if state.exposes_king(chess_move) {
+                    exposed_writer.send(KingExposedEvent);
+                }
                 if let Ok(b) = state.move_piece(chess_move) {
                     writer.send(MoveEvent(chess_move));
                     selected_piece.0 = None;
                     if b {
                         redraw_writer.send(RedrawBoardEvent);
                     }
-                    if state.should_promote {
+                    if !state.should_promote {
+                        return;
+                    }
+                    if settings.auto_queen && state.promote(ChessPieceType::Queen).is_ok() {
+                        promotion_move_writer.send(PromotionMoveEvent(ChessPieceType::Queen));
+                        redraw_writer.send(RedrawBoardEvent);
+                    } else {
                         promotion_writer.send(PromotionEvent);
                     }
                 }
@@ -88,6 +137,578 @@ pub fn highlight_piece(
     }
 }
 
+/// puts a dot on every square the selected piece can move to and a ring around the pieces
+/// it can take, only on your own turn
+pub fn show_move_hints(
+    mut commands: Commands,
+    hints: Query<Entity, With<MoveHint>>,
+    selected_piece: Res<SelectedPiece>,
+    state: Res<ChessState>,
+    color: Res<ChessColor>,
+    settings: Res<Settings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if !selected_piece.is_changed() && !state.is_changed() {
+        return;
+    }
+    for hint in hints.iter() {
+        commands.entity(hint).despawn_recursive();
+    }
+    let Some(from) = selected_piece.0.filter(|_| state.turn == *color) else {
+        return;
+    };
+    let moves: Vec<_> = state
+        .legal_moves()
+        .into_iter()
+        .filter(|chess_move| chess_move.from == from)
+        .collect();
+    if moves.is_empty() {
+        return;
+    }
+    let dot = Mesh2dHandle(meshes.add(Circle::new(0.15)));
+    let ring = Mesh2dHandle(meshes.add(Annulus::new(0.4, 0.5)));
+    let material = materials.add(settings.board_theme.move_hint());
+    for chess_move in moves {
+        // a pawn moving sideways onto an empty square takes en passant
+        let capture = state.get_location(chess_move.to).is_some()
+            || (state
+                .get_location(from)
+                .is_some_and(|piece| piece.piece_type == ChessPieceType::Pawn)
+                && chess_move.from.file != chess_move.to.file);
+        commands.spawn((
+            MaterialMesh2dBundle {
+                mesh: if capture { ring.clone() } else { dot.clone() },
+                material: material.clone(),
+                transform: Transform::from_translation(Vec3::new(0.0, 0.0, 3.0)),
+                ..default()
+            },
+            chess_move.to,
+            MoveHint,
+            GameWindow,
+        ));
+    }
+}
+
+/// right dragging from one square to another draws an arrow between them, or takes it away
+/// when it was there already, a left click clears them all
+pub fn draw_arrows(
+    mut commands: Commands,
+    window: Query<&Window, With<PrimaryWindow>>,
+    arrows: Query<(Entity, &Arrow)>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    tile_size: Res<TileSize>,
+    orientation: Res<BoardOrientation>,
+    mut start: ResMut<ArrowStart>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if mouse_input.just_pressed(MouseButton::Left) {
+        for (entity, _) in arrows.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    let window = window.single();
+    let square = cursor_square(window, tile_size.0, orientation.0);
+    if mouse_input.just_pressed(MouseButton::Right) {
+        start.0 = square;
+    }
+    if !mouse_input.just_released(MouseButton::Right) {
+        return;
+    }
+    let (Some(from), Some(to)) = (start.0.take(), square) else {
+        return;
+    };
+    if from == to {
+        return;
+    }
+    let arrow = Arrow { from, to };
+    if let Some((entity, _)) = arrows.iter().find(|(_, a)| **a == arrow) {
+        commands.entity(entity).despawn_recursive();
+        return;
+    }
+    spawn_arrow(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        arrow,
+        Color::srgba(1.0, 0.6, 0.0, 0.7),
+    );
+}
+
+/// an arrow of `color`, place_arrows puts it on the board
+fn spawn_arrow(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    arrow: Arrow,
+    color: Color,
+) {
+    // in squares, the board turning around doesn't change it
+    let length = Vec2::new(
+        arrow.to.file as u8 as f32 - arrow.from.file as u8 as f32,
+        arrow.to.rank as u8 as f32 - arrow.from.rank as u8 as f32,
+    )
+    .length();
+    const HEAD: f32 = 0.4;
+    commands
+        .spawn((SpatialBundle::default(), arrow, GameWindow))
+        .with_children(|parent| {
+            parent.spawn(SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size: Some(Vec2::new(length - HEAD, 0.15)),
+                    anchor: Anchor::CenterLeft,
+                    ..default()
+                },
+                ..default()
+            });
+            parent.spawn(MaterialMesh2dBundle {
+                mesh: Mesh2dHandle(meshes.add(Triangle2d::new(
+                    Vec2::new(length - HEAD, 0.3),
+                    Vec2::new(length - HEAD, -0.3),
+                    Vec2::new(length, 0.0),
+                ))),
+                material: materials.add(color),
+                ..default()
+            });
+        });
+}
+
+/// asks the server for a move, the analysis board searches one itself in the background.
+/// a game only gets a few hints
+pub fn ask_hint(
+    query: Query<&Interaction, (Changed<Interaction>, With<HintButton>)>,
+    buttons: Query<&Children, With<HintButton>>,
+    mut texts: Query<&mut Text>,
+    mut hints: ResMut<Hints>,
+    mut writer: EventWriter<AskHintEvent>,
+    state: Res<ChessState>,
+    color: Res<ChessColor>,
+    history: Res<MoveHistory>,
+    review: Res<Review>,
+    analysis: Res<AnalysisBoard>,
+) {
+    if !query.iter().any(|&i| i == Interaction::Pressed) {
+        return;
+    }
+    if hints.used >= HINTS
+        || hints.asked == Some(history.len())
+        || state.turn != *color
+        || state.should_promote
+        || review.is_some()
+    {
+        return;
+    }
+    if **analysis {
+        let position = *state;
+        #[cfg(not(target_arch = "wasm32"))]
+        let search = AsyncComputeTaskPool::get()
+            .spawn(async move { ai::search(&position, HINT_DEPTH).line.first().copied() });
+        #[cfg(target_arch = "wasm32")]
+        let search = ai::search(&position, HINT_DEPTH).line.first().copied();
+        hints.search = Some(search);
+    } else {
+        writer.send(AskHintEvent);
+    }
+    hints.asked = Some(history.len());
+    hints.used += 1;
+    for children in buttons.iter() {
+        for &child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(child) {
+                text.sections[0].value = hint_label(hints.used);
+            }
+        }
+    }
+}
+
+/// shows the move a hint found as an arrow, unless a move was played since
+pub fn show_hint(
+    mut commands: Commands,
+    mut hints: ResMut<Hints>,
+    mut received: EventReader<HintEvent>,
+    history: Res<MoveHistory>,
+    settings: Res<Settings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let asked = hints.asked;
+    #[cfg(not(target_arch = "wasm32"))]
+    let searched = hints
+        .search
+        .as_mut()
+        .and_then(|task| block_on(poll_once(task)));
+    #[cfg(target_arch = "wasm32")]
+    let searched = hints.search;
+    if searched.is_some() {
+        hints.search = None;
+    }
+    let Some((ply, found)) = searched.map(|found| (asked, found)).or_else(|| {
+        received
+            .read()
+            .last()
+            .map(|&HintEvent(ply, found)| (Some(ply), found))
+    }) else {
+        return;
+    };
+    if ply != Some(history.len()) {
+        return;
+    }
+    hints.asked = None;
+    let Some(played_move) = found else {
+        return;
+    };
+    spawn_arrow(
+        &mut commands,
+        &mut meshes,
+        &mut materials,
+        Arrow {
+            from: played_move.chess_move.from,
+            to: played_move.chess_move.to,
+        },
+        settings.board_theme.hint_arrow(),
+    );
+}
+
+/// a right click on a square colors it, red or green, blue and yellow while holding shift,
+/// ctrl or alt, clicking it again with the same color takes it away. every move clears them
+pub fn mark_squares(
+    mut commands: Commands,
+    window: Query<&Window, With<PrimaryWindow>>,
+    mut marks: Query<(Entity, &ChessboardLocation, &mut MarkColor, &mut Sprite)>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut moves: EventReader<MoveEvent>,
+    mut opponent_moves: EventReader<OpponentMoveEvent>,
+    tile_size: Res<TileSize>,
+    orientation: Res<BoardOrientation>,
+    start: Res<ArrowStart>,
+) {
+    if moves.read().count() + opponent_moves.read().count() > 0 {
+        for (entity, ..) in marks.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+    if !mouse_input.just_released(MouseButton::Right) {
+        return;
+    }
+    let window = window.single();
+    let Some(square) = cursor_square(window, tile_size.0, orientation.0) else {
+        return;
+    };
+    if start.0 != Some(square) {
+        return;
+    }
+    let pressed = |keys| keyboard_input.any_pressed(keys);
+    let color = if pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]) {
+        MarkColor::Green
+    } else if pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        MarkColor::Blue
+    } else if pressed([KeyCode::AltLeft, KeyCode::AltRight]) {
+        MarkColor::Yellow
+    } else {
+        MarkColor::Red
+    };
+    if let Some((entity, _, mut mark, mut sprite)) = marks
+        .iter_mut()
+        .find(|(_, location, ..)| **location == square)
+    {
+        if *mark == color {
+            commands.entity(entity).despawn_recursive();
+        } else {
+            *mark = color;
+            sprite.color = color.color();
+        }
+        return;
+    }
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: color.color(),
+                custom_size: Some(Vec2::splat(1.0)),
+                ..default()
+            },
+            transform: Transform::from_translation(Vec3::new(0.0, 0.0, 2.5)),
+            ..default()
+        },
+        square,
+        color,
+        GameWindow,
+    ));
+}
+
+/// how long the king square flashes after a move that would leave it in check
+const FLASH_TIME: f32 = 0.6;
+/// how often it turns on and off while flashing
+const FLASHES: f32 = 3.0;
+
+/// keeps the red square on the king of the player in check
+pub fn show_check(
+    mut query: Query<(
+        &mut Visibility,
+        &mut ChessboardLocation,
+        &mut CheckIndicator,
+    )>,
+    mut exposed: EventReader<KingExposedEvent>,
+    state: Res<ChessState>,
+    color: Res<ChessColor>,
+    time: Res<Time>,
+) {
+    let flash = exposed.read().count() > 0;
+    for (mut visibility, mut location, mut indicator) in query.iter_mut() {
+        if flash {
+            indicator.flash = Timer::from_seconds(FLASH_TIME, TimerMode::Once);
+        }
+        indicator.flash.tick(time.delta());
+        let flashing = !indicator.flash.finished();
+        let king = if flashing {
+            state.king_location(*color)
+        } else if state.in_check() {
+            state.king_location(state.turn)
+        } else {
+            None
+        };
+        let Some(king) = king else {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        };
+        location.set_if_neq(king);
+        // on and off a few times
+        let lit = !flashing || (indicator.flash.fraction() * FLASHES).fract() < 0.5;
+        visibility.set_if_neq(if lit {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        });
+    }
+}
+
+/// goes through the moves of a finished game with the arrow keys, home and end jump to the
+/// start and the end, or to a move clicked in the list
+pub fn step_review(
+    mut victory: EventReader<VictoryEvent>,
+    entries: Query<(&Interaction, &MoveListEntry), Changed<Interaction>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    history: Res<MoveHistory>,
+    mut review: ResMut<Review>,
+) {
+    let last = history.len();
+    if victory.read().count() > 0 {
+        review.0 = Some(last);
+    }
+    let Some(mut ply) = review.0 else {
+        return;
+    };
+    if keyboard_input.just_pressed(KeyCode::ArrowLeft) {
+        ply = ply.saturating_sub(1);
+    }
+    if keyboard_input.just_pressed(KeyCode::ArrowRight) {
+        ply = (ply + 1).min(last);
+    }
+    if keyboard_input.just_pressed(KeyCode::Home) {
+        ply = 0;
+    }
+    if keyboard_input.just_pressed(KeyCode::End) {
+        ply = last;
+    }
+    for (&interaction, entry) in entries.iter() {
+        if interaction == Interaction::Pressed {
+            ply = entry.0;
+        }
+    }
+    review.set_if_neq(Review(Some(ply)));
+}
+
+/// turns the board around with the flip button or f2
+pub fn flip_board(
+    query: Query<&Interaction, (Changed<Interaction>, With<FlipButton>)>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut orientation: ResMut<BoardOrientation>,
+) {
+    let clicked = query.iter().any(|&i| i == Interaction::Pressed);
+    if clicked || keyboard_input.just_pressed(KeyCode::F2) {
+        orientation.0 = !orientation.0;
+    }
+}
+
+/// the mouse wheel makes the board bigger or smaller, and keeps that size for the next games
+pub fn zoom_board(mut wheel: EventReader<MouseWheel>, mut settings: ResMut<Settings>) {
+    let steps: f32 = wheel
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            // touchpads scroll a few pixels at a time
+            MouseScrollUnit::Pixel => event.y / 50.0,
+        })
+        .sum();
+    // only a real change counts, the settings are saved and the board resized on every change
+    let size = settings.zoomed(steps);
+    if size != settings.board_size {
+        settings.board_size = size;
+    }
+}
+
+/// puts the game as pgn on the clipboard, to paste it into another chess program
+pub fn copy_pgn(
+    query: Query<&Interaction, (Changed<Interaction>, With<CopyPgnButton>)>,
+    buttons: Query<&Children, With<CopyPgnButton>>,
+    mut texts: Query<&mut Text>,
+    record: GameRecord,
+) {
+    if !query.iter().any(|&i| i == Interaction::Pressed) {
+        return;
+    }
+    let copied = clipboard::set(record.export());
+    for children in buttons.iter() {
+        for &child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(child) {
+                text.sections[0].value =
+                    if copied { "Copied" } else { "Couldn't copy" }.to_string();
+            }
+        }
+    }
+}
+
+/// writes the game as a pgn file to the downloads folder
+pub fn save_pgn(
+    query: Query<&Interaction, (Changed<Interaction>, With<SavePgnButton>)>,
+    buttons: Query<&Children, With<SavePgnButton>>,
+    mut texts: Query<&mut Text>,
+    record: GameRecord,
+) {
+    if !query.iter().any(|&i| i == Interaction::Pressed) {
+        return;
+    }
+    let label = match record.save() {
+        Ok(path) => {
+            info!("saved the game to {}", path.display());
+            "Saved to downloads"
+        }
+        Err(error) => {
+            warn!("couldn't save the game: {}", error);
+            "Couldn't save"
+        }
+    };
+    for children in buttons.iter() {
+        for &child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(child) {
+                text.sections[0].value = label.to_string();
+            }
+        }
+    }
+}
+
+/// the analysis board plays the moves of both sides, so you always play the side to move
+pub fn play_both_sides(
+    mut move_event: EventReader<MoveEvent>,
+    mut promotion_event: EventReader<PromotionMoveEvent>,
+    state: Res<ChessState>,
+    mut color: ResMut<ChessColor>,
+    mut history: ResMut<MoveHistory>,
+) {
+    for event in move_event.read() {
+        history.push(event.0.into());
+    }
+    for event in promotion_event.read() {
+        if let Some(last) = history.last_mut() {
+            last.promotion = Some(event.0);
+        }
+    }
+    color.set_if_neq(state.turn);
+}
+
+/// what changes when the analysis board starts over from another position
+#[derive(SystemParam)]
+pub struct BoardSetup<'w> {
+    start: ResMut<'w, GameStart>,
+    state: ResMut<'w, ChessState>,
+    history: ResMut<'w, MoveHistory>,
+    color: ResMut<'w, ChessColor>,
+    selected_piece: ResMut<'w, SelectedPiece>,
+    redraw: EventWriter<'w, RedrawBoardEvent>,
+}
+
+impl BoardSetup<'_> {
+    /// starts over from `position`, without any moves played
+    fn set(&mut self, position: ChessState) {
+        self.start.state = position;
+        *self.state = position;
+        self.history.clear();
+        *self.color = position.turn;
+        self.selected_piece.0 = None;
+        self.redraw.send(RedrawBoardEvent);
+    }
+}
+
+/// sets up the position of the fen on the clipboard
+pub fn paste_fen(
+    query: Query<&Interaction, (Changed<Interaction>, With<PasteFenButton>)>,
+    buttons: Query<&Children, With<PasteFenButton>>,
+    mut texts: Query<&mut Text>,
+    mut setup: BoardSetup,
+) {
+    if !query.iter().any(|&i| i == Interaction::Pressed) {
+        return;
+    }
+    let position = clipboard::get().and_then(|fen| ChessState::from_fen(fen.trim()));
+    let label = match position {
+        Some(position) => {
+            setup.set(position);
+            "Paste FEN"
+        }
+        None => "No FEN to paste",
+    };
+    for children in buttons.iter() {
+        for &child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(child) {
+                text.sections[0].value = label.to_string();
+            }
+        }
+    }
+}
+
+/// puts the position on the board as fen on the clipboard
+pub fn copy_fen(
+    query: Query<&Interaction, (Changed<Interaction>, With<CopyFenButton>)>,
+    buttons: Query<&Children, With<CopyFenButton>>,
+    mut texts: Query<&mut Text>,
+    start: Res<GameStart>,
+    history: Res<MoveHistory>,
+    state: Res<ChessState>,
+) {
+    if !query.iter().any(|&i| i == Interaction::Pressed) {
+        return;
+    }
+    let fullmove = (history.len() + start.state.turn as usize) / 2 + 1;
+    let copied = clipboard::set(state.fen(fullmove));
+    for children in buttons.iter() {
+        for &child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(child) {
+                text.sections[0].value =
+                    if copied { "Copied" } else { "Couldn't copy" }.to_string();
+            }
+        }
+    }
+}
+
+/// goes back to the position the moves started from, or to the usual starting position when
+/// no moves were played since
+pub fn reset_board(
+    query: Query<&Interaction, (Changed<Interaction>, With<ResetBoardButton>)>,
+    mut setup: BoardSetup,
+) {
+    if !query.iter().any(|&i| i == Interaction::Pressed) {
+        return;
+    }
+    let position = if setup.history.is_empty() {
+        ChessState::default()
+    } else {
+        setup.start.state
+    };
+    setup.set(position);
+}
+
 pub fn resign(
     query: Query<&Interaction, With<ResignButton>>,
     mut event_writer: EventWriter<ResignEvent>,
@@ -99,6 +720,28 @@ pub fn resign(
     }
 }
 
+pub fn back_to_menu(
+    query: Query<&Interaction, (Changed<Interaction>, With<MenuButton>)>,
+    mut event_writer: EventWriter<LeaveGameEvent>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            event_writer.send(LeaveGameEvent);
+        }
+    }
+}
+
+pub fn abort(
+    query: Query<&Interaction, (Changed<Interaction>, With<AbortButton>)>,
+    mut event_writer: EventWriter<AbortEvent>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            event_writer.send(AbortEvent);
+        }
+    }
+}
+
 pub fn request_draw(
     query: Query<&Interaction, With<DrawButton>>,
     mut event_writer: EventWriter<RequestDrawEvent>,
@@ -110,6 +753,99 @@ pub fn request_draw(
     }
 }
 
+/// agreeing to a draw is offering one back
+pub fn accept_draw(
+    query: Query<&Interaction, (Changed<Interaction>, With<AcceptDrawButton>)>,
+    mut event_writer: EventWriter<RequestDrawEvent>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            event_writer.send(RequestDrawEvent);
+        }
+    }
+}
+
+pub fn decline_draw(
+    query: Query<&Interaction, (Changed<Interaction>, With<DeclineDrawButton>)>,
+    mut event_writer: EventWriter<DeclineDrawEvent>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            event_writer.send(DeclineDrawEvent);
+        }
+    }
+}
+
+pub fn request_takeback(
+    query: Query<&Interaction, (Changed<Interaction>, With<TakebackButton>)>,
+    mut event_writer: EventWriter<RequestTakebackEvent>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            event_writer.send(RequestTakebackEvent);
+        }
+    }
+}
+
+pub fn respond_takeback(
+    accept: Query<&Interaction, (Changed<Interaction>, With<AcceptTakebackButton>)>,
+    decline: Query<&Interaction, (Changed<Interaction>, With<DeclineTakebackButton>)>,
+    mut event_writer: EventWriter<RespondTakebackEvent>,
+) {
+    for (&interaction, accepted) in accept
+        .iter()
+        .map(|i| (i, true))
+        .chain(decline.iter().map(|i| (i, false)))
+    {
+        if interaction == Interaction::Pressed {
+            event_writer.send(RespondTakebackEvent(accepted));
+        }
+    }
+}
+
+pub fn rematch(
+    query: Query<&Interaction, (Changed<Interaction>, With<RematchButton>)>,
+    mut event_writer: EventWriter<RematchEvent>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            event_writer.send(RematchEvent);
+        }
+    }
+}
+
+pub fn chat_input(
+    mut keyboard_event: EventReader<KeyboardInput>,
+    mut input: ResMut<ChatInput>,
+    mut writer: EventWriter<SendChatEvent>,
+) {
+    for event in keyboard_event.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Enter => {
+                let message = input.trim().to_string();
+                input.clear();
+                if !message.is_empty() {
+                    writer.send(SendChatEvent(message));
+                }
+            }
+            Key::Backspace => {
+                input.pop();
+            }
+            Key::Escape => input.clear(),
+            Key::Character(str) => input.push_str(str),
+            Key::Space => input.push(' '),
+            _ => {}
+        }
+        // the server drops messages that are too long
+        if input.chars().count() > MAX_CHAT_LENGTH {
+            input.0 = input.chars().take(MAX_CHAT_LENGTH).collect();
+        }
+    }
+}
+
 pub fn clicked_promotion_menu(
     query: Query<(&Interaction, &PromotionPiece), With<PromotionMenu>>,
     mut writer: EventWriter<PromotionMoveEvent>,
@@ -1,11 +1,21 @@
-use bevy::{prelude::*, window::WindowResized};
+use std::time::Duration;
+
+use bevy::{
+    audio::{Pitch, PitchBundle},
+    prelude::*,
+    sprite::Anchor,
+    window::{PrimaryWindow, WindowResized},
+    winit::{UpdateMode, WinitSettings},
+};
 
 use crate::api::{
-    chessmove::{ChessColor, ChessMove, ChessPieceType, ChessboardLocation},
+    analysis::GameAnalysis,
+    chessmove::{ChessColor, ChessMove, ChessPieceType, ChessboardLocation, PlayedMove},
     chessstate::ChessState,
+    GameId, PlayerInfo,
 };
 
-use super::{despawn_screen, GameState};
+use super::{despawn_screen, settings::Settings, GameState, VictoryEvent, FONT};
 
 mod chess_pieces;
 mod gameplay;
@@ -17,40 +27,183 @@ impl Plugin for GamePlugin {
         app.init_resource::<TileSize>()
             .init_resource::<ChessState>()
             .init_resource::<ChessColor>()
+            .init_resource::<BoardOrientation>()
             .init_resource::<SelectedPiece>()
+            .init_resource::<ArrowStart>()
+            .init_resource::<Review>()
+            .init_resource::<Outcome>()
+            .init_resource::<AnalysisBoard>()
+            .init_resource::<Hints>()
+            .init_resource::<MoveHistory>()
+            .init_resource::<RematchOffer>()
+            .init_resource::<ChatInput>()
+            .init_resource::<GameId>()
+            .init_resource::<Players>()
             .add_event::<MoveEvent>()
             .add_event::<OpponentMoveEvent>()
             .add_event::<RedrawBoardEvent>()
             .add_event::<ResignEvent>()
+            .add_event::<LeaveGameEvent>()
+            .add_event::<AbortEvent>()
             .add_event::<RequestDrawEvent>()
             .add_event::<DrawRequestedEvent>()
+            .add_event::<DeclineDrawEvent>()
+            .add_event::<DrawDeclinedEvent>()
+            .add_event::<DrawExpiredEvent>()
+            .add_event::<KingExposedEvent>()
             .add_event::<PromotionEvent>()
             .add_event::<PromotionMoveEvent>()
             .add_event::<OpponentPromotionEvent>()
+            .add_event::<AnalysisEvent>()
+            .add_event::<RequestTakebackEvent>()
+            .add_event::<TakebackRequestedEvent>()
+            .add_event::<RespondTakebackEvent>()
+            .add_event::<TakebackDeclinedEvent>()
+            .add_event::<AskHintEvent>()
+            .add_event::<HintEvent>()
+            .add_event::<RematchEvent>()
+            .add_event::<RematchOfferedEvent>()
+            .add_event::<SendChatEvent>()
+            .add_event::<ChatMessageEvent>()
+            .add_event::<OpponentDisconnectedEvent>()
+            .add_event::<OpponentReconnectedEvent>()
+            .add_event::<SpectatorsEvent>()
             .add_systems(
                 OnEnter(GameState::Gaming),
                 (setup, chess_pieces::spawn_chess_pieces, ui::setup),
             )
+            // a rematch goes from gaming to gaming, so the board gets rebuild here
+            .add_systems(
+                OnTransition {
+                    exited: GameState::Gaming,
+                    entered: GameState::Gaming,
+                },
+                (
+                    despawn_screen::<GameWindow>,
+                    setup,
+                    chess_pieces::spawn_chess_pieces,
+                    ui::setup,
+                )
+                    .chain(),
+            )
             .add_systems(
                 Update,
                 (
                     resize_notifier,
                     gameplay::select_piece.run_if(in_state(GameState::Gaming)),
                     gameplay::highlight_piece.run_if(in_state(GameState::Gaming)),
+                    gameplay::show_move_hints
+                        .after(gameplay::select_piece)
+                        .run_if(in_state(GameState::Gaming)),
+                    gameplay::show_check
+                        .after(gameplay::select_piece)
+                        .run_if(in_state(GameState::Gaming)),
                     gameplay::resign.run_if(in_state(GameState::Gaming)),
+                    gameplay::back_to_menu.run_if(in_state(GameState::Gaming)),
                     gameplay::request_draw.run_if(in_state(GameState::Gaming)),
                     chess_pieces::move_chess_piece.run_if(in_state(GameState::Gaming)),
                     chess_pieces::respawn_chess_pieces.run_if(in_state(GameState::Gaming)),
-                    resize_chessboard.run_if(in_state(GameState::Gaming)),
+                    resize_chessboard
+                        .after(chess_pieces::move_chess_piece)
+                        .run_if(in_state(GameState::Gaming)),
                     ui::turn_notifier.run_if(in_state(GameState::Gaming)),
                     ui::end_game.run_if(in_state(GameState::Gaming)),
                     ui::spawn_draw_message.run_if(in_state(GameState::Gaming)),
                     ui::despawn_messages.run_if(in_state(GameState::Gaming)),
                     ui::spawn_promotion_menu.run_if(in_state(GameState::Gaming)),
                     gameplay::clicked_promotion_menu.run_if(in_state(GameState::Gaming)),
+                    ui::spawn_analysis_report.run_if(in_state(GameState::Gaming)),
+                    ui::opening_notifier.run_if(in_state(GameState::Gaming)),
                 ),
             )
-            .add_systems(OnExit(GameState::Gaming), despawn_screen::<GameWindow>);
+            .add_systems(
+                Update,
+                (
+                    gameplay::accept_draw,
+                    gameplay::decline_draw,
+                    ui::dismiss_draw_message,
+                    ui::spawn_draw_declined_message,
+                    ui::spawn_draw_expired_message,
+                    gameplay::rematch,
+                    ui::spawn_rematch_message,
+                    ui::update_rematch_button,
+                    gameplay::chat_input,
+                    ui::chat_notifier,
+                    ui::latency_notifier,
+                    ui::clock_notifier,
+                    ui::opponent_connection_notifier,
+                    ui::spectator_notifier,
+                    gameplay::abort,
+                    ui::hide_abort_button,
+                    gameplay::flip_board,
+                    gameplay::draw_arrows,
+                    gameplay::mark_squares.before(gameplay::draw_arrows),
+                )
+                    .run_if(in_state(GameState::Gaming)),
+            )
+            // what the settings change about the board
+            .add_systems(
+                Update,
+                (
+                    place_coordinates,
+                    slide_pieces.after(resize_chessboard),
+                    place_arrows,
+                    play_sounds,
+                    gameplay::zoom_board,
+                )
+                    .run_if(in_state(GameState::Gaming)),
+            )
+            .add_systems(
+                Update,
+                (
+                    gameplay::request_takeback,
+                    gameplay::respond_takeback,
+                    ui::spawn_takeback_message,
+                    ui::spawn_takeback_declined_message,
+                )
+                    .run_if(in_state(GameState::Gaming)),
+            )
+            .add_systems(
+                Update,
+                (
+                    gameplay::ask_hint,
+                    gameplay::show_hint.after(gameplay::draw_arrows),
+                )
+                    .run_if(in_state(GameState::Gaming)),
+            )
+            // going through the moves after the game
+            .add_systems(
+                Update,
+                (
+                    gameplay::step_review,
+                    chess_pieces::show_review.after(gameplay::step_review),
+                    ui::spawn_move_list,
+                    ui::highlight_move_list.after(gameplay::step_review),
+                    gameplay::copy_pgn,
+                    gameplay::save_pgn,
+                )
+                    .run_if(in_state(GameState::Gaming)),
+            )
+            // setting up positions and moving both sides
+            .add_systems(
+                Update,
+                (
+                    gameplay::play_both_sides
+                        .after(gameplay::select_piece)
+                        .after(gameplay::clicked_promotion_menu),
+                    gameplay::paste_fen,
+                    gameplay::copy_fen,
+                    gameplay::reset_board,
+                )
+                    .run_if(
+                        in_state(GameState::Gaming).and_then(resource_equals(AnalysisBoard(true))),
+                    ),
+            )
+            .add_systems(Update, limit_frame_rate.run_if(in_state(GameState::Gaming)))
+            .add_systems(
+                OnExit(GameState::Gaming),
+                (despawn_screen::<GameWindow>, reset_game, full_frame_rate),
+            );
     }
 }
 
@@ -63,16 +216,133 @@ pub struct OpponentMoveEvent(pub ChessMove);
 #[derive(Resource, Default, DerefMut, Deref, Debug)]
 pub struct SelectedPiece(pub Option<ChessboardLocation>);
 
+/// the square a right drag started on
+#[derive(Resource, Default, DerefMut, Deref, Debug)]
+pub struct ArrowStart(pub Option<ChessboardLocation>);
+
+/// the number of moves played in the position shown after the game ended,
+/// None while the game goes on
+#[derive(Resource, Default, DerefMut, Deref, Debug, PartialEq, Eq)]
+pub struct Review(pub Option<usize>);
+
+/// how the game ended, None while it goes on
+#[derive(Resource, Default, DerefMut, Deref, Debug)]
+pub struct Outcome(pub Option<VictoryEvent>);
+
+/// a board without an opponent, where you play the moves of both sides
+#[derive(Resource, Default, DerefMut, Deref, Debug, PartialEq, Eq)]
+pub struct AnalysisBoard(pub bool);
+
+/// the hints asked for this game, in games that aren't rated
+#[derive(Resource, Default)]
+pub struct Hints {
+    pub used: u8,
+    /// the number of moves played when the hint was asked for, until it arrives
+    pub asked: Option<usize>,
+    /// the search on the analysis board, the server searches the hints of other games
+    #[cfg(not(target_arch = "wasm32"))]
+    pub search: Option<bevy::tasks::Task<Option<PlayedMove>>>,
+    /// browsers have no threads to search in the background, they search right away
+    #[cfg(target_arch = "wasm32")]
+    pub search: Option<Option<PlayedMove>>,
+}
+
+/// the color at the bottom of the screen, your own until the board gets flipped
+#[derive(Resource, Default, DerefMut, Deref, Debug)]
+pub struct BoardOrientation(pub ChessColor);
+
+/// every move played this game, kept by the networking plugin
+/// and used for things like naming the opening
+#[derive(Resource, Default, DerefMut, Deref, Debug)]
+pub struct MoveHistory(pub Vec<PlayedMove>);
+
+/// whether the opponent offered a rematch after the last game
+#[derive(Resource, Default, DerefMut, Deref, Debug)]
+pub struct RematchOffer(pub bool);
+
+/// who plays white and black
+#[derive(Resource, Default, Debug)]
+pub struct Players {
+    pub white: PlayerInfo,
+    pub black: PlayerInfo,
+    /// the game changes the ratings of both players, so it has no hints
+    pub rated: bool,
+}
+
+impl Players {
+    pub fn get(&self, color: ChessColor) -> &PlayerInfo {
+        match color {
+            ChessColor::White => &self.white,
+            ChessColor::Black => &self.black,
+        }
+    }
+}
+
+/// the chat message being typed
+#[derive(Resource, Default, DerefMut, Deref, Debug)]
+pub struct ChatInput(pub String);
+
 #[derive(Component)]
 pub struct GameWindow;
 
 #[derive(Component)]
 pub struct Highlight;
 
+/// marks a square the selected piece can move to
+#[derive(Component)]
+pub struct MoveHint;
+
+/// turns the square of a king in check red, and flashes when a move would leave yours in check
+#[derive(Component, Default)]
+pub struct CheckIndicator {
+    pub flash: Timer,
+}
+
 #[derive(Component)]
 pub struct ChessBoardComponent;
 
-#[derive(Resource, Default)]
+/// an arrow drawn on the board with the right mouse button
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Arrow {
+    pub from: ChessboardLocation,
+    pub to: ChessboardLocation,
+}
+
+/// the color of a square marked with a right click
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarkColor {
+    Red,
+    Green,
+    Blue,
+    Yellow,
+}
+
+impl MarkColor {
+    pub fn color(self) -> Color {
+        match self {
+            MarkColor::Red => Color::srgba(0.9, 0.2, 0.2, 0.5),
+            MarkColor::Green => Color::srgba(0.2, 0.8, 0.2, 0.5),
+            MarkColor::Blue => Color::srgba(0.2, 0.4, 0.9, 0.5),
+            MarkColor::Yellow => Color::srgba(0.9, 0.8, 0.1, 0.5),
+        }
+    }
+}
+
+/// a letter or number along the edge of the board
+#[derive(Component, Debug, Clone, Copy)]
+enum CoordinateLabel {
+    File(u8),
+    Rank(u8),
+}
+
+/// a piece on its way to the square it moved to
+#[derive(Component)]
+pub struct Slide {
+    pub from: Vec2,
+    pub timer: Timer,
+}
+
+#[derive(Resource, Default, PartialEq)]
 pub struct TileSize(pub f32);
 
 #[derive(Event)]
@@ -81,12 +351,33 @@ pub struct RedrawBoardEvent;
 #[derive(Event)]
 pub struct ResignEvent;
 
+/// going back to the main menu after the game ended
+#[derive(Event)]
+pub struct LeaveGameEvent;
+
+#[derive(Event)]
+pub struct AbortEvent;
+
 #[derive(Event)]
 pub struct RequestDrawEvent;
 
 #[derive(Event)]
 pub struct DrawRequestedEvent;
 
+#[derive(Event)]
+pub struct DeclineDrawEvent;
+
+#[derive(Event)]
+pub struct DrawDeclinedEvent;
+
+/// the draw offer wasn't answered in time, or the opponent moved instead
+#[derive(Event)]
+pub struct DrawExpiredEvent;
+
+/// a move got refused because it would leave your king in check
+#[derive(Event)]
+pub struct KingExposedEvent;
+
 #[derive(Event)]
 pub struct PromotionEvent;
 
@@ -96,8 +387,79 @@ pub struct PromotionMoveEvent(pub ChessPieceType);
 #[derive(Event)]
 pub struct OpponentPromotionEvent;
 
-fn setup(mut commands: Commands) {
-    commands.insert_resource(ChessState::default());
+#[derive(Event)]
+pub struct AnalysisEvent(pub GameAnalysis);
+
+/// asks the opponent to take back your last move
+#[derive(Event)]
+pub struct RequestTakebackEvent;
+
+/// the opponent wants to take back their last move
+#[derive(Event)]
+pub struct TakebackRequestedEvent;
+
+/// true accepts the takeback the opponent asked for
+#[derive(Event)]
+pub struct RespondTakebackEvent(pub bool);
+
+#[derive(Event)]
+pub struct TakebackDeclinedEvent;
+
+/// asks the server for a hint in the game being played
+#[derive(Event)]
+pub struct AskHintEvent;
+
+/// the move the server suggests for the position after this many moves
+#[derive(Event)]
+pub struct HintEvent(pub usize, pub Option<PlayedMove>);
+
+#[derive(Event)]
+pub struct RematchEvent;
+
+#[derive(Event)]
+pub struct RematchOfferedEvent;
+
+#[derive(Event)]
+pub struct SendChatEvent(pub String);
+
+/// the opponent lost their connection, the game waits this many seconds for them
+#[derive(Event)]
+pub struct OpponentDisconnectedEvent(pub u64);
+
+#[derive(Event)]
+pub struct OpponentReconnectedEvent;
+
+/// how many people are watching the game
+#[derive(Event)]
+pub struct SpectatorsEvent(pub u32);
+
+#[derive(Event)]
+pub struct ChatMessageEvent {
+    pub from: ChessColor,
+    pub message: String,
+}
+
+fn setup(
+    mut commands: Commands,
+    window: Query<&Window, With<PrimaryWindow>>,
+    settings: Res<Settings>,
+    asset_server: Res<AssetServer>,
+    color: Res<ChessColor>,
+) {
+    // the window could have changed size while the board wasn't shown
+    if let Ok(window) = window.get_single() {
+        commands.insert_resource(TileSize(tile_size(
+            window.width(),
+            window.height(),
+            settings.board_size,
+        )));
+    }
+    commands.insert_resource(RematchOffer::default());
+    commands.insert_resource(Review::default());
+    commands.insert_resource(Outcome::default());
+    commands.insert_resource(Hints::default());
+    commands.insert_resource(BoardOrientation(*color));
+    commands.insert_resource(ChatInput::default());
 
     // camera
     commands.spawn((
@@ -112,16 +474,13 @@ fn setup(mut commands: Commands) {
     ));
 
     // spawn chessboard
+    let (light, dark) = settings.board_theme.colors();
     for x in 0..8 {
         for y in 0..8 {
             commands.spawn((
                 SpriteBundle {
                     sprite: Sprite {
-                        color: if (x + y) % 2 == 0 {
-                            Color::srgb(0.0, 0.0, 0.0)
-                        } else {
-                            Color::srgb(1.0, 1.0, 1.0)
-                        },
+                        color: if (x + y) % 2 == 0 { dark } else { light },
                         ..default()
                     },
                     ..default()
@@ -136,7 +495,7 @@ fn setup(mut commands: Commands) {
     commands.spawn((
         SpriteBundle {
             sprite: Sprite {
-                color: Color::srgba(1.0, 1.0, 0.0, 0.3),
+                color: settings.board_theme.highlight(),
                 custom_size: Some(Vec2::splat(1.0)),
                 ..default()
             },
@@ -148,34 +507,265 @@ fn setup(mut commands: Commands) {
         Highlight,
         GameWindow,
     ));
+
+    let check = settings.board_theme.check();
+    commands
+        .spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: check,
+                    custom_size: Some(Vec2::splat(1.0)),
+                    ..default()
+                },
+                visibility: Visibility::Hidden,
+                transform: Transform::from_translation(Vec3::new(0.0, 0.0, 2.0)),
+                ..default()
+            },
+            ChessboardLocation::new(0, 0),
+            CheckIndicator::default(),
+            GameWindow,
+        ))
+        .with_children(|parent| {
+            // a frame around the square, for those who can't tell the color from the board
+            const BORDER: f32 = 0.12;
+            let edge = 0.5 - BORDER / 2.0;
+            for (position, size) in [
+                (Vec2::new(0.0, edge), Vec2::new(1.0, BORDER)),
+                (Vec2::new(0.0, -edge), Vec2::new(1.0, BORDER)),
+                (Vec2::new(edge, 0.0), Vec2::new(BORDER, 1.0)),
+                (Vec2::new(-edge, 0.0), Vec2::new(BORDER, 1.0)),
+            ] {
+                parent.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color: check.with_alpha(1.0),
+                        custom_size: Some(size),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(position.extend(0.1)),
+                    ..default()
+                });
+            }
+        });
+
+    if settings.coordinates {
+        for (index, letter) in "abcdefgh".chars().enumerate() {
+            let labels = [
+                (CoordinateLabel::File(index as u8), letter.to_string()),
+                (CoordinateLabel::Rank(index as u8), (index + 1).to_string()),
+            ];
+            for (label, text) in labels {
+                commands.spawn((
+                    Text2dBundle {
+                        text: Text::from_section(
+                            text,
+                            TextStyle {
+                                font: asset_server.load(FONT),
+                                color: Color::srgb(0.5, 0.5, 0.5),
+                                ..default()
+                            },
+                        ),
+                        text_anchor: match label {
+                            CoordinateLabel::File(_) => Anchor::BottomRight,
+                            CoordinateLabel::Rank(_) => Anchor::TopLeft,
+                        },
+                        ..default()
+                    },
+                    label,
+                    GameWindow,
+                ));
+            }
+        }
+    }
+}
+
+/// leaves nothing of the last game behind for the next one
+fn reset_game(mut commands: Commands) {
+    commands.insert_resource(ChessState::default());
+    commands.insert_resource(ChessColor::default());
+    commands.insert_resource(BoardOrientation::default());
+    commands.insert_resource(SelectedPiece::default());
+    commands.insert_resource(ArrowStart::default());
+    commands.insert_resource(Review::default());
+    commands.insert_resource(Outcome::default());
+    commands.insert_resource(AnalysisBoard::default());
+    commands.insert_resource(Hints::default());
+    commands.insert_resource(MoveHistory::default());
+    commands.insert_resource(RematchOffer::default());
+    commands.insert_resource(ChatInput::default());
+    commands.insert_resource(GameId::default());
+    commands.insert_resource(Players::default());
+    commands.insert_resource(TileSize::default());
+}
+
+/// how long the game waits for input between frames while waiting on the opponent, the
+/// clocks and the packets of the server still get looked at this often
+const IDLE_WAIT: Duration = Duration::from_millis(100);
+
+/// draws only a few frames while it's the opponent's turn and nothing slides over the board,
+/// moving the mouse or a key press still draws one right away
+fn limit_frame_rate(
+    mut winit: ResMut<WinitSettings>,
+    slides: Query<(), With<Slide>>,
+    state: Res<ChessState>,
+    color: Res<ChessColor>,
+) {
+    let mode = if state.turn != *color && slides.is_empty() {
+        UpdateMode::reactive_low_power(IDLE_WAIT)
+    } else {
+        UpdateMode::Continuous
+    };
+    if winit.focused_mode != mode {
+        winit.focused_mode = mode;
+    }
 }
 
-fn resize_notifier(mut resize_event: EventReader<WindowResized>, mut tile_size: ResMut<TileSize>) {
-    const BOARD_SIZE: f32 = 0.10;
+/// the menus draw every frame again
+fn full_frame_rate(mut winit: ResMut<WinitSettings>) {
+    winit.focused_mode = UpdateMode::Continuous;
+}
+
+/// the size of a square in a window this big, with the board filling `board_size` of it
+fn tile_size(width: f32, height: f32, board_size: f32) -> f32 {
+    width.min(height) * board_size / 8.0
+}
+
+fn resize_notifier(
+    mut resize_event: EventReader<WindowResized>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    settings: Res<Settings>,
+    mut tile_size: ResMut<TileSize>,
+) {
     for e in resize_event.read() {
-        tile_size.0 = e.width.min(e.height) * BOARD_SIZE;
+        tile_size.0 = self::tile_size(e.width, e.height, settings.board_size);
+    }
+    if settings.is_changed() {
+        if let Ok(window) = window.get_single() {
+            tile_size.set_if_neq(TileSize(self::tile_size(
+                window.width(),
+                window.height(),
+                settings.board_size,
+            )));
+        }
+    }
+}
+
+/// the middle of a square on screen
+fn square_position(location: ChessboardLocation, color: ChessColor, tile_size: f32) -> Vec2 {
+    let (file, rank) = (location.file as u8 as f32, location.rank as u8 as f32);
+    match color {
+        ChessColor::White => Vec2::new(-3.5 + file, -3.5 + rank) * tile_size,
+        ChessColor::Black => Vec2::new(3.5 - file, 3.5 - rank) * tile_size,
     }
 }
 
 fn resize_chessboard(
     mut chessboard: Query<(&mut Transform, &mut ChessboardLocation)>,
-    color: Res<ChessColor>,
+    orientation: Res<BoardOrientation>,
     tile_size: Res<TileSize>,
 ) {
     for (mut sprite, location) in chessboard.iter_mut() {
-        if !(location.is_changed() || tile_size.is_changed()) {
+        if !(location.is_changed() || tile_size.is_changed() || orientation.is_changed()) {
             continue;
         }
         sprite.scale = Vec3::splat(tile_size.0);
-        match *color {
-            ChessColor::White => {
-                sprite.translation.x = (-3.5 + location.file as u8 as f32) * tile_size.0;
-                sprite.translation.y = (-3.5 + location.rank as u8 as f32) * tile_size.0;
+        let position = square_position(*location, orientation.0, tile_size.0);
+        sprite.translation.x = position.x;
+        sprite.translation.y = position.y;
+    }
+}
+
+/// puts the files under the bottom row and the ranks in the corners of the left column
+fn place_coordinates(
+    mut labels: Query<(&mut Transform, &mut Text, &CoordinateLabel)>,
+    added: Query<(), Added<CoordinateLabel>>,
+    orientation: Res<BoardOrientation>,
+    tile_size: Res<TileSize>,
+) {
+    if !tile_size.is_changed() && !orientation.is_changed() && added.is_empty() {
+        return;
+    }
+    // the edge closest to the player
+    let edge = match orientation.0 {
+        ChessColor::White => 0,
+        ChessColor::Black => 7,
+    };
+    for (mut transform, mut text, &label) in labels.iter_mut() {
+        let (location, corner) = match label {
+            CoordinateLabel::File(file) => {
+                (ChessboardLocation::new(edge, file), Vec2::new(0.48, -0.48))
             }
-            ChessColor::Black => {
-                sprite.translation.x = (3.5 - location.file as u8 as f32) * tile_size.0;
-                sprite.translation.y = (3.5 - location.rank as u8 as f32) * tile_size.0;
+            CoordinateLabel::Rank(rank) => {
+                (ChessboardLocation::new(rank, edge), Vec2::new(-0.48, 0.48))
             }
+        };
+        let position = square_position(location, orientation.0, tile_size.0) + corner * tile_size.0;
+        transform.translation = position.extend(1.5);
+        text.sections[0].style.font_size = tile_size.0 * 0.25;
+    }
+}
+
+/// points the arrows from the middle of one square to the other, above the pieces
+fn place_arrows(
+    mut arrows: Query<(&mut Transform, &Arrow)>,
+    added: Query<(), Added<Arrow>>,
+    orientation: Res<BoardOrientation>,
+    tile_size: Res<TileSize>,
+) {
+    if !tile_size.is_changed() && !orientation.is_changed() && added.is_empty() {
+        return;
+    }
+    for (mut transform, arrow) in arrows.iter_mut() {
+        let from = square_position(arrow.from, orientation.0, tile_size.0);
+        let to = square_position(arrow.to, orientation.0, tile_size.0);
+        let direction = to - from;
+        *transform = Transform::from_translation(from.extend(4.0))
+            .with_rotation(Quat::from_rotation_z(direction.y.atan2(direction.x)))
+            .with_scale(Vec3::splat(tile_size.0));
+    }
+}
+
+/// moves pieces to their new square a bit at a time
+fn slide_pieces(
+    mut commands: Commands,
+    mut pieces: Query<(Entity, &mut Transform, &ChessboardLocation, &mut Slide)>,
+    orientation: Res<BoardOrientation>,
+    tile_size: Res<TileSize>,
+    time: Res<Time>,
+) {
+    for (entity, mut transform, location, mut slide) in pieces.iter_mut() {
+        slide.timer.tick(time.delta());
+        let target = square_position(*location, orientation.0, tile_size.0);
+        let position = slide.from.lerp(target, slide.timer.fraction());
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+        if slide.timer.finished() {
+            commands.entity(entity).remove::<Slide>();
         }
     }
 }
+
+/// a short beep for every move, and a higher one when the game ends
+fn play_sounds(
+    mut commands: Commands,
+    mut moves: EventReader<MoveEvent>,
+    mut opponent_moves: EventReader<OpponentMoveEvent>,
+    mut victory: EventReader<VictoryEvent>,
+    settings: Res<Settings>,
+    mut pitches: ResMut<Assets<Pitch>>,
+) {
+    // the events are read either way, so turning sounds on doesn't play old ones
+    let beeps = [
+        (moves.read().count() > 0, 440.0, 60),
+        (opponent_moves.read().count() > 0, 330.0, 60),
+        (victory.read().count() > 0, 660.0, 300),
+    ];
+    if !settings.sounds {
+        return;
+    }
+    for (_, frequency, millis) in beeps.into_iter().filter(|&(happened, _, _)| happened) {
+        commands.spawn(PitchBundle {
+            source: pitches.add(Pitch::new(frequency, Duration::from_millis(millis))),
+            settings: PlaybackSettings::DESPAWN,
+        });
+    }
+}
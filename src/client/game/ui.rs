@@ -2,29 +2,126 @@ use bevy::color::palettes::css as color;
 use bevy::prelude::*;
 
 use super::{
-    DrawRequestedEvent, GameWindow, MoveEvent, OpponentMoveEvent, OpponentPromotionEvent,
-    PromotionEvent, PromotionMoveEvent, RedrawBoardEvent, TileSize,
+    chess_pieces::piece_texture, AnalysisBoard, AnalysisEvent, ChatInput, ChatMessageEvent,
+    DeclineDrawEvent, DrawDeclinedEvent, DrawExpiredEvent, DrawRequestedEvent, GameWindow,
+    MoveEvent, MoveHistory, OpponentDisconnectedEvent, OpponentMoveEvent, OpponentPromotionEvent,
+    OpponentReconnectedEvent, Outcome, Players, PromotionEvent, PromotionMoveEvent,
+    RedrawBoardEvent, RematchEvent, RematchOffer, RematchOfferedEvent, RequestDrawEvent,
+    RespondTakebackEvent, Review, SendChatEvent, SpectatorsEvent, TakebackDeclinedEvent,
+    TakebackRequestedEvent, TileSize,
 };
 use crate::{
-    api::{chessmove::ChessColor, chessstate::ChessState, EndReason},
-    client::{VictoryEvent, FONT},
+    api::{
+        analysis::MoveQuality,
+        chessmove::{ChessColor, ChessPieceType},
+        chessstate::ChessState,
+        openings, EndReason, PlayerInfo, HINTS,
+    },
+    client::{
+        networking::{GameClock, GameStart, Latency},
+        settings::Settings,
+        VictoryEvent, FONT,
+    },
 };
 
 #[derive(Component)]
 pub struct ResignButton;
 
+/// takes the place of the resign button once the game is over
+#[derive(Component)]
+pub struct MenuButton;
+
 #[derive(Component)]
 pub struct DrawButton;
 
+#[derive(Component)]
+pub struct AbortButton;
+
+#[derive(Component)]
+pub struct FlipButton;
+
 #[derive(Component)]
 pub struct TurnText;
 
 #[derive(Component)]
 pub struct DrawText;
 
+/// the result in the middle of the screen
+#[derive(Component)]
+pub struct EndText;
+
+/// a move in the list shown after the game, clicking it shows the position after it
+#[derive(Component)]
+pub struct MoveListEntry(pub usize);
+
+#[derive(Component)]
+pub struct AcceptDrawButton;
+
+#[derive(Component)]
+pub struct DeclineDrawButton;
+
+#[derive(Component)]
+pub struct TakebackButton;
+
+#[derive(Component)]
+pub struct AcceptTakebackButton;
+
+#[derive(Component)]
+pub struct DeclineTakebackButton;
+
+#[derive(Component)]
+pub struct RematchButton;
+
+/// puts the game as pgn on the clipboard
+#[derive(Component)]
+pub struct CopyPgnButton;
+
+/// writes the game as pgn to the downloads folder
+#[derive(Component)]
+pub struct SavePgnButton;
+
+/// shows the move the computer would play, in games that aren't rated
+#[derive(Component)]
+pub struct HintButton;
+
+/// sets up the position of the fen on the clipboard on the analysis board
+#[derive(Component)]
+pub struct PasteFenButton;
+
+#[derive(Component)]
+pub struct CopyFenButton;
+
+/// starts the analysis board over
+#[derive(Component)]
+pub struct ResetBoardButton;
+
 #[derive(Component)]
 pub struct SurrenderText;
 
+#[derive(Component)]
+pub struct OpeningText;
+
+#[derive(Component)]
+pub struct LatencyText;
+
+#[derive(Component)]
+pub struct ClockText;
+
+#[derive(Component)]
+pub struct ConnectionStatusText;
+
+#[derive(Component)]
+pub struct SpectatorText;
+
+#[derive(Component)]
+pub struct ChatText;
+
+#[derive(Component)]
+pub struct ChatInputText;
+
+/// how many chat messages stay on screen
+const CHAT_LINES: usize = 8;
+
 #[derive(Component, Clone, Copy)]
 pub enum PromotionPiece {
     Queen,
@@ -36,14 +133,64 @@ pub enum PromotionPiece {
 #[derive(Component)]
 pub struct PromotionMenu;
 
-pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>, color: Res<ChessColor>) {
+/// whose turn it is, on the analysis board you play both sides
+fn turn_label(turn: ChessColor, color: ChessColor, analysis: bool) -> (String, Color) {
+    if analysis {
+        let side = if turn == ChessColor::White {
+            "white"
+        } else {
+            "black"
+        };
+        (format!("{side} to move"), color::INDIGO.into())
+    } else if turn == color {
+        (String::from("it's your turn"), color::INDIGO.into())
+    } else {
+        (String::from("it's the opponents turn"), color::GRAY.into())
+    }
+}
+
+pub fn hint_label(used: u8) -> String {
+    match HINTS.saturating_sub(used) {
+        0 => "No hints left".to_string(),
+        left => format!("Hint ({left} left)"),
+    }
+}
+
+/// what goes after a player's name, bots have no rating
+fn rating_label(player: &PlayerInfo) -> String {
+    if player.bot {
+        return " (bot)".to_string();
+    }
+    player
+        .rating
+        .map_or_else(String::new, |rating| format!(" ({rating})"))
+}
+
+pub fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    color: Res<ChessColor>,
+    players: Res<Players>,
+    state: Res<ChessState>,
+    analysis: Res<AnalysisBoard>,
+) {
     // color notifier
     commands.spawn((
         TextBundle::from_section(
-            if *color == ChessColor::White {
-                "you are white"
+            if analysis.0 {
+                "analysis board".to_string()
             } else {
-                "you are black"
+                format!(
+                    "you are {}{} against {}{}",
+                    if *color == ChessColor::White {
+                        "white"
+                    } else {
+                        "black"
+                    },
+                    rating_label(players.get(*color)),
+                    players.get(!*color).name,
+                    rating_label(players.get(!*color))
+                )
             },
             TextStyle {
                 font: asset_server.load(FONT),
@@ -63,21 +210,14 @@ pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>, color: Res<
     ));
 
     // turn notifier
+    let (turn, turn_color) = turn_label(state.turn, *color, analysis.0);
     commands.spawn((
         TextBundle::from_section(
-            if *color == ChessColor::White {
-                "it's your turn"
-            } else {
-                "it's the opponents turn"
-            },
+            turn,
             TextStyle {
                 font: asset_server.load(FONT),
                 font_size: 50.0,
-                color: if *color == ChessColor::White {
-                    color::INDIGO.into()
-                } else {
-                    color::GRAY.into()
-                },
+                color: turn_color,
             },
         )
         .with_style(Style {
@@ -90,6 +230,178 @@ pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>, color: Res<
         GameWindow,
     ));
 
+    // opening name
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(5.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            GameWindow,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load(FONT),
+                        font_size: 30.0,
+                        color: color::BLACK.into(),
+                    },
+                ),
+                OpeningText,
+            ));
+        });
+
+    // clocks
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(40.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            GameWindow,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load(FONT),
+                        font_size: 30.0,
+                        color: color::BLACK.into(),
+                    },
+                ),
+                ClockText,
+            ));
+        });
+
+    // latency
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load(FONT),
+                font_size: 20.0,
+                color: color::BLACK.into(),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(15.0),
+            top: Val::Px(60.0),
+            ..default()
+        }),
+        LatencyText,
+        GameWindow,
+    ));
+
+    // spectators
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load(FONT),
+                font_size: 20.0,
+                color: color::BLACK.into(),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(15.0),
+            top: Val::Px(85.0),
+            ..default()
+        }),
+        SpectatorText,
+        GameWindow,
+    ));
+
+    // the analysis board has nobody to talk to or to offer anything
+    if analysis.0 {
+        commands
+            .spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        top: Val::Px(15.0),
+                        right: Val::Px(15.0),
+                        display: Display::Flex,
+                        flex_direction: FlexDirection::Column,
+                        ..default()
+                    },
+                    background_color: color::MIDNIGHT_BLUE.into(),
+                    ..default()
+                },
+                GameWindow,
+            ))
+            .with_children(|parent| {
+                spawn_side_button(parent, &asset_server, "Back to menu", MenuButton);
+                spawn_side_button(parent, &asset_server, "Paste FEN", PasteFenButton);
+                spawn_side_button(parent, &asset_server, "Copy FEN", CopyFenButton);
+                spawn_side_button(parent, &asset_server, "Copy PGN", CopyPgnButton);
+                spawn_side_button(parent, &asset_server, "Reset", ResetBoardButton);
+                spawn_side_button(parent, &asset_server, &hint_label(0), HintButton);
+                spawn_side_button(parent, &asset_server, "Flip", FlipButton);
+            });
+        return;
+    }
+
+    // chat
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Percent(30.0),
+                    left: Val::Px(15.0),
+                    max_width: Val::Percent(25.0),
+                    display: Display::Flex,
+                    flex_direction: FlexDirection::Column,
+                    padding: UiRect::all(Val::Px(5.0)),
+                    ..default()
+                },
+                background_color: color::MIDNIGHT_BLUE.with_alpha(0.5).into(),
+                ..default()
+            },
+            GameWindow,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load(FONT),
+                        font_size: 20.0,
+                        color: color::ALICE_BLUE.into(),
+                    },
+                ),
+                ChatText,
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    "> ",
+                    TextStyle {
+                        font: asset_server.load(FONT),
+                        font_size: 20.0,
+                        color: color::GOLD.into(),
+                    },
+                ),
+                ChatInputText,
+            ));
+        });
+
     // resign and draw buttons
     commands
         .spawn((
@@ -163,48 +475,318 @@ pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>, color: Res<
                         },
                     ));
                 });
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            position_type: PositionType::Relative,
+                            display: Display::Flex,
+                            margin: UiRect::all(Val::Px(10.0)),
+                            padding: UiRect::all(Val::Px(10.0)),
+                            ..default()
+                        },
+                        background_color: color::BLUE.into(),
+                        ..default()
+                    },
+                    TakebackButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Takeback",
+                        TextStyle {
+                            font: asset_server.load(FONT),
+                            font_size: 30.0,
+                            color: color::ALICE_BLUE.into(),
+                        },
+                    ));
+                });
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            position_type: PositionType::Relative,
+                            display: Display::Flex,
+                            margin: UiRect::all(Val::Px(10.0)),
+                            padding: UiRect::all(Val::Px(10.0)),
+                            ..default()
+                        },
+                        background_color: color::BLUE.into(),
+                        ..default()
+                    },
+                    FlipButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Flip",
+                        TextStyle {
+                            font: asset_server.load(FONT),
+                            font_size: 30.0,
+                            color: color::ALICE_BLUE.into(),
+                        },
+                    ));
+                });
+        })
+        .with_children(|parent| {
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            position_type: PositionType::Relative,
+                            display: Display::Flex,
+                            margin: UiRect::all(Val::Px(10.0)),
+                            padding: UiRect::all(Val::Px(10.0)),
+                            ..default()
+                        },
+                        background_color: color::BLUE.into(),
+                        ..default()
+                    },
+                    AbortButton,
+                ))
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(
+                        "Abort",
+                        TextStyle {
+                            font: asset_server.load(FONT),
+                            font_size: 30.0,
+                            color: color::ALICE_BLUE.into(),
+                        },
+                    ));
+                });
+            // hints would make ratings meaningless
+            if !players.rated {
+                spawn_side_button(parent, &asset_server, &hint_label(0), HintButton);
+            }
         });
 }
 
+/// a game can only be aborted until both players moved
+pub fn hide_abort_button(
+    mut commands: Commands,
+    query: Query<Entity, With<AbortButton>>,
+    start: Res<GameStart>,
+    history: Res<MoveHistory>,
+) {
+    if start.current_ply(&history) < 2 {
+        return;
+    }
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
 pub fn turn_notifier(
     mut turn_text: Query<&mut Text, With<TurnText>>,
     event_reader: EventReader<OpponentMoveEvent>,
     event_reader2: EventReader<MoveEvent>,
     event_reader3: EventReader<OpponentPromotionEvent>,
     event_reader4: EventReader<PromotionMoveEvent>,
+    event_reader5: EventReader<RedrawBoardEvent>,
     state: Res<ChessState>,
     color: Res<ChessColor>,
+    analysis: Res<AnalysisBoard>,
 ) {
     if !event_reader.is_empty()
         || !event_reader2.is_empty()
         || !event_reader3.is_empty()
         || !event_reader4.is_empty()
+        || !event_reader5.is_empty()
     {
         for text in turn_text.iter_mut() {
             let t = text.into_inner();
-            let (text, c) = if state.turn == *color {
-                (String::from("it's your turn"), color::INDIGO.into())
-            } else {
-                (String::from("it's the opponents turn"), color::GRAY.into())
-            };
+            let (text, c) = turn_label(state.turn, *color, analysis.0);
             t.sections[0].value = text;
             t.sections[0].style.color = c;
         }
     }
 }
 
+pub fn opening_notifier(
+    mut opening_text: Query<&mut Text, With<OpeningText>>,
+    history: Res<MoveHistory>,
+) {
+    if !history.is_changed() {
+        return;
+    }
+    let opening = openings::classify(&history)
+        .map(|opening| format!("{} {}", opening.eco, opening.name))
+        .unwrap_or_default();
+    for mut text in opening_text.iter_mut() {
+        text.sections[0].value.clone_from(&opening);
+    }
+}
+
+pub fn clock_notifier(
+    clock: Res<GameClock>,
+    time: Res<Time>,
+    mut query: Query<&mut Text, With<ClockText>>,
+) {
+    let format = |color| {
+        clock
+            .remaining(color, time.elapsed())
+            .map(|left| match left.as_secs() {
+                // correspondence clocks run in days
+                secs @ 86400.. => format!("{}d {}h", secs / 86400, secs % 86400 / 3600),
+                secs => format!("{}:{:02}", secs / 60, secs % 60),
+            })
+    };
+    let value = match (format(ChessColor::White), format(ChessColor::Black)) {
+        (Some(white), Some(black)) => format!("white {} - {} black", white, black),
+        _ => String::new(),
+    };
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != value {
+            text.sections[0].value.clone_from(&value);
+        }
+    }
+}
+
+pub fn latency_notifier(latency: Res<Latency>, mut query: Query<&mut Text, With<LatencyText>>) {
+    let (true, Some(latency)) = (latency.is_changed(), latency.0) else {
+        return;
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!("ping {} ms", latency.as_millis());
+    }
+}
+
+pub fn spectator_notifier(
+    mut events: EventReader<SpectatorsEvent>,
+    mut query: Query<&mut Text, With<SpectatorText>>,
+) {
+    let Some(SpectatorsEvent(count)) = events.read().last() else {
+        return;
+    };
+    let value = match count {
+        0 => String::new(),
+        1 => "1 person watching".to_string(),
+        count => format!("{count} people watching"),
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value.clone_from(&value);
+    }
+}
+
+pub fn opponent_connection_notifier(
+    mut commands: Commands,
+    mut disconnected: EventReader<OpponentDisconnectedEvent>,
+    mut reconnected: EventReader<OpponentReconnectedEvent>,
+    mut victory: EventReader<VictoryEvent>,
+    query: Query<Entity, With<ConnectionStatusText>>,
+    asset_server: Res<AssetServer>,
+) {
+    // the message goes away once the opponent is back or the game is over
+    if reconnected.read().count() + victory.read().count() > 0 {
+        for entity in query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    for event in disconnected.read() {
+        commands.spawn((
+            TextBundle::from_section(
+                format!("Your opponent lost connection, waiting {}s...", event.0),
+                TextStyle {
+                    font: asset_server.load(FONT),
+                    font_size: 30.0,
+                    color: color::ORANGE_RED.into(),
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(50.0),
+                right: Val::Px(15.0),
+                ..default()
+            }),
+            ConnectionStatusText,
+            GameWindow,
+        ));
+    }
+}
+
+pub fn chat_notifier(
+    mut sent: EventReader<SendChatEvent>,
+    mut received: EventReader<ChatMessageEvent>,
+    input: Res<ChatInput>,
+    color: Res<ChessColor>,
+    mut log: Query<&mut Text, (With<ChatText>, Without<ChatInputText>)>,
+    mut input_text: Query<&mut Text, With<ChatInputText>>,
+) {
+    let lines: Vec<String> = sent
+        .read()
+        .map(|event| format!("you: {}", event.0))
+        .chain(received.read().map(|event| {
+            format!(
+                "{}: {}",
+                if event.from == *color {
+                    "you"
+                } else {
+                    "opponent"
+                },
+                event.message
+            )
+        }))
+        .collect();
+    if !lines.is_empty() {
+        for mut text in log.iter_mut() {
+            let value = &mut text.sections[0].value;
+            let mut all: Vec<&str> = value.lines().collect();
+            all.extend(lines.iter().map(String::as_str));
+            let start = all.len().saturating_sub(CHAT_LINES);
+            *value = all[start..].join("\n");
+        }
+    }
+    if input.is_changed() {
+        for mut text in input_text.iter_mut() {
+            text.sections[0].value = format!("> {}", input.0);
+        }
+    }
+}
+
 pub fn end_game(
     mut commands: Commands,
     mut event_reader: EventReader<VictoryEvent>,
     mut query: Query<&mut Text, With<SurrenderText>>,
+    resign_button: Query<(Entity, &Parent), With<ResignButton>>,
+    draw_button: Query<Entity, With<DrawButton>>,
+    takeback_button: Query<Entity, With<TakebackButton>>,
+    abort_button: Query<Entity, With<AbortButton>>,
+    hint_button: Query<Entity, With<HintButton>>,
     size: Res<TileSize>,
     asset_server: Res<AssetServer>,
+    mut outcome: ResMut<Outcome>,
 ) {
     for &victory in event_reader.read() {
+        outcome.0 = Some(victory);
         for text in query.iter_mut() {
-            text.into_inner().sections[0].value = "Exit".to_string();
+            text.into_inner().sections[0].value = "Back to menu".to_string();
+        }
+        // the draw button makes place for a rematch button and the ways to export the game
+        for entity in draw_button
+            .iter()
+            .chain(takeback_button.iter())
+            .chain(abort_button.iter())
+            .chain(hint_button.iter())
+        {
+            commands.entity(entity).despawn_recursive();
+        }
+        for (button, parent) in resign_button.iter() {
+            commands
+                .entity(button)
+                .remove::<ResignButton>()
+                .insert(MenuButton);
+            commands.entity(parent.get()).with_children(|parent| {
+                spawn_side_button(parent, &asset_server, "Rematch", RematchButton);
+                spawn_side_button(parent, &asset_server, "Copy PGN", CopyPgnButton);
+                spawn_side_button(parent, &asset_server, "Save PGN", SavePgnButton);
+            });
         }
         let (mut msg, reason) = match victory {
+            VictoryEvent::Draw(EndReason::Aborted) => {
+                ("Game aborted".to_string(), EndReason::Aborted)
+            }
             VictoryEvent::Win(reason) => ("You Win!".to_string(), reason),
             VictoryEvent::Draw(reason) => ("It's a draw".to_string(), reason),
             VictoryEvent::Loss(reason) => ("You lose...".to_string(), reason),
@@ -218,6 +800,8 @@ pub fn end_game(
             EndReason::InsufficientMaterial => "of insufficient material",
             EndReason::FiftyMoveRule => "of the fifty move rule",
             EndReason::RepetitionOfMoves => "of a repetition of moves",
+            EndReason::Aborted => "it was called off before it counted",
+            EndReason::Timeout => "a clock ran out",
         });
         // all this boilerplate for centering some text (css reference)
         commands
@@ -233,6 +817,7 @@ pub fn end_game(
                     },
                     ..default()
                 },
+                EndText,
                 GameWindow,
             ))
             .with_children(|parent| {
@@ -256,15 +841,359 @@ pub fn end_game(
     }
 }
 
+/// a button in the column at the right of the board
+fn spawn_side_button(
+    parent: &mut ChildBuilder,
+    asset_server: &AssetServer,
+    label: &str,
+    button: impl Component,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Relative,
+                    display: Display::Flex,
+                    margin: UiRect::all(Val::Px(10.0)),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: color::BLUE.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font: asset_server.load(FONT),
+                    font_size: 30.0,
+                    color: color::ALICE_BLUE.into(),
+                },
+            ));
+        });
+}
+
+/// lists the moves of the game once it ended, every one of them can be clicked
+pub fn spawn_move_list(
+    mut commands: Commands,
+    mut victory: EventReader<VictoryEvent>,
+    start: Res<GameStart>,
+    history: Res<MoveHistory>,
+    asset_server: Res<AssetServer>,
+) {
+    if victory.read().count() == 0 {
+        return;
+    }
+    let mut state = start.state;
+    let mut labels = Vec::new();
+    let mut number = 1;
+    for &played_move in history.iter() {
+        let san = state.san(played_move);
+        let turn = state.turn;
+        if state.play_move(played_move).is_err() {
+            break;
+        }
+        labels.push(match turn {
+            ChessColor::White => format!("{}. {}", number, san),
+            // a game can start with black to move
+            ChessColor::Black if labels.is_empty() => format!("{}... {}", number, san),
+            ChessColor::Black => san,
+        });
+        if turn == ChessColor::Black {
+            number += 1;
+        }
+    }
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(15.0),
+                    bottom: Val::Px(15.0),
+                    max_width: Val::Percent(25.0),
+                    flex_wrap: FlexWrap::Wrap,
+                    padding: UiRect::all(Val::Px(5.0)),
+                    ..default()
+                },
+                background_color: color::MIDNIGHT_BLUE.with_alpha(0.5).into(),
+                ..default()
+            },
+            GameWindow,
+        ))
+        .with_children(|parent| {
+            for (index, label) in labels.into_iter().enumerate() {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::horizontal(Val::Px(4.0)),
+                                ..default()
+                            },
+                            background_color: Color::NONE.into(),
+                            ..default()
+                        },
+                        MoveListEntry(index + 1),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            label,
+                            TextStyle {
+                                font: asset_server.load(FONT),
+                                font_size: 20.0,
+                                color: color::ALICE_BLUE.into(),
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+/// marks the move that was played last in the position being reviewed, the result only
+/// shows at the end of the game
+pub fn highlight_move_list(
+    mut entries: Query<(&mut BackgroundColor, &MoveListEntry)>,
+    added: Query<(), Added<MoveListEntry>>,
+    mut end_text: Query<&mut Visibility, With<EndText>>,
+    review: Res<Review>,
+    history: Res<MoveHistory>,
+) {
+    if !review.is_changed() && added.is_empty() {
+        return;
+    }
+    let Some(ply) = review.0 else {
+        return;
+    };
+    for (mut background, entry) in entries.iter_mut() {
+        *background = if entry.0 == ply {
+            color::BLUE.into()
+        } else {
+            Color::NONE.into()
+        };
+    }
+    for mut visibility in end_text.iter_mut() {
+        *visibility = if ply == history.len() {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+    }
+}
+
 pub fn spawn_draw_message(
     mut commands: Commands,
     mut reader: EventReader<DrawRequestedEvent>,
     asset_server: Res<AssetServer>,
+) {
+    for _ in reader.read() {
+        spawn_offer(
+            &mut commands,
+            &asset_server,
+            "Your opponent offers a draw",
+            AcceptDrawButton,
+            DeclineDrawButton,
+        );
+    }
+}
+
+pub fn spawn_takeback_message(
+    mut commands: Commands,
+    mut reader: EventReader<TakebackRequestedEvent>,
+    asset_server: Res<AssetServer>,
+) {
+    for _ in reader.read() {
+        spawn_offer(
+            &mut commands,
+            &asset_server,
+            "Your opponent wants to take back their last move",
+            AcceptTakebackButton,
+            DeclineTakebackButton,
+        );
+    }
+}
+
+/// a message about something the opponent offers, with buttons to accept or decline it
+fn spawn_offer(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    message: &str,
+    accept: impl Component,
+    decline: impl Component,
+) {
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Percent(40.0),
+                    right: Val::Px(15.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::FlexEnd,
+                    ..default()
+                },
+                ..default()
+            },
+            DrawText,
+            GameWindow,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                message,
+                TextStyle {
+                    font: asset_server.load(FONT),
+                    font_size: 30.0,
+                    color: color::BLACK.into(),
+                },
+            ));
+            parent.spawn(NodeBundle::default()).with_children(|parent| {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                margin: UiRect::all(Val::Px(10.0)),
+                                padding: UiRect::all(Val::Px(10.0)),
+                                ..default()
+                            },
+                            background_color: color::BLUE.into(),
+                            ..default()
+                        },
+                        accept,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            "Accept",
+                            TextStyle {
+                                font: asset_server.load(FONT),
+                                font_size: 30.0,
+                                color: color::ALICE_BLUE.into(),
+                            },
+                        ));
+                    });
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                margin: UiRect::all(Val::Px(10.0)),
+                                padding: UiRect::all(Val::Px(10.0)),
+                                ..default()
+                            },
+                            background_color: color::BLUE.into(),
+                            ..default()
+                        },
+                        decline,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            "Decline",
+                            TextStyle {
+                                font: asset_server.load(FONT),
+                                font_size: 30.0,
+                                color: color::ALICE_BLUE.into(),
+                            },
+                        ));
+                    });
+            });
+        });
+}
+
+pub fn spawn_draw_declined_message(
+    mut commands: Commands,
+    mut reader: EventReader<DrawDeclinedEvent>,
+    asset_server: Res<AssetServer>,
+) {
+    for _ in reader.read() {
+        commands.spawn((
+            TextBundle::from_section(
+                "Your opponent declined the draw",
+                TextStyle {
+                    font: asset_server.load(FONT),
+                    font_size: 30.0,
+                    color: color::BLACK.into(),
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(40.0),
+                right: Val::Px(15.0),
+                ..default()
+            }),
+            DrawText,
+            GameWindow,
+        ));
+    }
+}
+
+pub fn spawn_takeback_declined_message(
+    mut commands: Commands,
+    mut reader: EventReader<TakebackDeclinedEvent>,
+    asset_server: Res<AssetServer>,
+) {
+    for _ in reader.read() {
+        commands.spawn((
+            TextBundle::from_section(
+                "Your opponent declined the takeback",
+                TextStyle {
+                    font: asset_server.load(FONT),
+                    font_size: 30.0,
+                    color: color::BLACK.into(),
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(40.0),
+                right: Val::Px(15.0),
+                ..default()
+            }),
+            DrawText,
+            GameWindow,
+        ));
+    }
+}
+
+pub fn spawn_draw_expired_message(
+    mut commands: Commands,
+    mut reader: EventReader<DrawExpiredEvent>,
+    query: Query<Entity, With<DrawText>>,
+    asset_server: Res<AssetServer>,
+) {
+    for _ in reader.read() {
+        // the offer can't be accepted or declined anymore
+        for entity in query.iter() {
+            if let Some(text) = commands.get_entity(entity) {
+                text.despawn_recursive();
+            }
+        }
+        commands.spawn((
+            TextBundle::from_section(
+                "The draw offer expired",
+                TextStyle {
+                    font: asset_server.load(FONT),
+                    font_size: 30.0,
+                    color: color::BLACK.into(),
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Percent(40.0),
+                right: Val::Px(15.0),
+                ..default()
+            }),
+            DrawText,
+            GameWindow,
+        ));
+    }
+}
+
+pub fn spawn_rematch_message(
+    mut commands: Commands,
+    mut reader: EventReader<RematchOfferedEvent>,
+    asset_server: Res<AssetServer>,
 ) {
     for _ in reader.read() {
         commands.spawn((
             TextBundle::from_section(
-                "Your opponent wants a draw,\npress draw to agree",
+                "Your opponent wants a rematch",
                 TextStyle {
                     font: asset_server.load(FONT),
                     font_size: 30.0,
@@ -283,6 +1212,59 @@ pub fn spawn_draw_message(
     }
 }
 
+/// the rematch button accepts once the opponent offered one, and waits after offering one
+pub fn update_rematch_button(
+    mut commands: Commands,
+    mut pressed: EventReader<RematchEvent>,
+    buttons: Query<(Entity, &Children), With<RematchButton>>,
+    mut texts: Query<&mut Text>,
+    offer: Res<RematchOffer>,
+) {
+    let pressed = pressed.read().count() > 0;
+    if !pressed && !offer.is_changed() {
+        return;
+    }
+    for (entity, children) in buttons.iter() {
+        let label = match (pressed, **offer) {
+            (true, false) => {
+                // one offer is enough
+                commands.entity(entity).remove::<RematchButton>();
+                "Waiting for your opponent"
+            }
+            (true, true) => "Starting",
+            (false, true) => "Accept rematch",
+            (false, false) => "Rematch",
+        };
+        for &child in children.iter() {
+            if let Ok(mut text) = texts.get_mut(child) {
+                text.sections[0].value = label.to_string();
+            }
+        }
+    }
+}
+
+/// the offer is answered either way, accepting a draw sends a draw request back
+pub fn dismiss_draw_message(
+    mut commands: Commands,
+    mut reader: EventReader<DeclineDrawEvent>,
+    mut reader2: EventReader<RequestDrawEvent>,
+    mut reader3: EventReader<RespondTakebackEvent>,
+    query: Query<Entity, With<DrawText>>,
+) {
+    for _ in reader
+        .read()
+        .map(|_| ())
+        .chain(reader2.read().map(|_| ()))
+        .chain(reader3.read().map(|_| ()))
+    {
+        for entity in query.iter() {
+            if let Some(text) = commands.get_entity(entity) {
+                text.despawn_recursive();
+            }
+        }
+    }
+}
+
 pub fn despawn_messages(
     mut commands: Commands,
     mut reader: EventReader<MoveEvent>,
@@ -310,6 +1292,7 @@ pub fn spawn_promotion_menu(
     mut reader: EventReader<PromotionEvent>,
     asset_server: Res<AssetServer>,
     color: Res<ChessColor>,
+    settings: Res<Settings>,
 ) {
     for _ in reader.read() {
         info!("spawning promotion");
@@ -340,10 +1323,34 @@ pub fn spawn_promotion_menu(
                 GameWindow,
             ))
             .with_children(|parent| {
-                spawn_button_bundle(parent, &asset_server, *color, PromotionPiece::Bishop);
-                spawn_button_bundle(parent, &asset_server, *color, PromotionPiece::Knight);
-                spawn_button_bundle(parent, &asset_server, *color, PromotionPiece::Rook);
-                spawn_button_bundle(parent, &asset_server, *color, PromotionPiece::Queen);
+                spawn_button_bundle(
+                    parent,
+                    &asset_server,
+                    &settings,
+                    *color,
+                    PromotionPiece::Bishop,
+                );
+                spawn_button_bundle(
+                    parent,
+                    &asset_server,
+                    &settings,
+                    *color,
+                    PromotionPiece::Knight,
+                );
+                spawn_button_bundle(
+                    parent,
+                    &asset_server,
+                    &settings,
+                    *color,
+                    PromotionPiece::Rook,
+                );
+                spawn_button_bundle(
+                    parent,
+                    &asset_server,
+                    &settings,
+                    *color,
+                    PromotionPiece::Queen,
+                );
             });
     }
 }
@@ -351,19 +1358,18 @@ pub fn spawn_promotion_menu(
 fn spawn_button_bundle(
     commands: &mut ChildBuilder,
     asset_server: &AssetServer,
+    settings: &Settings,
     color: ChessColor,
     piece: PromotionPiece,
 ) {
-    let image = UiImage::new(asset_server.load(match (color, piece) {
-        (ChessColor::White, PromotionPiece::Queen) => "chess/white_queen.png",
-        (ChessColor::White, PromotionPiece::Rook) => "chess/white_rook.png",
-        (ChessColor::White, PromotionPiece::Knight) => "chess/white_knight.png",
-        (ChessColor::White, PromotionPiece::Bishop) => "chess/white_bishop.png",
-        (ChessColor::Black, PromotionPiece::Queen) => "chess/black_queen.png",
-        (ChessColor::Black, PromotionPiece::Rook) => "chess/black_rook.png",
-        (ChessColor::Black, PromotionPiece::Knight) => "chess/black_knight.png",
-        (ChessColor::Black, PromotionPiece::Bishop) => "chess/black_bishop.png",
-    }));
+    let piece_type = match piece {
+        PromotionPiece::Queen => ChessPieceType::Queen,
+        PromotionPiece::Rook => ChessPieceType::Rook,
+        PromotionPiece::Knight => ChessPieceType::Knight,
+        PromotionPiece::Bishop => ChessPieceType::Bishop,
+    };
+    let image =
+        UiImage::new(asset_server.load(piece_texture(&settings.piece_set, color, piece_type)));
     let bundle = ButtonBundle {
         style: Style {
             position_type: PositionType::Relative,
@@ -398,3 +1404,62 @@ fn spawn_button_bundle(
             });
         });
 }
+
+/// lists both accuracies and the worst moves of the game
+pub fn spawn_analysis_report(
+    mut commands: Commands,
+    mut reader: EventReader<AnalysisEvent>,
+    asset_server: Res<AssetServer>,
+) {
+    const MAX_LINES: usize = 12;
+    for AnalysisEvent(analysis) in reader.read() {
+        let mut report = format!(
+            "Game report\nwhite: {:.1}% accuracy, {} blunders\nblack: {:.1}% accuracy, {} blunders\n",
+            analysis.accuracy(ChessColor::White),
+            analysis.count(ChessColor::White, MoveQuality::Blunder),
+            analysis.accuracy(ChessColor::Black),
+            analysis.count(ChessColor::Black, MoveQuality::Blunder)
+        );
+        for (ply, annotated) in analysis
+            .moves
+            .iter()
+            .enumerate()
+            .filter(|(_, m)| {
+                matches!(
+                    m.quality,
+                    MoveQuality::Inaccuracy | MoveQuality::Mistake | MoveQuality::Blunder
+                )
+            })
+            .take(MAX_LINES)
+        {
+            report.push_str(&format!(
+                "\n{}{} {} {:?} (-{})",
+                ply / 2 + 1,
+                if ply % 2 == 0 { "." } else { "..." },
+                annotated.played_move,
+                annotated.quality,
+                annotated.centipawn_loss
+            ));
+            if let Some(best) = annotated.best_move {
+                report.push_str(&format!(", best was {best}"));
+            }
+        }
+        commands.spawn((
+            TextBundle::from_section(
+                report,
+                TextStyle {
+                    font: asset_server.load(FONT),
+                    font_size: 20.0,
+                    color: color::BLACK.into(),
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(70.0),
+                left: Val::Px(15.0),
+                ..default()
+            }),
+            GameWindow,
+        ));
+    }
+}
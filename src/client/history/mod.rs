@@ -0,0 +1,223 @@
+//! the games you finished on the server, newest first, a page at a time
+
+use bevy::color::palettes::css as color;
+use bevy::prelude::*;
+
+use crate::api::{chessmove::ChessColor, pgn, GameEnd, HistoryEntry, PlayerInfo, ReplayId};
+
+use super::{despawn_screen, loading::LeaveQueueEvent, GameState, FONT};
+
+/// how many games the server is asked for at once
+pub const PAGE: u32 = 10;
+
+pub struct HistoryPlugin;
+
+impl Plugin for HistoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<HistoryEvent>()
+            .add_event::<FetchHistoryEvent>()
+            .add_systems(OnEnter(GameState::History), setup)
+            .add_systems(
+                Update,
+                (show_history, older_games, leave_history).run_if(in_state(GameState::History)),
+            )
+            .add_systems(OnExit(GameState::History), despawn_screen::<HistoryScreen>);
+    }
+}
+
+#[derive(Component)]
+struct HistoryScreen;
+
+#[derive(Component)]
+struct HistoryTitle;
+
+/// the node the games are put in
+#[derive(Component)]
+struct HistoryList;
+
+/// asks for the games before the oldest one shown
+#[derive(Component)]
+struct OlderGamesButton(ReplayId);
+
+/// a page of finished games, from the server
+#[derive(Debug, Clone, Event)]
+pub struct HistoryEvent {
+    pub player: PlayerInfo,
+    pub games: Vec<HistoryEntry>,
+}
+
+/// asks the server for a page of your games, the ones before the given game or the newest
+#[derive(Debug, Clone, Copy, Event)]
+pub struct FetchHistoryEvent(pub Option<ReplayId>);
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                clear_color: ClearColorConfig::Custom(
+                    Srgba {
+                        red: 0.1,
+                        green: 0.2,
+                        blue: 0.3,
+                        alpha: 1.0,
+                    }
+                    .into(),
+                ),
+                ..default()
+            },
+            ..default()
+        },
+        HistoryScreen,
+    ));
+
+    commands.spawn((
+        TextBundle::from_section(
+            "asking the server...",
+            TextStyle {
+                font: asset_server.load(FONT),
+                font_size: 60.0,
+                color: color::GOLD.into(),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Px(15.0),
+            ..default()
+        }),
+        HistoryTitle,
+        HistoryScreen,
+    ));
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(85.0),
+                left: Val::Px(15.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        },
+        HistoryList,
+        HistoryScreen,
+    ));
+
+    commands.spawn((
+        TextBundle::from_section(
+            "Press escape to go back",
+            TextStyle {
+                font: asset_server.load(FONT),
+                font_size: 40.0,
+                color: color::GOLD.into(),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(15.0),
+            left: Val::Px(15.0),
+            ..default()
+        }),
+        HistoryScreen,
+    ));
+}
+
+fn entry_label(entry: &HistoryEntry) -> String {
+    let (outcome, reason) = match entry.result {
+        GameEnd::White(reason) if entry.color == ChessColor::White => ("won", reason),
+        GameEnd::Black(reason) if entry.color == ChessColor::Black => ("won", reason),
+        GameEnd::White(reason) | GameEnd::Black(reason) => ("lost", reason),
+        GameEnd::Draw(reason) => ("drew", reason),
+    };
+    format!(
+        "{} as {} against {} by {}, {} moves, {}",
+        outcome,
+        match entry.color {
+            ChessColor::White => "white",
+            ChessColor::Black => "black",
+        },
+        entry.opponent_name,
+        pgn::termination(reason),
+        entry.moves.div_ceil(2),
+        if entry.rated { "rated" } else { "casual" }
+    )
+}
+
+/// replaces the listed games with the page the server sent
+fn show_history(
+    mut commands: Commands,
+    mut events: EventReader<HistoryEvent>,
+    mut title: Query<&mut Text, With<HistoryTitle>>,
+    list: Query<Entity, With<HistoryList>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(HistoryEvent { player, games }) = events.read().last() else {
+        return;
+    };
+    for mut title in title.iter_mut() {
+        title.sections[0].value = match player.rating {
+            Some(rating) => format!("games of {} ({})", player.name, rating),
+            None => format!("games of {}", player.name),
+        };
+    }
+    let style = TextStyle {
+        font: asset_server.load(FONT),
+        font_size: 40.0,
+        color: color::WHITE.into(),
+    };
+    for list in list.iter() {
+        commands
+            .entity(list)
+            .despawn_descendants()
+            .with_children(|parent| {
+                if games.is_empty() {
+                    parent.spawn(TextBundle::from_section("no finished games", style.clone()));
+                }
+                for entry in games {
+                    parent.spawn(TextBundle::from_section(entry_label(entry), style.clone()));
+                }
+                // a full page may have more games before it
+                let Some(oldest) = games.last().filter(|_| games.len() as u32 == PAGE) else {
+                    return;
+                };
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::all(Val::Px(10.0)),
+                                ..default()
+                            },
+                            background_color: color::DARK_GRAY.into(),
+                            ..default()
+                        },
+                        OlderGamesButton(oldest.id),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section("older games", style.clone()));
+                    });
+            });
+    }
+}
+
+fn older_games(
+    query: Query<(&Interaction, &OlderGamesButton), Changed<Interaction>>,
+    mut writer: EventWriter<FetchHistoryEvent>,
+) {
+    for (&interaction, button) in query.iter() {
+        if interaction == Interaction::Pressed {
+            writer.send(FetchHistoryEvent(Some(button.0)));
+        }
+    }
+}
+
+/// drops the connection and goes back to the main menu
+fn leave_history(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut writer: EventWriter<LeaveQueueEvent>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        writer.send(LeaveQueueEvent);
+    }
+}
@@ -0,0 +1,451 @@
+//! every finished game is kept as a pgn file in the data directory of the platform, the library
+//! lists the newest ones and opens them to step through the moves
+
+use std::{fs, io, path::PathBuf};
+
+use bevy::color::palettes::css as color;
+use bevy::{ecs::system::SystemParam, prelude::*};
+use directories::{ProjectDirs, UserDirs};
+
+use crate::api::{
+    chessmove::ChessColor,
+    chessstate::ChessState,
+    pgn::{self, PgnGame},
+    ping::timestamp,
+    ClientNetConfig, EndReason, GameEnd, PlayerInfo,
+};
+
+use super::{
+    despawn_screen,
+    game::{AnalysisBoard, MoveHistory, Outcome, Players},
+    networking::{connection::ClientConnections, ConnectionAddress, GameStart},
+    GameState, VictoryEvent, FONT,
+};
+
+/// how many of the newest games the library lists
+const SHOWN: usize = 12;
+/// the event of a saved game that changed the ratings
+const RATED_EVENT: &str = "rated game";
+
+pub struct LibraryPlugin;
+
+impl Plugin for LibraryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<OpenedGame>()
+            .add_systems(OnEnter(GameState::Library), setup)
+            .add_systems(
+                Update,
+                (open_game, open_analysis, leave_library).run_if(in_state(GameState::Library)),
+            )
+            .add_systems(Update, save_game.run_if(in_state(GameState::Gaming)))
+            .add_systems(OnExit(GameState::Library), despawn_screen::<LibraryScreen>)
+            .add_systems(OnExit(GameState::Gaming), close_game);
+    }
+}
+
+#[derive(Component)]
+struct LibraryScreen;
+
+/// opens the game it holds when clicked
+#[derive(Component)]
+struct SavedGameButton(PgnGame);
+
+/// opens an empty board to set up positions on
+#[derive(Component)]
+struct AnalysisButton;
+
+/// the saved game on the board, its tags are kept when it is copied or saved again
+#[derive(Resource, Default)]
+pub struct OpenedGame(Option<PgnGame>);
+
+/// the folder the games are saved in
+fn directory() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "random_unnamed_chess_game").map(|dirs| dirs.data_dir().join("games"))
+}
+
+/// the folder exported games go in, the downloads folder or else the home folder
+fn export_directory() -> Option<PathBuf> {
+    let dirs = UserDirs::new()?;
+    Some(dirs.download_dir().unwrap_or(dirs.home_dir()).to_path_buf())
+}
+
+/// the newest saved games that could be read, the newest first
+fn saved_games() -> Vec<PgnGame> {
+    let Some(Ok(entries)) = directory().map(fs::read_dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<_> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "pgn"))
+        .collect();
+    // the files are named after the time the game ended
+    paths.sort_unstable_by(|a, b| b.cmp(a));
+    paths
+        .into_iter()
+        .filter_map(|path| pgn::read(&fs::read_to_string(path).ok()?))
+        .take(SHOWN)
+        .collect()
+}
+
+fn game_label(game: &PgnGame) -> String {
+    format!(
+        "{}: {} vs {}, {}",
+        game.tag("Date").unwrap_or("?"),
+        game.tag("White").unwrap_or("?"),
+        game.tag("Black").unwrap_or("?"),
+        game.tag("Result").unwrap_or("*")
+    )
+}
+
+/// the color you played in a saved game, games saved by others are shown from white's side
+fn your_color(game: &PgnGame) -> ChessColor {
+    match game.tag("YourColor") {
+        Some("black") => ChessColor::Black,
+        _ => ChessColor::White,
+    }
+}
+
+/// how a game ended for white and black, None for a game that was called off
+fn game_end(victory: VictoryEvent, color: ChessColor) -> Option<GameEnd> {
+    Some(match victory {
+        VictoryEvent::Draw(EndReason::Aborted) => return None,
+        VictoryEvent::Win(reason) if color == ChessColor::White => GameEnd::White(reason),
+        VictoryEvent::Loss(reason) if color == ChessColor::Black => GameEnd::White(reason),
+        VictoryEvent::Win(reason) | VictoryEvent::Loss(reason) => GameEnd::Black(reason),
+        VictoryEvent::Draw(reason) => GameEnd::Draw(reason),
+    })
+}
+
+/// everything the pgn of the game on the board is made of
+#[derive(SystemParam)]
+pub struct GameRecord<'w> {
+    start: Res<'w, GameStart>,
+    history: Res<'w, MoveHistory>,
+    players: Res<'w, Players>,
+    color: Res<'w, ChessColor>,
+    address: Res<'w, ConnectionAddress>,
+    outcome: Res<'w, Outcome>,
+    opened: Res<'w, OpenedGame>,
+}
+
+impl GameRecord<'_> {
+    /// the game with the headers other chess programs look for, as if it ended at `ended`
+    fn pgn(&self, end: Option<GameEnd>, ended: u64) -> String {
+        let (date, time) = pgn::date_time(ended);
+        let players = &self.players;
+        let mut tags = vec![
+            (
+                "Event",
+                if players.rated {
+                    RATED_EVENT
+                } else {
+                    "casual game"
+                }
+                .to_string(),
+            ),
+            ("Site", self.address.0.to_string()),
+            ("Date", date.clone()),
+            ("Round", "-".to_string()),
+            ("White", players.white.name.clone()),
+            ("Black", players.black.name.clone()),
+            ("Result", pgn::result(end).to_string()),
+        ];
+        if let (Some(white), Some(black)) = (players.white.rating, players.black.rating) {
+            tags.push(("WhiteElo", white.elo.to_string()));
+            tags.push(("BlackElo", black.elo.to_string()));
+        }
+        if let Some(GameEnd::White(reason) | GameEnd::Black(reason) | GameEnd::Draw(reason)) = end {
+            tags.push(("Termination", pgn::termination(reason).to_string()));
+        }
+        tags.push(("UTCDate", date));
+        tags.push(("UTCTime", time));
+        tags.push(("YourColor", format!("{:?}", *self.color).to_lowercase()));
+        pgn::write(tags, self.start.state, &self.history, end)
+    }
+
+    /// the game the way it ended, or still going on, a saved game keeps the tags it was read with
+    pub fn export(&self) -> String {
+        if let Some(game) = &self.opened.0 {
+            let tags = game
+                .tags
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.clone()))
+                .collect();
+            return pgn::write(tags, self.start.state, &self.history, game.end());
+        }
+        let end = self
+            .outcome
+            .and_then(|victory| game_end(victory, *self.color));
+        self.pgn(end, timestamp())
+    }
+
+    /// writes the game to the downloads folder, named after the players
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let directory = export_directory()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "there is no home folder"))?;
+        // names can hold anything, file names can't
+        let name = |player: &PlayerInfo| -> String {
+            player
+                .name
+                .chars()
+                .filter(|c| c.is_alphanumeric() || matches!(c, '-' | '_'))
+                .collect()
+        };
+        let path = directory.join(format!(
+            "{} vs {} {}.pgn",
+            name(&self.players.white),
+            name(&self.players.black),
+            timestamp()
+        ));
+        fs::create_dir_all(&directory)?;
+        fs::write(&path, self.export())?;
+        Ok(path)
+    }
+}
+
+/// keeps every game played over a connection once it ends, games opened from the library
+/// don't have one
+fn save_game(
+    mut victory: EventReader<VictoryEvent>,
+    connections: Res<ClientConnections<ClientNetConfig>>,
+    record: GameRecord,
+) {
+    for &victory in victory.read() {
+        if connections.iter().next().is_none() {
+            continue;
+        }
+        // nothing happened worth keeping
+        let Some(end) = game_end(victory, *record.color) else {
+            continue;
+        };
+        let ended = timestamp();
+        let text = record.pgn(Some(end), ended);
+        let Some(directory) = directory() else {
+            warn!("there is no data directory to save the game in");
+            continue;
+        };
+        if let Err(error) = fs::create_dir_all(&directory)
+            .and_then(|()| fs::write(directory.join(format!("{ended}.pgn")), text))
+        {
+            warn!("couldn't save the game: {}", error);
+        }
+    }
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                clear_color: ClearColorConfig::Custom(
+                    Srgba {
+                        red: 0.3,
+                        green: 0.2,
+                        blue: 0.1,
+                        alpha: 1.0,
+                    }
+                    .into(),
+                ),
+                ..default()
+            },
+            ..default()
+        },
+        LibraryScreen,
+    ));
+
+    let games = saved_games();
+    commands.spawn((
+        TextBundle::from_section(
+            if games.is_empty() {
+                "no saved games yet, every game you finish ends up here"
+            } else {
+                "saved games, click one to step through it"
+            },
+            TextStyle {
+                font: asset_server.load(FONT),
+                font_size: 60.0,
+                color: color::GOLD.into(),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Px(15.0),
+            ..default()
+        }),
+        LibraryScreen,
+    ));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(85.0),
+                    left: Val::Px(15.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            },
+            LibraryScreen,
+        ))
+        .with_children(|parent| {
+            for game in games {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::all(Val::Px(10.0)),
+                                ..default()
+                            },
+                            background_color: color::DARK_GRAY.into(),
+                            ..default()
+                        },
+                        SavedGameButton(game.clone()),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            game_label(&game),
+                            TextStyle {
+                                font: asset_server.load(FONT),
+                                font_size: 40.0,
+                                color: color::WHITE.into(),
+                            },
+                        ));
+                    });
+            }
+        });
+
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(15.0),
+                    right: Val::Px(15.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: color::DARK_GRAY.into(),
+                ..default()
+            },
+            AnalysisButton,
+            LibraryScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "analysis board",
+                TextStyle {
+                    font: asset_server.load(FONT),
+                    font_size: 40.0,
+                    color: color::WHITE.into(),
+                },
+            ));
+        });
+
+    commands.spawn((
+        TextBundle::from_section(
+            "Press escape to go back",
+            TextStyle {
+                font: asset_server.load(FONT),
+                font_size: 40.0,
+                color: color::GOLD.into(),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(15.0),
+            left: Val::Px(15.0),
+            ..default()
+        }),
+        LibraryScreen,
+    ));
+}
+
+/// shows the board the way it is after a game, with the moves to step through
+fn open_game(
+    query: Query<(&Interaction, &SavedGameButton), Changed<Interaction>>,
+    mut start: ResMut<GameStart>,
+    mut history: ResMut<MoveHistory>,
+    mut chess_state: ResMut<ChessState>,
+    mut color: ResMut<ChessColor>,
+    mut players: ResMut<Players>,
+    mut opened: ResMut<OpenedGame>,
+    mut victory: EventWriter<VictoryEvent>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for (&interaction, SavedGameButton(game)) in query.iter() {
+        if interaction != Interaction::Pressed {
+            continue;
+        }
+        opened.0 = Some(game.clone());
+        let player = |name: &str| PlayerInfo {
+            name: game.tag(name).unwrap_or("?").to_string(),
+            ..default()
+        };
+        *players = Players {
+            white: player("White"),
+            black: player("Black"),
+            rated: game.tag("Event") == Some(RATED_EVENT),
+        };
+        start.state = game.start;
+        history.0.clone_from(&game.moves);
+        *chess_state = game.start;
+        for &played_move in &game.moves {
+            chess_state.play_move(played_move).ok();
+        }
+        *color = your_color(game);
+        // the game screen shows the end of the game and the move list like right after playing
+        victory.send(match game.end() {
+            Some(GameEnd::Draw(reason)) => VictoryEvent::Draw(reason),
+            Some(GameEnd::White(reason)) if *color == ChessColor::White => {
+                VictoryEvent::Win(reason)
+            }
+            Some(GameEnd::Black(reason)) if *color == ChessColor::Black => {
+                VictoryEvent::Win(reason)
+            }
+            Some(GameEnd::White(reason) | GameEnd::Black(reason)) => VictoryEvent::Loss(reason),
+            None => VictoryEvent::Draw(EndReason::Aborted),
+        });
+        game_state.set(GameState::Gaming);
+    }
+}
+
+/// the next game on the board is a new one
+fn close_game(mut opened: ResMut<OpenedGame>) {
+    opened.0 = None;
+}
+
+fn open_analysis(
+    query: Query<&Interaction, (Changed<Interaction>, With<AnalysisButton>)>,
+    mut analysis: ResMut<AnalysisBoard>,
+    mut players: ResMut<Players>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            analysis.0 = true;
+            let player = PlayerInfo {
+                name: "?".to_string(),
+                ..default()
+            };
+            *players = Players {
+                white: player.clone(),
+                black: player,
+                rated: false,
+            };
+            game_state.set(GameState::Gaming);
+        }
+    }
+}
+
+fn leave_library(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        game_state.set(GameState::MainMenu);
+    }
+}
@@ -1,13 +1,45 @@
 use bevy::color::palettes::css as color;
 use bevy::prelude::*;
 
-use super::{despawn_screen, GameState, FONT};
+use crate::api::{
+    league::{LeagueId, LeagueSchedule},
+    matchmaking::LobbyCode,
+    simul::{SimulId, SimulInfo},
+};
+
+use super::{clipboard, despawn_screen, networking::league_summary, GameState, FONT};
 
 pub struct LoadPlugin;
 
 impl Plugin for LoadPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Loading), setup)
+        app.add_event::<QueueStatusEvent>()
+            .add_event::<LeaveQueueEvent>()
+            .add_event::<ChallengeReceivedEvent>()
+            .add_event::<AnswerChallengeEvent>()
+            .add_event::<LobbyCreatedEvent>()
+            .add_event::<LeagueEvent>()
+            .add_event::<StartLeagueEvent>()
+            .add_event::<SimulEvent>()
+            .add_event::<StartSimulEvent>()
+            .init_resource::<WaitingLeague>()
+            .init_resource::<WaitingSimul>()
+            .add_systems(OnEnter(GameState::Loading), setup)
+            .add_systems(
+                Update,
+                (
+                    queue_status_notifier,
+                    lobby_code_notifier,
+                    league_notifier,
+                    start_league,
+                    simul_notifier,
+                    start_simul,
+                    leave_queue,
+                    spawn_challenge_message,
+                    answer_challenge,
+                )
+                    .run_if(in_state(GameState::Loading)),
+            )
             .add_systems(OnExit(GameState::Loading), despawn_screen::<Load>);
     }
 }
@@ -15,7 +47,72 @@ impl Plugin for LoadPlugin {
 #[derive(Component)]
 pub struct Load;
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+#[derive(Component)]
+struct QueueStatusText;
+
+/// leaves the queue, lobby, league or simul like escape does
+#[derive(Component)]
+struct CancelButton;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub struct QueueStatusEvent {
+    pub position: u32,
+    pub players_waiting: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub struct LeaveQueueEvent;
+
+/// the challenge it shows, answered with y or n
+#[derive(Component)]
+struct ChallengeText(u32);
+
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub struct ChallengeReceivedEvent {
+    pub challenge: u32,
+    pub from: String,
+}
+
+/// the lobby is open, a friend joins it with the code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub struct LobbyCreatedEvent(pub LobbyCode);
+
+/// the schedule of a league you play in
+#[derive(Debug, Clone, Event)]
+pub struct LeagueEvent(pub LeagueSchedule);
+
+/// asks the server to start the league, only works for the player who opened it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub struct StartLeagueEvent(pub LeagueId);
+
+/// the league waiting for its creator to start it, s starts it
+#[derive(Resource, Default, Debug)]
+struct WaitingLeague(Option<LeagueId>);
+
+/// who is in the simul you host or joined
+#[derive(Debug, Clone, Event)]
+pub struct SimulEvent(pub SimulInfo);
+
+/// asks the server to start the simul, only works for its host
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub struct StartSimulEvent(pub SimulId);
+
+/// the simul waiting for its host to start it, s starts it
+#[derive(Resource, Default, Debug)]
+struct WaitingSimul(Option<SimulId>);
+
+/// true accepts the challenge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub struct AnswerChallengeEvent(pub u32, pub bool);
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut waiting: ResMut<WaitingLeague>,
+    mut waiting_simul: ResMut<WaitingSimul>,
+) {
+    waiting.0 = None;
+    waiting_simul.0 = None;
     commands.spawn((
         Camera2dBundle {
             camera: Camera {
@@ -48,4 +145,218 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         .with_style(default()),
         Load,
     ));
+
+    commands.spawn((
+        TextBundle::from_section(
+            "Press escape to leave the queue",
+            TextStyle {
+                font: asset_server.load(FONT),
+                font_size: 40.0,
+                color: color::GOLD.into(),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(15.0),
+            left: Val::Px(15.0),
+            ..default()
+        }),
+        QueueStatusText,
+        Load,
+    ));
+
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(15.0),
+                    right: Val::Px(15.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: color::DARK_GRAY.into(),
+                ..default()
+            },
+            CancelButton,
+            Load,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Cancel",
+                TextStyle {
+                    font: asset_server.load(FONT),
+                    font_size: 40.0,
+                    color: color::WHITE.into(),
+                },
+            ));
+        });
+}
+
+fn queue_status_notifier(
+    mut events: EventReader<QueueStatusEvent>,
+    mut query: Query<&mut Text, With<QueueStatusText>>,
+) {
+    let Some(status) = events.read().last() else {
+        return;
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!(
+            "You are number {} of {} players waiting, press escape to leave the queue",
+            status.position, status.players_waiting
+        );
+    }
+}
+
+fn lobby_code_notifier(
+    mut events: EventReader<LobbyCreatedEvent>,
+    mut query: Query<&mut Text, With<QueueStatusText>>,
+) {
+    let Some(LobbyCreatedEvent(code)) = events.read().last() else {
+        return;
+    };
+    // saves typing it over into a chat
+    let copied = clipboard::set(code.to_string());
+    for mut text in query.iter_mut() {
+        text.sections[0].value = format!(
+            "Your lobby code is {}{}, press escape to close the lobby",
+            code,
+            if copied { " (copied)" } else { "" }
+        );
+    }
+}
+
+fn league_notifier(
+    mut events: EventReader<LeagueEvent>,
+    mut query: Query<&mut Text, With<QueueStatusText>>,
+    mut waiting: ResMut<WaitingLeague>,
+) {
+    let Some(LeagueEvent(schedule)) = events.read().last() else {
+        return;
+    };
+    let value = if schedule.round == 0 {
+        waiting.0 = Some(schedule.id);
+        format!(
+            "League {} has {} players, others join with --league={}. Press s to start it if you opened it",
+            schedule.id.0,
+            schedule.players.len(),
+            schedule.id.0
+        )
+    } else {
+        waiting.0 = None;
+        // your next game starts as soon as your opponent is there
+        league_summary(schedule)
+    };
+    for mut text in query.iter_mut() {
+        text.sections[0].value.clone_from(&value);
+    }
+}
+
+fn start_league(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    waiting: Res<WaitingLeague>,
+    mut writer: EventWriter<StartLeagueEvent>,
+) {
+    if let (true, Some(id)) = (keyboard_input.just_pressed(KeyCode::KeyS), waiting.0) {
+        writer.send(StartLeagueEvent(id));
+    }
+}
+
+fn simul_notifier(
+    mut events: EventReader<SimulEvent>,
+    mut query: Query<&mut Text, With<QueueStatusText>>,
+    mut waiting: ResMut<WaitingSimul>,
+) {
+    let Some(SimulEvent(simul)) = events.read().last() else {
+        return;
+    };
+    waiting.0 = Some(simul.id);
+    let value = format!(
+        "Simul {} of {} has {} players, others join with --simul={}. Press s to start it if you host it",
+        simul.id.0,
+        simul.host.name,
+        simul.players.len(),
+        simul.id.0
+    );
+    for mut text in query.iter_mut() {
+        text.sections[0].value.clone_from(&value);
+    }
+}
+
+fn start_simul(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    waiting: Res<WaitingSimul>,
+    mut writer: EventWriter<StartSimulEvent>,
+) {
+    if let (true, Some(id)) = (keyboard_input.just_pressed(KeyCode::KeyS), waiting.0) {
+        writer.send(StartSimulEvent(id));
+    }
+}
+
+fn spawn_challenge_message(
+    mut commands: Commands,
+    mut events: EventReader<ChallengeReceivedEvent>,
+    query: Query<Entity, With<ChallengeText>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(event) = events.read().last() else {
+        return;
+    };
+    // only the newest challenge can be answered
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.spawn((
+        TextBundle::from_section(
+            format!(
+                "{} challenges you, press y to accept or n to decline",
+                event.from
+            ),
+            TextStyle {
+                font: asset_server.load(FONT),
+                font_size: 40.0,
+                color: color::ORANGE_RED.into(),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(70.0),
+            left: Val::Px(15.0),
+            ..default()
+        }),
+        ChallengeText(event.challenge),
+        Load,
+    ));
+}
+
+fn answer_challenge(
+    mut commands: Commands,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    query: Query<(Entity, &ChallengeText)>,
+    mut writer: EventWriter<AnswerChallengeEvent>,
+) {
+    let accept = if keyboard_input.just_pressed(KeyCode::KeyY) {
+        true
+    } else if keyboard_input.just_pressed(KeyCode::KeyN) {
+        false
+    } else {
+        return;
+    };
+    for (entity, challenge) in query.iter() {
+        writer.send(AnswerChallengeEvent(challenge.0, accept));
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn leave_queue(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    query: Query<&Interaction, (Changed<Interaction>, With<CancelButton>)>,
+    mut writer: EventWriter<LeaveQueueEvent>,
+) {
+    let cancelled = query
+        .iter()
+        .any(|&interaction| interaction == Interaction::Pressed);
+    if cancelled || keyboard_input.just_pressed(KeyCode::Escape) {
+        writer.send(LeaveQueueEvent);
+    }
 }
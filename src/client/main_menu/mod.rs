@@ -1,18 +1,29 @@
 use bevy::color::palettes::css as color;
 use bevy::input::keyboard::{Key, KeyboardInput};
-use bevy::{
-    diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin},
-    prelude::*,
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::tasks::{block_on, poll_once, IoTaskPool, Task};
+use bevy::{app::AppExit, prelude::*};
+use std::{
+    net::{SocketAddr, ToSocketAddrs},
+    time::Duration,
 };
-use copypasta::{ClipboardContext, ClipboardProvider};
-use std::net::ToSocketAddrs;
 
 #[cfg(feature = "server")]
 use crate::server;
 
+use crate::api::{
+    handicap::Handicap,
+    matchmaking::{ColorPreference, LobbyCode, TimeControl},
+    OngoingGame,
+};
+
 use super::{
-    despawn_screen,
-    networking::{ConnectionAddress, MakeConnectionEvent},
+    clipboard, despawn_screen,
+    networking::{
+        ConnectionAddress, ConnectionPurpose, MakeConnectionEvent, QueueRated, QueueTimeControl,
+        QueueTimeOdds,
+    },
+    settings::Settings,
     GameState, FONT,
 };
 
@@ -22,51 +33,281 @@ impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<TextSelectionState>()
             .init_resource::<ConnectionText>()
+            .init_resource::<LobbyCodeText>()
+            .add_event::<ServerStatsEvent>()
+            .add_event::<OngoingGamesEvent>()
+            .init_resource::<OngoingGames>()
+            .add_systems(Startup, lookup_saved_server)
+            .add_systems(
+                Update,
+                finish_server_lookup.run_if(resource_exists::<ServerLookup>),
+            )
             .add_systems(OnEnter(GameState::MainMenu), setup)
             .add_systems(
                 Update,
                 (
-                    text_update_system.run_if(in_state(GameState::MainMenu)),
                     text_color_system.run_if(in_state(GameState::MainMenu)),
                     keyboard_input_system.run_if(in_state(GameState::MainMenu)),
                     select_ui.run_if(in_state(GameState::MainMenu)),
                     change_background.run_if(in_state(GameState::MainMenu)),
+                    handicap_button.run_if(in_state(GameState::MainMenu)),
+                    color_button.run_if(in_state(GameState::MainMenu)),
+                    time_control_button.run_if(in_state(GameState::MainMenu)),
+                    time_odds_button.run_if(in_state(GameState::MainMenu)),
+                    rated_button.run_if(in_state(GameState::MainMenu)),
+                    stats_button.run_if(in_state(GameState::MainMenu)),
+                    show_stats.run_if(in_state(GameState::MainMenu)),
+                    games_button.run_if(in_state(GameState::MainMenu)),
+                    show_games.run_if(in_state(GameState::MainMenu)),
+                    create_lobby_button.run_if(in_state(GameState::MainMenu)),
+                    seeks_button.run_if(in_state(GameState::MainMenu)),
+                    history_button.run_if(in_state(GameState::MainMenu)),
+                    remember_server.run_if(in_state(GameState::MainMenu)),
                     connection_text_input.run_if(in_state(TextSelectionState::Connection)),
+                    lobby_code_input.run_if(in_state(TextSelectionState::Lobby)),
+                ),
+            )
+            // the main buttons
+            .add_systems(
+                Update,
+                (
+                    play_online_button.run_if(in_state(GameState::MainMenu)),
+                    play_computer_button.run_if(in_state(GameState::MainMenu)),
+                    library_button.run_if(in_state(GameState::MainMenu)),
+                    settings_button.run_if(in_state(GameState::MainMenu)),
+                    quit_button.run_if(in_state(GameState::MainMenu)),
                 ),
             )
-            .add_systems(OnExit(GameState::MainMenu), despawn_screen::<Menu>);
+            // the recent servers
+            .add_systems(
+                OnEnter(TextSelectionState::Connection),
+                spawn_recent_servers.run_if(in_state(GameState::MainMenu)),
+            )
+            .add_systems(
+                OnExit(TextSelectionState::Connection),
+                despawn_screen::<RecentServers>,
+            )
+            .add_systems(
+                Update,
+                recent_server_button.run_if(in_state(GameState::MainMenu)),
+            )
+            .add_systems(
+                OnExit(GameState::MainMenu),
+                (despawn_screen::<Menu>, deselect_text),
+            );
+        #[cfg(feature = "server")]
+        app.add_systems(
+            Update,
+            host_server_button.run_if(in_state(GameState::MainMenu)),
+        );
     }
 }
 
 #[derive(Component)]
 struct Menu;
 
-#[derive(Component)]
-struct FpsText;
-
 #[derive(Component)]
 struct ColorText;
 
 #[derive(Component)]
 struct TextSelectionInput;
 
+#[derive(Component)]
+struct HandicapButton;
+
+#[derive(Component)]
+struct HandicapText;
+
+#[derive(Component)]
+struct ColorButton;
+
+#[derive(Component)]
+struct ColorPreferenceText;
+
+#[derive(Component)]
+struct TimeControlButton;
+
+#[derive(Component)]
+struct TimeControlText;
+
+#[derive(Component)]
+struct TimeOddsButton;
+
+#[derive(Component)]
+struct TimeOddsText;
+
+#[derive(Component)]
+struct RatedButton;
+
+#[derive(Component)]
+struct RatedText;
+
+#[derive(Component)]
+struct StatsButton;
+
+#[derive(Component)]
+struct StatsText;
+
+#[derive(Component)]
+struct GamesButton;
+
+#[derive(Component)]
+struct GamesText;
+
+#[derive(Component)]
+struct CreateLobbyButton;
+
+#[derive(Component)]
+struct LobbyCodeInput;
+
+#[derive(Component)]
+struct SeeksButton;
+
+#[derive(Component)]
+struct HistoryButton;
+
+#[derive(Component)]
+struct PlayOnlineButton;
+
+#[derive(Component)]
+struct PlayComputerButton;
+
+/// starts a server in the background and joins its queue
+#[cfg(feature = "server")]
+#[derive(Component)]
+struct HostServerButton;
+
+#[derive(Component)]
+struct LibraryButton;
+
+#[derive(Component)]
+struct SettingsButton;
+
+/// the list of recent servers above the address box
+#[derive(Component)]
+struct RecentServers;
+
+/// puts the server it holds in the address box
+#[derive(Component)]
+struct RecentServerButton(String);
+
+#[derive(Component)]
+struct QuitButton;
+
+/// your correspondence games, the server sends them after logging in
+#[derive(Debug, Clone, Event)]
+pub struct OngoingGamesEvent(pub Vec<OngoingGame>);
+
+/// the correspondence games the main menu lists, the number keys resume them
+#[derive(Resource, Default, Debug)]
+struct OngoingGames(Vec<OngoingGame>);
+
+/// how busy the server is, asked for from the main menu
+#[derive(Debug, Clone, PartialEq, Eq, Event)]
+pub struct ServerStatsEvent {
+    pub games: u32,
+    pub players_online: u32,
+    pub queue_length: u32,
+    pub uptime: Duration,
+    pub opening: Option<String>,
+}
+
 #[derive(States, Default, Debug, Clone, Copy, Hash, PartialEq, Eq, Component)]
 enum TextSelectionState {
     #[default]
     None,
     Connection,
+    Lobby,
 }
 
+/// the text in the address box
 #[derive(Resource, Deref, DerefMut)]
-struct ConnectionText(pub String);
+pub struct ConnectionText(pub String);
+
+impl FromWorld for ConnectionText {
+    fn from_world(world: &mut World) -> Self {
+        Self(world.resource::<Settings>().server.clone())
+    }
+}
+
+/// a server looked up in the background, a host name can take a while to resolve
+#[derive(Resource)]
+pub struct ServerLookup {
+    server: String,
+    #[cfg(not(target_arch = "wasm32"))]
+    task: Task<Option<SocketAddr>>,
+    /// browsers can't look host names up, so there the lookup fails right away
+    #[cfg(target_arch = "wasm32")]
+    task: Option<SocketAddr>,
+}
+
+impl ServerLookup {
+    /// connects to `server` from now on, right away for an ip address and once it is looked up
+    /// for a host name
+    pub fn start(commands: &mut Commands, server: &str, address: &mut ConnectionAddress) {
+        if let Ok(found) = server.parse() {
+            *address = ConnectionAddress(found);
+            return commands.remove_resource::<ServerLookup>();
+        }
+        let host = server.to_string();
+        #[cfg(not(target_arch = "wasm32"))]
+        let task = IoTaskPool::get().spawn(async move { host.to_socket_addrs().ok()?.next() });
+        #[cfg(target_arch = "wasm32")]
+        let task = host
+            .to_socket_addrs()
+            .ok()
+            .and_then(|mut found| found.next());
+        commands.insert_resource(ServerLookup {
+            server: server.to_string(),
+            task,
+        });
+    }
+}
 
-impl Default for ConnectionText {
-    fn default() -> Self {
-        Self("127.0.0.1:1812".into())
+fn lookup_saved_server(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    mut address: ResMut<ConnectionAddress>,
+) {
+    ServerLookup::start(&mut commands, &settings.server, &mut address);
+}
+
+/// connects to the looked up server, unless another one was picked in the meantime
+fn finish_server_lookup(
+    mut commands: Commands,
+    mut lookup: ResMut<ServerLookup>,
+    text: Res<ConnectionText>,
+    mut address: ResMut<ConnectionAddress>,
+) {
+    #[cfg(not(target_arch = "wasm32"))]
+    let Some(found) = block_on(poll_once(&mut lookup.task)) else {
+        return;
+    };
+    #[cfg(target_arch = "wasm32")]
+    let found = lookup.task.take();
+    commands.remove_resource::<ServerLookup>();
+    match found {
+        Some(found) if text.0 == lookup.server => *address = ConnectionAddress(found),
+        Some(_) => {}
+        None => warn!("couldn't look up {}", lookup.server),
     }
 }
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+/// the code of the lobby to join, typed in after clicking the join lobby box
+#[derive(Resource, Default, Deref, DerefMut)]
+struct LobbyCodeText(pub String);
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    handicap: Res<Handicap>,
+    color_preference: Res<ColorPreference>,
+    time_control: Res<QueueTimeControl>,
+    time_odds: Res<QueueTimeOdds>,
+    rated: Res<QueueRated>,
+    lobby_code: Res<LobbyCodeText>,
+    connection_text: Res<ConnectionText>,
+) {
     // UI camera
     commands.spawn((
         Camera2dBundle {
@@ -87,46 +328,47 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         Menu,
     ));
 
-    commands.spawn((
-        TextBundle::from_section(
-            "epic chess game!\nenter for server + client\nspace for client only",
-            TextStyle {
-                font: asset_server.load(FONT),
-                font_size: 100.0,
-                color: color::WHITE.into(),
-            },
-        )
-        .with_text_justify(JustifyText::Center)
-        .with_style(Style {
-            position_type: PositionType::Absolute,
-
-            bottom: Val::Px(5.0),
-            right: Val::Px(15.0),
-            ..default()
-        }),
-        ColorText,
-        Menu,
-    ));
-
-    commands.spawn((
-        TextBundle::from_sections([
-            TextSection::new(
-                "FPS: ",
-                TextStyle {
-                    font: asset_server.load(FONT),
-                    font_size: 60.0,
-                    color: color::WHITE.into(),
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(80.0),
+                    left: Val::Px(15.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Start,
+                    row_gap: Val::Px(10.0),
+                    ..default()
                 },
-            ),
-            TextSection::from_style(TextStyle {
-                font: asset_server.load(FONT),
-                font_size: 60.0,
-                color: color::GOLD.into(),
-            }),
-        ]),
-        FpsText,
-        Menu,
-    ));
+                ..default()
+            },
+            Menu,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "epic chess game!",
+                    TextStyle {
+                        font: asset_server.load(FONT),
+                        font_size: 100.0,
+                        color: color::WHITE.into(),
+                    },
+                ),
+                ColorText,
+            ));
+            spawn_main_button(parent, &asset_server, "play online", PlayOnlineButton);
+            spawn_main_button(
+                parent,
+                &asset_server,
+                "play vs computer",
+                PlayComputerButton,
+            );
+            #[cfg(feature = "server")]
+            spawn_main_button(parent, &asset_server, "host server", HostServerButton);
+            spawn_main_button(parent, &asset_server, "saved games", LibraryButton);
+            spawn_main_button(parent, &asset_server, "settings", SettingsButton);
+            spawn_main_button(parent, &asset_server, "quit", QuitButton);
+        });
 
     commands
         .spawn((
@@ -150,7 +392,7 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         .with_children(|parent| {
             parent.spawn((
                 TextBundle::from_section(
-                    ConnectionText::default().0,
+                    connection_text.0.clone(),
                     TextStyle {
                         font: asset_server.load(FONT),
                         font_size: 60.0,
@@ -160,68 +402,855 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
                 TextSelectionInput,
             ));
         });
-}
 
-fn text_color_system(time: Res<Time>, mut query: Query<&mut Text, With<ColorText>>) {
-    for mut text in query.iter_mut() {
-        let seconds = time.elapsed_seconds();
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(5.0),
+                    right: Val::Px(15.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: color::DARK_GRAY.into(),
+                ..default()
+            },
+            HandicapButton,
+            Menu,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    handicap_label(*handicap),
+                    TextStyle {
+                        font: asset_server.load(FONT),
+                        font_size: 40.0,
+                        color: color::WHITE.into(),
+                    },
+                ),
+                HandicapText,
+            ));
+        });
 
-        // Update the color of the first and only section.
-        text.sections[0].style.color = Srgba {
-            red: (1.25 * seconds).sin() / 2.0 + 0.5,
-            green: (0.75 * seconds).sin() / 2.0 + 0.5,
-            blue: (0.50 * seconds).sin() / 2.0 + 0.5,
-            alpha: 1.0,
-        }
-        .into();
-    }
-}
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(75.0),
+                    right: Val::Px(15.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: color::DARK_GRAY.into(),
+                ..default()
+            },
+            ColorButton,
+            Menu,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    color_label(*color_preference),
+                    TextStyle {
+                        font: asset_server.load(FONT),
+                        font_size: 40.0,
+                        color: color::WHITE.into(),
+                    },
+                ),
+                ColorPreferenceText,
+            ));
+        });
 
-fn text_update_system(
-    diagnostics: Res<DiagnosticsStore>,
-    mut query: Query<&mut Text, With<FpsText>>,
-) {
-    for mut text in query.iter_mut() {
-        if let Some(fps) = diagnostics.get(&FrameTimeDiagnosticsPlugin::FPS) {
-            if let Some(value) = fps.smoothed() {
-                // Update the value of the second section
-                text.sections[1].value = format!("{value:.2}");
-            }
-        }
-    }
-}
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(145.0),
+                    right: Val::Px(15.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: color::DARK_GRAY.into(),
+                ..default()
+            },
+            TimeControlButton,
+            Menu,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    time_control_label(time_control.0),
+                    TextStyle {
+                        font: asset_server.load(FONT),
+                        font_size: 40.0,
+                        color: color::WHITE.into(),
+                    },
+                ),
+                TimeControlText,
+            ));
+        });
 
-fn keyboard_input_system(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut start_game: EventWriter<MakeConnectionEvent>,
-    #[cfg(feature = "server")] server_port: Res<ConnectionAddress>,
-) {
-    if keyboard_input.just_pressed(KeyCode::Enter) {
-        #[cfg(feature = "server")]
-        {
-            let port = server_port.0;
-            std::thread::spawn(move || server::start_server(port));
-        }
-        start_game.send(MakeConnectionEvent);
-    }
-    if keyboard_input.just_pressed(KeyCode::Space) {
-        start_game.send(MakeConnectionEvent);
-    }
-}
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(215.0),
+                    right: Val::Px(15.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: color::DARK_GRAY.into(),
+                ..default()
+            },
+            TimeOddsButton,
+            Menu,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    time_odds_label(time_odds.0),
+                    TextStyle {
+                        font: asset_server.load(FONT),
+                        font_size: 40.0,
+                        color: color::WHITE.into(),
+                    },
+                ),
+                TimeOddsText,
+            ));
+        });
 
-fn connection_text_input(
-    mut evr_char: EventReader<KeyboardInput>,
-    keys: Res<ButtonInput<KeyCode>>,
-    mut input: Query<&mut Text, With<TextSelectionInput>>,
-    mut string: ResMut<ConnectionText>,
-    mut address: ResMut<ConnectionAddress>,
-) {
-    let mut changed = false;
-    for ev in evr_char.read() {
-        if !ev.state.is_pressed() {
-            continue;
-        }
-        let control_pressed = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(285.0),
+                    right: Val::Px(15.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: color::DARK_GRAY.into(),
+                ..default()
+            },
+            RatedButton,
+            Menu,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    rated_label(rated.0),
+                    TextStyle {
+                        font: asset_server.load(FONT),
+                        font_size: 40.0,
+                        color: color::WHITE.into(),
+                    },
+                ),
+                RatedText,
+            ));
+        });
+
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(355.0),
+                    right: Val::Px(15.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: color::DARK_GRAY.into(),
+                ..default()
+            },
+            StatsButton,
+            Menu,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "server info",
+                    TextStyle {
+                        font: asset_server.load(FONT),
+                        font_size: 40.0,
+                        color: color::WHITE.into(),
+                    },
+                ),
+                StatsText,
+            ));
+        });
+
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(425.0),
+                    right: Val::Px(15.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: color::DARK_GRAY.into(),
+                ..default()
+            },
+            GamesButton,
+            Menu,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "my games",
+                    TextStyle {
+                        font: asset_server.load(FONT),
+                        font_size: 40.0,
+                        color: color::WHITE.into(),
+                    },
+                ),
+                GamesText,
+            ));
+        });
+
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(495.0),
+                    right: Val::Px(15.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: color::DARK_GRAY.into(),
+                ..default()
+            },
+            CreateLobbyButton,
+            Menu,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "play a friend",
+                TextStyle {
+                    font: asset_server.load(FONT),
+                    font_size: 40.0,
+                    color: color::WHITE.into(),
+                },
+            ));
+        });
+
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(565.0),
+                    right: Val::Px(15.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: color::DARK_GRAY.into(),
+                ..default()
+            },
+            TextSelectionInput,
+            TextSelectionState::Lobby,
+            Menu,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    lobby_code_label(&lobby_code),
+                    TextStyle {
+                        font: asset_server.load(FONT),
+                        font_size: 40.0,
+                        color: color::WHITE.into(),
+                    },
+                ),
+                LobbyCodeInput,
+            ));
+        });
+
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(635.0),
+                    right: Val::Px(15.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: color::DARK_GRAY.into(),
+                ..default()
+            },
+            SeeksButton,
+            Menu,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "open seeks",
+                TextStyle {
+                    font: asset_server.load(FONT),
+                    font_size: 40.0,
+                    color: color::WHITE.into(),
+                },
+            ));
+        });
+
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(705.0),
+                    right: Val::Px(15.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: color::DARK_GRAY.into(),
+                ..default()
+            },
+            HistoryButton,
+            Menu,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "game history",
+                TextStyle {
+                    font: asset_server.load(FONT),
+                    font_size: 40.0,
+                    color: color::WHITE.into(),
+                },
+            ));
+        });
+}
+
+/// a button in the column under the title
+fn spawn_main_button(
+    parent: &mut ChildBuilder,
+    asset_server: &AssetServer,
+    label: &str,
+    button: impl Component,
+) {
+    parent
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: color::DARK_GRAY.into(),
+                ..default()
+            },
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font: asset_server.load(FONT),
+                    font_size: 60.0,
+                    color: color::WHITE.into(),
+                },
+            ));
+        });
+}
+
+fn handicap_label(handicap: Handicap) -> String {
+    format!("odds you give: {handicap}")
+}
+
+fn handicap_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<HandicapButton>)>,
+    mut text: Query<&mut Text, With<HandicapText>>,
+    mut handicap: ResMut<Handicap>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            *handicap = handicap.next();
+            for mut text in text.iter_mut() {
+                text.sections[0].value = handicap_label(*handicap);
+            }
+        }
+    }
+}
+
+fn color_label(color_preference: ColorPreference) -> String {
+    format!("you play: {color_preference}")
+}
+
+fn color_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<ColorButton>)>,
+    mut text: Query<&mut Text, With<ColorPreferenceText>>,
+    mut color_preference: ResMut<ColorPreference>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            *color_preference = color_preference.next();
+            for mut text in text.iter_mut() {
+                text.sections[0].value = color_label(*color_preference);
+            }
+        }
+    }
+}
+
+fn time_control_label(time_control: Option<TimeControl>) -> String {
+    match time_control {
+        Some(time_control) => format!("clock: {time_control}"),
+        None => "clock: none".to_string(),
+    }
+}
+
+fn time_control_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<TimeControlButton>)>,
+    mut text: Query<&mut Text, With<TimeControlText>>,
+    mut time_control: ResMut<QueueTimeControl>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            time_control.0 = TimeControl::next(time_control.0);
+            for mut text in text.iter_mut() {
+                text.sections[0].value = time_control_label(time_control.0);
+            }
+        }
+    }
+}
+
+/// odds only count in casual games with a clock, they get dropped otherwise
+fn time_odds_label(time_odds: Option<TimeControl>) -> String {
+    match time_odds {
+        Some(time_odds) => format!("their clock: {time_odds}"),
+        None => "their clock: same".to_string(),
+    }
+}
+
+fn time_odds_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<TimeOddsButton>)>,
+    mut text: Query<&mut Text, With<TimeOddsText>>,
+    mut time_odds: ResMut<QueueTimeOdds>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            time_odds.0 = TimeControl::next(time_odds.0);
+            for mut text in text.iter_mut() {
+                text.sections[0].value = time_odds_label(time_odds.0);
+            }
+        }
+    }
+}
+
+fn rated_label(rated: bool) -> String {
+    if rated { "rated" } else { "casual" }.to_string()
+}
+
+fn rated_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<RatedButton>)>,
+    mut text: Query<&mut Text, With<RatedText>>,
+    mut rated: ResMut<QueueRated>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            rated.0 = !rated.0;
+            for mut text in text.iter_mut() {
+                text.sections[0].value = rated_label(rated.0);
+            }
+        }
+    }
+}
+
+fn play_online_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<PlayOnlineButton>)>,
+    mut make_connection: EventWriter<MakeConnectionEvent>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            make_connection.send(MakeConnectionEvent(ConnectionPurpose::Play));
+        }
+    }
+}
+
+fn play_computer_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<PlayComputerButton>)>,
+    mut make_connection: EventWriter<MakeConnectionEvent>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            make_connection.send(MakeConnectionEvent(ConnectionPurpose::Computer));
+        }
+    }
+}
+
+#[cfg(feature = "server")]
+fn host_server_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<HostServerButton>)>,
+    mut make_connection: EventWriter<MakeConnectionEvent>,
+    address: Res<ConnectionAddress>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            let address = address.0;
+            std::thread::spawn(move || server::start_server(address, Default::default()));
+            make_connection.send(MakeConnectionEvent(ConnectionPurpose::Play));
+        }
+    }
+}
+
+fn quit_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<QuitButton>)>,
+    mut exit: EventWriter<AppExit>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            exit.send(AppExit::Success);
+        }
+    }
+}
+
+fn library_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<LibraryButton>)>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            game_state.set(GameState::Library);
+        }
+    }
+}
+
+fn settings_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<SettingsButton>)>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            game_state.set(GameState::Settings);
+        }
+    }
+}
+
+/// the address the player connects to is the default the next time, and one of the recent servers
+fn remember_server(
+    mut events: EventReader<MakeConnectionEvent>,
+    text: Res<ConnectionText>,
+    mut settings: ResMut<Settings>,
+) {
+    if events.read().count() == 0
+        || settings.server == text.0 && settings.recent_servers.first() == Some(&text.0)
+    {
+        return;
+    }
+    if text
+        .to_socket_addrs()
+        .is_ok_and(|mut addresses| addresses.next().is_some())
+    {
+        settings.remember_server(&text.0);
+    }
+}
+
+/// lists the recent servers above the address box while it is selected
+fn spawn_recent_servers(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<Settings>,
+) {
+    if settings.recent_servers.is_empty() {
+        return;
+    }
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(75.0),
+                    left: Val::Px(15.0),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Start,
+                    row_gap: Val::Px(5.0),
+                    ..default()
+                },
+                ..default()
+            },
+            RecentServers,
+            Menu,
+        ))
+        .with_children(|parent| {
+            for server in &settings.recent_servers {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::all(Val::Px(5.0)),
+                                ..default()
+                            },
+                            background_color: color::DARK_GRAY.into(),
+                            ..default()
+                        },
+                        RecentServerButton(server.clone()),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            server.clone(),
+                            TextStyle {
+                                font: asset_server.load(FONT),
+                                font_size: 40.0,
+                                color: color::WHITE.into(),
+                            },
+                        ));
+                    });
+            }
+        });
+}
+
+/// puts the clicked recent server in the address box
+fn recent_server_button(
+    query: Query<(&Interaction, &RecentServerButton), Changed<Interaction>>,
+    mut input: Query<&mut Text, With<TextSelectionInput>>,
+    mut string: ResMut<ConnectionText>,
+    mut address: ResMut<ConnectionAddress>,
+) {
+    for (&interaction, RecentServerButton(server)) in query.iter() {
+        if interaction == Interaction::Pressed {
+            string.clone_from(server);
+            for mut text in input.iter_mut() {
+                show_address(&mut text, server, &mut address);
+            }
+        }
+    }
+}
+
+/// the text typed into the address box turns red when it isn't an address
+fn show_address(text: &mut Text, string: &str, address: &mut ConnectionAddress) {
+    text.sections[0].value = string.to_string();
+    match string.to_socket_addrs().map(|mut p| p.next()) {
+        Ok(Some(v)) => {
+            *address = ConnectionAddress(v);
+            text.sections[0].style.color = color::WHITE.into();
+        }
+        Err(_) | Ok(None) => text.sections[0].style.color = color::ORANGE_RED.into(),
+    };
+}
+
+/// the text boxes only take keys in the main menu
+fn deselect_text(mut selection_state: ResMut<NextState<TextSelectionState>>) {
+    selection_state.set(TextSelectionState::None);
+}
+
+fn stats_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<StatsButton>)>,
+    mut text: Query<&mut Text, With<StatsText>>,
+    mut make_connection: EventWriter<MakeConnectionEvent>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            make_connection.send(MakeConnectionEvent(ConnectionPurpose::Stats));
+            for mut text in text.iter_mut() {
+                text.sections[0].value = "asking the server...".to_string();
+            }
+        }
+    }
+}
+
+fn show_stats(
+    mut events: EventReader<ServerStatsEvent>,
+    mut text: Query<&mut Text, With<StatsText>>,
+) {
+    for stats in events.read() {
+        let minutes = stats.uptime.as_secs() / 60;
+        for mut text in text.iter_mut() {
+            text.sections[0].value = format!(
+                "{} games, {} online, {} in queue, up {}h {:02}m",
+                stats.games,
+                stats.players_online,
+                stats.queue_length,
+                minutes / 60,
+                minutes % 60
+            );
+            if let Some(opening) = &stats.opening {
+                text.sections[0].value += &format!("\nmost played: {}", opening);
+            }
+        }
+    }
+}
+
+fn games_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<GamesButton>)>,
+    mut text: Query<&mut Text, With<GamesText>>,
+    mut make_connection: EventWriter<MakeConnectionEvent>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            make_connection.send(MakeConnectionEvent(ConnectionPurpose::Games));
+            for mut text in text.iter_mut() {
+                text.sections[0].value = "asking the server...".to_string();
+            }
+        }
+    }
+}
+
+fn show_games(
+    mut events: EventReader<OngoingGamesEvent>,
+    mut text: Query<&mut Text, With<GamesText>>,
+    mut games: ResMut<OngoingGames>,
+) {
+    for OngoingGamesEvent(list) in events.read() {
+        let value = if list.is_empty() {
+            "no correspondence games".to_string()
+        } else {
+            (1..)
+                .zip(list)
+                .map(|(i, game)| {
+                    format!(
+                        "{}: vs {}{}",
+                        i,
+                        game.opponent.name,
+                        if game.your_move { ", your move" } else { "" }
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        for mut text in text.iter_mut() {
+            text.sections[0].value.clone_from(&value);
+        }
+        games.0.clone_from(list);
+    }
+}
+
+fn create_lobby_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<CreateLobbyButton>)>,
+    mut make_connection: EventWriter<MakeConnectionEvent>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            make_connection.send(MakeConnectionEvent(ConnectionPurpose::CreateLobby));
+        }
+    }
+}
+
+fn seeks_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<SeeksButton>)>,
+    mut make_connection: EventWriter<MakeConnectionEvent>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            make_connection.send(MakeConnectionEvent(ConnectionPurpose::Seeks));
+        }
+    }
+}
+
+fn history_button(
+    query: Query<&Interaction, (Changed<Interaction>, With<HistoryButton>)>,
+    mut make_connection: EventWriter<MakeConnectionEvent>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            make_connection.send(MakeConnectionEvent(ConnectionPurpose::History));
+        }
+    }
+}
+
+fn lobby_code_label(code: &str) -> String {
+    if code.is_empty() {
+        "join a friend: click and type their code".to_string()
+    } else {
+        format!("join a friend: {code}, enter to join")
+    }
+}
+
+fn lobby_code_input(
+    mut keyboard_event: EventReader<KeyboardInput>,
+    mut input: Query<&mut Text, With<LobbyCodeInput>>,
+    mut code: ResMut<LobbyCodeText>,
+    mut make_connection: EventWriter<MakeConnectionEvent>,
+) {
+    let mut changed = false;
+    for event in keyboard_event.read() {
+        if !event.state.is_pressed() {
+            continue;
+        }
+        match &event.logical_key {
+            Key::Enter => {
+                if let Ok(code) = code.parse::<LobbyCode>() {
+                    make_connection.send(MakeConnectionEvent(ConnectionPurpose::JoinLobby(code)));
+                }
+            }
+            Key::Backspace => {
+                code.pop();
+            }
+            Key::Character(str) => code.push_str(&str.to_ascii_uppercase()),
+            _ => {}
+        }
+        changed = true;
+    }
+    if changed {
+        for mut text in input.iter_mut() {
+            text.sections[0].value = lobby_code_label(&code);
+            text.sections[0].style.color = if code.is_empty() || code.parse::<LobbyCode>().is_ok() {
+                color::WHITE.into()
+            } else {
+                color::ORANGE_RED.into()
+            };
+        }
+    }
+}
+
+fn text_color_system(time: Res<Time>, mut query: Query<&mut Text, With<ColorText>>) {
+    for mut text in query.iter_mut() {
+        let seconds = time.elapsed_seconds();
+
+        // Update the color of the first and only section.
+        text.sections[0].style.color = Srgba {
+            red: (1.25 * seconds).sin() / 2.0 + 0.5,
+            green: (0.75 * seconds).sin() / 2.0 + 0.5,
+            blue: (0.50 * seconds).sin() / 2.0 + 0.5,
+            alpha: 1.0,
+        }
+        .into();
+    }
+}
+
+fn keyboard_input_system(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut start_game: EventWriter<MakeConnectionEvent>,
+    games: Res<OngoingGames>,
+    selection: Res<State<TextSelectionState>>,
+) {
+    // the number keys resume the listed games, unless they are typed into the address
+    if *selection.get() != TextSelectionState::None {
+        return;
+    }
+    let digits = [
+        KeyCode::Digit1,
+        KeyCode::Digit2,
+        KeyCode::Digit3,
+        KeyCode::Digit4,
+        KeyCode::Digit5,
+        KeyCode::Digit6,
+        KeyCode::Digit7,
+        KeyCode::Digit8,
+        KeyCode::Digit9,
+    ];
+    for (key, game) in digits.into_iter().zip(&games.0) {
+        if keyboard_input.just_pressed(key) {
+            start_game.send(MakeConnectionEvent(ConnectionPurpose::Resume(game.token)));
+        }
+    }
+}
+
+fn connection_text_input(
+    mut evr_char: EventReader<KeyboardInput>,
+    keys: Res<ButtonInput<KeyCode>>,
+    mut input: Query<&mut Text, With<TextSelectionInput>>,
+    mut string: ResMut<ConnectionText>,
+    mut address: ResMut<ConnectionAddress>,
+) {
+    let mut changed = false;
+    for ev in evr_char.read() {
+        if !ev.state.is_pressed() {
+            continue;
+        }
+        let control_pressed = keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
 
         match (control_pressed, &ev.logical_key, &ev.key_code) {
             (true, Key::Backspace, _) | (true, _, KeyCode::Backspace) => {
@@ -231,23 +1260,15 @@ fn connection_text_input(
                 string.pop();
             }
             (true, _, KeyCode::KeyV) => {
-                if let Ok(mut ctx) = ClipboardContext::new() {
-                    if let Ok(clipboard) = ctx.get_contents() {
-                        string.push_str(&clipboard);
-                    }
+                if let Some(clipboard) = clipboard::get() {
+                    string.push_str(&clipboard);
                 }
             }
             (true, _, KeyCode::KeyC) => {
-                if let Ok(mut ctx) = ClipboardContext::new() {
-                    let _ = ctx.set_contents(string.0.clone());
-                }
+                clipboard::set(string.0.clone());
             }
-            (true, _, KeyCode::KeyX) => {
-                if let Ok(mut ctx) = ClipboardContext::new() {
-                    if let Ok(()) = ctx.set_contents(string.0.clone()) {
-                        string.clear();
-                    }
-                }
+            (true, _, KeyCode::KeyX) if clipboard::set(string.0.clone()) => {
+                string.clear();
             }
             (false, Key::Character(str), _) => {
                 string.push_str(str);
@@ -260,15 +1281,7 @@ fn connection_text_input(
         changed = true;
     }
     if changed {
-        let input = input.single_mut().into_inner();
-        input.sections[0].value.clone_from(&string);
-        match string.to_socket_addrs().map(|mut p| p.next()) {
-            Ok(Some(v)) => {
-                *address = ConnectionAddress(v);
-                input.sections[0].style.color = color::WHITE.into();
-            }
-            Err(_) | Ok(None) => input.sections[0].style.color = color::ORANGE_RED.into(),
-        };
+        show_address(&mut input.single_mut(), &string, &mut address);
     }
 }
 
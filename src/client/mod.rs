@@ -2,26 +2,70 @@ use bevy::{diagnostic::FrameTimeDiagnosticsPlugin, prelude::*};
 
 use crate::api::EndReason;
 
+mod announcement;
+mod clipboard;
+mod fps;
 mod game;
+mod history;
+mod library;
 mod loading;
 mod main_menu;
 mod networking;
+mod seeks;
+mod settings;
+mod settings_menu;
+
+pub use networking::{Credentials, LeagueEntry, SimulEntry};
 
 const FONT: &str = "fonts/impact.ttf";
 
-pub fn start_client() {
-    App::new()
-        .add_plugins(DefaultPlugins)
-        .init_state::<GameState>()
-        .add_event::<VictoryEvent>()
-        .add_plugins((
-            networking::NetworkingPlugin,
-            main_menu::MenuPlugin,
-            game::GamePlugin,
-            loading::LoadPlugin,
-            FrameTimeDiagnosticsPlugin,
-        ))
-        .run();
+pub fn start_client(
+    name: Option<String>,
+    challenge: Option<String>,
+    league: Option<networking::LeagueEntry>,
+    simul: Option<networking::SimulEntry>,
+    account: Option<networking::Credentials>,
+) {
+    let mut settings = settings::Settings::load();
+    // a name from the command line is remembered for the next time
+    if name.is_some() && name != settings.name {
+        settings.name.clone_from(&name);
+        if let Err(error) = settings.save() {
+            warn!("couldn't save the settings: {}", error);
+        }
+    }
+    let mut app = App::new();
+    app.add_plugins(DefaultPlugins.set(WindowPlugin {
+        primary_window: Some(Window {
+            // in a browser the canvas follows the size of the page
+            fit_canvas_to_parent: true,
+            ..default()
+        }),
+        ..default()
+    }))
+    .insert_resource(networking::PlayerName(name.or(settings.name.clone())))
+    .insert_resource(settings)
+    .insert_resource(networking::ChallengeTarget(challenge))
+    .insert_resource(networking::LeagueTarget(league))
+    .insert_resource(networking::SimulTarget(simul))
+    .insert_resource(networking::AccountLogin(account))
+    .init_state::<GameState>()
+    .add_event::<VictoryEvent>()
+    .add_plugins((
+        networking::NetworkingPlugin,
+        announcement::AnnouncementPlugin,
+        fps::FpsPlugin,
+        main_menu::MenuPlugin,
+        game::GamePlugin,
+        history::HistoryPlugin,
+        library::LibraryPlugin,
+        loading::LoadPlugin,
+        seeks::SeeksPlugin,
+        settings::SettingsPlugin,
+        settings_menu::SettingsMenuPlugin,
+        FrameTimeDiagnosticsPlugin,
+    ))
+    .run();
 }
 
 #[derive(Default, Debug, Clone, Copy, Hash, PartialEq, Eq, States)]
@@ -30,6 +74,13 @@ pub enum GameState {
     MainMenu,
     Loading,
     Gaming,
+    /// browsing the games other players offer
+    Seeks,
+    Settings,
+    /// the games saved after playing them
+    Library,
+    /// the games you finished on the server
+    History,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
@@ -0,0 +1,365 @@
+//! the connection to the server in a browser. bevy_slinet needs threads and sockets a browser
+//! doesn't have, so the browser build talks to the websocket transport of the server with the
+//! websocket of the browser. it has the same api as the client of bevy_slinet and frames packets
+//! the same way, a little endian u32 length followed by the packet encoded by wire
+
+use std::{
+    cell::RefCell,
+    error::Error,
+    fmt,
+    marker::PhantomData,
+    net::SocketAddr,
+    ops::Deref,
+    rc::Rc,
+    sync::mpsc::{self, Receiver, Sender},
+};
+
+use bevy::prelude::*;
+use js_sys::{ArrayBuffer, Uint8Array};
+use wasm_bindgen::{closure::Closure, JsCast, JsValue};
+use web_sys::{BinaryType, MessageEvent, WebSocket};
+
+use crate::api::{
+    wire::{self, WireError},
+    ClientPacket, ServerPacket,
+};
+
+/// bytes of the length in front of every packet
+const LENGTH: usize = 4;
+
+pub struct ClientPlugin<C>(PhantomData<fn() -> C>);
+
+impl<C> Default for ClientPlugin<C> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<C> ClientPlugin<C> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<C: 'static> Plugin for ClientPlugin<C> {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ConnectionRequestEvent<C>>()
+            .add_event::<ConnectionEstablishEvent<C>>()
+            .add_event::<DisconnectionEvent<C>>()
+            .add_event::<PacketReceiveEvent<C>>()
+            .insert_resource(ClientConnections::<C>(Vec::new()))
+            .insert_non_send_resource(Sockets::<C> {
+                sockets: Vec::new(),
+                next_id: 0,
+            })
+            .add_systems(PreUpdate, (open_sockets::<C>, poll_sockets::<C>).chain());
+    }
+}
+
+/// a connection to the server, the resource is the one opened last
+#[derive(Resource)]
+pub struct ClientConnection<C> {
+    id: u32,
+    address: SocketAddr,
+    outgoing: Sender<Outgoing>,
+    config: PhantomData<fn() -> C>,
+}
+
+impl<C> Clone for ClientConnection<C> {
+    fn clone(&self) -> Self {
+        Self {
+            id: self.id,
+            address: self.address,
+            outgoing: self.outgoing.clone(),
+            config: PhantomData,
+        }
+    }
+}
+
+impl<C> ClientConnection<C> {
+    /// queues the packet, it goes out once the websocket is open
+    pub fn send(&self, packet: ClientPacket) -> Result<(), SendError> {
+        let packet = wire::encode(&packet).map_err(SendError::Packet)?;
+        let mut frame = (packet.len() as u32).to_le_bytes().to_vec();
+        frame.extend(packet);
+        self.outgoing
+            .send(Outgoing::Frame(frame))
+            .map_err(|_| SendError::Closed)
+    }
+
+    pub fn disconnect(&self) {
+        // a connection that is closed already has nothing left to close
+        let _ = self.outgoing.send(Outgoing::Close);
+    }
+}
+
+#[derive(Debug)]
+pub enum SendError {
+    Packet(WireError),
+    /// the connection is gone
+    Closed,
+}
+
+impl fmt::Display for SendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SendError::Packet(err) => err.fmt(f),
+            SendError::Closed => write!(f, "the connection is closed"),
+        }
+    }
+}
+
+impl Error for SendError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SendError::Packet(err) => Some(err),
+            SendError::Closed => None,
+        }
+    }
+}
+
+enum Outgoing {
+    Frame(Vec<u8>),
+    Close,
+}
+
+/// every open connection, the oldest first
+#[derive(Resource)]
+pub struct ClientConnections<C>(Vec<ClientConnection<C>>);
+
+impl<C> Deref for ClientConnections<C> {
+    type Target = Vec<ClientConnection<C>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// opens a connection, followed by a ConnectionEstablishEvent or a DisconnectionEvent
+#[derive(Event)]
+pub struct ConnectionRequestEvent<C> {
+    address: SocketAddr,
+    config: PhantomData<fn() -> C>,
+}
+
+impl<C> ConnectionRequestEvent<C> {
+    pub fn new(address: SocketAddr) -> Self {
+        Self {
+            address,
+            config: PhantomData,
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct ConnectionEstablishEvent<C> {
+    pub connection: ClientConnection<C>,
+}
+
+/// the connection closed, or couldn't be opened
+#[derive(Event)]
+pub struct DisconnectionEvent<C> {
+    pub address: SocketAddr,
+    config: PhantomData<fn() -> C>,
+}
+
+#[derive(Event)]
+pub struct PacketReceiveEvent<C> {
+    pub connection: ClientConnection<C>,
+    pub packet: ServerPacket,
+}
+
+/// what the callbacks of a websocket saw since the last frame
+#[derive(Default)]
+struct Received {
+    open: bool,
+    closed: bool,
+    bytes: Vec<u8>,
+}
+
+/// a websocket and the callbacks it calls, the websocket can't leave the main thread
+struct Socket<C> {
+    connection: ClientConnection<C>,
+    websocket: WebSocket,
+    outgoing: Receiver<Outgoing>,
+    received: Rc<RefCell<Received>>,
+    /// whether the game got its ConnectionEstablishEvent
+    established: bool,
+    /// the start of a packet that didn't arrive completely yet
+    buffer: Vec<u8>,
+    _on_open: Closure<dyn FnMut()>,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_close: Closure<dyn FnMut()>,
+}
+
+impl<C> Socket<C> {
+    fn open(id: u32, address: SocketAddr) -> Result<Self, JsValue> {
+        let websocket = WebSocket::new(&format!("ws://{address}"))?;
+        websocket.set_binary_type(BinaryType::Arraybuffer);
+        let received = Rc::<RefCell<Received>>::default();
+        let on_open = {
+            let received = Rc::clone(&received);
+            Closure::<dyn FnMut()>::new(move || received.borrow_mut().open = true)
+        };
+        let on_message = {
+            let received = Rc::clone(&received);
+            Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+                // the server only sends binary messages
+                if let Ok(data) = event.data().dyn_into::<ArrayBuffer>() {
+                    let mut received = received.borrow_mut();
+                    received.bytes.extend(Uint8Array::new(&data).to_vec());
+                }
+            })
+        };
+        // an error closes the websocket too
+        let on_close = {
+            let received = Rc::clone(&received);
+            Closure::<dyn FnMut()>::new(move || received.borrow_mut().closed = true)
+        };
+        websocket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        websocket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        websocket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+        websocket.set_onerror(Some(on_close.as_ref().unchecked_ref()));
+        let (sender, outgoing) = mpsc::channel();
+        Ok(Self {
+            connection: ClientConnection {
+                id,
+                address,
+                outgoing: sender,
+                config: PhantomData,
+            },
+            websocket,
+            outgoing,
+            received,
+            established: false,
+            buffer: Vec::new(),
+            _on_open: on_open,
+            _on_message: on_message,
+            _on_close: on_close,
+        })
+    }
+
+    /// the packets that arrived completely, Err once one of them is broken
+    fn packets(&mut self) -> Result<Vec<ServerPacket>, WireError> {
+        self.buffer
+            .append(&mut std::mem::take(&mut self.received.borrow_mut().bytes));
+        let mut packets = Vec::new();
+        while let Some(length) = self.buffer.get(..LENGTH) {
+            let length = u32::from_le_bytes([length[0], length[1], length[2], length[3]]) as usize;
+            if self.buffer.len() < LENGTH + length {
+                break;
+            }
+            packets.push(wire::decode(&self.buffer[LENGTH..LENGTH + length])?);
+            self.buffer.drain(..LENGTH + length);
+        }
+        Ok(packets)
+    }
+
+    fn close(&self) {
+        self.websocket.set_onopen(None);
+        self.websocket.set_onmessage(None);
+        self.websocket.set_onclose(None);
+        self.websocket.set_onerror(None);
+        if let Err(error) = self.websocket.close() {
+            warn!("couldn't close the websocket: {:?}", error);
+        }
+    }
+}
+
+/// the websockets of every connection
+struct Sockets<C> {
+    sockets: Vec<Socket<C>>,
+    next_id: u32,
+}
+
+fn open_sockets<C: 'static>(
+    mut requests: EventReader<ConnectionRequestEvent<C>>,
+    mut sockets: NonSendMut<Sockets<C>>,
+    mut disconnections: EventWriter<DisconnectionEvent<C>>,
+) {
+    for request in requests.read() {
+        let id = sockets.next_id;
+        sockets.next_id += 1;
+        match Socket::open(id, request.address) {
+            Ok(socket) => sockets.sockets.push(socket),
+            Err(error) => {
+                warn!("couldn't connect to {}: {:?}", request.address, error);
+                disconnections.send(DisconnectionEvent {
+                    address: request.address,
+                    config: PhantomData,
+                });
+            }
+        }
+    }
+}
+
+/// passes on what the websockets received and sends what the game queued, like bevy_slinet
+/// the connection resource is the last one left after a connection closes
+fn poll_sockets<C: 'static>(
+    mut commands: Commands,
+    mut sockets: NonSendMut<Sockets<C>>,
+    mut connections: ResMut<ClientConnections<C>>,
+    mut established: EventWriter<ConnectionEstablishEvent<C>>,
+    mut packets: EventWriter<PacketReceiveEvent<C>>,
+    mut disconnections: EventWriter<DisconnectionEvent<C>>,
+) {
+    sockets.sockets.retain_mut(|socket| {
+        let (open, mut closed) = {
+            let received = socket.received.borrow();
+            (received.open, received.closed)
+        };
+        if open && !socket.established {
+            socket.established = true;
+            commands.insert_resource(socket.connection.clone());
+            connections.0.push(socket.connection.clone());
+            established.send(ConnectionEstablishEvent {
+                connection: socket.connection.clone(),
+            });
+        }
+        match socket.packets() {
+            Ok(received) => {
+                packets.send_batch(received.into_iter().map(|packet| PacketReceiveEvent {
+                    connection: socket.connection.clone(),
+                    packet,
+                }));
+            }
+            Err(error) => {
+                warn!("got a broken packet, disconnecting: {}", error);
+                closed = true;
+            }
+        }
+        // packets queued while the websocket was connecting wait for it to open
+        let outgoing = socket.established.then(|| socket.outgoing.try_iter());
+        for outgoing in outgoing.into_iter().flatten() {
+            let sent = match outgoing {
+                Outgoing::Frame(frame) => socket.websocket.send_with_u8_array(&frame),
+                Outgoing::Close => {
+                    closed = true;
+                    break;
+                }
+            };
+            if let Err(error) = sent {
+                warn!("couldn't send a packet: {:?}", error);
+                closed = true;
+                break;
+            }
+        }
+        if !closed {
+            return true;
+        }
+        socket.close();
+        let id = socket.connection.id;
+        if socket.established {
+            commands.remove_resource::<ClientConnection<C>>();
+            connections.0.retain(|connection| connection.id != id);
+            if let Some(connection) = connections.0.last() {
+                commands.insert_resource(connection.clone());
+            }
+        }
+        disconnections.send(DisconnectionEvent {
+            address: socket.connection.address,
+            config: PhantomData,
+        });
+        false
+    });
+}
@@ -1,55 +1,148 @@
 use std::{
     fmt,
     net::{Ipv4Addr, SocketAddr, SocketAddrV4},
+    time::Duration,
 };
 
-use bevy::{prelude::*, window::WindowCloseRequested};
-use bevy_slinet::client::{
+use bevy::{ecs::system::SystemParam, prelude::*, window::WindowCloseRequested};
+use connection::{
     ClientConnection, ClientConnections, ClientPlugin, ConnectionEstablishEvent,
-    ConnectionRequestEvent, PacketReceiveEvent,
+    ConnectionRequestEvent, DisconnectionEvent, PacketReceiveEvent,
 };
 
 use crate::{
     api::{
-        chessmove::ChessColor, chessstate::ChessState, ClientPacket, Config, GameEnd, ServerPacket,
+        chessmove::{compress_chessboard, ChessColor, CompressedChessboard, PlayedMove},
+        chessstate::ChessState,
+        clock::Clock,
+        handicap::Handicap,
+        league::{LeagueId, LeagueSchedule},
+        matchmaking::{ColorPreference, LobbyCode, Seek, TimeControl, Variant},
+        ping::{round_trip, timestamp, PingTimer},
+        simul::SimulId,
+        ClientNetConfig, ClientPacket, GameEnd, GameId, GameToken, ServerError, ServerPacket,
+        SessionToken,
+    },
+    client::game::{
+        AnalysisEvent, AskHintEvent, ChatMessageEvent, HintEvent, MoveHistory,
+        OpponentDisconnectedEvent, OpponentPromotionEvent, OpponentReconnectedEvent, Players,
+        RematchEvent, RematchOffer, RematchOfferedEvent, RequestTakebackEvent,
+        RespondTakebackEvent, SendChatEvent, SpectatorsEvent, TakebackDeclinedEvent,
+        TakebackRequestedEvent,
     },
-    client::game::OpponentPromotionEvent,
 };
 
 use super::{
+    announcement::AnnouncementEvent,
     game::{
-        DrawRequestedEvent, MoveEvent, OpponentMoveEvent, PromotionMoveEvent, RedrawBoardEvent,
+        AbortEvent, DeclineDrawEvent, DrawDeclinedEvent, DrawExpiredEvent, DrawRequestedEvent,
+        LeaveGameEvent, MoveEvent, OpponentMoveEvent, PromotionMoveEvent, RedrawBoardEvent,
         RequestDrawEvent, ResignEvent,
     },
+    history::{FetchHistoryEvent, HistoryEvent, PAGE},
+    loading::{
+        AnswerChallengeEvent, ChallengeReceivedEvent, LeagueEvent, LeaveQueueEvent,
+        LobbyCreatedEvent, QueueStatusEvent, SimulEvent, StartLeagueEvent, StartSimulEvent,
+    },
+    main_menu::{OngoingGamesEvent, ServerStatsEvent},
+    seeks::{SeekEvent, SeeksEvent},
     GameState, VictoryEvent,
 };
 
+#[cfg(target_arch = "wasm32")]
+pub mod browser;
+mod simul;
+
+/// the client of bevy_slinet, browsers get one with the same api, see browser
+#[cfg(not(target_arch = "wasm32"))]
+pub use bevy_slinet::client as connection;
+#[cfg(target_arch = "wasm32")]
+pub use browser as connection;
+
 pub struct NetworkingPlugin;
 
 impl Plugin for NetworkingPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<ConnectionAddress>()
+            .init_resource::<Handicap>()
+            .init_resource::<ColorPreference>()
+            .init_resource::<QueueTimeControl>()
+            .init_resource::<QueueTimeOdds>()
+            .init_resource::<QueueRated>()
+            .init_resource::<Latency>()
+            .init_resource::<Rejoin>()
+            .init_resource::<GameStart>()
+            .init_resource::<GameClock>()
+            .init_resource::<PingTimer>()
+            .init_resource::<Session>()
+            .init_resource::<ConnectionPurpose>()
+            .init_resource::<simul::SimulBoards>()
             .add_event::<MakeConnectionEvent>()
-            .add_plugins(ClientPlugin::<Config>::new())
+            .add_plugins(ClientPlugin::<ClientNetConfig>::new())
             .add_systems(
                 Update,
                 (
                     send_move.run_if(
                         in_state(GameState::Gaming)
-                            .and_then(resource_exists::<ClientConnection<Config>>),
+                            .and_then(resource_exists::<ClientConnection<ClientNetConfig>>),
                     ),
-                    send_promotion.run_if(
+                    // an automatic promotion is sent in the same frame as its move
+                    send_promotion.after(send_move).run_if(
                         in_state(GameState::Gaming)
-                            .and_then(resource_exists::<ClientConnection<Config>>),
+                            .and_then(resource_exists::<ClientConnection<ClientNetConfig>>),
                     ),
                     make_connection,
                     receive_connection,
                     receive_packet,
                     window_close,
                     resign,
+                    leave_game,
+                    leave_queue,
+                    answer_challenge,
+                    start_league,
+                    start_simul,
+                    seek_action.run_if(in_state(GameState::Seeks)),
                     request_draw.run_if(
                         in_state(GameState::Gaming)
-                            .and_then(resource_exists::<ClientConnection<Config>>),
+                            .and_then(resource_exists::<ClientConnection<ClientNetConfig>>),
+                    ),
+                    decline_draw.run_if(
+                        in_state(GameState::Gaming)
+                            .and_then(resource_exists::<ClientConnection<ClientNetConfig>>),
+                    ),
+                    rematch.run_if(
+                        in_state(GameState::Gaming)
+                            .and_then(resource_exists::<ClientConnection<ClientNetConfig>>),
+                    ),
+                    send_chat.run_if(
+                        in_state(GameState::Gaming)
+                            .and_then(resource_exists::<ClientConnection<ClientNetConfig>>),
+                    ),
+                    abort.run_if(
+                        in_state(GameState::Gaming)
+                            .and_then(resource_exists::<ClientConnection<ClientNetConfig>>),
+                    ),
+                    send_ping.run_if(resource_exists::<ClientConnection<ClientNetConfig>>),
+                    reconnect.run_if(in_state(GameState::Gaming)),
+                ),
+            )
+            .add_systems(OnExit(GameState::Gaming), forget_game)
+            .add_systems(Update, fetch_history.run_if(in_state(GameState::History)))
+            .add_systems(
+                Update,
+                (takeback, ask_hint).run_if(
+                    in_state(GameState::Gaming)
+                        .and_then(resource_exists::<ClientConnection<ClientNetConfig>>),
+                ),
+            )
+            // a simul host plays all its games over one connection
+            .add_systems(
+                Update,
+                (
+                    simul::follow_boards.before(receive_packet),
+                    simul::switch_board.run_if(
+                        in_state(GameState::Gaming)
+                            .and_then(resource_exists::<ClientConnection<ClientNetConfig>>),
                     ),
                 ),
             );
@@ -57,11 +150,223 @@ impl Plugin for NetworkingPlugin {
 }
 
 #[derive(Event)]
-pub struct MakeConnectionEvent;
+pub struct MakeConnectionEvent(pub ConnectionPurpose);
+
+/// what the current connection is for
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionPurpose {
+    /// join the queue, or challenge someone
+    #[default]
+    Play,
+    /// only ask the server how busy it is
+    Stats,
+    /// only ask for your correspondence games
+    Games,
+    /// take your seat in a correspondence game back
+    Resume(GameToken),
+    /// open a private game for a friend
+    CreateLobby,
+    /// play the private game a friend opened
+    JoinLobby(LobbyCode),
+    /// browse the games other players offer
+    Seeks,
+    /// join the queue and play the server's bot right away
+    Computer,
+    /// list the games you finished
+    History,
+}
+
+/// the clock to ask for when joining the queue, None plays without one
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct QueueTimeControl(pub Option<TimeControl>);
+
+/// the clock the opponent gets when it should differ from yours, only for casual games
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct QueueTimeOdds(pub Option<TimeControl>);
+
+impl QueueTimeOdds {
+    /// the seek to join the queue or offer a game with
+    pub fn seek(&self, time_control: Option<TimeControl>, rated: bool) -> Seek {
+        Seek {
+            variant: Variant::Standard,
+            time_control,
+            time_odds: self.0,
+            rated,
+        }
+        .without_invalid_odds()
+    }
+}
+
+/// whether to ask for a game that changes the ratings when joining the queue
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct QueueRated(pub bool);
 
 #[derive(Resource, Clone, Copy, Debug)]
 pub struct ConnectionAddress(pub SocketAddr);
 
+/// what is needed to get back into the game after losing the connection
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct Rejoin {
+    pub token: GameToken,
+    /// true while reconnecting, a second lost connection gives up
+    pub attempting: bool,
+}
+
+/// the position the move history is played from
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct GameStart {
+    pub state: ChessState,
+}
+
+impl GameStart {
+    /// moves played in the current game, both sides check it to notice when they got out of sync
+    pub fn current_ply(&self, history: &MoveHistory) -> u32 {
+        history.len() as u32
+    }
+
+    /// the board after every move in the history, for spotting repetitions
+    pub fn boards(&self, history: &MoveHistory) -> Vec<CompressedChessboard> {
+        let mut state = self.state;
+        history
+            .iter()
+            .map_while(|&played_move| {
+                state.play_move(played_move).ok()?;
+                Some(compress_chessboard(&state.board))
+            })
+            .collect()
+    }
+
+    /// rewinds to ply `since` and plays `moves` from there,
+    /// false if the client doesn't know the game that far or the moves don't fit
+    pub fn resync(
+        &self,
+        state: &mut ChessState,
+        history: &mut MoveHistory,
+        since: u32,
+        moves: &[PlayedMove],
+    ) -> bool {
+        if since as usize > history.len() {
+            return false;
+        }
+        history.truncate(since as usize);
+        history.extend_from_slice(moves);
+        let mut replayed = self.state;
+        for &played_move in history.iter() {
+            if replayed.play_move(played_move).is_err() {
+                return false;
+            }
+        }
+        *state = replayed;
+        true
+    }
+}
+
+/// how long the shown clocks take to catch up with a new reading instead of jumping to it
+const CLOCK_SMOOTHING: Duration = Duration::from_millis(500);
+
+/// the last clock reading from the server and when it was taken, in time since startup
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct GameClock {
+    pub clock: Option<Clock>,
+    pub received: Duration,
+    /// the color whose clock runs after the reading
+    pub running: ChessColor,
+    /// milliseconds the shown clocks of white and black were ahead of the reading, fades out over CLOCK_SMOOTHING
+    pub correction: [i64; 2],
+}
+
+impl GameClock {
+    /// takes a new reading, the readings of a new game should start from the default
+    pub fn update(
+        &mut self,
+        clock: Option<Clock>,
+        running: ChessColor,
+        now: Duration,
+        latency: Option<Duration>,
+    ) {
+        let shown = [ChessColor::White, ChessColor::Black].map(|color| self.remaining(color, now));
+        *self = GameClock {
+            clock,
+            // the reading is half a round-trip old by the time it arrives
+            received: now.saturating_sub(latency.unwrap_or_default() / 2),
+            running,
+            correction: [0; 2],
+        };
+        for (i, color) in [ChessColor::White, ChessColor::Black]
+            .into_iter()
+            .enumerate()
+        {
+            if let (Some(before), Some(after)) = (shown[i], self.remaining(color, now)) {
+                self.correction[i] = before.as_millis() as i64 - after.as_millis() as i64;
+            }
+        }
+    }
+
+    /// time `color` has left, only the running clock is losing time
+    pub fn remaining(&self, color: ChessColor, now: Duration) -> Option<Duration> {
+        let mut remaining = self.clock?.remaining(color);
+        let elapsed = now.saturating_sub(self.received);
+        if color == self.running {
+            remaining = remaining.saturating_sub(elapsed);
+        }
+        let fade = 1.0 - (elapsed.as_secs_f64() / CLOCK_SMOOTHING.as_secs_f64()).min(1.0);
+        let correction = (self.correction[color as usize] as f64 * fade) as i64;
+        Some(Duration::from_millis(
+            (remaining.as_millis() as i64 + correction).max(0) as u64,
+        ))
+    }
+}
+
+/// the name to say hello with, None lets the server make one up
+#[derive(Resource, Default, Clone, Debug)]
+pub struct PlayerName(pub Option<String>);
+
+/// the player to challenge instead of joining the queue
+#[derive(Resource, Default, Clone, Debug)]
+pub struct ChallengeTarget(pub Option<String>);
+
+/// how to get into a league from the command line
+#[derive(Clone, Copy, Debug)]
+pub enum LeagueEntry {
+    Create { double: bool },
+    Join(LeagueId),
+}
+
+/// the league to play in instead of joining the queue
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct LeagueTarget(pub Option<LeagueEntry>);
+
+/// how to get into a simul from the command line
+#[derive(Clone, Copy, Debug)]
+pub enum SimulEntry {
+    Create,
+    Join(SimulId),
+}
+
+/// the simul to play in instead of joining the queue
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct SimulTarget(pub Option<SimulEntry>);
+
+/// a registered account to log into, or to create first
+#[derive(Clone, Debug)]
+pub struct Credentials {
+    pub name: String,
+    pub password: String,
+    pub register: bool,
+}
+
+/// the account from the command line, None plays with an anonymous account
+#[derive(Resource, Default, Clone, Debug)]
+pub struct AccountLogin(pub Option<Credentials>);
+
+/// the account token from the server, sent again on every new connection
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct Session(pub Option<SessionToken>);
+
+/// round-trip time to the server, None until the first pong arrives
+#[derive(Resource, Default, Clone, Copy, Debug)]
+pub struct Latency(pub Option<Duration>);
+
 impl Default for ConnectionAddress {
     fn default() -> Self {
         Self(SocketAddr::V4(SocketAddrV4::new(
@@ -73,86 +378,382 @@ impl Default for ConnectionAddress {
 
 pub fn send_move(
     mut move_event: EventReader<MoveEvent>,
-    connection: Res<ClientConnection<Config>>,
+    connection: Res<ClientConnection<ClientNetConfig>>,
+    game: Res<GameId>,
+    start: Res<GameStart>,
+    mut history: ResMut<MoveHistory>,
 ) {
     for event in move_event.read() {
         connection
-            .send(ClientPacket::Move(event.0))
+            .send(ClientPacket::Move(
+                *game,
+                start.current_ply(&history),
+                event.0,
+            ))
             .unwrap_or_else(connection_error);
+        history.push(event.0.into());
     }
 }
 
 pub fn send_promotion(
     mut promotion_event: EventReader<PromotionMoveEvent>,
-    connection: Res<ClientConnection<Config>>,
+    connection: Res<ClientConnection<ClientNetConfig>>,
+    game: Res<GameId>,
+    mut history: ResMut<MoveHistory>,
 ) {
     for event in promotion_event.read() {
         connection
-            .send(ClientPacket::Promotion(event.0))
+            .send(ClientPacket::Promotion(*game, event.0))
             .unwrap_or_else(connection_error);
+        // a promotion always belongs to the last move
+        if let Some(last) = history.last_mut() {
+            last.promotion = Some(event.0);
+        }
         info!("promotion packet send");
     }
 }
 
 pub fn make_connection(
     mut connection_event: EventReader<MakeConnectionEvent>,
-    mut connection_request: EventWriter<ConnectionRequestEvent<Config>>,
+    mut connection_request: EventWriter<ConnectionRequestEvent<ClientNetConfig>>,
     address: Res<ConnectionAddress>,
+    mut purpose: ResMut<ConnectionPurpose>,
 ) {
-    for _ in connection_event.read() {
+    for event in connection_event.read() {
+        *purpose = event.0;
         connection_request.send(ConnectionRequestEvent::new(address.0));
     }
 }
 
 pub fn receive_connection(
-    mut connection_event: EventReader<ConnectionEstablishEvent<Config>>,
+    mut connection_event: EventReader<ConnectionEstablishEvent<ClientNetConfig>>,
     mut game_state: ResMut<NextState<GameState>>,
+    handicap: Res<Handicap>,
+    color_preference: Res<ColorPreference>,
+    time_control: Res<QueueTimeControl>,
+    time_odds: Res<QueueTimeOdds>,
+    rated: Res<QueueRated>,
+    rejoin: Res<Rejoin>,
+    name: Res<PlayerName>,
+    session: Res<Session>,
+    challenge: Res<ChallengeTarget>,
+    league: Res<LeagueTarget>,
+    simul: Res<SimulTarget>,
+    purpose: Res<ConnectionPurpose>,
+    account: Res<AccountLogin>,
 ) {
-    for _ in connection_event.read() {
+    for event in connection_event.read() {
+        if *purpose == ConnectionPurpose::Stats {
+            event
+                .connection
+                .send(ClientPacket::ServerStats)
+                .unwrap_or_else(connection_error);
+            continue;
+        }
+        // once the server handed out a session the token is enough
+        let login = match (&account.0, session.0) {
+            (Some(credentials), None) if credentials.register => ClientPacket::Register {
+                name: credentials.name.clone(),
+                password: credentials.password.clone(),
+            },
+            (Some(credentials), None) => ClientPacket::PasswordLogin {
+                name: credentials.name.clone(),
+                password: credentials.password.clone(),
+            },
+            (_, token) => ClientPacket::Login { token },
+        };
+        event
+            .connection
+            .send(login)
+            .unwrap_or_else(connection_error);
+        // registered accounts keep their name
+        if let Some(name) = name.0.as_ref().filter(|_| account.0.is_none()) {
+            event
+                .connection
+                .send(ClientPacket::Hello {
+                    name: name.clone(),
+                    bot: false,
+                })
+                .unwrap_or_else(connection_error);
+        }
+        // the server lists the correspondence games after logging in
+        if *purpose == ConnectionPurpose::Games {
+            continue;
+        }
+        if let ConnectionPurpose::Resume(token) = *purpose {
+            event
+                .connection
+                .send(ClientPacket::Resume(token))
+                .unwrap_or_else(connection_error);
+            continue;
+        }
+        if rejoin.attempting {
+            event
+                .connection
+                .send(ClientPacket::Resume(rejoin.token))
+                .unwrap_or_else(connection_error);
+            continue;
+        }
+        match *purpose {
+            ConnectionPurpose::CreateLobby => {
+                event
+                    .connection
+                    .send(ClientPacket::CreateLobby {
+                        handicap: *handicap,
+                        variant: Variant::Standard,
+                        time_control: time_control.0,
+                        color: *color_preference,
+                    })
+                    .unwrap_or_else(connection_error);
+                game_state.set(GameState::Loading);
+                continue;
+            }
+            ConnectionPurpose::JoinLobby(code) => {
+                event
+                    .connection
+                    .send(ClientPacket::JoinLobby(code))
+                    .unwrap_or_else(connection_error);
+                game_state.set(GameState::Loading);
+                continue;
+            }
+            ConnectionPurpose::Seeks => {
+                event
+                    .connection
+                    .send(ClientPacket::ListSeeks)
+                    .unwrap_or_else(connection_error);
+                game_state.set(GameState::Seeks);
+                continue;
+            }
+            // the history is asked for once the server says who you are
+            ConnectionPurpose::History => {
+                game_state.set(GameState::History);
+                continue;
+            }
+            _ => {}
+        }
+        if let Some(target) = &challenge.0 {
+            event
+                .connection
+                .send(ClientPacket::Challenge {
+                    target: target.clone(),
+                    variant: Variant::Standard,
+                    time_control: None,
+                })
+                .unwrap_or_else(connection_error);
+            game_state.set(GameState::Loading);
+            continue;
+        }
+        if let Some(entry) = league.0 {
+            event
+                .connection
+                .send(match entry {
+                    LeagueEntry::Create { double } => ClientPacket::CreateLeague {
+                        time_control: time_control.0,
+                        rated: rated.0,
+                        double,
+                    },
+                    LeagueEntry::Join(id) => ClientPacket::JoinLeague(id),
+                })
+                .unwrap_or_else(connection_error);
+            game_state.set(GameState::Loading);
+            continue;
+        }
+        if let Some(entry) = simul.0 {
+            event
+                .connection
+                .send(match entry {
+                    SimulEntry::Create => ClientPacket::CreateSimul {
+                        time_control: time_control.0,
+                    },
+                    SimulEntry::Join(id) => ClientPacket::JoinSimul(id),
+                })
+                .unwrap_or_else(connection_error);
+            game_state.set(GameState::Loading);
+            continue;
+        }
+        let seek = time_odds.seek(time_control.0, rated.0);
+        event
+            .connection
+            .send(ClientPacket::JoinQueue {
+                handicap: *handicap,
+                variant: seek.variant,
+                time_control: seek.time_control,
+                time_odds: seek.time_odds,
+                rated: seek.rated,
+                color: *color_preference,
+            })
+            .unwrap_or_else(connection_error);
+        if *purpose == ConnectionPurpose::Computer {
+            event
+                .connection
+                .send(ClientPacket::PlayComputer)
+                .unwrap_or_else(connection_error);
+        }
         game_state.set(GameState::Loading);
     }
 }
 
+/// what the loading screen shows while waiting for a game
+#[derive(SystemParam)]
+pub struct LoadingEvents<'w> {
+    queue_status: EventWriter<'w, QueueStatusEvent>,
+    challenge: EventWriter<'w, ChallengeReceivedEvent>,
+    lobby_created: EventWriter<'w, LobbyCreatedEvent>,
+    league: EventWriter<'w, LeagueEvent>,
+    simul: EventWriter<'w, SimulEvent>,
+}
+
+/// who else is connected to the game
+#[derive(SystemParam)]
+pub struct PresenceEvents<'w> {
+    opponent_disconnected: EventWriter<'w, OpponentDisconnectedEvent>,
+    opponent_reconnected: EventWriter<'w, OpponentReconnectedEvent>,
+    spectators: EventWriter<'w, SpectatorsEvent>,
+}
+
+/// what the opponent offers, and their answers to your offers
+#[derive(SystemParam)]
+pub struct OfferEvents<'w> {
+    draw: EventWriter<'w, DrawRequestedEvent>,
+    draw_declined: EventWriter<'w, DrawDeclinedEvent>,
+    draw_expired: EventWriter<'w, DrawExpiredEvent>,
+    rematch: EventWriter<'w, RematchOfferedEvent>,
+    takeback: EventWriter<'w, TakebackRequestedEvent>,
+    takeback_declined: EventWriter<'w, TakebackDeclinedEvent>,
+}
+
+/// everything receiving a packet can trigger
+#[derive(SystemParam)]
+pub struct ReceivedPacketEvents<'w> {
+    move_event: EventWriter<'w, OpponentMoveEvent>,
+    redraw_event: EventWriter<'w, RedrawBoardEvent>,
+    victory_event: EventWriter<'w, VictoryEvent>,
+    offers: OfferEvents<'w>,
+    promotion_event: EventWriter<'w, OpponentPromotionEvent>,
+    analysis_event: EventWriter<'w, AnalysisEvent>,
+    hint_event: EventWriter<'w, HintEvent>,
+    chat_event: EventWriter<'w, ChatMessageEvent>,
+    presence: PresenceEvents<'w>,
+    announcement_event: EventWriter<'w, AnnouncementEvent>,
+    loading: LoadingEvents<'w>,
+    server_stats_event: EventWriter<'w, ServerStatsEvent>,
+    ongoing_games_event: EventWriter<'w, OngoingGamesEvent>,
+    seeks_event: EventWriter<'w, SeeksEvent>,
+    history_event: EventWriter<'w, HistoryEvent>,
+    fetch_history_event: EventWriter<'w, FetchHistoryEvent>,
+}
+
 pub fn receive_packet(
-    mut packet_event: EventReader<PacketReceiveEvent<Config>>,
+    mut packet_event: EventReader<PacketReceiveEvent<ClientNetConfig>>,
     mut color: ResMut<ChessColor>,
     mut chess_state: ResMut<ChessState>,
     mut game_state: ResMut<NextState<GameState>>,
-    mut move_event: EventWriter<OpponentMoveEvent>,
-    mut redraw_event: EventWriter<RedrawBoardEvent>,
-    mut victory_event: EventWriter<VictoryEvent>,
-    mut draw_event: EventWriter<DrawRequestedEvent>,
-    mut promotion_event: EventWriter<OpponentPromotionEvent>,
+    mut events: ReceivedPacketEvents,
+    mut rematch_offer: ResMut<RematchOffer>,
+    mut latency: ResMut<Latency>,
+    mut game: ResMut<GameId>,
+    mut players: ResMut<Players>,
+    mut rejoin: ResMut<Rejoin>,
+    mut game_start: ResMut<GameStart>,
+    mut history: ResMut<MoveHistory>,
+    mut game_clock: ResMut<GameClock>,
+    mut session: ResMut<Session>,
+    time: Res<Time>,
+    purpose: Res<ConnectionPurpose>,
 ) {
     for packet in packet_event.read() {
-        info!("got a packet, {:?}", packet.packet);
-        match packet.packet {
-            ServerPacket::MatchFound(c) => {
-                *color = c;
+        if !matches!(packet.packet, ServerPacket::Ping(_) | ServerPacket::Pong(_)) {
+            info!("got a packet, {:?}", packet.packet);
+        }
+        // late packets of a previous game
+        if packet.packet.game().is_some_and(|id| id != *game) {
+            warn!("ignoring a packet for game {:?}", packet.packet.game());
+            continue;
+        }
+        match &packet.packet {
+            ServerPacket::MatchFound {
+                game: id,
+                color: c,
+                white,
+                black,
+                rated,
+                start,
+                token,
+                moves,
+                clock,
+            } => {
+                *game_start = GameStart { state: *start };
+                history.clear();
+                *rejoin = Rejoin {
+                    token: *token,
+                    attempting: false,
+                };
+                *game = *id;
+                *players = Players {
+                    white: white.clone(),
+                    black: black.clone(),
+                    rated: *rated,
+                };
+                *color = *c;
+                *chess_state = *start;
+                if !game_start.resync(&mut chess_state, &mut history, 0, moves) {
+                    warn!("couldn't replay the moves of the resumed game");
+                }
+                *game_clock = GameClock::default();
+                game_clock.update(*clock, chess_state.turn, time.elapsed(), latency.0);
                 game_state.set(GameState::Gaming);
             }
-            ServerPacket::InvalidMove(state) => {
-                *chess_state = state;
-                redraw_event.send(RedrawBoardEvent);
+            ServerPacket::Resync {
+                since,
+                moves,
+                clock,
+                ..
+            } => {
+                // with nothing to undo or catch up on, keep the selection and promotion menu
+                if *since != game_start.current_ply(&history) || !moves.is_empty() {
+                    if !game_start.resync(&mut chess_state, &mut history, *since, moves) {
+                        warn!("couldn't resync to move {}", since);
+                    }
+                    events.redraw_event.send(RedrawBoardEvent);
+                }
+                game_clock.update(*clock, chess_state.turn, time.elapsed(), latency.0);
             }
-            ServerPacket::StateReminder(state) => {
-                *chess_state = state;
-                redraw_event.send(RedrawBoardEvent);
+            ServerPacket::Move(_, p, _, _) if *p != game_start.current_ply(&history) => {
+                warn!(
+                    "got move {} but expected move {}, asking for the missing moves",
+                    p,
+                    game_start.current_ply(&history)
+                );
+                packet
+                    .connection
+                    .send(ClientPacket::Resync(
+                        *game,
+                        game_start.current_ply(&history),
+                    ))
+                    .unwrap_or_else(connection_error);
             }
-            ServerPacket::Move(chess_move) => match chess_state.move_piece(chess_move) {
-                Ok(b) => {
-                    move_event.send(OpponentMoveEvent(chess_move));
-                    if b {
-                        redraw_event.send(RedrawBoardEvent);
+            ServerPacket::Move(_, _, chess_move, clock) => {
+                let mover = chess_state.turn;
+                match chess_state.move_piece(*chess_move) {
+                    Ok(b) => {
+                        game_clock.update(*clock, !mover, time.elapsed(), latency.0);
+                        history.push((*chess_move).into());
+                        events.move_event.send(OpponentMoveEvent(*chess_move));
+                        if b {
+                            events.redraw_event.send(RedrawBoardEvent);
+                        }
                     }
+                    Err(_) => packet
+                        .connection
+                        .send(ClientPacket::Resync(
+                            *game,
+                            game_start.current_ply(&history),
+                        ))
+                        .unwrap_or_else(connection_error),
                 }
-                Err(_) => packet
-                    .connection
-                    .send(ClientPacket::Reconnect)
-                    .unwrap_or_else(connection_error),
-            },
-            ServerPacket::EndGame(end) => {
-                victory_event.send(match end {
+            }
+            ServerPacket::EndGame(_, end) => {
+                events.victory_event.send(match *end {
                     GameEnd::White(reason) => {
                         if *color == ChessColor::White {
                             VictoryEvent::Win(reason)
@@ -170,27 +771,300 @@ pub fn receive_packet(
                     GameEnd::Draw(reason) => VictoryEvent::Draw(reason),
                 });
             }
-            ServerPacket::DrawRequested => {
-                draw_event.send(DrawRequestedEvent);
+            ServerPacket::DrawRequested(_) => {
+                events.offers.draw.send(DrawRequestedEvent);
+            }
+            ServerPacket::DrawDeclined(_) => {
+                events.offers.draw_declined.send(DrawDeclinedEvent);
             }
-            ServerPacket::Promotion(piece) => {
-                if chess_state.promote(piece).is_err() {
+            ServerPacket::DrawExpired(_) => {
+                events.offers.draw_expired.send(DrawExpiredEvent);
+            }
+            ServerPacket::Promotion(_, piece) => {
+                if chess_state.promote(*piece).is_err() {
                     packet
                         .connection
-                        .send(ClientPacket::Reconnect)
+                        .send(ClientPacket::Resync(
+                            *game,
+                            game_start.current_ply(&history).saturating_sub(1),
+                        ))
                         .unwrap_or_else(connection_error);
                 } else {
-                    redraw_event.send(RedrawBoardEvent);
-                    promotion_event.send(OpponentPromotionEvent);
+                    if let Some(last) = history.last_mut() {
+                        last.promotion = Some(*piece);
+                    }
+                    events.redraw_event.send(RedrawBoardEvent);
+                    events.promotion_event.send(OpponentPromotionEvent);
+                }
+            }
+            ServerPacket::TakebackRequested(_) => {
+                events.offers.takeback.send(TakebackRequestedEvent);
+            }
+            ServerPacket::TakebackDeclined(_) => {
+                events.offers.takeback_declined.send(TakebackDeclinedEvent);
+            }
+            ServerPacket::Session { token, player } => {
+                info!("logged in as {} ({:?})", player.name, player.id);
+                session.0 = Some(*token);
+                if *purpose == ConnectionPurpose::History {
+                    events.fetch_history_event.send(FetchHistoryEvent(None));
                 }
             }
+            ServerPacket::ChallengeReceived {
+                challenge, from, ..
+            } => {
+                events.loading.challenge.send(ChallengeReceivedEvent {
+                    challenge: *challenge,
+                    from: from.name.clone(),
+                });
+            }
+            ServerPacket::ChallengeDeclined { target } => {
+                events.announcement_event.send(AnnouncementEvent(format!(
+                    "{} declined your challenge or isn't online",
+                    target
+                )));
+                packet.connection.disconnect();
+                game_state.set(GameState::MainMenu);
+            }
+            ServerPacket::SpectateState { .. } => {
+                warn!("got a spectator state without asking to spectate");
+            }
+            // the client follows the game move by move
+            ServerPacket::Position { .. } => {}
+            ServerPacket::Clock(_, clock) => {
+                game_clock.update(Some(*clock), !*color, time.elapsed(), latency.0);
+            }
+            ServerPacket::DailyPuzzle(puzzle) => {
+                info!("daily puzzle: {:?}", puzzle);
+            }
+            ServerPacket::Analysis(_, analysis) | ServerPacket::ReplayAnalysis(_, analysis) => {
+                events.analysis_event.send(AnalysisEvent(analysis.clone()));
+            }
+            ServerPacket::Hint(_, ply, found) => {
+                events.hint_event.send(HintEvent(*ply as usize, *found));
+            }
+            ServerPacket::RematchOffered(_) => {
+                **rematch_offer = true;
+                events.offers.rematch.send(RematchOfferedEvent);
+            }
+            ServerPacket::OpponentDisconnected { grace, .. } => {
+                events
+                    .presence
+                    .opponent_disconnected
+                    .send(OpponentDisconnectedEvent(*grace));
+            }
+            ServerPacket::OpponentReconnected(_) => {
+                events
+                    .presence
+                    .opponent_reconnected
+                    .send(OpponentReconnectedEvent);
+            }
+            ServerPacket::Ping(sent) => packet
+                .connection
+                .send(ClientPacket::Pong(*sent))
+                .unwrap_or_else(connection_error),
+            ServerPacket::Pong(sent) => {
+                latency.0 = Some(round_trip(*sent));
+            }
+            ServerPacket::Chat { from, message, .. } => {
+                events.chat_event.send(ChatMessageEvent {
+                    from: *from,
+                    message: message.clone(),
+                });
+            }
+            ServerPacket::QueueStatus {
+                position,
+                players_waiting,
+            } => {
+                events.loading.queue_status.send(QueueStatusEvent {
+                    position: *position,
+                    players_waiting: *players_waiting,
+                });
+            }
+            ServerPacket::Announcement(message) => {
+                events
+                    .announcement_event
+                    .send(AnnouncementEvent(message.clone()));
+            }
+            ServerPacket::Unsupported => debug!("skipping a packet from a newer server"),
+            ServerPacket::Error(error) => {
+                warn!("the server refused a packet: {:?}", error);
+                let message = match error {
+                    // moves and offers that cross the end of the game on their way
+                    ServerError::RateLimited | ServerError::NotInGame => None,
+                    ServerError::NameTaken => Some("someone else registered that name already"),
+                    ServerError::WrongPassword => Some("wrong name or password"),
+                    ServerError::AccountsUnavailable => {
+                        Some("the server doesn't have accounts, playing anonymously")
+                    }
+                    ServerError::NoSuchGame => Some("the server doesn't have that game"),
+                    ServerError::NoSuchPlayer => Some("nobody with that name played here"),
+                    ServerError::NoSuchLobby => Some("there is no lobby with that code (anymore)"),
+                    ServerError::NoSuchSeek => Some("that seek was taken or withdrawn"),
+                    ServerError::NoSuchLeague => {
+                        Some("that league doesn't exist, started without you or was closed")
+                    }
+                    ServerError::TimeControlNotAllowed => {
+                        Some("the server doesn't allow games with that clock")
+                    }
+                    ServerError::QueueClosed => Some("the server isn't pairing players right now"),
+                    ServerError::ServerFull => Some("the server is full, try again later"),
+                    ServerError::NoSuchSimul => {
+                        Some("that simul doesn't exist, started without you or was closed")
+                    }
+                    ServerError::DrawOfferTooSoon => {
+                        Some("you offered a draw a moment ago, play a few moves first")
+                    }
+                    ServerError::InGame => Some("one of you is playing a game already"),
+                    ServerError::AccountRequired => {
+                        Some("log in with an account to play rated games")
+                    }
+                    ServerError::TooManyAttempts => {
+                        Some("too many wrong passwords, try again in a few minutes")
+                    }
+                    ServerError::NoBots => {
+                        Some("the server has no computer opponent, waiting for a player instead")
+                    }
+                    ServerError::NoHints => Some("there are no hints left in this game"),
+                    ServerError::Unsupported => Some("the server is too old to do that"),
+                    ServerError::NameRegistered => {
+                        Some("someone registered that name, log in to play with it")
+                    }
+                };
+                if let Some(message) = message {
+                    events
+                        .announcement_event
+                        .send(AnnouncementEvent(message.to_string()));
+                }
+                // the connection was only opened for that game
+                if matches!(
+                    error,
+                    ServerError::NoSuchLobby
+                        | ServerError::NoSuchLeague
+                        | ServerError::NoSuchSimul
+                        | ServerError::TimeControlNotAllowed
+                        | ServerError::QueueClosed
+                        | ServerError::ServerFull
+                        | ServerError::AccountRequired
+                ) {
+                    packet.connection.disconnect();
+                    game_state.set(GameState::MainMenu);
+                }
+            }
+            ServerPacket::ServerStats {
+                games,
+                players_online,
+                queue_length,
+                uptime,
+                opening,
+            } => {
+                events.server_stats_event.send(ServerStatsEvent {
+                    games: *games,
+                    players_online: *players_online,
+                    queue_length: *queue_length,
+                    uptime: Duration::from_secs(*uptime),
+                    opening: opening.clone(),
+                });
+                // the connection was only opened to ask
+                packet.connection.disconnect();
+            }
+            ServerPacket::OngoingGames(games) => {
+                let your_move = games.iter().filter(|game| game.your_move).count();
+                if your_move != 0 {
+                    events.announcement_event.send(AnnouncementEvent(format!(
+                        "it's your move in {} correspondence game{}",
+                        your_move,
+                        if your_move == 1 { "" } else { "s" }
+                    )));
+                }
+                events
+                    .ongoing_games_event
+                    .send(OngoingGamesEvent(games.clone()));
+                if *purpose == ConnectionPurpose::Games {
+                    packet.connection.disconnect();
+                }
+            }
+            // there is nothing to watch them in yet
+            ServerPacket::RecentGames(games) => {
+                info!("your last {} games: {:?}", games.len(), games);
+            }
+            ServerPacket::History { player, games } => {
+                events.history_event.send(HistoryEvent {
+                    player: player.clone(),
+                    games: games.clone(),
+                });
+            }
+            ServerPacket::LobbyCreated(code) => {
+                events.loading.lobby_created.send(LobbyCreatedEvent(*code));
+            }
+            ServerPacket::Seeks(seeks) => {
+                events.seeks_event.send(SeeksEvent(seeks.clone()));
+            }
+            ServerPacket::Spectators(_, count) => {
+                events.presence.spectators.send(SpectatorsEvent(*count));
+            }
+            ServerPacket::League(schedule) => {
+                info!("league schedule: {:?}", schedule);
+                // the loading screen shows the league until it starts, after that it's news
+                if schedule.round != 0 {
+                    events
+                        .announcement_event
+                        .send(AnnouncementEvent(league_summary(schedule)));
+                }
+                events.loading.league.send(LeagueEvent(schedule.clone()));
+            }
+            ServerPacket::Simul(simul) => {
+                events.loading.simul.send(SimulEvent(simul.clone()));
+            }
+            ServerPacket::Replay {
+                id,
+                white,
+                black,
+                moves,
+                result,
+                ..
+            } => {
+                info!(
+                    "replay of {:?}, {} against {}: {:?} after {} moves",
+                    id,
+                    white,
+                    black,
+                    result,
+                    moves.len()
+                );
+            }
         }
     }
 }
 
+/// the round and the standings, scores in half points are shown as 1.5
+pub fn league_summary(schedule: &LeagueSchedule) -> String {
+    let standings = schedule
+        .standings()
+        .into_iter()
+        .map(|(player, score)| {
+            format!(
+                "{} {}{}",
+                player.name,
+                score / 2,
+                if score % 2 == 1 { ".5" } else { "" }
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ");
+    if schedule.is_finished() {
+        format!("league {} is over: {}", schedule.id.0, standings)
+    } else {
+        format!(
+            "league {}, round {} of {}: {}",
+            schedule.id.0, schedule.round, schedule.rounds, standings
+        )
+    }
+}
+
 fn window_close(
     mut close_event: EventReader<WindowCloseRequested>,
-    connections: Res<ClientConnections<Config>>,
+    connections: Res<ClientConnections<ClientNetConfig>>,
 ) {
     for _ in close_event.read() {
         for connection in connections.iter() {
@@ -201,30 +1075,286 @@ fn window_close(
 
 fn resign(
     mut resign_event: EventReader<ResignEvent>,
-    connections: Res<ClientConnections<Config>>,
+    connections: Res<ClientConnections<ClientNetConfig>>,
     mut game_state: ResMut<NextState<GameState>>,
 ) {
     for _ in resign_event.read() {
-        if connections.is_empty() {
-            game_state.set(GameState::MainMenu);
+        for connection in connections.iter() {
+            connection.disconnect();
+        }
+        game_state.set(GameState::MainMenu);
+    }
+}
+
+/// the server keeps finished games open for a rematch, leaving closes the connection so it
+/// knows the seat is empty, the next game makes a new one
+fn leave_game(
+    mut leave_event: EventReader<LeaveGameEvent>,
+    connections: Res<ClientConnections<ClientNetConfig>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for _ in leave_event.read() {
+        for connection in connections.iter() {
+            connection.disconnect();
+        }
+        game_state.set(GameState::MainMenu);
+    }
+}
+
+/// the clock and rejoin token of the last game mean nothing to the next one
+fn forget_game(mut commands: Commands) {
+    commands.insert_resource(Rejoin::default());
+    commands.insert_resource(GameStart::default());
+    commands.insert_resource(GameClock::default());
+    commands.insert_resource(Latency::default());
+}
+
+fn answer_challenge(
+    mut answer_event: EventReader<AnswerChallengeEvent>,
+    connections: Res<ClientConnections<ClientNetConfig>>,
+) {
+    for &AnswerChallengeEvent(challenge, accept) in answer_event.read() {
+        for connection in connections.iter() {
+            connection
+                .send(ClientPacket::AnswerChallenge(challenge, accept))
+                .unwrap_or_else(connection_error);
         }
+    }
+}
+
+fn leave_queue(
+    mut leave_event: EventReader<LeaveQueueEvent>,
+    connections: Res<ClientConnections<ClientNetConfig>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for _ in leave_event.read() {
         for connection in connections.iter() {
+            connection
+                .send(ClientPacket::LeaveQueue)
+                .unwrap_or_else(connection_error);
             connection.disconnect();
         }
+        game_state.set(GameState::MainMenu);
     }
 }
 
+/// lists, posts and accepts seeks over the connection opened for the seek list
+fn seek_action(
+    mut seek_event: EventReader<SeekEvent>,
+    connections: Res<ClientConnections<ClientNetConfig>>,
+    handicap: Res<Handicap>,
+    color_preference: Res<ColorPreference>,
+    time_control: Res<QueueTimeControl>,
+    time_odds: Res<QueueTimeOdds>,
+    rated: Res<QueueRated>,
+) {
+    for &event in seek_event.read() {
+        let seek = time_odds.seek(time_control.0, rated.0);
+        let packet = match event {
+            SeekEvent::Refresh => ClientPacket::ListSeeks,
+            SeekEvent::Post => ClientPacket::CreateSeek {
+                handicap: *handicap,
+                variant: seek.variant,
+                time_control: seek.time_control,
+                time_odds: seek.time_odds,
+                rated: seek.rated,
+                color: *color_preference,
+            },
+            SeekEvent::Accept(id) => ClientPacket::AcceptSeek(id),
+        };
+        for connection in connections.iter() {
+            connection
+                .send(packet.clone())
+                .unwrap_or_else(connection_error);
+        }
+    }
+}
+
+/// asks for a page of your finished games over the connection opened for the history
+fn fetch_history(
+    mut fetch_event: EventReader<FetchHistoryEvent>,
+    connections: Res<ClientConnections<ClientNetConfig>>,
+) {
+    for &FetchHistoryEvent(before) in fetch_event.read() {
+        for connection in connections.iter() {
+            connection
+                .send(ClientPacket::History {
+                    player: None,
+                    count: PAGE,
+                    before,
+                })
+                .unwrap_or_else(connection_error);
+        }
+    }
+}
+
+fn start_league(
+    mut start_event: EventReader<StartLeagueEvent>,
+    connections: Res<ClientConnections<ClientNetConfig>>,
+) {
+    for &StartLeagueEvent(id) in start_event.read() {
+        for connection in connections.iter() {
+            connection
+                .send(ClientPacket::StartLeague(id))
+                .unwrap_or_else(connection_error);
+        }
+    }
+}
+
+fn start_simul(
+    mut start_event: EventReader<StartSimulEvent>,
+    connections: Res<ClientConnections<ClientNetConfig>>,
+) {
+    for &StartSimulEvent(id) in start_event.read() {
+        for connection in connections.iter() {
+            connection
+                .send(ClientPacket::StartSimul(id))
+                .unwrap_or_else(connection_error);
+        }
+    }
+}
+
+fn abort(
+    mut abort_event: EventReader<AbortEvent>,
+    connection: Res<ClientConnection<ClientNetConfig>>,
+    game: Res<GameId>,
+) {
+    for _ in abort_event.read() {
+        connection
+            .send(ClientPacket::Abort(*game))
+            .unwrap_or_else(connection_error);
+    }
+}
+
+/// claims the draw when the rules allow it, otherwise offers one
 fn request_draw(
     mut resign_event: EventReader<RequestDrawEvent>,
-    connection: Res<ClientConnection<Config>>,
+    connection: Res<ClientConnection<ClientNetConfig>>,
+    game: Res<GameId>,
+    chess_state: Res<ChessState>,
+    start: Res<GameStart>,
+    history: Res<MoveHistory>,
 ) {
     for _ in resign_event.read() {
+        let packet = if chess_state
+            .claimable_draw(&start.boards(&history))
+            .is_some()
+        {
+            ClientPacket::ClaimDraw(*game)
+        } else {
+            ClientPacket::RequestDraw(*game)
+        };
         connection
-            .send(ClientPacket::RequestDraw)
+            .send(packet)
             .unwrap_or_else(|x| warn!("connection error {:?}", x));
     }
 }
 
+fn decline_draw(
+    mut decline_event: EventReader<DeclineDrawEvent>,
+    connection: Res<ClientConnection<ClientNetConfig>>,
+    game: Res<GameId>,
+) {
+    for _ in decline_event.read() {
+        connection
+            .send(ClientPacket::DeclineDraw(*game))
+            .unwrap_or_else(connection_error);
+    }
+}
+
+fn rematch(
+    mut rematch_event: EventReader<RematchEvent>,
+    connection: Res<ClientConnection<ClientNetConfig>>,
+    rematch_offer: Res<RematchOffer>,
+    game: Res<GameId>,
+) {
+    for _ in rematch_event.read() {
+        connection
+            .send(if **rematch_offer {
+                ClientPacket::AcceptRematch(*game)
+            } else {
+                ClientPacket::OfferRematch(*game)
+            })
+            .unwrap_or_else(connection_error);
+    }
+}
+
+/// an accepted takeback comes back as a resync that rewinds the board
+fn takeback(
+    mut request_event: EventReader<RequestTakebackEvent>,
+    mut respond_event: EventReader<RespondTakebackEvent>,
+    connection: Res<ClientConnection<ClientNetConfig>>,
+    game: Res<GameId>,
+) {
+    let packets = request_event
+        .read()
+        .map(|_| ClientPacket::RequestTakeback(*game))
+        .chain(
+            respond_event
+                .read()
+                .map(|&RespondTakebackEvent(accept)| ClientPacket::RespondTakeback(*game, accept)),
+        );
+    for packet in packets {
+        connection.send(packet).unwrap_or_else(connection_error);
+    }
+}
+
+fn ask_hint(
+    mut hint_event: EventReader<AskHintEvent>,
+    connection: Res<ClientConnection<ClientNetConfig>>,
+    game: Res<GameId>,
+) {
+    for _ in hint_event.read() {
+        connection
+            .send(ClientPacket::Hint(*game))
+            .unwrap_or_else(connection_error);
+    }
+}
+
+fn send_chat(
+    mut chat_event: EventReader<SendChatEvent>,
+    connection: Res<ClientConnection<ClientNetConfig>>,
+    game: Res<GameId>,
+) {
+    for event in chat_event.read() {
+        connection
+            .send(ClientPacket::Chat(*game, event.0.clone()))
+            .unwrap_or_else(connection_error);
+    }
+}
+
+fn send_ping(
+    connection: Res<ClientConnection<ClientNetConfig>>,
+    mut timer: ResMut<PingTimer>,
+    time: Res<Time>,
+) {
+    if timer.0.tick(time.delta()).just_finished() {
+        connection
+            .send(ClientPacket::Ping(timestamp()))
+            .unwrap_or_else(connection_error);
+    }
+}
+
+/// tries to get back into the game once when the connection drops
+fn reconnect(
+    mut disconnect_event: EventReader<DisconnectionEvent<ClientNetConfig>>,
+    mut connection_request: EventWriter<ConnectionRequestEvent<ClientNetConfig>>,
+    mut rejoin: ResMut<Rejoin>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    for event in disconnect_event.read() {
+        if rejoin.attempting {
+            warn!("couldn't rejoin the game");
+            rejoin.attempting = false;
+            game_state.set(GameState::MainMenu);
+        } else {
+            info!("lost the connection, trying to rejoin");
+            rejoin.attempting = true;
+            connection_request.send(ConnectionRequestEvent::new(event.address));
+        }
+    }
+}
+
 fn connection_error(err: impl fmt::Debug) {
     warn!("connection error {:?}", err);
 }
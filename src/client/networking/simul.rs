@@ -0,0 +1,260 @@
+//! the boards a simul host plays besides the one on screen
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+
+use super::{
+    connection::{ClientConnection, PacketReceiveEvent},
+    connection_error, GameClock, GameStart, Rejoin, SimulEntry, SimulTarget,
+};
+use crate::{
+    api::{
+        chessmove::ChessColor, chessstate::ChessState, ClientNetConfig, ClientPacket, GameEnd,
+        GameId, ServerPacket,
+    },
+    client::{
+        announcement::AnnouncementEvent,
+        game::{MoveHistory, Players},
+        GameState,
+    },
+};
+
+/// everything the client knows about a game that isn't on screen
+#[derive(Debug)]
+struct Board {
+    color: ChessColor,
+    players: Players,
+    start: GameStart,
+    history: MoveHistory,
+    state: ChessState,
+    rejoin: Rejoin,
+}
+
+impl Board {
+    fn opponent(&self) -> &str {
+        &self.players.get(!self.color).name
+    }
+}
+
+/// the games of a simul host that wait in the background, tab switches between them
+#[derive(Resource, Default, Debug)]
+pub struct SimulBoards {
+    boards: BTreeMap<GameId, Board>,
+    /// the game on screen is over, it doesn't go back in the background
+    finished: bool,
+}
+
+/// keeps the background boards of a simul host up to date, runs before receive_packet
+/// so every game but the newest one goes in the background when they start
+pub fn follow_boards(
+    mut packet_event: EventReader<PacketReceiveEvent<ClientNetConfig>>,
+    mut simul: ResMut<SimulBoards>,
+    mut announcement: EventWriter<AnnouncementEvent>,
+    target: Res<SimulTarget>,
+    state: Res<State<GameState>>,
+    game: Res<GameId>,
+    color: Res<ChessColor>,
+    mut players: ResMut<Players>,
+    start: Res<GameStart>,
+    mut history: ResMut<MoveHistory>,
+    chess_state: Res<ChessState>,
+    rejoin: Res<Rejoin>,
+) {
+    let packets: Vec<_> = packet_event.read().collect();
+    if !matches!(target.0, Some(SimulEntry::Create)) {
+        return;
+    }
+    // receive_packet puts the newest game on screen
+    let newest = packets
+        .iter()
+        .rposition(|packet| matches!(packet.packet, ServerPacket::MatchFound { .. }));
+    if newest.is_some() && *state.get() == GameState::Gaming && !simul.finished {
+        simul.boards.insert(
+            *game,
+            Board {
+                color: *color,
+                players: std::mem::take(&mut *players),
+                start: *start,
+                history: std::mem::take(&mut *history),
+                state: *chess_state,
+                rejoin: *rejoin,
+            },
+        );
+    }
+    for (index, packet) in packets.into_iter().enumerate() {
+        if let ServerPacket::MatchFound {
+            game: id,
+            color,
+            white,
+            black,
+            rated,
+            start,
+            token,
+            moves,
+            ..
+        } = &packet.packet
+        {
+            if Some(index) == newest {
+                simul.finished = false;
+                continue;
+            }
+            let mut board = Board {
+                color: *color,
+                players: Players {
+                    white: white.clone(),
+                    black: black.clone(),
+                    rated: *rated,
+                },
+                start: GameStart { state: *start },
+                history: MoveHistory::default(),
+                state: *start,
+                rejoin: Rejoin {
+                    token: *token,
+                    attempting: false,
+                },
+            };
+            let Board {
+                start,
+                state,
+                history,
+                ..
+            } = &mut board;
+            start.resync(state, history, 0, moves);
+            simul.boards.insert(*id, board);
+            continue;
+        }
+        let Some(id) = packet.packet.game() else {
+            continue;
+        };
+        if id == *game {
+            if let ServerPacket::EndGame(..) = packet.packet {
+                simul.finished = true;
+            }
+            continue;
+        }
+        let Some(board) = simul.boards.get_mut(&id) else {
+            continue;
+        };
+        match &packet.packet {
+            ServerPacket::Move(_, ply, chess_move, _) => {
+                let expected = board.start.current_ply(&board.history);
+                if *ply != expected || board.state.move_piece(*chess_move).is_err() {
+                    packet
+                        .connection
+                        .send(ClientPacket::Resync(id, expected))
+                        .unwrap_or_else(connection_error);
+                    continue;
+                }
+                board.history.push((*chess_move).into());
+                if board.state.turn == board.color && !board.state.should_promote {
+                    announcement.send(AnnouncementEvent(format!(
+                        "your move against {}, tab switches boards",
+                        board.opponent()
+                    )));
+                }
+            }
+            ServerPacket::Promotion(_, piece) => {
+                if board.state.promote(*piece).is_err() {
+                    let ply = board.start.current_ply(&board.history).saturating_sub(1);
+                    packet
+                        .connection
+                        .send(ClientPacket::Resync(id, ply))
+                        .unwrap_or_else(connection_error);
+                } else if let Some(last) = board.history.last_mut() {
+                    last.promotion = Some(*piece);
+                }
+            }
+            ServerPacket::Resync { since, moves, .. } => {
+                let Board {
+                    start,
+                    state,
+                    history,
+                    ..
+                } = board;
+                if !start.resync(state, history, *since, moves) {
+                    warn!("couldn't resync the board against {}", board.opponent());
+                }
+            }
+            ServerPacket::EndGame(_, end) => {
+                let result = match (*end, board.color) {
+                    (GameEnd::Draw(_), _) => "drew",
+                    (GameEnd::White(_), ChessColor::White)
+                    | (GameEnd::Black(_), ChessColor::Black) => "won",
+                    _ => "lost",
+                };
+                announcement.send(AnnouncementEvent(format!(
+                    "you {} against {}",
+                    result,
+                    board.opponent()
+                )));
+                simul.boards.remove(&id);
+            }
+            _ => {}
+        }
+    }
+    if *state.get() == GameState::MainMenu {
+        simul.boards.clear();
+    }
+}
+
+/// puts the next background board on screen and the current one in the background
+pub fn switch_board(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    connection: Res<ClientConnection<ClientNetConfig>>,
+    mut simul: ResMut<SimulBoards>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut game: ResMut<GameId>,
+    mut game_clock: ResMut<GameClock>,
+    mut color: ResMut<ChessColor>,
+    mut players: ResMut<Players>,
+    mut start: ResMut<GameStart>,
+    mut history: ResMut<MoveHistory>,
+    mut chess_state: ResMut<ChessState>,
+    mut rejoin: ResMut<Rejoin>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::Tab) {
+        return;
+    }
+    // the boards after this one first, then the ones before it
+    let Some(next) = simul
+        .boards
+        .range(*game..)
+        .chain(simul.boards.range(..*game))
+        .map(|(&id, _)| id)
+        .find(|&id| id != *game)
+    else {
+        return;
+    };
+    let Some(board) = simul.boards.remove(&next) else {
+        return;
+    };
+    if !simul.finished {
+        simul.boards.insert(
+            *game,
+            Board {
+                color: *color,
+                players: std::mem::take(&mut *players),
+                start: *start,
+                history: std::mem::take(&mut *history),
+                state: *chess_state,
+                rejoin: *rejoin,
+            },
+        );
+    }
+    simul.finished = false;
+    *game = next;
+    *color = board.color;
+    *players = board.players;
+    *start = board.start;
+    *history = board.history;
+    *chess_state = board.state;
+    *rejoin = board.rejoin;
+    *game_clock = GameClock::default();
+    // the answer brings the clock and anything missed while switching
+    connection
+        .send(ClientPacket::Resync(next, start.current_ply(&history)))
+        .unwrap_or_else(connection_error);
+    // going from gaming to gaming rebuilds the board
+    game_state.set(GameState::Gaming);
+}
@@ -0,0 +1,289 @@
+use std::time::Duration;
+
+use bevy::color::palettes::css as color;
+use bevy::prelude::*;
+
+use crate::api::{handicap::Handicap, OpenSeek, SeekId};
+
+use super::{despawn_screen, loading::LeaveQueueEvent, GameState, FONT};
+
+/// how often the list asks the server for the seeks again
+const REFRESH_INTERVAL: Duration = Duration::from_secs(3);
+
+pub struct SeeksPlugin;
+
+impl Plugin for SeeksPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SeekEvent>()
+            .add_event::<SeeksEvent>()
+            .init_resource::<RefreshTimer>()
+            .add_systems(OnEnter(GameState::Seeks), setup)
+            .add_systems(
+                Update,
+                (refresh, show_seeks, accept_seek, post_seek, leave_seeks)
+                    .run_if(in_state(GameState::Seeks)),
+            )
+            .add_systems(OnExit(GameState::Seeks), despawn_screen::<SeekScreen>);
+    }
+}
+
+#[derive(Component)]
+struct SeekScreen;
+
+/// the node the seek buttons are put in
+#[derive(Component)]
+struct SeekList;
+
+/// accepts the seek it shows when clicked
+#[derive(Component)]
+struct SeekButton(SeekId);
+
+#[derive(Component)]
+struct PostSeekButton;
+
+#[derive(Component)]
+struct PostSeekText;
+
+/// what the seek list asks of the server
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Event)]
+pub enum SeekEvent {
+    Refresh,
+    /// offers a game with the settings from the main menu
+    Post,
+    Accept(SeekId),
+}
+
+/// the seeks of the other players, from the server
+#[derive(Debug, Clone, Event)]
+pub struct SeeksEvent(pub Vec<OpenSeek>);
+
+#[derive(Resource, Debug)]
+struct RefreshTimer(Timer);
+
+impl Default for RefreshTimer {
+    fn default() -> Self {
+        Self(Timer::new(REFRESH_INTERVAL, TimerMode::Repeating))
+    }
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                clear_color: ClearColorConfig::Custom(
+                    Srgba {
+                        red: 0.1,
+                        green: 0.3,
+                        blue: 0.2,
+                        alpha: 1.0,
+                    }
+                    .into(),
+                ),
+                ..default()
+            },
+            ..default()
+        },
+        SeekScreen,
+    ));
+
+    commands.spawn((
+        TextBundle::from_section(
+            "open seeks, click one to play it",
+            TextStyle {
+                font: asset_server.load(FONT),
+                font_size: 60.0,
+                color: color::GOLD.into(),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Px(15.0),
+            ..default()
+        }),
+        SeekScreen,
+    ));
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                top: Val::Px(85.0),
+                left: Val::Px(15.0),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(10.0),
+                ..default()
+            },
+            ..default()
+        },
+        SeekList,
+        SeekScreen,
+    ));
+
+    commands
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(5.0),
+                    right: Val::Px(15.0),
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: color::DARK_GRAY.into(),
+                ..default()
+            },
+            PostSeekButton,
+            SeekScreen,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "post a seek",
+                    TextStyle {
+                        font: asset_server.load(FONT),
+                        font_size: 40.0,
+                        color: color::WHITE.into(),
+                    },
+                ),
+                PostSeekText,
+            ));
+        });
+
+    commands.spawn((
+        TextBundle::from_section(
+            "Press escape to go back",
+            TextStyle {
+                font: asset_server.load(FONT),
+                font_size: 40.0,
+                color: color::GOLD.into(),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(15.0),
+            left: Val::Px(15.0),
+            ..default()
+        }),
+        SeekScreen,
+    ));
+}
+
+fn refresh(mut timer: ResMut<RefreshTimer>, time: Res<Time>, mut writer: EventWriter<SeekEvent>) {
+    if timer.0.tick(time.delta()).just_finished() {
+        writer.send(SeekEvent::Refresh);
+    }
+}
+
+fn seek_label(seek: &OpenSeek) -> String {
+    let mut label = seek.player.name.clone();
+    if let Some(rating) = seek.player.rating {
+        label.push_str(&format!(" ({rating})"));
+    }
+    label.push_str(&match seek.seek.time_control {
+        Some(time_control) => format!(", {time_control}"),
+        None => ", no clock".to_string(),
+    });
+    if let Some(time_odds) = seek.seek.time_odds {
+        label.push_str(&format!(", you get {time_odds}"));
+    }
+    label.push_str(if seek.seek.rated {
+        ", rated"
+    } else {
+        ", casual"
+    });
+    label.push_str(&format!(", plays {}", seek.color));
+    if seek.handicap != Handicap::None {
+        label.push_str(&format!(", gives {} odds", seek.handicap));
+    }
+    label
+}
+
+/// replaces the listed seeks with the ones the server sent
+fn show_seeks(
+    mut commands: Commands,
+    mut events: EventReader<SeeksEvent>,
+    list: Query<Entity, With<SeekList>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Some(SeeksEvent(seeks)) = events.read().last() else {
+        return;
+    };
+    for list in list.iter() {
+        commands
+            .entity(list)
+            .despawn_descendants()
+            .with_children(|parent| {
+                if seeks.is_empty() {
+                    parent.spawn(TextBundle::from_section(
+                        "nobody is seeking a game right now",
+                        TextStyle {
+                            font: asset_server.load(FONT),
+                            font_size: 40.0,
+                            color: color::WHITE.into(),
+                        },
+                    ));
+                }
+                for seek in seeks {
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    padding: UiRect::all(Val::Px(10.0)),
+                                    ..default()
+                                },
+                                background_color: color::DARK_GRAY.into(),
+                                ..default()
+                            },
+                            SeekButton(seek.id),
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                seek_label(seek),
+                                TextStyle {
+                                    font: asset_server.load(FONT),
+                                    font_size: 40.0,
+                                    color: color::WHITE.into(),
+                                },
+                            ));
+                        });
+                }
+            });
+    }
+}
+
+fn accept_seek(
+    query: Query<(&Interaction, &SeekButton), Changed<Interaction>>,
+    mut writer: EventWriter<SeekEvent>,
+) {
+    for (&interaction, button) in query.iter() {
+        if interaction == Interaction::Pressed {
+            writer.send(SeekEvent::Accept(button.0));
+        }
+    }
+}
+
+fn post_seek(
+    query: Query<&Interaction, (Changed<Interaction>, With<PostSeekButton>)>,
+    mut text: Query<&mut Text, With<PostSeekText>>,
+    mut writer: EventWriter<SeekEvent>,
+) {
+    for &interaction in query.iter() {
+        if interaction == Interaction::Pressed {
+            writer.send(SeekEvent::Post);
+            for mut text in text.iter_mut() {
+                text.sections[0].value = "seek posted, waiting for someone to accept".to_string();
+            }
+        }
+    }
+}
+
+/// takes back your seek and goes back to the main menu
+fn leave_seeks(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut writer: EventWriter<LeaveQueueEvent>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        writer.send(LeaveQueueEvent);
+    }
+}
@@ -0,0 +1,336 @@
+//! the choices the player made, kept in settings.toml in the config directory of the platform
+
+use std::{fmt::Display, fs, path::PathBuf, time::Duration};
+
+#[cfg(not(target_arch = "wasm32"))]
+use bevy::asset::io::file::FileAssetReader;
+use bevy::prelude::*;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        // last, so the changes made on the frame the window closes are still saved
+        app.add_systems(Last, save_settings);
+    }
+}
+
+/// the piece set the game comes with
+const DEFAULT_PIECE_SET: &str = "classic";
+/// where the address box points before anything else was typed in
+pub const DEFAULT_SERVER: &str = "127.0.0.1:1812";
+/// how many servers the address box remembers
+const RECENT_SERVERS: usize = 5;
+/// the share of the smaller side of the window the board fills
+const DEFAULT_BOARD_SIZE: f32 = 0.8;
+const MIN_BOARD_SIZE: f32 = 0.5;
+const MAX_BOARD_SIZE: f32 = 1.0;
+/// how much a notch of the mouse wheel changes the board size, the slider snaps to it too
+const BOARD_SIZE_STEP: f32 = 0.05;
+/// how long the settings wait for more changes before they are written, dragging the board size
+/// slider or scrolling changes them every frame
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default)]
+pub struct Settings {
+    pub board_theme: BoardTheme,
+    /// the share of the smaller side of the window the board fills
+    pub board_size: f32,
+    /// the folder in assets/chess the pieces come from
+    pub piece_set: String,
+    pub sounds: bool,
+    /// promotes to a queen without asking
+    pub auto_queen: bool,
+    /// letters and numbers along the edges of the board
+    pub coordinates: bool,
+    pub animation_speed: AnimationSpeed,
+    /// frames per second in the top of the screen, f3 turns it on and off too
+    pub show_fps: bool,
+    /// the address in the main menu, the last server played on
+    pub server: String,
+    /// the servers connected to before, the newest first
+    pub recent_servers: Vec<String>,
+    /// the name to play with when the game starts without --name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            board_theme: BoardTheme::default(),
+            board_size: DEFAULT_BOARD_SIZE,
+            piece_set: DEFAULT_PIECE_SET.to_string(),
+            sounds: true,
+            auto_queen: false,
+            coordinates: true,
+            animation_speed: AnimationSpeed::default(),
+            show_fps: false,
+            server: DEFAULT_SERVER.to_string(),
+            recent_servers: Vec::new(),
+            name: None,
+        }
+    }
+}
+
+impl Settings {
+    fn path() -> Option<PathBuf> {
+        ProjectDirs::from("", "", "random_unnamed_chess_game")
+            .map(|dirs| dirs.config_dir().join("settings.toml"))
+    }
+
+    /// the saved settings, the defaults when there are none yet
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        let Ok(text) = fs::read_to_string(&path) else {
+            return Self::default();
+        };
+        let mut settings: Self = toml::from_str(&text).unwrap_or_else(|error| {
+            warn!("couldn't read {}: {}", path.display(), error);
+            Self::default()
+        });
+        // the folder of the set could be gone since
+        if !piece_sets().contains(&settings.piece_set) {
+            settings.piece_set = DEFAULT_PIECE_SET.to_string();
+        }
+        if !(MIN_BOARD_SIZE..=MAX_BOARD_SIZE).contains(&settings.board_size) {
+            settings.board_size = DEFAULT_BOARD_SIZE;
+        }
+        settings
+    }
+
+    /// the board size at `position` along the slider, 0 is the smallest and 1 the biggest
+    pub fn board_size_at(position: f32) -> f32 {
+        let size = MIN_BOARD_SIZE + position.clamp(0.0, 1.0) * (MAX_BOARD_SIZE - MIN_BOARD_SIZE);
+        ((size / BOARD_SIZE_STEP).round() * BOARD_SIZE_STEP).clamp(MIN_BOARD_SIZE, MAX_BOARD_SIZE)
+    }
+
+    /// where the board size is along the slider, the opposite of [`Settings::board_size_at`]
+    pub fn board_size_position(&self) -> f32 {
+        (self.board_size - MIN_BOARD_SIZE) / (MAX_BOARD_SIZE - MIN_BOARD_SIZE)
+    }
+
+    /// the board size `steps` steps bigger, or smaller for negative steps
+    pub fn zoomed(&self, steps: f32) -> f32 {
+        (self.board_size + steps * BOARD_SIZE_STEP).clamp(MIN_BOARD_SIZE, MAX_BOARD_SIZE)
+    }
+
+    /// the piece set after the one picked now
+    pub fn next_piece_set(&self) -> String {
+        let sets = piece_sets();
+        let index = sets.iter().position(|set| *set == self.piece_set);
+        index
+            .and_then(|index| sets.get((index + 1) % sets.len()))
+            .or(sets.first())
+            .cloned()
+            .unwrap_or_else(|| DEFAULT_PIECE_SET.to_string())
+    }
+
+    /// makes `server` the one in the address box and the newest of the recent servers
+    pub fn remember_server(&mut self, server: &str) {
+        self.server = server.to_string();
+        self.recent_servers.retain(|recent| recent != server);
+        self.recent_servers.insert(0, server.to_string());
+        self.recent_servers.truncate(RECENT_SERVERS);
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::path().ok_or("there is no config directory")?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, toml::to_string(self)?)?;
+        Ok(())
+    }
+}
+
+/// the folders in assets/chess, every one of them holds a piece set
+#[cfg(not(target_arch = "wasm32"))]
+pub fn piece_sets() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(FileAssetReader::get_base_path().join("assets/chess")) else {
+        return vec![DEFAULT_PIECE_SET.to_string()];
+    };
+    let mut sets: Vec<String> = entries
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .collect();
+    sets.sort();
+    sets
+}
+
+/// a browser can't list the folders on the server it loads the assets from
+#[cfg(target_arch = "wasm32")]
+pub fn piece_sets() -> Vec<String> {
+    vec![DEFAULT_PIECE_SET.to_string()]
+}
+
+/// writes the settings once they stopped changing for a moment, or right away when the game closes
+fn save_settings(
+    settings: Res<Settings>,
+    time: Res<Time>,
+    mut exit: EventReader<AppExit>,
+    mut pending: Local<Option<Timer>>,
+) {
+    if settings.is_changed() && !settings.is_added() {
+        *pending = Some(Timer::new(SAVE_DELAY, TimerMode::Once));
+    }
+    let exiting = exit.read().count() > 0;
+    let Some(timer) = pending.as_mut() else {
+        return;
+    };
+    if !timer.tick(time.delta()).finished() && !exiting {
+        return;
+    }
+    *pending = None;
+    if let Err(error) = settings.save() {
+        warn!("couldn't save the settings: {}", error);
+    }
+}
+
+/// the colors of the squares and of what gets drawn on them
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum BoardTheme {
+    /// black and white, with bold highlights
+    #[default]
+    HighContrast,
+    Green,
+    Blue,
+    Wood,
+    /// for deuteranopia and protanopia, blue and orange instead of red and green
+    RedGreen,
+    /// for tritanopia, red and teal instead of blue and yellow
+    BlueYellow,
+}
+
+impl BoardTheme {
+    pub const ALL: [BoardTheme; 6] = [
+        BoardTheme::HighContrast,
+        BoardTheme::Green,
+        BoardTheme::Blue,
+        BoardTheme::Wood,
+        BoardTheme::RedGreen,
+        BoardTheme::BlueYellow,
+    ];
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&t| t == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// the light and the dark squares
+    pub fn colors(self) -> (Color, Color) {
+        match self {
+            BoardTheme::HighContrast => (Color::srgb(1.0, 1.0, 1.0), Color::srgb(0.0, 0.0, 0.0)),
+            BoardTheme::Green => (Color::srgb(0.93, 0.93, 0.82), Color::srgb(0.46, 0.59, 0.34)),
+            BoardTheme::Blue => (Color::srgb(0.87, 0.89, 0.9), Color::srgb(0.55, 0.64, 0.71)),
+            BoardTheme::Wood => (Color::srgb(0.94, 0.85, 0.71), Color::srgb(0.71, 0.53, 0.39)),
+            BoardTheme::RedGreen => (Color::srgb(0.93, 0.93, 0.93), Color::srgb(0.5, 0.55, 0.65)),
+            BoardTheme::BlueYellow => (Color::srgb(0.95, 0.9, 0.9), Color::srgb(0.6, 0.5, 0.5)),
+        }
+    }
+
+    /// the square of the selected piece
+    pub fn highlight(self) -> Color {
+        match self {
+            BoardTheme::HighContrast => Color::srgba(1.0, 0.85, 0.0, 0.6),
+            BoardTheme::RedGreen => Color::srgba(0.9, 0.6, 0.0, 0.6),
+            BoardTheme::BlueYellow => Color::srgba(0.0, 0.6, 0.5, 0.5),
+            _ => Color::srgba(1.0, 1.0, 0.0, 0.3),
+        }
+    }
+
+    /// the dots and rings on the squares the selected piece can go to
+    pub fn move_hint(self) -> Color {
+        match self {
+            BoardTheme::HighContrast => Color::srgba(0.0, 0.8, 1.0, 0.9),
+            BoardTheme::RedGreen => Color::srgba(0.0, 0.45, 0.7, 0.8),
+            BoardTheme::BlueYellow => Color::srgba(0.8, 0.1, 0.3, 0.8),
+            _ => Color::srgba(0.2, 0.6, 0.2, 0.6),
+        }
+    }
+
+    /// the square of a king in check, framed so it doesn't depend on the color alone
+    pub fn check(self) -> Color {
+        match self {
+            BoardTheme::HighContrast => Color::srgba(1.0, 0.0, 0.0, 0.8),
+            BoardTheme::RedGreen => Color::srgba(0.95, 0.9, 0.25, 0.8),
+            BoardTheme::BlueYellow => Color::srgba(0.85, 0.1, 0.1, 0.7),
+            _ => Color::srgba(1.0, 0.0, 0.0, 0.5),
+        }
+    }
+
+    /// the arrow of a hint, the arrows you draw are orange
+    pub fn hint_arrow(self) -> Color {
+        match self {
+            BoardTheme::RedGreen => Color::srgba(0.35, 0.7, 0.9, 0.8),
+            BoardTheme::BlueYellow => Color::srgba(0.0, 0.6, 0.5, 0.8),
+            _ => Color::srgba(0.2, 0.8, 0.2, 0.7),
+        }
+    }
+}
+
+impl Display for BoardTheme {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            BoardTheme::HighContrast => "high contrast",
+            BoardTheme::Green => "classic green",
+            BoardTheme::Blue => "blue",
+            BoardTheme::Wood => "wood",
+            BoardTheme::RedGreen => "colorblind red-green",
+            BoardTheme::BlueYellow => "colorblind blue-yellow",
+        })
+    }
+}
+
+/// how long a piece takes to slide to its new square
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum AnimationSpeed {
+    Off,
+    Slow,
+    #[default]
+    Normal,
+    Fast,
+}
+
+impl AnimationSpeed {
+    pub const ALL: [AnimationSpeed; 4] = [
+        AnimationSpeed::Off,
+        AnimationSpeed::Slow,
+        AnimationSpeed::Normal,
+        AnimationSpeed::Fast,
+    ];
+
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&s| s == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// None moves pieces right away
+    pub fn duration(self) -> Option<Duration> {
+        match self {
+            AnimationSpeed::Off => None,
+            AnimationSpeed::Slow => Some(Duration::from_millis(400)),
+            AnimationSpeed::Normal => Some(Duration::from_millis(200)),
+            AnimationSpeed::Fast => Some(Duration::from_millis(100)),
+        }
+    }
+}
+
+impl Display for AnimationSpeed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            AnimationSpeed::Off => "off",
+            AnimationSpeed::Slow => "slow",
+            AnimationSpeed::Normal => "normal",
+            AnimationSpeed::Fast => "fast",
+        })
+    }
+}
@@ -0,0 +1,322 @@
+use bevy::color::palettes::css as color;
+use bevy::prelude::*;
+use bevy::ui::RelativeCursorPosition;
+
+use super::{
+    despawn_screen,
+    main_menu::{ConnectionText, ServerLookup},
+    networking::ConnectionAddress,
+    settings::{Settings, DEFAULT_SERVER},
+    GameState, FONT,
+};
+
+pub struct SettingsMenuPlugin;
+
+impl Plugin for SettingsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::Settings), setup)
+            .add_systems(
+                Update,
+                (change_setting, drag_board_size, leave_settings)
+                    .run_if(in_state(GameState::Settings)),
+            )
+            .add_systems(
+                OnExit(GameState::Settings),
+                despawn_screen::<SettingsScreen>,
+            );
+    }
+}
+
+#[derive(Component)]
+struct SettingsScreen;
+
+/// the bar the board size is dragged along
+#[derive(Component)]
+struct BoardSizeSlider;
+
+/// the part of the slider left of the board size
+#[derive(Component)]
+struct BoardSizeFill;
+
+/// a button that changes one setting when clicked, its text shows the current value
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq)]
+enum Setting {
+    BoardTheme,
+    /// a slider instead of a button
+    BoardSize,
+    PieceSet,
+    Sounds,
+    AutoQueen,
+    Coordinates,
+    AnimationSpeed,
+    ShowFps,
+    /// resets the address the main menu starts with
+    Server,
+    /// forgets the name given with --name
+    Name,
+    Back,
+}
+
+impl Setting {
+    const ALL: [Setting; 11] = [
+        Setting::BoardTheme,
+        Setting::BoardSize,
+        Setting::PieceSet,
+        Setting::Sounds,
+        Setting::AutoQueen,
+        Setting::Coordinates,
+        Setting::AnimationSpeed,
+        Setting::ShowFps,
+        Setting::Server,
+        Setting::Name,
+        Setting::Back,
+    ];
+
+    fn label(self, settings: &Settings) -> String {
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        match self {
+            Setting::BoardTheme => format!("board: {}", settings.board_theme),
+            Setting::BoardSize => format!("board size: {:.0}%", settings.board_size * 100.0),
+            Setting::PieceSet => format!("pieces: {}", settings.piece_set),
+            Setting::Sounds => format!("sounds: {}", on_off(settings.sounds)),
+            Setting::AutoQueen => {
+                format!("always promote to a queen: {}", on_off(settings.auto_queen))
+            }
+            Setting::Coordinates => format!("coordinates: {}", on_off(settings.coordinates)),
+            Setting::AnimationSpeed => format!("animations: {}", settings.animation_speed),
+            Setting::ShowFps => format!("fps counter (f3): {}", on_off(settings.show_fps)),
+            Setting::Server => format!("server: {} (click to reset)", settings.server),
+            Setting::Name => match &settings.name {
+                Some(name) => format!("name: {name} (click to forget)"),
+                None => "name: picked by the server".to_string(),
+            },
+            Setting::Back => "back".to_string(),
+        }
+    }
+
+    fn change(self, settings: &mut Settings) {
+        match self {
+            Setting::BoardTheme => settings.board_theme = settings.board_theme.next(),
+            // changed by dragging the slider
+            Setting::BoardSize => {}
+            Setting::PieceSet => settings.piece_set = settings.next_piece_set(),
+            Setting::Sounds => settings.sounds = !settings.sounds,
+            Setting::AutoQueen => settings.auto_queen = !settings.auto_queen,
+            Setting::Coordinates => settings.coordinates = !settings.coordinates,
+            Setting::AnimationSpeed => {
+                settings.animation_speed = settings.animation_speed.next();
+            }
+            Setting::ShowFps => settings.show_fps = !settings.show_fps,
+            Setting::Server => {
+                settings.server = DEFAULT_SERVER.to_string();
+                settings.recent_servers.clear();
+            }
+            Setting::Name => settings.name = None,
+            Setting::Back => {}
+        }
+    }
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>, settings: Res<Settings>) {
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                clear_color: ClearColorConfig::Custom(
+                    Srgba {
+                        red: 0.2,
+                        green: 0.2,
+                        blue: 0.3,
+                        alpha: 1.0,
+                    }
+                    .into(),
+                ),
+                ..default()
+            },
+            ..default()
+        },
+        SettingsScreen,
+    ));
+
+    commands.spawn((
+        TextBundle::from_section(
+            "settings, escape goes back",
+            TextStyle {
+                font: asset_server.load(FONT),
+                font_size: 60.0,
+                color: color::GOLD.into(),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(5.0),
+            left: Val::Px(15.0),
+            ..default()
+        }),
+        SettingsScreen,
+    ));
+
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(85.0),
+                    left: Val::Px(15.0),
+                    flex_direction: FlexDirection::Column,
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                },
+                ..default()
+            },
+            SettingsScreen,
+        ))
+        .with_children(|parent| {
+            let text_style = TextStyle {
+                font: asset_server.load(FONT),
+                font_size: 40.0,
+                color: color::WHITE.into(),
+            };
+            for setting in Setting::ALL {
+                if setting == Setting::BoardSize {
+                    board_size_slider(parent, text_style.clone(), &settings);
+                    continue;
+                }
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                padding: UiRect::all(Val::Px(10.0)),
+                                ..default()
+                            },
+                            background_color: color::DARK_GRAY.into(),
+                            ..default()
+                        },
+                        setting,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle::from_section(setting.label(&settings), text_style.clone()),
+                            setting,
+                        ));
+                    });
+            }
+        });
+}
+
+/// the board size with a bar under it, clicking or dragging along the bar picks the size
+fn board_size_slider(parent: &mut ChildBuilder, text_style: TextStyle, settings: &Settings) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                padding: UiRect::all(Val::Px(10.0)),
+                flex_direction: FlexDirection::Column,
+                row_gap: Val::Px(5.0),
+                ..default()
+            },
+            background_color: color::DARK_GRAY.into(),
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(Setting::BoardSize.label(settings), text_style),
+                Setting::BoardSize,
+            ));
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(400.0),
+                            height: Val::Px(20.0),
+                            ..default()
+                        },
+                        background_color: color::GRAY.into(),
+                        ..default()
+                    },
+                    RelativeCursorPosition::default(),
+                    BoardSizeSlider,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(settings.board_size_position() * 100.0),
+                                height: Val::Percent(100.0),
+                                ..default()
+                            },
+                            background_color: color::GOLD.into(),
+                            ..default()
+                        },
+                        BoardSizeFill,
+                    ));
+                });
+        });
+}
+
+/// sets the board size to where the slider is held, for as long as the mouse button is down
+fn drag_board_size(
+    slider: Query<(&Interaction, &RelativeCursorPosition), With<BoardSizeSlider>>,
+    mut fill: Query<&mut Style, With<BoardSizeFill>>,
+    mut texts: Query<(&mut Text, &Setting)>,
+    mut settings: ResMut<Settings>,
+) {
+    let Some(position) = slider
+        .iter()
+        .filter(|(&interaction, _)| interaction == Interaction::Pressed)
+        .find_map(|(_, cursor)| cursor.normalized)
+    else {
+        return;
+    };
+    let size = Settings::board_size_at(position.x);
+    if size == settings.board_size {
+        return;
+    }
+    settings.board_size = size;
+    for mut style in fill.iter_mut() {
+        style.width = Val::Percent(settings.board_size_position() * 100.0);
+    }
+    for (mut text, &shown) in texts.iter_mut() {
+        if shown == Setting::BoardSize {
+            text.sections[0].value = Setting::BoardSize.label(&settings);
+        }
+    }
+}
+
+fn change_setting(
+    mut commands: Commands,
+    query: Query<(&Interaction, &Setting), Changed<Interaction>>,
+    mut texts: Query<(&mut Text, &Setting)>,
+    mut settings: ResMut<Settings>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut connection_text: ResMut<ConnectionText>,
+    mut address: ResMut<ConnectionAddress>,
+) {
+    for (&interaction, &setting) in query.iter() {
+        if interaction != Interaction::Pressed {
+            continue;
+        }
+        if setting == Setting::Back {
+            game_state.set(GameState::MainMenu);
+            continue;
+        }
+        setting.change(&mut settings);
+        // the main menu connects to the server it shows
+        if setting == Setting::Server {
+            connection_text.0.clone_from(&settings.server);
+            ServerLookup::start(&mut commands, &settings.server, &mut address);
+        }
+        for (mut text, &shown) in texts.iter_mut() {
+            if shown == setting {
+                text.sections[0].value = setting.label(&settings);
+            }
+        }
+    }
+}
+
+fn leave_settings(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        game_state.set(GameState::MainMenu);
+    }
+}
@@ -9,34 +9,307 @@ mod server;
 #[cfg(not(any(feature = "client", feature = "server")))]
 compile_error!("You must enable at least the client or server feature!");
 
-fn main() {
+// the server needs threads and sockets a browser doesn't have
+#[cfg(all(target_arch = "wasm32", feature = "server"))]
+compile_error!("build the browser client with --no-default-features --features client");
+
+use clap::{Parser, Subcommand};
+
+#[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+use api::transport::Transport;
+
+/// a chess game with a server and a client, without a command it opens the game
+#[derive(Parser, Debug)]
+#[command(version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// how to talk to the server, tcp or websocket
+    #[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
+    #[arg(long, global = true)]
+    transport: Option<Transport>,
+    #[cfg(feature = "client")]
+    #[command(flatten)]
+    client: ClientArgs,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// runs the server without a window
     #[cfg(feature = "server")]
+    #[command(long_flag = "server", short_flag = 's')]
+    Serve(Box<ServeArgs>),
+    /// plays random games without a window and prints how many moves per second the rules
+    /// engine manages
+    BenchSelfplay {
+        #[arg(default_value_t = 100)]
+        games: u64,
+    },
+    /// starts a server and plays random games against it with pairs of bots, prints how many
+    /// moves per second it handles and every move or result it disagrees with the rules about
+    #[cfg(feature = "server")]
+    #[command(long_flag = "simulate")]
+    Simulate {
+        /// how many games are played at the same time
+        #[arg(default_value_t = 10)]
+        pairs: u32,
+        /// stops after this many finished games
+        #[arg(long, default_value_t = 100)]
+        games: u64,
+        #[arg(long, short, default_value_t = server::DEFAULT_PORT + 1)]
+        port: u16,
+        /// milliseconds a bot waits before it moves
+        #[arg(long, default_value_t = 150)]
+        think: u64,
+    },
+}
+
+#[cfg(feature = "client")]
+#[derive(clap::Args, Debug)]
+struct ClientArgs {
+    /// the name your opponent sees
+    #[arg(long)]
+    name: Option<String>,
+    /// plays the player with this name instead of whoever is in the queue
+    #[arg(long)]
+    challenge: Option<String>,
+    /// new, new-double or the number of a league to join
+    #[arg(long, value_parser = parse_league)]
+    league: Option<client::LeagueEntry>,
+    /// new to host a simul, or the number of a simul to join
+    #[arg(long, value_parser = parse_simul, conflicts_with_all = ["league", "challenge"])]
+    simul: Option<client::SimulEntry>,
+    /// creates an account, as name:password
+    #[arg(long, value_parser = parse_account, conflicts_with = "login")]
+    register: Option<(String, String)>,
+    /// logs into an account, as name:password
+    #[arg(long, value_parser = parse_account)]
+    login: Option<(String, String)>,
+}
+
+#[cfg(feature = "client")]
+fn parse_league(league: &str) -> Result<client::LeagueEntry, String> {
+    match league {
+        "new" => Ok(client::LeagueEntry::Create { double: false }),
+        "new-double" => Ok(client::LeagueEntry::Create { double: true }),
+        id => id
+            .parse()
+            .map(|id| client::LeagueEntry::Join(api::league::LeagueId(id)))
+            .map_err(|_| "expected new, new-double or a league number".to_string()),
+    }
+}
+
+#[cfg(feature = "client")]
+fn parse_simul(simul: &str) -> Result<client::SimulEntry, String> {
+    match simul {
+        "new" => Ok(client::SimulEntry::Create),
+        id => id
+            .parse()
+            .map(|id| client::SimulEntry::Join(api::simul::SimulId(id)))
+            .map_err(|_| "expected new or a simul number".to_string()),
+    }
+}
+
+#[cfg(feature = "client")]
+fn parse_account(account: &str) -> Result<(String, String), String> {
+    account
+        .split_once(':')
+        .map(|(name, password)| (name.to_string(), password.to_string()))
+        .ok_or_else(|| "expected an account as name:password".to_string())
+}
+
+#[cfg(feature = "server")]
+#[derive(clap::Args, Debug, Default)]
+struct ServeArgs {
+    /// the address to listen on, an ip or host name, :: for ipv6 [default: 0.0.0.0]
+    #[arg(long)]
+    bind: Option<String>,
+    /// [default: 1812]
+    #[arg(short, long)]
+    port: Option<u16>,
+    /// a toml file with the server settings, the flags win over it
+    #[arg(long)]
+    config: Option<std::path::PathBuf>,
+    /// stops pairing players from the queue while this many games are running
+    #[arg(long)]
+    max_games: Option<u32>,
+    /// the sqlite database to keep everything in
+    #[arg(long)]
+    database: Option<String>,
+    /// remembers nothing after the server stops, even if the config file names a database
+    #[arg(long, conflicts_with = "database")]
+    no_persistence: bool,
+    /// greets everyone who connects
+    #[arg(long)]
+    motd: Option<String>,
+    /// the address to serve the http status pages on
+    #[arg(long)]
+    http: Option<String>,
+    /// posts json to this url when a game starts or ends, can be given more than once
+    #[arg(long = "webhook")]
+    webhooks: Vec<String>,
+    /// the directory every finished game gets appended to as pgn
+    #[arg(long)]
+    pgn_archive: Option<std::path::PathBuf>,
+    /// seconds a disconnected player gets to come back [default: 30]
+    #[arg(long)]
+    grace: Option<u64>,
+    /// stops the clock of a disconnected player until they come back
+    #[arg(long)]
+    pause_clock: bool,
+    /// seconds a league game waits for its players before they forfeit [default: 300]
+    #[arg(long)]
+    league_no_show: Option<u64>,
+    /// seconds a player waits in the queue before the server's bot plays them, 0 never
+    /// [default: 30]
+    #[arg(long)]
+    bot_after: Option<u64>,
+    /// seconds a game without a running clock waits for a move before it is called off, 0 never
+    /// [default: 1800]
+    #[arg(long)]
+    stale_after: Option<u64>,
+    /// seconds each player has for their first move before the game is aborted, 0 forever
+    /// [default: 30]
+    #[arg(long)]
+    first_move: Option<u64>,
+    /// ends games where neither player can checkmate anymore as a draw
+    #[arg(long)]
+    adjudicate: bool,
+    /// the transport of the server, if it isn't the one from --transport
+    #[cfg(feature = "websocket")]
+    #[arg(long)]
+    server_transport: Option<Transport>,
+}
+
+#[cfg(feature = "server")]
+fn serve(args: ServeArgs) {
+    use clap::{error::ErrorKind, CommandFactory};
+    use std::time::Duration;
+
+    let fail =
+        |kind: ErrorKind, message: String| -> ! { Cli::command().error(kind, message).exit() };
+    // the config file sets what it has, the flags override that
+    let file = args
+        .config
+        .as_ref()
+        .map_or_else(server::ConfigFile::default, |path| {
+            server::ConfigFile::read(path).unwrap_or_else(|error| fail(ErrorKind::Io, error))
+        });
+    let mut options = server::ServerOptions {
+        config: args.config.clone(),
+        ..Default::default()
+    };
+    file.apply(&mut options);
+    if args.motd.is_some() {
+        options.motd = args.motd;
+    }
+    if args.database.is_some() {
+        options.database = args.database;
+    }
+    if args.no_persistence {
+        options.database = None;
+    }
+    if args.http.is_some() {
+        options.http = args.http;
+    }
+    if !args.webhooks.is_empty() {
+        options.webhooks = args.webhooks;
+    }
+    if args.pgn_archive.is_some() {
+        options.pgn_archive = args.pgn_archive;
+    }
+    if let Some(seconds) = args.grace {
+        options.grace_period = Duration::from_secs(seconds);
+    }
+    if args.pause_clock {
+        options.pause_clock = true;
+    }
+    if let Some(seconds) = args.league_no_show {
+        options.league_no_show = Duration::from_secs(seconds);
+    }
+    if args.max_games.is_some() {
+        options.rules.max_games = args.max_games;
+    }
+    if let Some(seconds) = args.bot_after {
+        options.rules.bot_after = (seconds != 0).then(|| Duration::from_secs(seconds));
+    }
+    if let Some(seconds) = args.stale_after {
+        options.rules.stale_after = (seconds != 0).then(|| Duration::from_secs(seconds));
+    }
+    if let Some(seconds) = args.first_move {
+        options.rules.first_move = (seconds != 0).then(|| Duration::from_secs(seconds));
+    }
+    if args.adjudicate {
+        options.rules.adjudicate = true;
+    }
+    let address = args
+        .bind
+        .as_deref()
+        .or(file.address.as_deref())
+        .unwrap_or(server::DEFAULT_BIND);
+    let port = args.port.or(file.port).unwrap_or(server::DEFAULT_PORT);
+    let addr = server::bind_address(address, port)
+        .unwrap_or_else(|error| fail(ErrorKind::ValueValidation, error));
+    println!("the server listens on {addr}");
+    server::start_server(addr, options);
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    #[cfg(all(feature = "websocket", not(target_arch = "wasm32")))]
     {
-        use std::net::{Ipv4Addr, SocketAddr, ToSocketAddrs};
-
-        let args: Vec<_> = std::env::args().collect();
-
-        if cfg!(not(feature = "client"))
-            || args.get(1).is_some_and(|x| x == "--server" || x == "-s")
-        {
-            let addr = if let Some((_, port)) = args
-                .iter()
-                .filter_map(|s| s.split_once('='))
-                .find(|&arg| arg.0 == "-p" || arg.0 == "--port")
-            {
-                port.to_socket_addrs()
-                    .map(|mut p| p.next())
-                    .unwrap_or_default()
-                    .expect("invalid port or domain")
-            } else {
-                SocketAddr::new(Ipv4Addr::new(127, 0, 0, 1).into(), 1812)
+        use api::transport::{set_client_transport, set_server_transport};
+
+        let client = cli.transport.unwrap_or_default();
+        set_client_transport(client);
+        set_server_transport(match &cli.command {
+            #[cfg(feature = "server")]
+            Some(Command::Serve(args)) => args.server_transport.unwrap_or(client),
+            _ => client,
+        });
+    }
+
+    match cli.command {
+        Some(Command::BenchSelfplay { games }) => println!("{}", api::selfplay::run(games)),
+        #[cfg(feature = "server")]
+        Some(Command::Simulate {
+            pairs,
+            games,
+            port,
+            think,
+        }) => server::simulate::run(
+            std::net::SocketAddr::new(std::net::Ipv4Addr::LOCALHOST.into(), port),
+            server::simulate::SimulateOptions {
+                pairs,
+                games,
+                think: std::time::Duration::from_millis(think),
+            },
+        ),
+        #[cfg(feature = "server")]
+        Some(Command::Serve(args)) => serve(*args),
+        #[cfg(feature = "client")]
+        None => {
+            let args = cli.client;
+            let account = match (args.register, args.login) {
+                (Some((name, password)), _) => Some((name, password, true)),
+                (None, Some((name, password))) => Some((name, password, false)),
+                (None, None) => None,
             };
-            server::start_server(addr);
-            #[cfg(feature = "client")]
-            return;
+            client::start_client(
+                args.name,
+                args.challenge,
+                args.league,
+                args.simul,
+                account.map(|(name, password, register)| client::Credentials {
+                    name,
+                    password,
+                    register,
+                }),
+            );
         }
+        // a server without a client has nothing else to do
+        #[cfg(not(feature = "client"))]
+        None => serve(ServeArgs::default()),
     }
-
-    #[cfg(feature = "client")]
-    client::start_client();
 }
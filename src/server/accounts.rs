@@ -0,0 +1,498 @@
+use std::collections::HashMap;
+#[cfg(feature = "accounts")]
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "accounts")]
+use argon2::{
+    password_hash::{self, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use bevy::prelude::*;
+#[cfg(feature = "accounts")]
+use bevy::tasks::{block_on, poll_once, AsyncComputeTaskPool, Task};
+use bevy_slinet::{
+    connection::{ConnectionId, EcsConnection},
+    server::PacketReceiveEvent,
+};
+use rand::prelude::*;
+
+use super::{
+    connection_error, correspondence,
+    reconnect::{self, GracePeriod},
+    storage::{Storage, Store},
+    ChessGameMap, ConnectionMap, PlayerNames,
+};
+use crate::api::{
+    valid_name, ClientPacket, PlayerId, PlayerInfo, ServerError, ServerNetConfig, ServerPacket,
+    SessionToken,
+};
+
+/// a player the server remembers across connections
+#[derive(Debug, Clone)]
+pub struct Account {
+    pub id: PlayerId,
+    pub name: String,
+    /// registered with a password, anonymous accounts can't play rated games
+    pub registered: bool,
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct Accounts {
+    /// every account by the token that logs into it
+    pub by_token: HashMap<SessionToken, Account>,
+    /// the connections that logged in
+    pub sessions: HashMap<ConnectionId, SessionToken>,
+    /// passwords being hashed or checked, one per connection at most
+    #[cfg(feature = "accounts")]
+    pending: Vec<PendingLogin>,
+    #[cfg(feature = "accounts")]
+    failures: LoginFailures,
+}
+
+impl Accounts {
+    /// logs a connection into the account of `token`, or into a new account if there is none
+    pub fn login(
+        &mut self,
+        connection: &EcsConnection<ServerPacket>,
+        token: Option<SessionToken>,
+        next_player: &mut PlayerId,
+        storage: &dyn Storage,
+    ) -> PlayerId {
+        // registered accounts can log in with a token from before a restart
+        if let Some(token) = token.filter(|token| !self.by_token.contains_key(token)) {
+            match storage.session(token) {
+                Ok(Some(account)) => {
+                    self.by_token.insert(token, account);
+                }
+                Ok(None) => {}
+                Err(error) => warn!("couldn't look up a session: {}", error),
+            }
+        }
+        let token = match token {
+            Some(token) if self.by_token.contains_key(&token) => token,
+            _ => {
+                if token.is_some() {
+                    warn!("{:?} logged in with an unknown token", connection.id());
+                }
+                let token = SessionToken(thread_rng().gen());
+                self.by_token.insert(
+                    token,
+                    Account {
+                        id: *next_player,
+                        name: format!("player {}", next_player.0),
+                        registered: false,
+                    },
+                );
+                next_player.0 += 1;
+                token
+            }
+        };
+        self.start_session(connection, token)
+    }
+
+    /// logs the connection into the account of `token` and tells it so
+    fn start_session(
+        &mut self,
+        connection: &EcsConnection<ServerPacket>,
+        token: SessionToken,
+    ) -> PlayerId {
+        self.sessions.insert(connection.id(), token);
+        let account = &self.by_token[&token];
+        connection
+            .send(ServerPacket::Session {
+                token,
+                player: PlayerInfo {
+                    id: account.id,
+                    name: account.name.clone(),
+                    rating: None,
+                    bot: false,
+                },
+            })
+            .unwrap_or_else(connection_error);
+        account.id
+    }
+
+    /// the account a connection is logged into
+    pub fn account(&self, connection: ConnectionId) -> Option<&Account> {
+        let token = self.sessions.get(&connection)?;
+        self.by_token.get(token)
+    }
+
+    /// whether the connection logged into a registered account, rated games need one
+    pub fn registered(&self, connection: ConnectionId) -> bool {
+        self.account(connection)
+            .is_some_and(|account| account.registered)
+    }
+
+    pub fn account_mut(&mut self, connection: ConnectionId) -> Option<&mut Account> {
+        let token = self.sessions.get(&connection)?;
+        self.by_token.get_mut(token)
+    }
+
+    /// gives the registered account a connection logged into a new name and tells it so,
+    /// anonymous accounts get theirs from Hello
+    fn rename(
+        &mut self,
+        connection: &EcsConnection<ServerPacket>,
+        name: &str,
+        storage: &mut dyn Storage,
+    ) -> Result<(), ServerError> {
+        let token = *self
+            .sessions
+            .get(&connection.id())
+            .ok_or(ServerError::AccountRequired)?;
+        let account = self
+            .by_token
+            .get(&token)
+            .filter(|account| account.registered)
+            .ok_or(ServerError::AccountRequired)?;
+        let id = account.id;
+        match storage.rename_account(id, name) {
+            Ok(true) => {}
+            Ok(false) => return Err(ServerError::NameTaken),
+            Err(error) => {
+                warn!("couldn't rename {:?}: {}", connection.id(), error);
+                return Err(ServerError::AccountsUnavailable);
+            }
+        }
+        // the account can be logged in with more than one token
+        for account in self
+            .by_token
+            .values_mut()
+            .filter(|account| account.id == id)
+        {
+            account.name = name.to_string();
+        }
+        self.start_session(connection, token);
+        Ok(())
+    }
+
+    pub fn logout(&mut self, connection: ConnectionId) {
+        self.sessions.remove(&connection);
+        #[cfg(feature = "accounts")]
+        self.pending
+            .retain(|login| login.connection.id() != connection);
+    }
+
+    /// starts registering a new account, or logging into one with its password. hashing the
+    /// password takes a while, so it happens on another thread and finish_logins picks it up
+    #[cfg(feature = "accounts")]
+    fn password_login(
+        &mut self,
+        connection: &EcsConnection<ServerPacket>,
+        name: &str,
+        password: &str,
+        register: bool,
+        storage: &dyn Storage,
+    ) -> Result<(), ServerError> {
+        if self
+            .pending
+            .iter()
+            .any(|login| login.connection.id() == connection.id())
+        {
+            return Err(ServerError::RateLimited);
+        }
+        if self.failures.locked(name) {
+            warn!("{:?} tried to log into {} too often", connection.id(), name);
+            return Err(ServerError::TooManyAttempts);
+        }
+        let existing = storage.account_by_name(name).map_err(|error| {
+            warn!("couldn't log {:?} in: {}", connection.id(), error);
+            ServerError::AccountsUnavailable
+        })?;
+        let password = password.to_string();
+        let task = match (existing, register) {
+            (Some(_), true) => return Err(ServerError::NameTaken),
+            (None, true) => PasswordTask::Register(
+                AsyncComputeTaskPool::get().spawn(async move { hash_password(&password).ok() }),
+            ),
+            (Some((account, hash)), false) => PasswordTask::Login(
+                account,
+                AsyncComputeTaskPool::get().spawn(async move { verify_password(&password, &hash) }),
+            ),
+            (None, false) => {
+                self.failures.fail(name);
+                return Err(ServerError::WrongPassword);
+            }
+        };
+        self.pending.push(PendingLogin {
+            connection: connection.clone(),
+            name: name.to_string(),
+            task,
+        });
+        Ok(())
+    }
+
+    #[cfg(not(feature = "accounts"))]
+    fn password_login(
+        &mut self,
+        _: &EcsConnection<ServerPacket>,
+        _: &str,
+        _: &str,
+        _: bool,
+        _: &dyn Storage,
+    ) -> Result<(), ServerError> {
+        Err(ServerError::AccountsUnavailable)
+    }
+
+    /// the account of a password that got hashed or checked, None while it's still going on
+    #[cfg(feature = "accounts")]
+    fn finish_password_login(
+        &mut self,
+        login: &mut PendingLogin,
+        next_player: &mut PlayerId,
+        storage: &mut dyn Storage,
+    ) -> Option<Result<PlayerId, ServerError>> {
+        let connection = login.connection.clone();
+        // what broke on the server only ends up in the log
+        let unavailable = |error: &dyn std::fmt::Display| {
+            warn!("couldn't log {:?} in: {}", connection.id(), error);
+            ServerError::AccountsUnavailable
+        };
+        let account = match &mut login.task {
+            PasswordTask::Register(task) => {
+                let hash = block_on(poll_once(task))?;
+                let Some(hash) = hash else {
+                    return Some(Err(unavailable(&"hashing the password failed")));
+                };
+                let account = Account {
+                    id: *next_player,
+                    name: login.name.clone(),
+                    registered: true,
+                };
+                // someone else can have registered the name while the password got hashed
+                match storage.add_account(&account, &hash) {
+                    Ok(true) => {}
+                    Ok(false) => return Some(Err(ServerError::NameTaken)),
+                    Err(error) => return Some(Err(unavailable(&error))),
+                }
+                next_player.0 += 1;
+                account
+            }
+            PasswordTask::Login(account, task) => {
+                if !block_on(poll_once(task))? {
+                    self.failures.fail(&login.name);
+                    return Some(Err(ServerError::WrongPassword));
+                }
+                account.clone()
+            }
+        };
+        let token = SessionToken(thread_rng().gen());
+        if let Err(error) = storage.add_session(token, account.id) {
+            return Some(Err(unavailable(&error)));
+        }
+        self.by_token.insert(token, account);
+        Some(Ok(self.start_session(&login.connection, token)))
+    }
+}
+
+/// how many wrong passwords an account takes before it locks for a while
+#[cfg(feature = "accounts")]
+const MAX_FAILURES: u32 = 5;
+/// how long an account stays locked after too many wrong passwords
+#[cfg(feature = "accounts")]
+const LOCKED_FOR: Duration = Duration::from_secs(5 * 60);
+
+/// wrong passwords per account name, whichever connection they come from
+#[cfg(feature = "accounts")]
+#[derive(Default, Debug)]
+struct LoginFailures(HashMap<String, (u32, Instant)>);
+
+#[cfg(feature = "accounts")]
+impl LoginFailures {
+    fn locked(&mut self, name: &str) -> bool {
+        self.0.retain(|_, (_, since)| since.elapsed() < LOCKED_FOR);
+        self.0
+            .get(name)
+            .is_some_and(|&(failures, _)| failures >= MAX_FAILURES)
+    }
+
+    fn fail(&mut self, name: &str) {
+        self.0
+            .entry(name.to_string())
+            .or_insert((0, Instant::now()))
+            .0 += 1;
+    }
+}
+
+#[cfg(feature = "accounts")]
+#[derive(Debug)]
+enum PasswordTask {
+    /// the hash of the new password, None if hashing failed
+    Register(Task<Option<String>>),
+    /// whether the password is right
+    Login(Account, Task<bool>),
+}
+
+/// a password being hashed or checked
+#[cfg(feature = "accounts")]
+#[derive(Debug)]
+struct PendingLogin {
+    connection: EcsConnection<ServerPacket>,
+    name: String,
+    task: PasswordTask,
+}
+
+/// passwords only get stored hashed
+#[cfg(feature = "accounts")]
+fn hash_password(password: &str) -> Result<String, password_hash::Error> {
+    let salt = SaltString::generate(&mut thread_rng());
+    Ok(Argon2::default()
+        .hash_password(password.as_bytes(), &salt)?
+        .to_string())
+}
+
+#[cfg(feature = "accounts")]
+fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|hash| {
+        Argon2::default()
+            .verify_password(password.as_bytes(), &hash)
+            .is_ok()
+    })
+}
+
+/// puts a connection that logged in back in its games and tells it about its correspondence games
+fn logged_in(
+    connection: &EcsConnection<ServerPacket>,
+    result: Result<PlayerId, ServerError>,
+    game_map: &mut ChessGameMap,
+    connection_map: &mut ConnectionMap,
+    grace: &GracePeriod,
+) {
+    let answer = match result {
+        Ok(id) => {
+            reconnect::resume_account(game_map, connection_map, connection, id, grace);
+            // the correspondence games are waiting for whoever logs in
+            ServerPacket::OngoingGames(correspondence::ongoing_games(game_map, id))
+        }
+        Err(error) => ServerPacket::Error(error),
+    };
+    connection.send(answer).unwrap_or_else(connection_error);
+}
+
+/// logs connections in with a token or a password, registers new accounts and names players
+pub fn logins(
+    mut packets: EventReader<PacketReceiveEvent<ServerNetConfig>>,
+    mut accounts: ResMut<Accounts>,
+    mut names: ResMut<PlayerNames>,
+    mut next_player: ResMut<PlayerId>,
+    mut store: ResMut<Store>,
+    mut game_map: ChessGameMap,
+    mut connection_map: ResMut<ConnectionMap>,
+    grace: Res<GracePeriod>,
+) {
+    for packet in packets.read() {
+        let result = match &packet.packet {
+            ClientPacket::Login { token } => {
+                Ok(accounts.login(&packet.connection, *token, &mut next_player, &*store.0))
+            }
+            ClientPacket::Register { name, password }
+            | ClientPacket::PasswordLogin { name, password } => {
+                let name = name.trim();
+                if !valid_name(name) || password.is_empty() {
+                    warn!(
+                        "{:?} send an invalid name or password",
+                        packet.connection.id()
+                    );
+                    continue;
+                }
+                match accounts.password_login(
+                    &packet.connection,
+                    name,
+                    password,
+                    matches!(packet.packet, ClientPacket::Register { .. }),
+                    &*store.0,
+                ) {
+                    // finish_logins answers once the password is checked
+                    Ok(()) => continue,
+                    Err(error) => Err(error),
+                }
+            }
+            ClientPacket::Hello { name, .. } => {
+                let id = packet.connection.id();
+                let name = name.trim();
+                if !valid_name(name) {
+                    warn!("{:?} send an invalid name", id);
+                    continue;
+                }
+                // a registered account keeps its name, Rename changes it
+                if accounts.registered(id) {
+                    continue;
+                }
+                match store.account_by_name(name) {
+                    Ok(None) => {}
+                    Ok(Some(_)) => {
+                        packet
+                            .connection
+                            .send(ServerPacket::Error(ServerError::NameRegistered))
+                            .unwrap_or_else(connection_error);
+                        continue;
+                    }
+                    Err(error) => {
+                        warn!("couldn't look up the name of {:?}: {}", id, error);
+                        continue;
+                    }
+                }
+                // anonymous accounts remember it for the next connection
+                if let Some(account) = accounts.account_mut(id) {
+                    account.name = name.to_string();
+                }
+                names.0.insert(id, name.to_string());
+                continue;
+            }
+            ClientPacket::Rename(name) => {
+                let name = name.trim();
+                if !valid_name(name) {
+                    warn!("{:?} send an invalid name", packet.connection.id());
+                    continue;
+                }
+                if let Err(error) = accounts.rename(&packet.connection, name, &mut *store.0) {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(error))
+                        .unwrap_or_else(connection_error);
+                }
+                continue;
+            }
+            _ => continue,
+        };
+        logged_in(
+            &packet.connection,
+            result,
+            &mut game_map,
+            &mut connection_map,
+            &grace,
+        );
+    }
+}
+
+/// logs in the connections whose password got hashed or checked
+#[cfg(feature = "accounts")]
+pub fn finish_logins(
+    mut accounts: ResMut<Accounts>,
+    mut next_player: ResMut<PlayerId>,
+    mut store: ResMut<Store>,
+    mut game_map: ChessGameMap,
+    mut connection_map: ResMut<ConnectionMap>,
+    grace: Res<GracePeriod>,
+) {
+    let mut pending = std::mem::take(&mut accounts.pending);
+    pending.retain_mut(|login| {
+        let Some(result) = accounts.finish_password_login(login, &mut next_player, &mut *store.0)
+        else {
+            return true;
+        };
+        logged_in(
+            &login.connection,
+            result,
+            &mut game_map,
+            &mut connection_map,
+            &grace,
+        );
+        false
+    });
+    accounts.pending.append(&mut pending);
+}
+
+#[cfg(not(feature = "accounts"))]
+pub fn finish_logins() {}
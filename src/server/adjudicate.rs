@@ -0,0 +1,31 @@
+use bevy::prelude::*;
+
+use super::{config::GameRules, Board, EndGameEvent};
+use crate::api::{EndReason, GameEnd, GameId};
+
+/// ends dead positions as a draw, when the server is told to, the rules only end a king and
+/// a bishop or knight against a lone king on their own
+pub fn adjudicate_games(
+    games: Query<(&GameId, &Board)>,
+    mut writer: EventWriter<EndGameEvent>,
+    rules: Res<GameRules>,
+) {
+    if !rules.adjudicate {
+        return;
+    }
+    for (&id, board) in &games {
+        // the promotion still changes the material
+        if board.state.should_promote {
+            continue;
+        }
+        if !board.state.is_dead() {
+            continue;
+        }
+        let _game = info_span!("game", id = id.0).entered();
+        info!("adjudicated as a dead draw");
+        writer.send(EndGameEvent(
+            id,
+            GameEnd::Draw(EndReason::InsufficientMaterial),
+        ));
+    }
+}
@@ -0,0 +1,234 @@
+//! commands typed into the server terminal, everything else typed there gets announced
+
+use std::{collections::HashSet, net::IpAddr};
+
+use bevy::prelude::*;
+use bevy_slinet::{connection::EcsConnection, server::ServerConnections};
+
+use super::{
+    accounts::Accounts,
+    announcements::AnnouncementEvent,
+    archive,
+    config::ReloadConfigEvent,
+    connection_error, name_of,
+    queue::{GameQueue, QueueOpen},
+    shutdown::Shutdown,
+    ChessGames, EndGameEvent, PlayerNames,
+};
+use crate::api::{EndReason, GameEnd, GameId, ServerError, ServerNetConfig, ServerPacket};
+
+const HELP: &str = "commands:
+  /games               lists the running games
+  /pgn <game>          prints the moves of a running game
+  /abort <game>        ends a game without a result
+  /kick <name>         disconnects a player
+  /ban <name>          disconnects a player and refuses their address until the server stops
+  /unban <address>     lets an address connect again
+  /say <message>       announces a message, like typing it without a /
+  /queue <on|off>      opens or closes the queue, closing it sends everyone waiting back
+  /reload              reads the config file again
+  /shutdown            saves the games and stops the server";
+
+#[derive(Event, Debug, Clone, PartialEq, Eq)]
+pub enum AdminCommand {
+    Help,
+    Games,
+    Pgn(GameId),
+    Abort(GameId),
+    Kick(String),
+    Ban(String),
+    Unban(IpAddr),
+    Say(String),
+    Queue(bool),
+    Reload,
+    Shutdown,
+}
+
+impl AdminCommand {
+    /// a line starting with a /
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let (command, argument) = line.split_once(' ').unwrap_or((line, ""));
+        let argument = argument.trim();
+        let game = || {
+            argument
+                .parse()
+                .map(GameId)
+                .map_err(|_| format!("{} needs a game number", command))
+        };
+        let text = |what: &str| {
+            if argument.is_empty() {
+                Err(format!("{} needs {}", command, what))
+            } else {
+                Ok(argument.to_string())
+            }
+        };
+        Ok(match command {
+            "/help" => AdminCommand::Help,
+            "/games" => AdminCommand::Games,
+            "/pgn" => AdminCommand::Pgn(game()?),
+            "/abort" => AdminCommand::Abort(game()?),
+            "/kick" => AdminCommand::Kick(text("a name")?),
+            "/ban" => AdminCommand::Ban(text("a name")?),
+            "/unban" => AdminCommand::Unban(
+                argument
+                    .parse()
+                    .map_err(|_| "/unban needs an ip address".to_string())?,
+            ),
+            "/say" => AdminCommand::Say(text("a message")?),
+            "/queue" => match argument {
+                "on" => AdminCommand::Queue(true),
+                "off" => AdminCommand::Queue(false),
+                _ => return Err("/queue needs on or off".to_string()),
+            },
+            "/reload" => AdminCommand::Reload,
+            "/shutdown" => AdminCommand::Shutdown,
+            _ => return Err(format!("unknown command {}, try /help", command)),
+        })
+    }
+}
+
+/// the addresses that get disconnected as soon as they connect
+#[derive(Resource, Default, Debug)]
+pub struct Bans(pub HashSet<IpAddr>);
+
+/// every connection playing or talking under that name
+fn connections_named(
+    name: &str,
+    connections: &ServerConnections<ServerNetConfig>,
+    game_map: &ChessGames,
+    accounts: &Accounts,
+    names: &PlayerNames,
+) -> Vec<EcsConnection<ServerPacket>> {
+    let mut found: Vec<_> = connections
+        .iter()
+        .filter(|c| name_of(c.id(), accounts, names).as_deref() == Some(name))
+        .cloned()
+        .collect();
+    // anonymous players only have the name their game gave them
+    for game in game_map.iter() {
+        let players = game.players;
+        for (seat, seat_name) in [
+            (&players.white, &players.white_name),
+            (&players.black, &players.black_name),
+        ] {
+            if let Some(connection) = seat.as_ref().filter(|_| seat_name == name) {
+                if !found.iter().any(|c| c.id() == connection.id()) {
+                    found.push(connection.clone());
+                }
+            }
+        }
+    }
+    found
+}
+
+pub fn run_commands(
+    mut commands: EventReader<AdminCommand>,
+    connections: Res<ServerConnections<ServerNetConfig>>,
+    game_map: ChessGames,
+    accounts: Res<Accounts>,
+    names: Res<PlayerNames>,
+    mut bans: ResMut<Bans>,
+    mut queue: ResMut<GameQueue>,
+    mut queue_open: ResMut<QueueOpen>,
+    mut end_game: EventWriter<EndGameEvent>,
+    mut announce: EventWriter<AnnouncementEvent>,
+    mut reload: EventWriter<ReloadConfigEvent>,
+    shutdown: Res<Shutdown>,
+) {
+    for command in commands.read() {
+        match command {
+            AdminCommand::Help => info!("{}", HELP),
+            AdminCommand::Games => {
+                info!("{} running games", game_map.len());
+                let mut games: Vec<_> = game_map.iter().collect();
+                games.sort_by_key(|game| *game.id);
+                for game in games {
+                    let players = game.players;
+                    let clock = players
+                        .seek
+                        .time_control
+                        .map_or("no clock".to_string(), |t| t.to_string());
+                    let missing = match (&players.white, &players.black) {
+                        _ if players.bot.is_some() => ", against the bot",
+                        (Some(_), Some(_)) => "",
+                        (None, Some(_)) => ", white is gone",
+                        (Some(_), None) => ", black is gone",
+                        (None, None) => ", both players are gone",
+                    };
+                    info!(
+                        "game {}: {} vs {}, {} moves, {}, {} spectators{}",
+                        game.id.0,
+                        players.white_name,
+                        players.black_name,
+                        game.board.moves.len(),
+                        clock,
+                        game.spectators.0.len(),
+                        missing
+                    );
+                }
+            }
+            AdminCommand::Pgn(id) => match game_map.get(id) {
+                Some(game) => info!(
+                    "game {}:\n{}",
+                    id.0,
+                    archive::pgn(game.players, game.board, None)
+                ),
+                None => warn!("there is no game {}", id.0),
+            },
+            AdminCommand::Abort(id) => {
+                if game_map.contains_key(id) {
+                    info!("aborting game {}", id.0);
+                    end_game.send(EndGameEvent(*id, GameEnd::Draw(EndReason::Aborted)));
+                } else {
+                    warn!("there is no game {}", id.0);
+                }
+            }
+            AdminCommand::Kick(name) | AdminCommand::Ban(name) => {
+                let found = connections_named(name, &connections, &game_map, &accounts, &names);
+                if found.is_empty() {
+                    warn!("nobody called {} is connected", name);
+                }
+                let ban = matches!(command, AdminCommand::Ban(_));
+                for connection in found {
+                    if ban {
+                        let address = connection.peer_addr().ip();
+                        info!("banned {} ({})", name, address);
+                        bans.0.insert(address);
+                    } else {
+                        info!("kicked {}", name);
+                    }
+                    connection
+                        .send(ServerPacket::Announcement(
+                            "the server disconnected you".to_string(),
+                        ))
+                        .unwrap_or_else(connection_error);
+                    connection.disconnect();
+                }
+            }
+            AdminCommand::Unban(address) => {
+                if !bans.0.remove(address) {
+                    warn!("{} wasn't banned", address);
+                }
+            }
+            AdminCommand::Say(message) => {
+                announce.send(AnnouncementEvent(message.clone()));
+            }
+            AdminCommand::Queue(open) => {
+                queue_open.0 = *open;
+                info!("the queue is {}", if *open { "open" } else { "closed" });
+                if !open {
+                    for player in std::mem::take(&mut queue.0).into_values().flatten() {
+                        player
+                            .connection
+                            .send(ServerPacket::Error(ServerError::QueueClosed))
+                            .unwrap_or_else(connection_error);
+                    }
+                }
+            }
+            AdminCommand::Reload => {
+                reload.send(ReloadConfigEvent);
+            }
+            AdminCommand::Shutdown => shutdown.request(),
+        }
+    }
+}
@@ -0,0 +1,189 @@
+//! runs the engine over finished games on a few background workers, the reports go to the
+//! players once they are done and into storage for anyone asking about the game later
+
+use std::collections::VecDeque;
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, poll_once, AsyncComputeTaskPool, Task},
+};
+use bevy_slinet::{connection::EcsConnection, server::PacketReceiveEvent};
+
+use super::{accounts::Accounts, connection_error, storage::Store};
+use crate::api::{
+    analysis::{analyze, GameAnalysis},
+    chessmove::PlayedMove,
+    chessstate::ChessState,
+    ClientPacket, GameId, ReplayId, ServerError, ServerNetConfig, ServerPacket,
+};
+
+/// how many games get analyzed at the same time, the rest wait their turn
+const WORKERS: usize = 2;
+/// how many games can wait for a worker before FetchAnalysis is turned down
+const MAX_WAITING: usize = 32;
+
+/// a game waiting for a worker
+struct PendingAnalysis {
+    /// None for games that were fetched from storage
+    game: Option<GameId>,
+    /// None when the game couldn't be stored
+    replay: Option<ReplayId>,
+    players: Vec<EcsConnection<ServerPacket>>,
+    /// asked for the game with FetchAnalysis while it was being analyzed
+    fetched_by: Vec<EcsConnection<ServerPacket>>,
+    start: ChessState,
+    moves: Vec<PlayedMove>,
+}
+
+pub struct AnalysisTask {
+    analysis: PendingAnalysis,
+    task: Task<GameAnalysis>,
+}
+
+/// finished games being analyzed in the background
+#[derive(Resource, Default)]
+pub struct AnalysisTasks {
+    waiting: VecDeque<PendingAnalysis>,
+    running: Vec<AnalysisTask>,
+}
+
+impl AnalysisTasks {
+    pub fn analyze_game(
+        &mut self,
+        game: GameId,
+        replay: Option<ReplayId>,
+        players: Vec<EcsConnection<ServerPacket>>,
+        start: ChessState,
+        moves: Vec<PlayedMove>,
+    ) {
+        self.waiting.push_back(PendingAnalysis {
+            game: Some(game),
+            replay,
+            players,
+            fetched_by: Vec::new(),
+            start,
+            moves,
+        });
+    }
+
+    /// the analysis of `replay` that is waiting or running
+    fn find_mut(&mut self, replay: ReplayId) -> Option<&mut PendingAnalysis> {
+        self.waiting
+            .iter_mut()
+            .chain(self.running.iter_mut().map(|task| &mut task.analysis))
+            .find(|analysis| analysis.replay == Some(replay))
+    }
+}
+
+/// hands waiting games to free workers, sends the finished reports and stores them
+pub fn send_analysis(mut tasks: ResMut<AnalysisTasks>, mut store: ResMut<Store>) {
+    let tasks = &mut *tasks;
+    tasks.running.retain_mut(|task| {
+        let Some(report) = block_on(poll_once(&mut task.task)) else {
+            return true;
+        };
+        let analysis = &task.analysis;
+        if let Some(replay) = analysis.replay {
+            if let Err(error) = store.save_analysis(replay, &report) {
+                warn!("couldn't store the analysis of {:?}: {}", replay, error);
+            }
+            for connection in &analysis.fetched_by {
+                connection
+                    .send(ServerPacket::ReplayAnalysis(replay, report.clone()))
+                    .unwrap_or_else(connection_error);
+            }
+        }
+        if let Some(game) = analysis.game {
+            for player in &analysis.players {
+                player
+                    .send(ServerPacket::Analysis(game, report.clone()))
+                    .unwrap_or_else(connection_error);
+            }
+        }
+        false
+    });
+    while tasks.running.len() < WORKERS {
+        let Some(analysis) = tasks.waiting.pop_front() else {
+            break;
+        };
+        let start = analysis.start;
+        let moves = analysis.moves.clone();
+        tasks.running.push(AnalysisTask {
+            analysis,
+            task: AsyncComputeTaskPool::get().spawn(async move { analyze(start, &moves) }),
+        });
+    }
+}
+
+/// answers FetchAnalysis from storage, games of the asking player that weren't analyzed yet
+/// get queued
+pub fn fetch_analysis(
+    mut packets: EventReader<PacketReceiveEvent<ServerNetConfig>>,
+    mut tasks: ResMut<AnalysisTasks>,
+    accounts: Res<Accounts>,
+    store: Res<Store>,
+) {
+    for packet in packets.read() {
+        let ClientPacket::FetchAnalysis(replay) = packet.packet else {
+            continue;
+        };
+        // only the players get the report, to everyone else the game doesn't exist
+        let game = match store.game(replay) {
+            Ok(Some(game))
+                if accounts
+                    .account(packet.connection.id())
+                    .is_some_and(|account| game.played_by(account.id)) =>
+            {
+                game
+            }
+            Ok(_) => {
+                packet
+                    .connection
+                    .send(ServerPacket::Error(ServerError::NoSuchGame))
+                    .unwrap_or_else(connection_error);
+                continue;
+            }
+            Err(error) => {
+                warn!("couldn't read game {:?}: {}", replay, error);
+                packet
+                    .connection
+                    .send(ServerPacket::Error(ServerError::NoSuchGame))
+                    .unwrap_or_else(connection_error);
+                continue;
+            }
+        };
+        match store.analysis(replay) {
+            Ok(Some(analysis)) => {
+                packet
+                    .connection
+                    .send(ServerPacket::ReplayAnalysis(replay, analysis))
+                    .unwrap_or_else(connection_error);
+                continue;
+            }
+            Ok(None) => {}
+            Err(error) => warn!("couldn't read the analysis of {:?}: {}", replay, error),
+        }
+        if let Some(analysis) = tasks.find_mut(replay) {
+            let connection_id = packet.connection.id();
+            if analysis.fetched_by.iter().all(|c| c.id() != connection_id) {
+                analysis.fetched_by.push(packet.connection.clone());
+            }
+            continue;
+        }
+        if tasks.waiting.len() >= MAX_WAITING {
+            packet
+                .connection
+                .send(ServerPacket::Error(ServerError::ServerFull))
+                .unwrap_or_else(connection_error);
+            continue;
+        }
+        tasks.waiting.push_back(PendingAnalysis {
+            game: None,
+            replay: Some(replay),
+            players: Vec::new(),
+            fetched_by: vec![packet.connection.clone()],
+            start: game.start,
+            moves: game.moves,
+        });
+    }
+}
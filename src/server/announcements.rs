@@ -0,0 +1,82 @@
+use std::{
+    io::BufRead,
+    sync::{
+        mpsc::{self, Receiver},
+        Mutex,
+    },
+};
+
+use bevy::prelude::*;
+use bevy_slinet::server::ServerConnections;
+
+use super::{admin::AdminCommand, connection_error};
+use crate::api::{ServerNetConfig, ServerPacket};
+
+/// the message of the day, send to everyone who connects
+#[derive(Resource, Default, Debug)]
+pub struct Motd(pub Option<String>);
+
+/// broadcasts a message to every connected client
+#[derive(Event, Debug)]
+pub struct AnnouncementEvent(pub String);
+
+/// lines typed into the terminal running the server
+#[derive(Resource)]
+pub struct ConsoleInput(Mutex<Receiver<String>>);
+
+impl Default for ConsoleInput {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let Ok(line) = line else {
+                    break;
+                };
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Self(Mutex::new(receiver))
+    }
+}
+
+/// lines typed into the server terminal get announced, unless they are a command like /reload
+pub fn read_console(
+    console: Res<ConsoleInput>,
+    mut writer: EventWriter<AnnouncementEvent>,
+    mut commands: EventWriter<AdminCommand>,
+) {
+    let Ok(receiver) = console.0.lock() else {
+        return;
+    };
+    for line in receiver.try_iter() {
+        let line = line.trim();
+        match line {
+            "" => {}
+            command if command.starts_with('/') => match AdminCommand::parse(command) {
+                Ok(command) => {
+                    commands.send(command);
+                }
+                Err(error) => warn!("{}", error),
+            },
+            _ => {
+                writer.send(AnnouncementEvent(line.to_string()));
+            }
+        }
+    }
+}
+
+pub fn broadcast(
+    mut events: EventReader<AnnouncementEvent>,
+    connections: Res<ServerConnections<ServerNetConfig>>,
+) {
+    for AnnouncementEvent(message) in events.read() {
+        info!("announcing: {message}");
+        for connection in connections.iter() {
+            connection
+                .send(ServerPacket::Announcement(message.clone()))
+                .unwrap_or_else(connection_error);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+//! writes every finished game to a pgn file, so other chess programs can open them
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+use bevy::prelude::*;
+
+use super::{Board, Players};
+use crate::api::{pgn, GameEnd, GameId};
+
+/// the directory the finished games are appended to, None doesn't archive them
+#[derive(Resource, Debug, Default)]
+pub struct PgnArchive(pub Option<PathBuf>);
+
+impl PgnArchive {
+    /// appends the game to games.pgn in the archive directory, creating it the first time
+    pub fn append(
+        &self,
+        id: GameId,
+        players: &Players,
+        board: &Board,
+        result: GameEnd,
+        ended: u64,
+    ) {
+        let Some(directory) = &self.0 else {
+            return;
+        };
+        if let Err(error) = append(directory, &pgn(players, board, Some((result, ended)))) {
+            warn!("couldn't archive game {:?}: {}", id, error);
+        }
+    }
+}
+
+fn append(directory: &Path, pgn: &str) -> io::Result<()> {
+    fs::create_dir_all(directory)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(directory.join("games.pgn"))?;
+    file.write_all(pgn.as_bytes())
+}
+
+/// the game in portable game notation, with an empty line after it. a game that is still
+/// running has no result and end time yet
+pub fn pgn(players: &Players, board: &Board, result: Option<(GameEnd, u64)>) -> String {
+    let end = result.map(|(end, _)| end);
+    let (start_date, start_time) = pgn::date_time(board.started);
+    let rated = players.is_rated();
+
+    let mut tags = vec![
+        (
+            "Event",
+            if rated { "rated game" } else { "casual game" }.to_string(),
+        ),
+        ("Site", "?".to_string()),
+        ("Date", start_date.clone()),
+        ("Round", "-".to_string()),
+        ("White", players.white_name.clone()),
+        ("Black", players.black_name.clone()),
+        ("Result", pgn::result(end).to_string()),
+    ];
+    if rated {
+        tags.push(("WhiteElo", players.ratings[0].elo.to_string()));
+        tags.push(("BlackElo", players.ratings[1].elo.to_string()));
+    }
+    tags.push((
+        "TimeControl",
+        match players.seek.time_control {
+            None => "-".to_string(),
+            Some(time_control) if time_control.per_move != 0 => {
+                format!("1/{}", time_control.per_move)
+            }
+            Some(time_control) => format!("{}+{}", time_control.initial, time_control.increment),
+        },
+    ));
+    tags.push((
+        "Termination",
+        match end {
+            Some(GameEnd::White(reason) | GameEnd::Black(reason) | GameEnd::Draw(reason)) => {
+                pgn::termination(reason)
+            }
+            None => "unterminated",
+        }
+        .to_string(),
+    ));
+    tags.push(("UTCDate", start_date));
+    tags.push(("UTCTime", start_time));
+    if let Some((_, ended)) = result {
+        let (end_date, end_time) = pgn::date_time(ended);
+        tags.push(("EndDate", end_date));
+        tags.push(("EndTime", end_time));
+    }
+    pgn::write(tags, board.start, &board.moves, end)
+}
@@ -0,0 +1,283 @@
+//! a bot the server plays with players who waited too long for an opponent
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, poll_once, AsyncComputeTaskPool, Task},
+};
+use bevy_slinet::{
+    connection::ConnectionId,
+    server::{DisconnectionEvent, PacketReceiveEvent},
+};
+use rand::prelude::*;
+
+use super::{
+    config::GameRules, connection_error, metrics, open_game, queue::GameQueue, ratings,
+    shutdown::Shutdown, storage::Store, ChessGameMap, ConnectionMap, EndGameEvent, Game,
+    QueuedPlayer,
+};
+use crate::api::{
+    ai,
+    chessmove::{compress_chessboard, ChessColor, PlayedMove},
+    handicap::Handicap,
+    matchmaking::ColorPreference,
+    ping::timestamp,
+    ClientPacket, GameId, PlayerId, Rating, ServerError, ServerNetConfig, ServerPacket,
+};
+
+/// how long a player waits in the queue before the bot plays them, unless the server is told otherwise
+pub const WAIT: Duration = Duration::from_secs(30);
+/// how many plies the bot looks ahead, unless the server is told otherwise
+pub const DEPTH: u8 = 3;
+/// what the bot is called in its games
+const NAME: &str = "computer";
+
+#[derive(Resource, Default)]
+pub struct Bots {
+    /// when the players in the queue joined it, in milliseconds since the unix epoch
+    waiting: HashMap<ConnectionId, u64>,
+    /// the moves being searched, with the ply they are for
+    thinking: HashMap<GameId, (u32, Task<Option<PlayedMove>>)>,
+}
+
+/// the connections that said hello as a bot, engines playing on the server
+#[derive(Resource, Default, Debug)]
+pub struct Engines(HashSet<ConnectionId>);
+
+impl Engines {
+    pub fn contains(&self, connection_id: ConnectionId) -> bool {
+        self.0.contains(&connection_id)
+    }
+}
+
+/// keeps track of which connections are engines
+pub fn engines(
+    mut packets: EventReader<PacketReceiveEvent<ServerNetConfig>>,
+    mut disconnections: EventReader<DisconnectionEvent<ServerNetConfig>>,
+    mut engines: ResMut<Engines>,
+) {
+    for packet in packets.read() {
+        if let ClientPacket::Hello { bot, .. } = packet.packet {
+            let id = packet.connection.id();
+            if bot && engines.0.insert(id) {
+                info!(connection = ?id, "an engine connected");
+            } else if !bot {
+                engines.0.remove(&id);
+            }
+        }
+    }
+    for disconnection in disconnections.read() {
+        engines.0.remove(&disconnection.connection.id());
+    }
+}
+
+/// a game of the player against the bot, never rated since the bot has no account to rate
+fn bot_game(mut player: QueuedPlayer, bot: PlayerId, ratings: [Rating; 2]) -> Game {
+    player.seek.rated = false;
+    let color = player
+        .color
+        .resolve(ColorPreference::Random)
+        .unwrap_or_else(|| {
+            if thread_rng().gen_bool(0.5) {
+                ChessColor::White
+            } else {
+                ChessColor::Black
+            }
+        });
+    let bot_player = QueuedPlayer {
+        id: bot,
+        name: NAME.to_string(),
+        handicap: Handicap::None,
+        seek: player.seek.mirrored(),
+        ..player.clone()
+    };
+    let mut game = match color {
+        ChessColor::White => Game::new(player, bot_player, ratings),
+        ChessColor::Black => {
+            let [human, bot] = ratings;
+            Game::new(bot_player, player, [bot, human])
+        }
+    };
+    match color {
+        ChessColor::White => game.players.black = None,
+        ChessColor::Black => game.players.white = None,
+    }
+    game.players.bot = Some(!color);
+    game
+}
+
+/// gives everyone who waited in the queue longer than the rules allow, or asked for it with
+/// PlayComputer, a game against the bot. runs before create_game so a player asking for the bot
+/// doesn't get paired with someone else first
+pub fn fill_queue(
+    mut packets: EventReader<PacketReceiveEvent<ServerNetConfig>>,
+    mut queue: ResMut<GameQueue>,
+    mut bots: ResMut<Bots>,
+    mut game_map: ChessGameMap,
+    mut connection_map: ResMut<ConnectionMap>,
+    mut next_game: ResMut<GameId>,
+    mut next_player: ResMut<PlayerId>,
+    store: Res<Store>,
+    rules: Res<GameRules>,
+    shutdown: Res<Shutdown>,
+    engines: Res<Engines>,
+) {
+    let now = timestamp();
+    bots.waiting.retain(|&id, _| queue.contains(id));
+    for player in queue.0.values().flatten() {
+        bots.waiting.entry(player.connection.id()).or_insert(now);
+    }
+    let asked: Vec<_> = packets
+        .read()
+        .filter(|packet| matches!(packet.packet, ClientPacket::PlayComputer))
+        .map(|packet| packet.connection.clone())
+        .collect();
+    let Some(wait) = rules.bot_after else {
+        // they keep waiting for a real opponent
+        for connection in asked {
+            connection
+                .send(ServerPacket::Error(ServerError::NoBots))
+                .unwrap_or_else(connection_error);
+        }
+        return;
+    };
+    if shutdown.is_shutting_down() {
+        return;
+    }
+    // asking for the bot skips the wait
+    let bored: Vec<_> = asked
+        .iter()
+        .map(|connection| connection.id())
+        .chain(
+            bots.waiting
+                .iter()
+                .filter(|&(_, &since)| now.saturating_sub(since) >= wait.as_millis() as u64)
+                .map(|(&id, _)| id),
+        )
+        .collect();
+    // the games started here are only spawned once the commands ran
+    let mut started = 0;
+    for connection_id in bored {
+        if rules
+            .max_games
+            .is_some_and(|max| game_map.len() + started >= max as usize)
+        {
+            // the queue may still find them an opponent once a game ends
+            for connection in asked.iter().filter(|c| c.id() == connection_id) {
+                connection
+                    .send(ServerPacket::Error(ServerError::ServerFull))
+                    .unwrap_or_else(connection_error);
+            }
+            continue;
+        }
+        let Some(player) = queue.take(connection_id) else {
+            continue;
+        };
+        bots.waiting.remove(&connection_id);
+        let bot = *next_player;
+        next_player.0 += 1;
+        let game = bot_game(
+            player.clone(),
+            bot,
+            [
+                ratings::get(&*store.0, player.id),
+                ratings::get(&*store.0, bot),
+            ],
+        );
+        let _game = info_span!("game", id = next_game.0).entered();
+        info!(player = %player.name, seek = ?player.seek, "match created against the bot");
+        open_game(
+            game,
+            &mut next_game,
+            &mut game_map,
+            &mut connection_map,
+            &engines,
+        );
+        started += 1;
+    }
+}
+
+/// searches a move in the background whenever it is the bot's turn and plays it once found
+pub fn play_bots(
+    mut bots: ResMut<Bots>,
+    mut game_map: ChessGameMap,
+    mut writer: EventWriter<EndGameEvent>,
+    rules: Res<GameRules>,
+) {
+    bots.thinking.retain(|id, _| game_map.contains_key(id));
+    for mut game in game_map.iter_mut() {
+        let id = *game.id;
+        let Some(color) = game.players.bot else {
+            continue;
+        };
+        // the bot doesn't take moves back and doesn't agree to draws
+        if game.board.takeback == Some(!color) {
+            game.board.takeback = None;
+            game.players
+                .send_players(ServerPacket::TakebackDeclined(id));
+        }
+        if game.board.draw == Some(!color) {
+            game.board.draw = None;
+            game.players.send_players(ServerPacket::DrawDeclined(id));
+        }
+        if game.board.state.turn != color || game.board.state.should_promote {
+            continue;
+        }
+        let ply = game.board.ply();
+        let found = match bots.thinking.get_mut(&id) {
+            // a takeback may have changed the position since the search started
+            Some((searched, task)) if *searched == ply => block_on(poll_once(task)),
+            _ => {
+                let state = game.board.state;
+                let depth = rules.bot_depth;
+                let task = AsyncComputeTaskPool::get()
+                    .spawn(async move { ai::search(&state, depth).line.first().copied() });
+                bots.thinking.insert(id, (ply, task));
+                continue;
+            }
+        };
+        let Some(found) = found else {
+            continue;
+        };
+        bots.thinking.remove(&id);
+        // without a move the game is over already
+        let Some(played_move) = found else {
+            continue;
+        };
+        let _game = info_span!("game", id = id.0).entered();
+        let mut end = match game.play(played_move.chess_move, Duration::ZERO) {
+            Ok(end) => end,
+            Err(_) => {
+                warn!(ply, ?played_move, "the bot found an illegal move");
+                continue;
+            }
+        };
+        debug!(ply, ?played_move, "bot moved");
+        metrics::count(&metrics::MOVES);
+        if let Some(piece) = played_move.promotion {
+            let board = &mut *game.board;
+            if board.state.promote(piece).is_err() {
+                warn!(ply, ?played_move, "the bot couldn't promote");
+                continue;
+            }
+            if let Some(last) = board.moves.last_mut() {
+                last.promotion = Some(piece);
+            }
+            if let Some(last) = board.move_history.last_mut() {
+                *last = compress_chessboard(&board.state.board);
+            }
+            game.players
+                .send_players(ServerPacket::Promotion(id, piece));
+            game.spectators.send(ServerPacket::Promotion(id, piece));
+            game.players.send_engines(id, &game.board, game.clock.0);
+            end = game.board.state.check_game_end(&game.board.move_history);
+        }
+        if let Some(reason) = end {
+            writer.send(EndGameEvent(id, reason));
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_slinet::connection::{ConnectionId, EcsConnection};
+use rand::prelude::*;
+
+use super::{connection_error, ConnectionMap, QueuedPlayer, StartGameEvent};
+use crate::api::{PlayerInfo, ServerError, ServerPacket};
+
+#[derive(Debug)]
+pub struct Challenge {
+    pub challenger: QueuedPlayer,
+    /// the name the challenger asked for, to tell them who declined
+    pub target_name: String,
+    pub target: ConnectionId,
+}
+
+/// challenges waiting for an answer
+#[derive(Resource, Default, Debug)]
+pub struct Challenges {
+    pub pending: HashMap<u32, Challenge>,
+    next_id: u32,
+}
+
+impl Challenges {
+    /// sends the challenge to the target
+    pub fn add(
+        &mut self,
+        challenger: QueuedPlayer,
+        target_name: String,
+        target: &EcsConnection<ServerPacket>,
+    ) {
+        let id = self.next_id;
+        self.next_id += 1;
+        target
+            .send(ServerPacket::ChallengeReceived {
+                challenge: id,
+                from: PlayerInfo {
+                    id: challenger.id,
+                    name: challenger.name.clone(),
+                    rating: None,
+                    bot: false,
+                },
+                variant: challenger.seek.variant,
+                time_control: challenger.seek.time_control,
+            })
+            .unwrap_or_else(connection_error);
+        self.pending.insert(
+            id,
+            Challenge {
+                challenger,
+                target_name,
+                target: target.id(),
+            },
+        );
+    }
+
+    /// starts the game if the target accepts, `target` is how they'll play.
+    /// nobody can accept while one of them is in a game
+    pub fn answer(
+        &mut self,
+        id: u32,
+        target: QueuedPlayer,
+        accept: bool,
+        connection_map: &ConnectionMap,
+        start_game: &mut EventWriter<StartGameEvent>,
+    ) {
+        match self.pending.get(&id) {
+            Some(challenge) if challenge.target == target.connection.id() => {}
+            _ => {
+                return warn!(
+                    "{:?} answered challenge {} which isn't theirs",
+                    target.connection.id(),
+                    id
+                )
+            }
+        }
+        let Some(challenge) = self.pending.remove(&id) else {
+            return;
+        };
+        if !accept {
+            return challenge
+                .challenger
+                .connection
+                .send(ServerPacket::ChallengeDeclined {
+                    target: challenge.target_name,
+                })
+                .unwrap_or_else(connection_error);
+        }
+        let challenger_busy = connection_map
+            .0
+            .contains_key(&challenge.challenger.connection.id());
+        if challenger_busy || connection_map.0.contains_key(&target.connection.id()) {
+            warn!("challenge {} was accepted while a player is in a game", id);
+            target
+                .connection
+                .send(ServerPacket::Error(ServerError::InGame))
+                .unwrap_or_else(connection_error);
+            if !challenger_busy {
+                challenge
+                    .challenger
+                    .connection
+                    .send(ServerPacket::ChallengeDeclined {
+                        target: challenge.target_name,
+                    })
+                    .unwrap_or_else(connection_error);
+            }
+            return;
+        }
+        let target = QueuedPlayer {
+            seek: challenge.challenger.seek,
+            ..target
+        };
+        // the challenger picked the game, the colors are up to chance
+        if thread_rng().gen_bool(0.5) {
+            start_game.send(StartGameEvent {
+                white: challenge.challenger,
+                black: target,
+            });
+        } else {
+            start_game.send(StartGameEvent {
+                white: target,
+                black: challenge.challenger,
+            });
+        }
+    }
+
+    /// drops every challenge from or to a connection that left
+    pub fn remove_connection(&mut self, connection_id: ConnectionId) {
+        self.pending.retain(|_, c| {
+            c.target != connection_id && c.challenger.connection.id() != connection_id
+        });
+    }
+}
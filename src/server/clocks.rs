@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use super::{
+    config::GameRules,
+    league::Leagues,
+    reconnect::{resignation, GracePeriod},
+    simul::Simuls,
+    Board, EndGameEvent, GameClock, Players,
+};
+use crate::api::{
+    chessmove::{ChessColor, ChessPieceType},
+    chessstate::ChessState,
+    ping::timestamp,
+    EndReason, GameEnd, GameId,
+};
+
+/// how long a draw offer waits for an answer, unless the server is told otherwise
+pub const DRAW_OFFER_EXPIRY: Duration = Duration::from_secs(60);
+
+/// how long each player has for their first move before the game is aborted, unless the
+/// server is told otherwise
+pub const FIRST_MOVE: Duration = Duration::from_secs(30);
+
+/// aborts games where a player didn't make their first move in time, so nobody loses
+/// (rating) to an opponent who never showed up, in a league the player who didn't show up loses
+pub fn abort_unstarted_games(
+    games: Query<(&GameId, &Players, &Board)>,
+    leagues: Res<Leagues>,
+    simuls: Res<Simuls>,
+    mut writer: EventWriter<EndGameEvent>,
+    rules: Res<GameRules>,
+) {
+    let Some(first_move) = rules.first_move else {
+        return;
+    };
+    let now = timestamp();
+    for (&id, players, board) in &games {
+        if board.ply() >= 2 || players.is_correspondence() || players.bot == Some(board.state.turn)
+        {
+            continue;
+        }
+        // a simul host gets to take their time on every board
+        let host = simuls.is_host(id, players.player(board.state.turn).id);
+        if host || now.saturating_sub(board.last_move) <= first_move.as_millis() as u64 {
+            continue;
+        }
+        info!(
+            id = id.0,
+            "{:?} didn't make their first move", board.state.turn
+        );
+        // the round goes on without them, like when they don't show up at all
+        let end = if leagues.plays(id) {
+            resignation(board.state.turn)
+        } else {
+            GameEnd::Draw(EndReason::Aborted)
+        };
+        writer.send(EndGameEvent(id, end));
+    }
+}
+
+/// takes back the draw offers nobody answered in time
+pub fn expire_draw_offers(
+    mut games: Query<(&GameId, &Players, &mut Board)>,
+    rules: Res<GameRules>,
+) {
+    let Some(expiry) = rules.draw_offer_expiry else {
+        return;
+    };
+    let now = timestamp();
+    for (&id, players, mut board) in &mut games {
+        if board.draw.is_some()
+            && now.saturating_sub(board.draw_offered) > expiry.as_millis() as u64
+        {
+            board.expire_draw(id, players);
+        }
+    }
+}
+
+/// ends games where the player to move ran out of time
+pub fn flag_games(
+    games: Query<(&GameId, &Players, &Board, &GameClock)>,
+    mut writer: EventWriter<EndGameEvent>,
+    grace: Res<GracePeriod>,
+) {
+    let now = timestamp();
+    for (&id, players, board, clock) in &games {
+        let Some(clock) = clock.0 else {
+            continue;
+        };
+        let color = board.state.turn;
+        if grace.paused(players, color) || !clock.left(color, now).is_zero() {
+            continue;
+        }
+        writer.send(EndGameEvent(id, timeout(&board.state, color)));
+    }
+}
+
+/// `color` flagged, their opponent wins unless a lone king is all they have left
+fn timeout(state: &ChessState, color: ChessColor) -> GameEnd {
+    let opponent_can_mate = state
+        .board
+        .iter()
+        .flatten()
+        .flatten()
+        .any(|piece| piece.color != color && piece.piece_type != ChessPieceType::King);
+    match color {
+        _ if !opponent_can_mate => GameEnd::Draw(EndReason::Timeout),
+        ChessColor::White => GameEnd::Black(EndReason::Timeout),
+        ChessColor::Black => GameEnd::White(EndReason::Timeout),
+    }
+}
@@ -0,0 +1,314 @@
+//! the server config file, read at startup and again with /reload on the server console
+
+use std::{
+    fs,
+    net::{IpAddr, SocketAddr, ToSocketAddrs},
+    path::PathBuf,
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use super::{
+    announcements::Motd,
+    archive::PgnArchive,
+    bots, clocks,
+    league::NoShowWait,
+    reconnect::{self, GracePeriod},
+    ServerOptions,
+};
+use crate::api::matchmaking::{Seek, TimeControl};
+
+/// the port the server listens on unless told otherwise
+pub const DEFAULT_PORT: u16 = 1812;
+/// every ipv4 address, `::` listens on ipv6 and, on most systems, ipv4 too
+pub const DEFAULT_BIND: &str = "0.0.0.0";
+
+/// the address to listen on from an ip or host name, which can have a port of its own,
+/// ipv6 addresses need brackets around them when they do: `[::1]:1812`
+pub fn bind_address(address: &str, port: u16) -> Result<SocketAddr, String> {
+    let address = address.trim();
+    if let Ok(ip) = address
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+    {
+        return Ok(SocketAddr::new(ip, port));
+    }
+    if let Ok(address) = address.parse::<SocketAddr>() {
+        return Ok(address);
+    }
+    // a host name
+    let resolved = if address.contains(':') {
+        address.to_socket_addrs()
+    } else {
+        (address, port).to_socket_addrs()
+    };
+    resolved
+        .map_err(|error| format!("couldn't resolve {}: {}", address, error))?
+        .next()
+        .ok_or_else(|| format!("{} has no address", address))
+}
+
+/// what games the server allows, can change while it runs
+#[derive(Resource, Debug, Clone)]
+pub struct GameRules {
+    /// the queue stops pairing players while this many games are running and other games
+    /// are refused, None has no limit
+    pub max_games: Option<u32>,
+    /// connections past this many are told the server is full and disconnected
+    pub max_connections: Option<u32>,
+    /// players past this many can't join the queue
+    pub max_queue: Option<u32>,
+    /// the clocks games can be played with, None allows every clock
+    pub time_controls: Option<Vec<TimeControl>>,
+    /// whether games without a clock are allowed
+    pub untimed: bool,
+    /// how long a player waits in the queue before the bot plays them, None never
+    pub bot_after: Option<Duration>,
+    /// how many plies the bot looks ahead
+    pub bot_depth: u8,
+    /// how long a game without a running clock waits for a move before it is called off,
+    /// None forever
+    pub stale_after: Option<Duration>,
+    /// ends games where neither player has the material to checkmate as a draw
+    pub adjudicate: bool,
+    /// how long a draw offer waits for an answer, None until the opponent moves
+    pub draw_offer_expiry: Option<Duration>,
+    /// how long each player has for their first move before the game is aborted (or lost in a
+    /// league), None forever
+    pub first_move: Option<Duration>,
+}
+
+impl Default for GameRules {
+    fn default() -> Self {
+        Self {
+            max_games: None,
+            max_connections: None,
+            max_queue: None,
+            time_controls: None,
+            untimed: true,
+            bot_after: Some(bots::WAIT),
+            bot_depth: bots::DEPTH,
+            stale_after: Some(reconnect::STALE_AFTER),
+            adjudicate: false,
+            draw_offer_expiry: Some(clocks::DRAW_OFFER_EXPIRY),
+            first_move: Some(clocks::FIRST_MOVE),
+        }
+    }
+}
+
+impl GameRules {
+    fn allows_clock(&self, time_control: Option<TimeControl>) -> bool {
+        match (time_control, &self.time_controls) {
+            (None, _) => self.untimed,
+            (Some(_), None) => true,
+            (Some(time_control), Some(allowed)) => allowed.contains(&time_control),
+        }
+    }
+
+    /// whether the clocks of both players are allowed
+    pub fn allows(&self, seek: &Seek) -> bool {
+        self.allows_clock(seek.time_control) && self.allows_clock(seek.opponent_time_control())
+    }
+}
+
+/// a clock the config file allows, `{ minutes = 3, increment = 2 }` or `{ days = 1 }`
+#[derive(Deserialize, Debug, Clone, Copy, Default)]
+#[serde(default, deny_unknown_fields)]
+struct AllowedClock {
+    minutes: u64,
+    /// seconds
+    increment: u64,
+    /// seconds
+    delay: u64,
+    /// a correspondence game with this many days per move
+    days: u64,
+}
+
+impl AllowedClock {
+    fn time_control(&self) -> TimeControl {
+        if self.days != 0 {
+            TimeControl::correspondence(self.days)
+        } else {
+            TimeControl::new(self.minutes.saturating_mul(60), self.increment, self.delay)
+        }
+    }
+}
+
+/// everything the config file can set, whatever it leaves out stays as it was
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    /// the address to listen on, only read at startup
+    pub address: Option<String>,
+    /// only read at startup
+    pub port: Option<u16>,
+    /// only read at startup
+    pub database: Option<String>,
+    /// the address of the http status pages, only read at startup
+    pub http: Option<String>,
+    /// only read at startup
+    pub webhooks: Option<Vec<String>>,
+    pub pgn_archive: Option<PathBuf>,
+    pub motd: Option<String>,
+    pub max_games: Option<u32>,
+    pub max_connections: Option<u32>,
+    pub max_queue: Option<u32>,
+    time_controls: Option<Vec<AllowedClock>>,
+    pub untimed_games: Option<bool>,
+    /// seconds
+    pub grace_period: Option<u64>,
+    pub pause_clock: Option<bool>,
+    /// seconds
+    pub league_no_show: Option<u64>,
+    /// seconds, 0 turns the bot off
+    pub bot_after: Option<u64>,
+    pub bot_depth: Option<u8>,
+    /// seconds, 0 lets games wait for a move forever
+    pub stale_after: Option<u64>,
+    pub adjudicate: Option<bool>,
+    /// seconds, 0 keeps offers until the opponent moves
+    pub draw_offer_expiry: Option<u64>,
+    /// seconds, 0 lets players take as long as they like
+    pub first_move: Option<u64>,
+}
+
+impl ConfigFile {
+    pub fn read(path: &PathBuf) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|error| format!("couldn't read {}: {}", path.display(), error))?;
+        toml::from_str(&text).map_err(|error| format!("invalid {}: {}", path.display(), error))
+    }
+
+    /// overrides the options the file sets
+    pub fn apply(&self, options: &mut ServerOptions) {
+        if let Some(database) = &self.database {
+            options.database = Some(database.clone());
+        }
+        if let Some(http) = &self.http {
+            options.http = Some(http.clone());
+        }
+        if let Some(webhooks) = &self.webhooks {
+            options.webhooks = webhooks.clone();
+        }
+        if let Some(pgn_archive) = &self.pgn_archive {
+            options.pgn_archive = Some(pgn_archive.clone());
+        }
+        if let Some(motd) = &self.motd {
+            options.motd = Some(motd.clone());
+        }
+        if let Some(max_games) = self.max_games {
+            options.rules.max_games = Some(max_games);
+        }
+        if let Some(max_connections) = self.max_connections {
+            options.rules.max_connections = Some(max_connections);
+        }
+        if let Some(max_queue) = self.max_queue {
+            options.rules.max_queue = Some(max_queue);
+        }
+        if let Some(time_controls) = &self.time_controls {
+            options.rules.time_controls = Some(
+                time_controls
+                    .iter()
+                    .map(AllowedClock::time_control)
+                    .filter(|time_control| {
+                        let valid = time_control.is_valid();
+                        if !valid {
+                            warn!("the config allows a clock that is too long: {time_control}");
+                        }
+                        valid
+                    })
+                    .collect(),
+            );
+        }
+        if let Some(untimed) = self.untimed_games {
+            options.rules.untimed = untimed;
+        }
+        if let Some(seconds) = self.grace_period {
+            options.grace_period = Duration::from_secs(seconds);
+        }
+        if let Some(pause_clock) = self.pause_clock {
+            options.pause_clock = pause_clock;
+        }
+        if let Some(seconds) = self.league_no_show {
+            options.league_no_show = Duration::from_secs(seconds);
+        }
+        if let Some(seconds) = self.bot_after {
+            options.rules.bot_after = (seconds != 0).then(|| Duration::from_secs(seconds));
+        }
+        if let Some(depth) = self.bot_depth {
+            options.rules.bot_depth = depth;
+        }
+        if let Some(seconds) = self.stale_after {
+            options.rules.stale_after = (seconds != 0).then(|| Duration::from_secs(seconds));
+        }
+        if let Some(adjudicate) = self.adjudicate {
+            options.rules.adjudicate = adjudicate;
+        }
+        if let Some(seconds) = self.draw_offer_expiry {
+            options.rules.draw_offer_expiry = (seconds != 0).then(|| Duration::from_secs(seconds));
+        }
+        if let Some(seconds) = self.first_move {
+            options.rules.first_move = (seconds != 0).then(|| Duration::from_secs(seconds));
+        }
+    }
+}
+
+/// the config file the server was started with
+#[derive(Resource, Debug, Default)]
+pub struct ConfigPath(pub Option<PathBuf>);
+
+/// reads the config file again, typed as /reload into the server console
+#[derive(Event, Debug)]
+pub struct ReloadConfigEvent;
+
+/// applies the config file to the running server, a broken file changes nothing
+pub fn reload_config(
+    mut events: EventReader<ReloadConfigEvent>,
+    path: Res<ConfigPath>,
+    mut motd: ResMut<Motd>,
+    mut archive: ResMut<PgnArchive>,
+    mut rules: ResMut<GameRules>,
+    mut grace: ResMut<GracePeriod>,
+    mut no_show: ResMut<NoShowWait>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    let Some(path) = &path.0 else {
+        return warn!("there is no config file to reload, start the server with --config=");
+    };
+    let file = match ConfigFile::read(path) {
+        Ok(file) => file,
+        Err(error) => return warn!("{}", error),
+    };
+    if file.address.is_some()
+        || file.port.is_some()
+        || file.database.is_some()
+        || file.http.is_some()
+        || file.webhooks.is_some()
+    {
+        info!("the addresses, port, database and webhooks only change after a restart");
+    }
+    let mut options = ServerOptions {
+        motd: motd.0.clone(),
+        pgn_archive: archive.0.clone(),
+        rules: rules.clone(),
+        grace_period: grace.duration,
+        pause_clock: grace.pause_clock,
+        league_no_show: no_show.0,
+        ..default()
+    };
+    file.apply(&mut options);
+    motd.0 = options.motd;
+    archive.0 = options.pgn_archive;
+    *rules = options.rules;
+    *grace = GracePeriod {
+        duration: options.grace_period,
+        pause_clock: options.pause_clock,
+    };
+    no_show.0 = options.league_no_show;
+    info!("reloaded {}", path.display());
+}
@@ -0,0 +1,27 @@
+use super::ChessGameMap;
+use crate::api::{chessmove::ChessColor, GameToken, OngoingGame, PlayerId};
+
+/// the correspondence games `player` has a seat in, with the tokens to resume them
+pub fn ongoing_games(game_map: &ChessGameMap, player: PlayerId) -> Vec<OngoingGame> {
+    let mut games = Vec::new();
+    for game in game_map.iter() {
+        let players = game.players;
+        if !players.is_correspondence() {
+            continue;
+        }
+        for color in [ChessColor::White, ChessColor::Black] {
+            if players.player(color).id != player {
+                continue;
+            }
+            games.push(OngoingGame {
+                token: GameToken {
+                    game: *game.id,
+                    key: players.rejoin_key(color),
+                },
+                opponent: players.player(!color),
+                your_move: game.board.state.turn == color,
+            });
+        }
+    }
+    games
+}
@@ -0,0 +1,389 @@
+//! keeps everything the server remembers in a sqlite database, so it survives restarts
+
+use std::sync::{Mutex, MutexGuard};
+
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::{
+    accounts::Account,
+    persist::SavedGame,
+    ratings::PlayerRating,
+    storage::{GameRecord, Storage, StorageResult},
+};
+use crate::api::{analysis::GameAnalysis, puzzle::Puzzle, PlayerId, ReplayId, SessionToken};
+
+/// bumped whenever an existing table changes, `migrate` brings older databases up to it
+const SCHEMA_VERSION: i64 = 1;
+
+/// rusqlite connections can't be shared between threads, so it sits behind a mutex
+#[derive(Debug)]
+pub struct SqliteStorage(Mutex<Connection>);
+
+impl SqliteStorage {
+    /// opens the database at `path`, creating the tables the first time
+    pub fn open(path: &str) -> rusqlite::Result<Self> {
+        let connection = Connection::open(path)?;
+        migrate(&connection)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS accounts (
+                id INTEGER PRIMARY KEY,
+                name TEXT NOT NULL UNIQUE,
+                password TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS sessions (
+                token INTEGER PRIMARY KEY,
+                account INTEGER NOT NULL REFERENCES accounts(id)
+            );
+            CREATE TABLE IF NOT EXISTS ratings (
+                player INTEGER PRIMARY KEY,
+                elo REAL NOT NULL,
+                games INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS games (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                white INTEGER NOT NULL,
+                black INTEGER NOT NULL,
+                white_name TEXT NOT NULL,
+                black_name TEXT NOT NULL,
+                rated INTEGER NOT NULL,
+                ended INTEGER NOT NULL,
+                result BLOB NOT NULL,
+                start BLOB NOT NULL,
+                moves BLOB NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS games_white ON games (white);
+            CREATE INDEX IF NOT EXISTS games_black ON games (black);
+            CREATE TABLE IF NOT EXISTS analyses (
+                game INTEGER PRIMARY KEY REFERENCES games(id),
+                analysis BLOB NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS puzzles (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                puzzle BLOB NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS ongoing_games (
+                id INTEGER PRIMARY KEY,
+                game BLOB NOT NULL
+            );",
+        )?;
+        connection.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+        Ok(Self(Mutex::new(connection)))
+    }
+
+    fn connection(&self) -> MutexGuard<'_, Connection> {
+        // a panic while holding the lock can't leave a sqlite connection half written
+        self.0
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// updates the tables of a database an older server made, new tables are just created
+fn migrate(connection: &Connection) -> rusqlite::Result<()> {
+    let version: i64 = connection.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    let has_games = connection
+        .prepare("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'games'")?
+        .exists([])?;
+    if version < 1 && has_games {
+        let has_names = connection
+            .prepare("SELECT 1 FROM pragma_table_info('games') WHERE name = 'white_name'")?
+            .exists([])?;
+        if !has_names {
+            // games from before the history didn't keep names, the accounts still know most
+            connection.execute_batch(
+                "ALTER TABLE games ADD COLUMN white_name TEXT NOT NULL DEFAULT '';
+                ALTER TABLE games ADD COLUMN black_name TEXT NOT NULL DEFAULT '';
+                ALTER TABLE games ADD COLUMN ended INTEGER NOT NULL DEFAULT 0;
+                UPDATE games SET
+                    white_name = COALESCE((SELECT name FROM accounts WHERE id = games.white), ''),
+                    black_name = COALESCE((SELECT name FROM accounts WHERE id = games.black), '');",
+            )?;
+        }
+    }
+    Ok(())
+}
+
+fn account(row: &rusqlite::Row) -> rusqlite::Result<Account> {
+    Ok(Account {
+        id: PlayerId(row.get(0)?),
+        name: row.get(1)?,
+        registered: true,
+    })
+}
+
+/// the columns a game record is read from
+const GAME_COLUMNS: &str =
+    "id, white, black, white_name, black_name, rated, ended, result, start, moves";
+
+/// reads a row of GAME_COLUMNS, the blobs get decoded afterwards
+fn game_row(row: &rusqlite::Row) -> rusqlite::Result<(i64, GameRecordRow)> {
+    Ok((
+        row.get(0)?,
+        GameRecordRow {
+            white: PlayerId(row.get(1)?),
+            black: PlayerId(row.get(2)?),
+            white_name: row.get(3)?,
+            black_name: row.get(4)?,
+            rated: row.get(5)?,
+            ended: row.get::<_, i64>(6)? as u64,
+            result: row.get(7)?,
+            start: row.get(8)?,
+            moves: row.get(9)?,
+        },
+    ))
+}
+
+/// a game record with its blobs still encoded
+struct GameRecordRow {
+    white: PlayerId,
+    black: PlayerId,
+    white_name: String,
+    black_name: String,
+    rated: bool,
+    ended: u64,
+    result: Vec<u8>,
+    start: Vec<u8>,
+    moves: Vec<u8>,
+}
+
+impl GameRecordRow {
+    fn decode(self) -> StorageResult<GameRecord> {
+        Ok(GameRecord {
+            white: self.white,
+            black: self.black,
+            white_name: self.white_name,
+            black_name: self.black_name,
+            start: bincode::deserialize(&self.start)?,
+            moves: bincode::deserialize(&self.moves)?,
+            result: bincode::deserialize(&self.result)?,
+            rated: self.rated,
+            ended: self.ended,
+        })
+    }
+}
+
+impl Storage for SqliteStorage {
+    fn next_player_id(&self) -> StorageResult<PlayerId> {
+        let max: Option<u32> = self.connection().query_row(
+            "SELECT MAX(id) FROM (
+                SELECT MAX(id) AS id FROM accounts
+                UNION ALL SELECT MAX(player) FROM ratings
+                UNION ALL SELECT MAX(white) FROM games
+                UNION ALL SELECT MAX(black) FROM games
+            )",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(PlayerId(max.map_or(0, |max| max + 1)))
+    }
+
+    fn add_account(&mut self, account: &Account, password_hash: &str) -> StorageResult<bool> {
+        match self.connection().execute(
+            "INSERT INTO accounts (id, name, password) VALUES (?1, ?2, ?3)",
+            params![account.id.0, account.name, password_hash],
+        ) {
+            Ok(_) => Ok(true),
+            // the name is UNIQUE
+            Err(rusqlite::Error::SqliteFailure(error, _))
+                if error.code == rusqlite::ErrorCode::ConstraintViolation =>
+            {
+                Ok(false)
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn account_by_name(&self, name: &str) -> StorageResult<Option<(Account, String)>> {
+        Ok(self
+            .connection()
+            .query_row(
+                "SELECT id, name, password FROM accounts WHERE name = ?1",
+                params![name],
+                |row| Ok((account(row)?, row.get(2)?)),
+            )
+            .optional()?)
+    }
+
+    fn rename_account(&mut self, player: PlayerId, name: &str) -> StorageResult<bool> {
+        match self.connection().execute(
+            "UPDATE accounts SET name = ?2 WHERE id = ?1",
+            params![player.0, name],
+        ) {
+            Ok(1) => Ok(true),
+            Ok(_) => Err("no account with that id".into()),
+            Err(rusqlite::Error::SqliteFailure(error, _))
+                if error.code == rusqlite::ErrorCode::ConstraintViolation =>
+            {
+                Ok(false)
+            }
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    fn add_session(&mut self, token: SessionToken, player: PlayerId) -> StorageResult<()> {
+        self.connection().execute(
+            "INSERT OR REPLACE INTO sessions (token, account) VALUES (?1, ?2)",
+            // sqlite integers are signed
+            params![token.0 as i64, player.0],
+        )?;
+        Ok(())
+    }
+
+    fn session(&self, token: SessionToken) -> StorageResult<Option<Account>> {
+        Ok(self
+            .connection()
+            .query_row(
+                "SELECT accounts.id, accounts.name FROM sessions
+                JOIN accounts ON accounts.id = sessions.account
+                WHERE sessions.token = ?1",
+                params![token.0 as i64],
+                account,
+            )
+            .optional()?)
+    }
+
+    fn rating(&self, player: PlayerId) -> StorageResult<Option<PlayerRating>> {
+        Ok(self
+            .connection()
+            .query_row(
+                "SELECT elo, games FROM ratings WHERE player = ?1",
+                params![player.0],
+                |row| {
+                    Ok(PlayerRating {
+                        elo: row.get(0)?,
+                        games: row.get(1)?,
+                    })
+                },
+            )
+            .optional()?)
+    }
+
+    fn set_rating(&mut self, player: PlayerId, rating: PlayerRating) -> StorageResult<()> {
+        self.connection().execute(
+            "INSERT OR REPLACE INTO ratings (player, elo, games) VALUES (?1, ?2, ?3)",
+            params![player.0, rating.elo, rating.games],
+        )?;
+        Ok(())
+    }
+
+    fn save_game(&mut self, game: GameRecord) -> StorageResult<ReplayId> {
+        let connection = self.connection();
+        connection.execute(
+            "INSERT INTO games (white, black, white_name, black_name, rated, ended, result, start, moves)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            params![
+                game.white.0,
+                game.black.0,
+                game.white_name,
+                game.black_name,
+                game.rated,
+                game.ended as i64,
+                bincode::serialize(&game.result)?,
+                bincode::serialize(&game.start)?,
+                bincode::serialize(&game.moves)?,
+            ],
+        )?;
+        Ok(ReplayId(connection.last_insert_rowid() as u64))
+    }
+
+    fn recent_games(
+        &self,
+        player: PlayerId,
+        before: Option<ReplayId>,
+        limit: usize,
+    ) -> StorageResult<Vec<(ReplayId, GameRecord)>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare(&format!(
+            "SELECT {GAME_COLUMNS} FROM games WHERE (white = ?1 OR black = ?1) AND id < ?3 ORDER BY id DESC LIMIT ?2"
+        ))?;
+        let before = before.map_or(i64::MAX, |id| id.0 as i64);
+        let rows = statement
+            .query_map(params![player.0, limit as i64, before], game_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        rows.into_iter()
+            .map(|(id, row)| Ok((ReplayId(id as u64), row.decode()?)))
+            .collect()
+    }
+
+    fn game(&self, id: ReplayId) -> StorageResult<Option<GameRecord>> {
+        let row = self
+            .connection()
+            .query_row(
+                &format!("SELECT {GAME_COLUMNS} FROM games WHERE id = ?1"),
+                params![id.0 as i64],
+                game_row,
+            )
+            .optional()?;
+        row.map(|(_, row)| row.decode()).transpose()
+    }
+
+    fn save_analysis(&mut self, game: ReplayId, analysis: &GameAnalysis) -> StorageResult<()> {
+        self.connection().execute(
+            "INSERT OR REPLACE INTO analyses (game, analysis) VALUES (?1, ?2)",
+            params![game.0 as i64, bincode::serialize(analysis)?],
+        )?;
+        Ok(())
+    }
+
+    fn analysis(&self, game: ReplayId) -> StorageResult<Option<GameAnalysis>> {
+        let blob = self
+            .connection()
+            .query_row(
+                "SELECT analysis FROM analyses WHERE game = ?1",
+                params![game.0 as i64],
+                |row| row.get::<_, Vec<u8>>(0),
+            )
+            .optional()?;
+        Ok(blob.map(|blob| bincode::deserialize(&blob)).transpose()?)
+    }
+
+    fn add_puzzles(&mut self, puzzles: &[Puzzle]) -> StorageResult<()> {
+        let connection = self.connection();
+        for puzzle in puzzles {
+            connection.execute(
+                "INSERT INTO puzzles (puzzle) VALUES (?1)",
+                params![bincode::serialize(puzzle)?],
+            )?;
+        }
+        Ok(())
+    }
+
+    fn puzzles(&self) -> StorageResult<Vec<Puzzle>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare("SELECT puzzle FROM puzzles ORDER BY id")?;
+        let blobs = statement
+            .query_map([], |row| row.get::<_, Vec<u8>>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(blobs
+            .iter()
+            .map(|blob| bincode::deserialize(blob))
+            .collect::<Result<_, _>>()?)
+    }
+
+    fn save_ongoing_games(&mut self, games: &[SavedGame]) -> StorageResult<()> {
+        let mut connection = self.connection();
+        // all or nothing, a crash halfway must not lose the games saved before
+        let transaction = connection.transaction()?;
+        transaction.execute("DELETE FROM ongoing_games", [])?;
+        for game in games {
+            transaction.execute(
+                "INSERT INTO ongoing_games (id, game) VALUES (?1, ?2)",
+                params![game.id.0, bincode::serialize(game)?],
+            )?;
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn ongoing_games(&self) -> StorageResult<Vec<SavedGame>> {
+        let connection = self.connection();
+        let mut statement = connection.prepare("SELECT game FROM ongoing_games")?;
+        let blobs = statement
+            .query_map([], |row| row.get::<_, Vec<u8>>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(blobs
+            .iter()
+            .map(|blob| bincode::deserialize(blob))
+            .collect::<Result<_, _>>()?)
+    }
+}
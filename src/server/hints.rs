@@ -0,0 +1,82 @@
+//! the engine suggests moves to players of games that aren't rated, a few per game, so the
+//! client can't hand out more of them or any in rated games
+
+use std::collections::HashMap;
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, poll_once, AsyncComputeTaskPool, Task},
+};
+use bevy_slinet::{connection::EcsConnection, server::PacketReceiveEvent};
+
+use super::{connection_error, ChessGames};
+use crate::api::{
+    ai, chessmove::PlayedMove, ClientPacket, GameId, ServerError, ServerNetConfig, ServerPacket,
+    HINTS, HINT_DEPTH,
+};
+
+/// a hint being searched for
+struct HintSearch {
+    connection: EcsConnection<ServerPacket>,
+    game: GameId,
+    /// the moves played when the search started
+    ply: u32,
+    task: Task<Option<PlayedMove>>,
+}
+
+#[derive(Resource, Default)]
+pub struct Hints {
+    /// how many hints white and black got in each running game
+    used: HashMap<GameId, [u8; 2]>,
+    searching: Vec<HintSearch>,
+}
+
+/// answers Hint with a search in the background, games that are rated, over or out of hints get
+/// NoHints
+pub fn hints(
+    mut packets: EventReader<PacketReceiveEvent<ServerNetConfig>>,
+    mut hints: ResMut<Hints>,
+    games: ChessGames,
+) {
+    // the count of a game is forgotten once it ended
+    hints.used.retain(|game, _| games.contains_key(game));
+    for packet in packets.read() {
+        let ClientPacket::Hint(id) = packet.packet else {
+            continue;
+        };
+        // only the player to move gets one, and hints would make ratings meaningless
+        let hint = games.get(&id).and_then(|game| {
+            let color = game.players.color_of(packet.connection.id())?;
+            let state = game.board.state;
+            let allowed = !game.players.is_rated() && state.turn == color && !state.should_promote;
+            allowed.then_some((color, state, game.board.ply()))
+        });
+        let used = hints.used.entry(id).or_default();
+        let Some((color, state, ply)) = hint.filter(|&(color, ..)| used[color as usize] < HINTS)
+        else {
+            packet
+                .connection
+                .send(ServerPacket::Error(ServerError::NoHints))
+                .unwrap_or_else(connection_error);
+            continue;
+        };
+        used[color as usize] += 1;
+        hints.searching.push(HintSearch {
+            connection: packet.connection.clone(),
+            game: id,
+            ply,
+            task: AsyncComputeTaskPool::get()
+                .spawn(async move { ai::search(&state, HINT_DEPTH).line.first().copied() }),
+        });
+    }
+    hints.searching.retain_mut(|search| {
+        let Some(found) = block_on(poll_once(&mut search.task)) else {
+            return true;
+        };
+        search
+            .connection
+            .send(ServerPacket::Hint(search.game, search.ply, found))
+            .unwrap_or_else(connection_error);
+        false
+    });
+}
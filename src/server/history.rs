@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+use bevy_slinet::server::PacketReceiveEvent;
+
+use super::{
+    accounts::{Account, Accounts},
+    connection_error, ratings,
+    storage::{GameRecord, Store},
+};
+use crate::api::{
+    chessmove::ChessColor, ClientPacket, GameSummary, HistoryEntry, PlayerId, PlayerInfo, ReplayId,
+    ServerError, ServerNetConfig, ServerPacket,
+};
+
+/// how many finished games RecentGames lists
+const RECENT_GAMES: usize = 20;
+/// the most games a single History answer lists
+const MAX_HISTORY: u32 = 100;
+
+/// lists the games players finished and sends the moves of one for replaying it
+pub fn send_history(
+    mut packets: EventReader<PacketReceiveEvent<ServerNetConfig>>,
+    accounts: Res<Accounts>,
+    store: Res<Store>,
+) {
+    for packet in packets.read() {
+        let answer = match &packet.packet {
+            ClientPacket::RecentGames => {
+                // a connection that didn't log in hasn't played anything
+                let games = match accounts.account(packet.connection.id()) {
+                    Some(account) => games_of(&store, account.id, None, RECENT_GAMES),
+                    None => Vec::new(),
+                };
+                ServerPacket::RecentGames(
+                    games
+                        .into_iter()
+                        .map(|(id, game)| GameSummary {
+                            id,
+                            white: game.white_name,
+                            black: game.black_name,
+                            result: game.result,
+                            moves: game.moves.len() as u32,
+                            rated: game.rated,
+                            ended: game.ended,
+                        })
+                        .collect(),
+                )
+            }
+            ClientPacket::History {
+                player,
+                count,
+                before,
+            } => {
+                let account = match player {
+                    Some(name) => find_account(&accounts, &store, name),
+                    None => accounts.account(packet.connection.id()).cloned(),
+                };
+                match account {
+                    Some(account) => ServerPacket::History {
+                        games: games_of(
+                            &store,
+                            account.id,
+                            *before,
+                            (*count).min(MAX_HISTORY) as usize,
+                        )
+                        .into_iter()
+                        .map(|(id, game)| entry(account.id, id, game))
+                        .collect(),
+                        player: PlayerInfo {
+                            id: account.id,
+                            rating: Some(ratings::get(&*store.0, account.id)),
+                            name: account.name,
+                            bot: false,
+                        },
+                    },
+                    None => ServerPacket::Error(ServerError::NoSuchPlayer),
+                }
+            }
+            ClientPacket::FetchReplay(id) => match store.game(*id) {
+                // only the players get the moves, to everyone else the game doesn't exist
+                Ok(Some(game))
+                    if accounts
+                        .account(packet.connection.id())
+                        .is_some_and(|account| game.played_by(account.id)) =>
+                {
+                    ServerPacket::Replay {
+                        id: *id,
+                        white: game.white_name,
+                        black: game.black_name,
+                        start: game.start,
+                        moves: game.moves,
+                        result: game.result,
+                    }
+                }
+                Ok(_) => ServerPacket::Error(ServerError::NoSuchGame),
+                Err(error) => {
+                    warn!("couldn't read game {:?}: {}", id, error);
+                    ServerPacket::Error(ServerError::NoSuchGame)
+                }
+            },
+            _ => continue,
+        };
+        packet
+            .connection
+            .send(answer)
+            .unwrap_or_else(connection_error);
+    }
+}
+
+fn games_of(
+    store: &Store,
+    player: PlayerId,
+    before: Option<ReplayId>,
+    limit: usize,
+) -> Vec<(ReplayId, GameRecord)> {
+    store
+        .recent_games(player, before, limit)
+        .unwrap_or_else(|error| {
+            warn!("couldn't read the games of {:?}: {}", player, error);
+            Vec::new()
+        })
+}
+
+/// a registered account, or someone online who logged in with that name
+fn find_account(accounts: &Accounts, store: &Store, name: &str) -> Option<Account> {
+    match store.account_by_name(name) {
+        Ok(Some((account, _))) => return Some(account),
+        Ok(None) => {}
+        Err(error) => warn!("couldn't look up {}: {}", name, error),
+    }
+    accounts
+        .by_token
+        .values()
+        .find(|account| account.name == name)
+        .cloned()
+}
+
+/// the game from the side of `player`
+fn entry(player: PlayerId, id: ReplayId, game: GameRecord) -> HistoryEntry {
+    let (color, opponent, opponent_name) = if game.white == player {
+        (ChessColor::White, game.black, game.black_name)
+    } else {
+        (ChessColor::Black, game.white, game.white_name)
+    };
+    HistoryEntry {
+        id,
+        color,
+        opponent,
+        opponent_name,
+        result: game.result,
+        moves: game.moves.len() as u32,
+        rated: game.rated,
+        ended: game.ended,
+    }
+}
@@ -0,0 +1,255 @@
+//! a small http server next to the game, so dashboards and websites can see what is going on
+//! without speaking the game protocol
+
+use std::{
+    io::{self, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc::{self, Receiver, Sender},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use bevy::prelude::*;
+use bevy_slinet::server::ServerConnections;
+use serde::Serialize;
+
+use super::{archive, metrics, queue::GameQueue, ChessGames, OpeningStats};
+use crate::api::{GameId, ServerNetConfig};
+
+/// how long a request waits for the game to answer it
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(5);
+/// how long a client gets to send the request line and headers
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+/// the most bytes of request line and headers read, nothing here needs more
+const MAX_REQUEST: usize = 8 * 1024;
+/// connections served at once, more get closed right away
+const MAX_CONNECTIONS: usize = 32;
+
+struct Request {
+    path: String,
+    reply: Sender<Response>,
+}
+
+struct Response {
+    status: &'static str,
+    content_type: &'static str,
+    body: String,
+}
+
+impl Response {
+    fn text(body: impl Into<String>) -> Self {
+        Self {
+            status: "200 OK",
+            content_type: "text/plain; charset=utf-8",
+            body: body.into(),
+        }
+    }
+
+    fn json(body: &impl Serialize) -> Self {
+        Self {
+            status: "200 OK",
+            content_type: "application/json",
+            body: serde_json::to_string(body).unwrap_or_default(),
+        }
+    }
+
+    fn error(status: &'static str) -> Self {
+        Self {
+            status,
+            content_type: "text/plain; charset=utf-8",
+            body: format!("{status}\n"),
+        }
+    }
+}
+
+/// requests the http threads got, answered by the game
+#[derive(Resource)]
+pub struct HttpRequests(Mutex<Receiver<Request>>);
+
+impl HttpRequests {
+    pub fn bind(address: &str) -> Self {
+        let listener = TcpListener::bind(address).expect("couldn't open the http address");
+        info!("serving http on {}", address);
+        let (sender, receiver) = mpsc::channel();
+        let open = Arc::new(AtomicUsize::new(0));
+        std::thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                if open.fetch_add(1, Ordering::Relaxed) >= MAX_CONNECTIONS {
+                    open.fetch_sub(1, Ordering::Relaxed);
+                    continue;
+                }
+                let sender = sender.clone();
+                let open = open.clone();
+                std::thread::spawn(move || {
+                    if let Err(error) = serve(stream, sender) {
+                        warn!("http connection error {:?}", error);
+                    }
+                    open.fetch_sub(1, Ordering::Relaxed);
+                });
+            }
+        });
+        Self(Mutex::new(receiver))
+    }
+}
+
+/// reads the request line and headers, None when they're too long
+fn read_head(mut stream: &TcpStream) -> io::Result<Option<String>> {
+    // a timeout per read would let a client keep the thread by sending a byte at a time
+    let deadline = Instant::now() + REQUEST_TIMEOUT;
+    let mut head = Vec::new();
+    let mut buffer = [0; 1024];
+    while !head.windows(4).any(|end| end == b"\r\n\r\n") {
+        if head.len() > MAX_REQUEST {
+            return Ok(None);
+        }
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        stream.set_read_timeout(Some(left))?;
+        let read = stream.read(&mut buffer)?;
+        if read == 0 {
+            break;
+        }
+        head.extend_from_slice(&buffer[..read]);
+    }
+    // only the request line matters, the headers just have to be read before closing
+    Ok(Some(
+        String::from_utf8_lossy(&head)
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+    ))
+}
+
+/// answers a single request and closes the connection
+fn serve(mut stream: TcpStream, sender: Sender<Request>) -> io::Result<()> {
+    stream.set_write_timeout(Some(ANSWER_TIMEOUT))?;
+    let Some(request_line) = read_head(&stream)? else {
+        return respond(
+            &mut stream,
+            Response::error("431 Request Header Fields Too Large"),
+        );
+    };
+    let mut parts = request_line.split_whitespace();
+    let response = match (parts.next(), parts.next()) {
+        (Some("GET"), Some(path)) => {
+            let (reply, answer) = mpsc::channel();
+            sender
+                .send(Request {
+                    path: path.to_string(),
+                    reply,
+                })
+                .ok();
+            answer
+                .recv_timeout(ANSWER_TIMEOUT)
+                .unwrap_or_else(|_| Response::error("503 Service Unavailable"))
+        }
+        (Some(_), Some(_)) => Response::error("405 Method Not Allowed"),
+        _ => Response::error("400 Bad Request"),
+    };
+    respond(&mut stream, response)
+}
+
+fn respond(stream: &mut TcpStream, response: Response) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        response.status,
+        response.content_type,
+        response.body.len(),
+        response.body
+    )
+}
+
+#[derive(Serialize)]
+struct Stats {
+    games: u32,
+    players_online: u32,
+    queue_length: u32,
+    /// seconds
+    uptime: u64,
+    opening: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct GameSummary {
+    id: u32,
+    white: String,
+    black: String,
+    /// in plies
+    moves: u32,
+    time_control: Option<String>,
+    rated: bool,
+    spectators: u32,
+}
+
+pub fn answer_http(
+    requests: Res<HttpRequests>,
+    connections: Res<ServerConnections<ServerNetConfig>>,
+    game_map: ChessGames,
+    queue: Res<GameQueue>,
+    opening_stats: Res<OpeningStats>,
+    time: Res<Time>,
+) {
+    let Ok(receiver) = requests.0.lock() else {
+        return;
+    };
+    for request in receiver.try_iter() {
+        let response = match request.path.as_str() {
+            "/health" => Response::text("ok\n"),
+            "/stats" => Response::json(&Stats {
+                games: game_map.len() as u32,
+                players_online: connections.iter().count() as u32,
+                queue_length: queue.players_waiting() as u32,
+                uptime: time.elapsed().as_secs(),
+                opening: opening_stats.most_played(),
+            }),
+            "/metrics" => Response {
+                status: "200 OK",
+                content_type: "text/plain; version=0.0.4",
+                body: metrics::render(
+                    connections.iter().count() as u32,
+                    game_map.len() as u32,
+                    queue.players_waiting() as u32,
+                    time.elapsed().as_secs(),
+                ),
+            },
+            "/games" => {
+                let mut games: Vec<_> = game_map
+                    .iter()
+                    .map(|game| GameSummary {
+                        id: game.id.0,
+                        white: game.players.white_name.clone(),
+                        black: game.players.black_name.clone(),
+                        moves: game.board.moves.len() as u32,
+                        time_control: game.players.seek.time_control.map(|t| t.to_string()),
+                        rated: game.players.is_rated(),
+                        spectators: game.spectators.0.len() as u32,
+                    })
+                    .collect();
+                games.sort_by_key(|game| game.id);
+                Response::json(&games)
+            }
+            path => match path
+                .strip_prefix("/game/")
+                .and_then(|id| id.strip_suffix(".pgn"))
+                .and_then(|id| id.parse().ok())
+                .and_then(|id| game_map.get(&GameId(id)))
+            {
+                Some(game) => Response {
+                    status: "200 OK",
+                    content_type: "application/x-chess-pgn",
+                    body: archive::pgn(game.players, game.board, None),
+                },
+                None => Response::error("404 Not Found"),
+            },
+        };
+        // the thread may have given up waiting
+        request.reply.send(response).ok();
+    }
+}
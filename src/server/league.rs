@@ -0,0 +1,401 @@
+use std::{collections::HashMap, time::Duration};
+
+use bevy::prelude::*;
+use bevy_slinet::{
+    connection::{ConnectionId, EcsConnection},
+    server::PacketReceiveEvent,
+};
+
+use super::{
+    accounts::Accounts, config::GameRules, connection_error, identify, ratings, storage::Store,
+    ConnectionMap, EndGameEvent, PlayerNames, Players, QueuedPlayer, StartGameEvent,
+};
+use crate::api::{
+    chessmove::ChessColor,
+    handicap::Handicap,
+    league::{round_robin, LeagueId, LeagueResult, LeagueSchedule, Pairing},
+    matchmaking::{ColorPreference, Seek, Variant},
+    ping::timestamp,
+    ClientPacket, GameId, PlayerId, PlayerInfo, ServerError, ServerNetConfig, ServerPacket,
+};
+
+/// how long a league game waits for its players before the missing ones forfeit,
+/// unless the server is told otherwise
+pub const NO_SHOW_WAIT: Duration = Duration::from_secs(5 * 60);
+
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct NoShowWait(pub Duration);
+
+#[derive(Debug)]
+struct Participant {
+    info: PlayerInfo,
+    /// None while they are disconnected, an account can come back by joining again
+    connection: Option<EcsConnection<ServerPacket>>,
+}
+
+#[derive(Debug)]
+pub struct League {
+    /// the connection that opened the league, only it can start it
+    creator: ConnectionId,
+    seek: Seek,
+    double: bool,
+    players: Vec<Participant>,
+    /// 0 until the league starts
+    round: u32,
+    rounds: u32,
+    /// milliseconds since the unix epoch
+    round_started: u64,
+    pairings: Vec<Pairing>,
+    /// pairings whose game was asked for but hasn't shown up in the game map yet
+    starting: Vec<usize>,
+    /// the running games and their pairing
+    games: HashMap<GameId, usize>,
+}
+
+impl League {
+    fn schedule(&self, id: LeagueId) -> LeagueSchedule {
+        LeagueSchedule {
+            id,
+            seek: self.seek,
+            double: self.double,
+            players: self.players.iter().map(|p| p.info.clone()).collect(),
+            round: self.round,
+            rounds: self.rounds,
+            pairings: self.pairings.clone(),
+        }
+    }
+
+    /// tells everyone in the league how it stands
+    fn send_schedule(&self, id: LeagueId) {
+        let schedule = self.schedule(id);
+        for connection in self.players.iter().filter_map(|p| p.connection.as_ref()) {
+            connection
+                .send(ServerPacket::League(schedule.clone()))
+                .unwrap_or_else(connection_error);
+        }
+    }
+
+    fn participant(&self, player: PlayerId) -> Option<&Participant> {
+        self.players.iter().find(|p| p.info.id == player)
+    }
+
+    fn is_participant(&self, connection_id: ConnectionId) -> bool {
+        self.players
+            .iter()
+            .any(|p| p.connection.as_ref().map(|c| c.id()) == Some(connection_id))
+    }
+
+    /// pairs everyone with everyone and starts the first round
+    fn start(&mut self) {
+        self.pairings = round_robin(self.players.len(), self.double)
+            .into_iter()
+            .map(|(round, white, black)| Pairing {
+                round,
+                white: self.players[white].info.id,
+                black: self.players[black].info.id,
+                result: None,
+            })
+            .collect();
+        self.rounds = self.pairings.iter().map(|p| p.round).max().unwrap_or(0);
+        self.round = 1;
+        self.round_started = timestamp();
+    }
+
+    /// the player seated for a league game, if they are connected and not busy with another game
+    fn ready(
+        &self,
+        player: PlayerId,
+        color: ChessColor,
+        connection_map: &ConnectionMap,
+    ) -> Option<QueuedPlayer> {
+        let participant = self.participant(player)?;
+        let connection = participant
+            .connection
+            .as_ref()
+            .filter(|c| !connection_map.0.contains_key(&c.id()))?;
+        Some(QueuedPlayer {
+            connection: connection.clone(),
+            id: player,
+            name: participant.info.name.clone(),
+            handicap: Handicap::None,
+            seek: self.seek,
+            color: match color {
+                ChessColor::White => ColorPreference::White,
+                ChessColor::Black => ColorPreference::Black,
+            },
+        })
+    }
+}
+
+/// round-robin leagues, open ones take players until their creator starts them
+#[derive(Resource, Default, Debug)]
+pub struct Leagues {
+    leagues: HashMap<LeagueId, League>,
+    next_id: u32,
+}
+
+impl Leagues {
+    /// whether `game` is a league game
+    pub fn plays(&self, game: GameId) -> bool {
+        self.leagues
+            .values()
+            .any(|league| league.games.contains_key(&game))
+    }
+
+    /// a league that didn't start forgets a player who left, and closes when it was its creator,
+    /// a running league waits for them to come back
+    pub fn remove_connection(&mut self, connection_id: ConnectionId) {
+        let is_connection =
+            |p: &Participant| p.connection.as_ref().map(|c| c.id()) == Some(connection_id);
+        self.leagues.retain(|&id, league| {
+            if !league.players.iter().any(is_connection) {
+                return true;
+            }
+            if league.round != 0 {
+                for participant in league.players.iter_mut().filter(|p| is_connection(p)) {
+                    participant.connection = None;
+                }
+                return true;
+            }
+            league.players.retain(|p| !is_connection(p));
+            if league.creator != connection_id {
+                league.send_schedule(id);
+                return true;
+            }
+            for connection in league.players.iter().filter_map(|p| p.connection.as_ref()) {
+                connection
+                    .send(ServerPacket::Error(ServerError::NoSuchLeague))
+                    .unwrap_or_else(connection_error);
+            }
+            false
+        });
+    }
+}
+
+/// opens, joins and starts leagues
+pub fn leagues(
+    mut packets: EventReader<PacketReceiveEvent<ServerNetConfig>>,
+    mut leagues: ResMut<Leagues>,
+    connection_map: Res<ConnectionMap>,
+    accounts: Res<Accounts>,
+    names: Res<PlayerNames>,
+    mut next_player: ResMut<PlayerId>,
+    store: Res<Store>,
+    rules: Res<GameRules>,
+) {
+    for packet in packets.read() {
+        let connection_id = packet.connection.id();
+        match packet.packet {
+            ClientPacket::CreateLeague {
+                time_control,
+                rated,
+                double,
+            } => {
+                if connection_map.0.contains_key(&connection_id) {
+                    warn!("{:?} opened a league during a game", connection_id);
+                    continue;
+                }
+                let seek = Seek {
+                    variant: Variant::Standard,
+                    time_control,
+                    time_odds: None,
+                    rated,
+                };
+                if !seek.is_valid() {
+                    warn!(
+                        "{:?} opened a league for games that can't exist",
+                        connection_id
+                    );
+                    continue;
+                }
+                if !rules.allows(&seek) {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(ServerError::TimeControlNotAllowed))
+                        .unwrap_or_else(connection_error);
+                    continue;
+                }
+                if rated && !accounts.registered(connection_id) {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(ServerError::AccountRequired))
+                        .unwrap_or_else(connection_error);
+                    continue;
+                }
+                let (player, name) = identify(connection_id, &accounts, &names, &mut next_player);
+                let id = LeagueId(leagues.next_id);
+                leagues.next_id += 1;
+                info!("{} opened league {:?}", name, id);
+                let league = League {
+                    creator: connection_id,
+                    seek,
+                    double,
+                    players: vec![Participant {
+                        info: PlayerInfo {
+                            id: player,
+                            name,
+                            rating: Some(ratings::get(&*store.0, player)),
+                            bot: false,
+                        },
+                        connection: Some(packet.connection.clone()),
+                    }],
+                    round: 0,
+                    rounds: 0,
+                    round_started: 0,
+                    pairings: Vec::new(),
+                    starting: Vec::new(),
+                    games: HashMap::new(),
+                };
+                league.send_schedule(id);
+                leagues.leagues.insert(id, league);
+            }
+            ClientPacket::JoinLeague(id) => {
+                let Some(league) = leagues.leagues.get_mut(&id) else {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(ServerError::NoSuchLeague))
+                        .unwrap_or_else(connection_error);
+                    continue;
+                };
+                if league.is_participant(connection_id) {
+                    warn!("{:?} joined league {:?} twice", connection_id, id);
+                    continue;
+                }
+                if league.seek.rated && !accounts.registered(connection_id) {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(ServerError::AccountRequired))
+                        .unwrap_or_else(connection_error);
+                    continue;
+                }
+                let (player, name) = identify(connection_id, &accounts, &names, &mut next_player);
+                // accounts keep their id, so they can take their place back after a disconnect
+                if let Some(participant) = league.players.iter_mut().find(|p| p.info.id == player) {
+                    participant.connection = Some(packet.connection.clone());
+                } else if league.round == 0 {
+                    league.players.push(Participant {
+                        info: PlayerInfo {
+                            id: player,
+                            name,
+                            rating: Some(ratings::get(&*store.0, player)),
+                            bot: false,
+                        },
+                        connection: Some(packet.connection.clone()),
+                    });
+                } else {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(ServerError::NoSuchLeague))
+                        .unwrap_or_else(connection_error);
+                    continue;
+                }
+                league.send_schedule(id);
+            }
+            ClientPacket::StartLeague(id) => {
+                let Some(league) = leagues.leagues.get_mut(&id) else {
+                    warn!("{:?} started unknown league {:?}", connection_id, id);
+                    continue;
+                };
+                if league.creator != connection_id || league.round != 0 {
+                    warn!("{:?} can't start league {:?}", connection_id, id);
+                    continue;
+                }
+                if league.players.len() < 2 {
+                    warn!("league {:?} needs a second player to start", id);
+                    continue;
+                }
+                league.start();
+                info!(
+                    "league {:?} started with {} players",
+                    id,
+                    league.players.len()
+                );
+                league.send_schedule(id);
+            }
+            ClientPacket::LeaveQueue => leagues.remove_connection(connection_id),
+            _ => {}
+        }
+    }
+}
+
+/// starts the games of the current round once both players are there, forfeits the players
+/// that don't show up in time, records the results and moves on to the next round
+pub fn run_leagues(
+    mut leagues: ResMut<Leagues>,
+    mut ended: EventReader<EndGameEvent>,
+    mut start_game: EventWriter<StartGameEvent>,
+    games: Query<(&GameId, &Players)>,
+    connection_map: Res<ConnectionMap>,
+    wait: Res<NoShowWait>,
+) {
+    let ended: Vec<_> = ended.read().map(|event| (event.0, event.1)).collect();
+    let now = timestamp();
+    leagues.leagues.retain(|&id, league| {
+        if league.round == 0 {
+            return true;
+        }
+        let mut changed = false;
+        for &(game, end) in &ended {
+            if let Some(index) = league.games.remove(&game) {
+                league.pairings[index].result = Some(LeagueResult::Played(end));
+                changed = true;
+            }
+        }
+        // the games asked for last frame are in the game map now
+        for index in std::mem::take(&mut league.starting) {
+            let pairing = league.pairings[index];
+            match games.iter().find(|(_, players)| {
+                players.white_id == pairing.white && players.black_id == pairing.black
+            }) {
+                Some((&game, _)) => {
+                    league.games.insert(game, index);
+                }
+                None => warn!("league {:?} lost a game it started", id),
+            }
+        }
+        for index in 0..league.pairings.len() {
+            let pairing = league.pairings[index];
+            if pairing.round != league.round
+                || pairing.result.is_some()
+                || league.games.values().any(|&i| i == index)
+            {
+                continue;
+            }
+            let white = league.ready(pairing.white, ChessColor::White, &connection_map);
+            let black = league.ready(pairing.black, ChessColor::Black, &connection_map);
+            match (white, black) {
+                (Some(white), Some(black)) => {
+                    start_game.send(StartGameEvent { white, black });
+                    league.starting.push(index);
+                }
+                (white, black)
+                    if now.saturating_sub(league.round_started) > wait.0.as_millis() as u64 =>
+                {
+                    let present = match (white, black) {
+                        (Some(_), None) => Some(ChessColor::White),
+                        (None, Some(_)) => Some(ChessColor::Black),
+                        _ => None,
+                    };
+                    league.pairings[index].result = Some(LeagueResult::Forfeit(present));
+                    changed = true;
+                }
+                _ => {}
+            }
+        }
+        let round_over = league
+            .pairings
+            .iter()
+            .filter(|p| p.round == league.round)
+            .all(|p| p.result.is_some());
+        if round_over && league.round < league.rounds {
+            league.round += 1;
+            league.round_started = now;
+            changed = true;
+        }
+        if changed {
+            league.send_schedule(id);
+        }
+        // a finished league only lives on in the schedule its players got
+        !(round_over && league.round == league.rounds)
+    });
+}
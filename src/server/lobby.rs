@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_slinet::{connection::ConnectionId, server::PacketReceiveEvent};
+use rand::prelude::*;
+
+use super::{
+    accounts::Accounts, config::GameRules, connection_error, identify, ConnectionMap, PlayerNames,
+    QueuedPlayer, StartGameEvent,
+};
+use crate::api::{
+    handicap::Handicap,
+    matchmaking::{ColorPreference, LobbyCode, Seek, LOBBY_CODE_CHARACTERS},
+    ClientPacket, PlayerId, ServerError, ServerNetConfig, ServerPacket,
+};
+
+/// private games waiting for the player with their code, by code
+#[derive(Resource, Default, Debug)]
+pub struct Lobbies(pub HashMap<LobbyCode, QueuedPlayer>);
+
+impl Lobbies {
+    /// a code no open lobby has
+    fn new_code(&self) -> LobbyCode {
+        let mut rng = thread_rng();
+        loop {
+            let code = LobbyCode(
+                [(); 6]
+                    .map(|_| LOBBY_CODE_CHARACTERS[rng.gen_range(0..LOBBY_CODE_CHARACTERS.len())]),
+            );
+            if !self.0.contains_key(&code) {
+                return code;
+            }
+        }
+    }
+
+    /// closes the lobby a connection opened
+    pub fn remove_connection(&mut self, connection_id: ConnectionId) {
+        self.0
+            .retain(|_, host| host.connection.id() != connection_id);
+    }
+}
+
+/// opens lobbies and starts their game when someone joins with the code
+pub fn lobbies(
+    mut packets: EventReader<PacketReceiveEvent<ServerNetConfig>>,
+    mut lobbies: ResMut<Lobbies>,
+    mut start_game: EventWriter<StartGameEvent>,
+    connection_map: Res<ConnectionMap>,
+    accounts: Res<Accounts>,
+    names: Res<PlayerNames>,
+    mut next_player: ResMut<PlayerId>,
+    rules: Res<GameRules>,
+) {
+    for packet in packets.read() {
+        let id = packet.connection.id();
+        match packet.packet {
+            ClientPacket::CreateLobby {
+                handicap,
+                variant,
+                time_control,
+                color,
+            } => {
+                // friends don't change each others ratings
+                let seek = Seek {
+                    variant,
+                    time_control,
+                    time_odds: None,
+                    rated: false,
+                };
+                if connection_map.0.contains_key(&id) {
+                    warn!("{:?} opened a lobby during a game", id);
+                    continue;
+                }
+                if !seek.is_valid() {
+                    warn!("{:?} opened a lobby for a game that can't exist", id);
+                    continue;
+                }
+                if !rules.allows(&seek) {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(ServerError::TimeControlNotAllowed))
+                        .unwrap_or_else(connection_error);
+                    continue;
+                }
+                // a new lobby replaces the one opened before
+                lobbies.remove_connection(id);
+                let (player, name) = identify(id, &accounts, &names, &mut next_player);
+                let code = lobbies.new_code();
+                info!("{} opened lobby {}", name, code);
+                lobbies.0.insert(
+                    code,
+                    QueuedPlayer {
+                        connection: packet.connection.clone(),
+                        id: player,
+                        name,
+                        handicap,
+                        seek,
+                        color,
+                    },
+                );
+                packet
+                    .connection
+                    .send(ServerPacket::LobbyCreated(code))
+                    .unwrap_or_else(connection_error);
+            }
+            ClientPacket::JoinLobby(code) => {
+                if connection_map.0.contains_key(&id) {
+                    warn!("{:?} joined a lobby during a game", id);
+                    continue;
+                }
+                if lobbies
+                    .0
+                    .get(&code)
+                    .is_some_and(|host| host.connection.id() == id)
+                {
+                    warn!("{:?} tried to join their own lobby", id);
+                    continue;
+                }
+                // a host that got a game from the queue in the meantime can't play this one
+                let Some(host) = lobbies
+                    .0
+                    .remove(&code)
+                    .filter(|host| !connection_map.0.contains_key(&host.connection.id()))
+                else {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(ServerError::NoSuchLobby))
+                        .unwrap_or_else(connection_error);
+                    continue;
+                };
+                let (player, name) = identify(id, &accounts, &names, &mut next_player);
+                let guest = QueuedPlayer {
+                    connection: packet.connection.clone(),
+                    id: player,
+                    name,
+                    handicap: Handicap::None,
+                    seek: host.seek,
+                    color: ColorPreference::Random,
+                };
+                // the host picked the colors, or left them to chance
+                start_game.send(StartGameEvent::seat(host, guest));
+            }
+            ClientPacket::LeaveQueue => lobbies.remove_connection(id),
+            _ => {}
+        }
+    }
+}
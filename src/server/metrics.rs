@@ -0,0 +1,112 @@
+//! counters for the /metrics page of the http server, in the prometheus text format
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+/// moves the server accepted
+pub static MOVES: AtomicU64 = AtomicU64::new(0);
+/// illegal moves and moves out of turn
+pub static REJECTED_MOVES: AtomicU64 = AtomicU64::new(0);
+/// packets dropped because a connection sent them too fast
+pub static RATE_LIMITED: AtomicU64 = AtomicU64::new(0);
+/// packets that couldn't be sent to a connection
+pub static SEND_ERRORS: AtomicU64 = AtomicU64::new(0);
+
+/// upper bounds of the game duration histogram, in seconds
+const DURATION_BUCKETS: [u64; 7] = [60, 180, 600, 1800, 3600, 4 * 3600, 24 * 3600];
+static DURATIONS: [AtomicU64; DURATION_BUCKETS.len()] =
+    [const { AtomicU64::new(0) }; DURATION_BUCKETS.len()];
+/// seconds
+static DURATION_SUM: AtomicU64 = AtomicU64::new(0);
+static GAMES_FINISHED: AtomicU64 = AtomicU64::new(0);
+
+pub fn count(counter: &AtomicU64) {
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+/// adds a finished game to the duration histogram
+pub fn record_game(duration: Duration) {
+    let seconds = duration.as_secs();
+    for (bucket, &bound) in DURATIONS.iter().zip(&DURATION_BUCKETS) {
+        if seconds <= bound {
+            count(bucket);
+        }
+    }
+    DURATION_SUM.fetch_add(seconds, Ordering::Relaxed);
+    count(&GAMES_FINISHED);
+}
+
+/// everything the server knows about itself, for prometheus to scrape
+#[cfg(feature = "http")]
+pub fn render(connections: u32, games: u32, queue_length: u32, uptime: u64) -> String {
+    let value = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+    let mut text = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, value: u64| {
+        text.push_str(&format!(
+            "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n"
+        ));
+    };
+    metric(
+        "chess_connections",
+        "gauge",
+        "connected clients",
+        connections as u64,
+    );
+    metric("chess_games_active", "gauge", "running games", games as u64);
+    metric(
+        "chess_queue_length",
+        "gauge",
+        "players waiting for a game",
+        queue_length as u64,
+    );
+    metric(
+        "chess_uptime_seconds",
+        "gauge",
+        "time since the start",
+        uptime,
+    );
+    metric(
+        "chess_moves_total",
+        "counter",
+        "moves played",
+        value(&MOVES),
+    );
+    metric(
+        "chess_rejected_moves_total",
+        "counter",
+        "illegal moves and moves out of turn",
+        value(&REJECTED_MOVES),
+    );
+    metric(
+        "chess_rate_limited_packets_total",
+        "counter",
+        "packets dropped for coming too fast",
+        value(&RATE_LIMITED),
+    );
+    metric(
+        "chess_send_errors_total",
+        "counter",
+        "packets that couldn't be sent",
+        value(&SEND_ERRORS),
+    );
+    text.push_str(
+        "# HELP chess_game_duration_seconds how long finished games took\n\
+         # TYPE chess_game_duration_seconds histogram\n",
+    );
+    for (bucket, bound) in DURATIONS.iter().zip(DURATION_BUCKETS) {
+        text.push_str(&format!(
+            "chess_game_duration_seconds_bucket{{le=\"{bound}\"}} {}\n",
+            value(bucket)
+        ));
+    }
+    let finished = value(&GAMES_FINISHED);
+    text.push_str(&format!(
+        "chess_game_duration_seconds_bucket{{le=\"+Inf\"}} {finished}\n\
+         chess_game_duration_seconds_sum {}\n\
+         chess_game_duration_seconds_count {finished}\n",
+        value(&DURATION_SUM)
+    ));
+    text
+}
@@ -1,190 +1,1336 @@
-use std::{collections::HashMap, fmt, net::SocketAddr};
+use std::{collections::HashMap, fmt, net::SocketAddr, path::PathBuf, time::Duration};
 
-use bevy::prelude::*;
+use bevy::{
+    ecs::{query::QueryData, system::SystemParam},
+    log::LogPlugin,
+    prelude::*,
+};
 use bevy_slinet::{
     connection::{ConnectionId, EcsConnection},
-    server::{DisconnectionEvent, NewConnectionEvent, PacketReceiveEvent, ServerPlugin},
+    server::{
+        DisconnectionEvent, NewConnectionEvent, PacketReceiveEvent, ServerConnections, ServerPlugin,
+    },
 };
 
 use rand::prelude::*;
 
 use crate::api::{
-    chessmove::{compress_chessboard, ChessColor, CompressedChessboard},
-    chessstate::ChessState,
-    ClientPacket, Config, EndReason, GameEnd, ServerPacket,
+    chessmove::{compress_chessboard, ChessColor, ChessMove, CompressedChessboard, PlayedMove},
+    chessstate::{ChessState, InvalidMoveError},
+    clock::Clock,
+    handicap::{starting_position, Handicap},
+    matchmaking::{ColorPreference, Seek, TimeControl},
+    openings,
+    ping::{round_trip, timestamp, PingTimer},
+    ClientPacket, EndReason, GameEnd, GameId, GameToken, PlayerId, PlayerInfo, Rating, ServerError,
+    ServerNetConfig, ServerPacket, DRAW_OFFER_INTERVAL, MAX_CHAT_LENGTH,
 };
+pub use config::{bind_address, ConfigFile, DEFAULT_BIND, DEFAULT_PORT};
+use queue::GameQueue;
+
+mod accounts;
+mod adjudicate;
+mod admin;
+mod analysis;
+mod announcements;
+mod archive;
+mod bots;
+mod challenge;
+mod clocks;
+mod config;
+mod correspondence;
+#[cfg(feature = "sqlite")]
+mod database;
+mod hints;
+mod history;
+#[cfg(feature = "http")]
+mod http;
+mod league;
+mod lobby;
+mod metrics;
+mod persist;
+mod ping;
+mod puzzles;
+mod queue;
+mod ratelimit;
+mod ratings;
+mod reconnect;
+mod rematch;
+mod seeks;
+mod shutdown;
+mod simul;
+pub mod simulate;
+mod spectate;
+mod stats;
+mod storage;
+#[cfg(feature = "http")]
+mod webhooks;
+
+/// how the server runs, the defaults remember nothing after it stops
+#[derive(Debug, Clone)]
+pub struct ServerOptions {
+    /// greets every player that connects
+    pub motd: Option<String>,
+    /// the sqlite database to keep everything in
+    pub database: Option<String>,
+    /// the directory every finished game gets appended to as pgn
+    pub pgn_archive: Option<PathBuf>,
+    /// how long a disconnected player gets to come back before losing
+    pub grace_period: Duration,
+    /// stops the clock of a disconnected player until they come back
+    pub pause_clock: bool,
+    /// how long a league game waits for its players before they forfeit
+    pub league_no_show: Duration,
+    /// the games the server is willing to host
+    pub rules: config::GameRules,
+    /// the config file to read again on /reload
+    pub config: Option<PathBuf>,
+    /// the address to serve the http status pages on
+    pub http: Option<String>,
+    /// the urls that get json posted to them when a game starts or ends
+    pub webhooks: Vec<String>,
+}
 
-pub fn start_server(addr: SocketAddr) {
-    App::new()
+impl Default for ServerOptions {
+    fn default() -> Self {
+        Self {
+            motd: None,
+            database: None,
+            pgn_archive: None,
+            grace_period: reconnect::GRACE_PERIOD,
+            pause_clock: false,
+            league_no_show: league::NO_SHOW_WAIT,
+            rules: config::GameRules::default(),
+            config: None,
+            http: None,
+            webhooks: Vec::new(),
+        }
+    }
+}
+
+pub fn start_server(addr: SocketAddr, options: ServerOptions) {
+    let store = storage::Store::open(options.database.as_deref());
+    // players keep their ids, new ones count on from the highest the server handed out
+    let next_player = store.next_player_id().expect("couldn't read the accounts");
+    let puzzles = store.puzzles().expect("couldn't read the puzzles");
+    let games = persist::restore_games(&store);
+    let next_player = games
+        .values()
+        .flat_map(|game| [game.players.white_id, game.players.black_id])
+        .map(|id| PlayerId(id.0 + 1))
+        .fold(next_player, PlayerId::max);
+    // new games get ids the restored ones don't have
+    let next_game = games.keys().max().map_or(GameId(0), |id| GameId(id.0 + 1));
+    let mut app = App::new();
+    app.init_resource::<GameEntities>();
+    GameEntities::register_hooks(app.world_mut());
+    for (id, game) in games {
+        app.world_mut().spawn((id, game));
+    }
+    app.insert_resource(announcements::Motd(options.motd))
+        .init_resource::<announcements::ConsoleInput>()
         .init_resource::<ConnectionMap>()
         .init_resource::<GameQueue>()
-        .init_resource::<ChessGameMap>()
-        .init_resource::<GameId>()
+        .init_resource::<queue::RecentOpponents>()
+        .init_resource::<queue::QueueOpen>()
+        .init_resource::<admin::Bans>()
+        .insert_resource(next_game)
+        .insert_resource(next_player)
+        .init_resource::<PlayerNames>()
+        .init_resource::<accounts::Accounts>()
+        .insert_resource(store)
+        .insert_resource(archive::PgnArchive(options.pgn_archive))
+        .insert_resource(reconnect::GracePeriod {
+            duration: options.grace_period,
+            pause_clock: options.pause_clock,
+        })
+        .init_resource::<challenge::Challenges>()
+        .init_resource::<lobby::Lobbies>()
+        .init_resource::<seeks::Seeks>()
+        .init_resource::<league::Leagues>()
+        .init_resource::<simul::Simuls>()
+        .insert_resource(league::NoShowWait(options.league_no_show))
+        .insert_resource(options.rules)
+        .insert_resource(config::ConfigPath(options.config))
+        .insert_resource(shutdown::Shutdown::new(options.database.is_some()))
+        .insert_resource(puzzles::PuzzlePool(puzzles))
+        .init_resource::<puzzles::PuzzleTasks>()
+        .init_resource::<analysis::AnalysisTasks>()
+        .init_resource::<hints::Hints>()
+        .init_resource::<OpeningStats>()
+        .init_resource::<rematch::FinishedGames>()
+        .init_resource::<ping::Latencies>()
+        .init_resource::<ping::LastSeen>()
+        .init_resource::<ratelimit::RateLimits>()
+        .init_resource::<PingTimer>()
+        .init_resource::<queue::StatusTimer>()
+        .init_resource::<bots::Bots>()
+        .init_resource::<bots::Engines>()
+        .init_resource::<persist::SaveTimer>()
         .add_event::<EndGameEvent>()
+        .add_event::<StartGameEvent>()
+        .add_event::<announcements::AnnouncementEvent>()
+        .add_event::<config::ReloadConfigEvent>()
+        .add_event::<admin::AdminCommand>()
         .add_plugins(MinimalPlugins)
-        .add_plugins(ServerPlugin::<Config>::bind(addr))
+        // RUST_LOG=debug also shows every move
+        .add_plugins(LogPlugin::default())
+        .add_plugins(ServerPlugin::<ServerNetConfig>::bind(addr))
+        // flooded packets are dropped before anything reads them
+        .add_systems(Update, ratelimit::filter_packets)
         .add_systems(
             Update,
             (
                 create_game,
+                start_game,
                 new_connection_system,
                 receive_packet,
+                accounts::logins.before(receive_packet),
                 disconnect,
                 end_game,
+                puzzles::collect_puzzles,
+                analysis::send_analysis,
+                ping::send_pings,
+                ping::track_activity,
+                ping::reap_dead_connections.after(ping::track_activity),
+                announcements::read_console,
+                announcements::broadcast.after(announcements::read_console),
+                queue::send_queue_status.after(create_game),
+                clocks::flag_games.before(end_game),
+                clocks::expire_draw_offers.after(receive_packet),
+                persist::save_games.after(end_game),
+                spectate::remove_spectators,
+                league::run_leagues.before(start_game),
+            )
+                .after(ratelimit::filter_packets),
+        )
+        // packets answered outside of receive_packet
+        .add_systems(
+            Update,
+            (
+                stats::send_stats,
+                accounts::finish_logins,
+                history::send_history,
+                analysis::fetch_analysis,
+                hints::hints,
+                lobby::lobbies,
+                seeks::seeks,
+                league::leagues,
+                simul::simuls.before(start_game),
+                simul::run_simuls.before(simul::simuls),
+                queue::join_queue,
+            )
+                .after(ratelimit::filter_packets),
+        )
+        // ending games nobody plays or can win
+        .add_systems(
+            Update,
+            (
+                reconnect::forfeit_abandoned_games.before(end_game),
+                reconnect::abort_stale_games.before(end_game),
+                adjudicate::adjudicate_games.before(end_game),
+                clocks::abort_unstarted_games.before(end_game),
             ),
         )
-        .run();
+        // the server's own bot
+        .add_systems(
+            Update,
+            (
+                bots::fill_queue
+                    .after(queue::join_queue)
+                    .before(create_game)
+                    .before(start_game),
+                bots::play_bots.before(end_game),
+                bots::engines.before(queue::join_queue).before(start_game),
+            )
+                .after(ratelimit::filter_packets),
+        )
+        // the server console and shutting down
+        .add_systems(
+            Update,
+            (
+                admin::run_commands
+                    .after(announcements::read_console)
+                    .before(announcements::broadcast)
+                    .before(end_game),
+                config::reload_config.after(admin::run_commands),
+                shutdown::begin_shutdown
+                    .after(admin::run_commands)
+                    .before(start_game)
+                    .before(end_game),
+                shutdown::finish_shutdown.after(end_game),
+            ),
+        );
+    match options.http {
+        #[cfg(feature = "http")]
+        Some(address) => {
+            app.insert_resource(http::HttpRequests::bind(&address))
+                .add_systems(Update, http::answer_http);
+        }
+        #[cfg(not(feature = "http"))]
+        Some(_) => panic!("the server needs the http feature for --http"),
+        None => {}
+    }
+    if !options.webhooks.is_empty() {
+        #[cfg(feature = "http")]
+        app.insert_resource(webhooks::Webhooks::new(options.webhooks))
+            .add_systems(Update, webhooks::post_starts.before(end_game));
+        #[cfg(not(feature = "http"))]
+        panic!("the server needs the http feature for webhooks");
+    }
+    app.run();
 }
 
 #[derive(Event)]
 pub struct EndGameEvent(GameId, GameEnd);
 
+#[derive(Event)]
+pub struct StartGameEvent {
+    pub white: QueuedPlayer,
+    pub black: QueuedPlayer,
+}
+
+impl StartGameEvent {
+    /// honors the color preferences, randomizes color when neither cares
+    pub fn seat(a: QueuedPlayer, b: QueuedPlayer) -> Self {
+        let swap = match a.color.resolve(b.color) {
+            Some(color) => color == ChessColor::Black,
+            None => thread_rng().gen_bool(0.5),
+        };
+        if swap {
+            Self { white: b, black: a }
+        } else {
+            Self { white: a, black: b }
+        }
+    }
+}
+
+/// the games every connection plays, a simul host plays many at once
 #[derive(Resource, Default, Debug)]
-pub struct ConnectionMap(pub HashMap<ConnectionId, GameId>);
+pub struct ConnectionMap(pub HashMap<ConnectionId, Vec<GameId>>);
+
+impl ConnectionMap {
+    pub fn insert(&mut self, connection_id: ConnectionId, game: GameId) {
+        self.0.entry(connection_id).or_default().push(game);
+    }
+
+    /// takes the connection out of the game, it keeps playing its other games
+    pub fn remove(&mut self, connection_id: ConnectionId, game: GameId) {
+        let Some(games) = self.0.get_mut(&connection_id) else {
+            return;
+        };
+        games.retain(|&g| g != game);
+        if games.is_empty() {
+            self.0.remove(&connection_id);
+        }
+    }
+}
 
+/// the entity of every running game, the hooks of GameId keep it in step with the entities,
+/// so a game is in here exactly when it can be queried
 #[derive(Resource, Default, Debug)]
-pub struct ChessGameMap(pub HashMap<GameId, Game>);
+pub struct GameEntities(HashMap<GameId, Entity>);
 
-#[derive(Resource, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default, Debug)]
-pub struct GameId(u32);
+impl GameEntities {
+    fn register_hooks(world: &mut World) {
+        world
+            .register_component_hooks::<GameId>()
+            .on_add(|mut world, entity, _| {
+                let id = *world.get::<GameId>(entity).expect("the game has its id");
+                world.resource_mut::<GameEntities>().0.insert(id, entity);
+            })
+            .on_remove(|mut world, entity, _| {
+                let id = *world.get::<GameId>(entity).expect("the game has its id");
+                world.resource_mut::<GameEntities>().0.remove(&id);
+            });
+    }
+}
+
+/// every part of a running game, for the systems that need all of them, systems that only
+/// need a few query those on their own
+#[derive(QueryData)]
+#[query_data(mutable)]
+pub struct RunningGame {
+    pub id: &'static GameId,
+    pub players: &'static mut Players,
+    pub board: &'static mut Board,
+    pub clock: &'static mut GameClock,
+    pub spectators: &'static mut Spectators,
+}
+
+/// the running games for systems that start, end or change them, started and ended games
+/// only show up after the commands ran
+#[derive(SystemParam)]
+pub struct ChessGameMap<'w, 's> {
+    entities: Res<'w, GameEntities>,
+    games: Query<'w, 's, RunningGame>,
+    commands: Commands<'w, 's>,
+}
+
+impl ChessGameMap<'_, '_> {
+    pub fn len(&self) -> usize {
+        self.entities.0.len()
+    }
+
+    pub fn contains_key(&self, id: &GameId) -> bool {
+        self.entities.0.contains_key(id)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = RunningGameReadOnlyItem<'_>> {
+        self.games.iter()
+    }
+
+    pub fn get_mut(&mut self, id: &GameId) -> Option<RunningGameItem<'_>> {
+        self.games.get_mut(*self.entities.0.get(id)?).ok()
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = RunningGameItem<'_>> {
+        self.games.iter_mut()
+    }
 
+    pub fn insert(&mut self, id: GameId, game: Game) {
+        self.commands.spawn((id, game));
+    }
+
+    pub fn remove(&mut self, id: &GameId) {
+        if let Some(&entity) = self.entities.0.get(id) {
+            self.commands.entity(entity).despawn();
+        }
+    }
+}
+
+/// the running games for systems that only look at them
+#[derive(SystemParam)]
+pub struct ChessGames<'w, 's> {
+    entities: Res<'w, GameEntities>,
+    games: Query<'w, 's, RunningGameReadOnly>,
+}
+
+impl ChessGames<'_, '_> {
+    pub fn len(&self) -> usize {
+        self.entities.0.len()
+    }
+
+    pub fn contains_key(&self, id: &GameId) -> bool {
+        self.entities.0.contains_key(id)
+    }
+
+    pub fn get(&self, id: &GameId) -> Option<RunningGameReadOnlyItem<'_>> {
+        self.games.get(*self.entities.0.get(id)?).ok()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = RunningGameReadOnlyItem<'_>> {
+        self.games.iter()
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &GameId> {
+        self.entities.0.keys()
+    }
+
+    /// whether games started or ended since the system last ran
+    pub fn is_changed(&self) -> bool {
+        self.entities.is_changed()
+    }
+}
+
+/// how often every opening was played on this server
 #[derive(Resource, Default, Debug)]
-pub struct GameQueue(pub Vec<EcsConnection<ServerPacket>>);
+pub struct OpeningStats(pub HashMap<&'static str, u32>);
 
-#[derive(Resource, Debug)]
-pub struct Game {
-    pub white: EcsConnection<ServerPacket>,
-    pub black: EcsConnection<ServerPacket>,
+impl OpeningStats {
+    pub fn most_played(&self) -> Option<&'static str> {
+        self.0
+            .iter()
+            .max_by_key(|&(name, count)| (count, std::cmp::Reverse(name)))
+            .map(|(name, _)| *name)
+    }
+}
+
+/// the names connections said hello with
+#[derive(Resource, Default, Debug)]
+pub struct PlayerNames(pub HashMap<ConnectionId, String>);
+
+#[derive(Debug, Clone)]
+pub struct QueuedPlayer {
+    pub connection: EcsConnection<ServerPacket>,
+    pub id: PlayerId,
+    pub name: String,
+    pub handicap: Handicap,
+    pub seek: Seek,
+    pub color: ColorPreference,
+}
+
+/// who plays a game and what they were looking for
+#[derive(Component, Debug)]
+pub struct Players {
+    /// None while the player hasn't come back to a game restored after a restart
+    pub white: Option<EcsConnection<ServerPacket>>,
+    pub black: Option<EcsConnection<ServerPacket>>,
+    pub white_id: PlayerId,
+    pub black_id: PlayerId,
+    pub white_name: String,
+    pub black_name: String,
+    pub white_handicap: Handicap,
+    pub black_handicap: Handicap,
+    /// what white was looking for, black's seek mirrors it
+    pub seek: Seek,
+    /// the keys white and black need to resume the game from a new connection
+    pub rejoin_keys: [u64; 2],
+    /// since when white's and black's seats are empty, in milliseconds since the unix epoch
+    pub vacated: [Option<u64>; 2],
+    /// white's and black's rating when the game started
+    pub ratings: [Rating; 2],
+    /// the seat the server's bot plays, it never has a connection
+    pub bot: Option<ChessColor>,
+    /// whether white and black are engines that said hello as a bot
+    pub engines: [bool; 2],
+}
+
+/// the position, the moves that led to it and what the players offered each other
+#[derive(Component, Debug)]
+pub struct Board {
     pub state: ChessState,
+    /// the position before the first move, for rewinding after a takeback
+    pub start: ChessState,
     pub draw: Option<ChessColor>,
+    /// when the draw was offered, in milliseconds since the unix epoch
+    pub draw_offered: u64,
+    /// the ply of white's and black's last draw offer
+    pub draw_offers: [Option<u32>; 2],
+    /// the player who asked to take back their last move
+    pub takeback: Option<ChessColor>,
     pub move_history: Vec<CompressedChessboard>,
+    pub moves: Vec<PlayedMove>,
+    /// milliseconds since the unix epoch
+    pub started: u64,
+    /// when the last move was played or taken back, or the game started, in milliseconds since
+    /// the unix epoch
+    pub last_move: u64,
+}
+
+/// None for games without a time control
+#[derive(Component, Debug, Clone, Copy)]
+pub struct GameClock(pub Option<Clock>);
+
+/// connections watching the game, they can't do anything in it
+#[derive(Component, Debug, Default)]
+pub struct Spectators(pub Vec<EcsConnection<ServerPacket>>);
+
+/// a game before it is spawned, every part of it becomes its own component
+#[derive(Bundle, Debug)]
+pub struct Game {
+    pub players: Players,
+    pub board: Board,
+    pub clock: GameClock,
+    pub spectators: Spectators,
 }
 
 impl Game {
-    pub fn new(white: EcsConnection<ServerPacket>, black: EcsConnection<ServerPacket>) -> Self {
+    pub fn new(white: QueuedPlayer, black: QueuedPlayer, ratings: [Rating; 2]) -> Self {
         Self {
-            white,
-            black,
-            state: default(),
-            draw: None,
-            move_history: Vec::new(),
+            // every player gives their own odds
+            board: Board::new(starting_position(white.handicap, black.handicap)),
+            clock: GameClock(
+                white
+                    .seek
+                    .time_control
+                    .zip(black.seek.time_control)
+                    .map(|(white, black)| Clock::new(white, black, timestamp())),
+            ),
+            players: Players {
+                white: Some(white.connection),
+                black: Some(black.connection),
+                white_id: white.id,
+                black_id: black.id,
+                white_name: white.name,
+                black_name: black.name,
+                white_handicap: white.handicap,
+                black_handicap: black.handicap,
+                seek: white.seek,
+                rejoin_keys: thread_rng().gen(),
+                vacated: [None; 2],
+                ratings,
+                bot: None,
+                engines: [false; 2],
+            },
+            spectators: Spectators::default(),
         }
     }
+}
 
-    /// sends a packet to the opponent
+impl RunningGameItem<'_> {
+    /// plays a move for whoever's turn it is and tells their opponent and the spectators,
+    /// Ok with how the game ended if the move ended it
+    pub fn play(
+        &mut self,
+        player_move: ChessMove,
+        round_trip: Duration,
+    ) -> Result<Option<GameEnd>, InvalidMoveError> {
+        let id = *self.id;
+        let board = &mut *self.board;
+        let mover = board.state.turn;
+        let ply = board.ply();
+        board.state.move_piece(player_move)?;
+        // an offer stands until the player it was made to moves
+        if board.draw == Some(!mover) {
+            board.expire_draw(id, &self.players);
+        }
+        board.takeback = None;
+        board.last_move = timestamp();
+        let time_control = self.players.time_control(mover);
+        if let (Some(clock), Some(time_control)) = (&mut self.clock.0, time_control) {
+            clock.charge(mover, time_control, timestamp(), round_trip);
+        }
+        let packet = ServerPacket::Move(id, ply, player_move, self.clock.0);
+        if let Some(opponent) = self.players.connection(!mover) {
+            opponent
+                .send(packet.clone())
+                .unwrap_or_else(connection_error);
+        }
+        self.spectators.send(packet);
+        board
+            .move_history
+            .push(compress_chessboard(&board.state.board));
+        board.moves.push(player_move.into());
+        // the game goes on until the pawn is promoted
+        if board.state.should_promote {
+            return Ok(None);
+        }
+        self.players.send_engines(id, board, self.clock.0);
+        Ok(board.state.check_game_end(&board.move_history))
+    }
+}
+
+impl Players {
+    /// the players as they were queued, for starting a rematch, None if a seat is empty
+    pub fn players(&self) -> Option<(QueuedPlayer, QueuedPlayer)> {
+        Some((
+            QueuedPlayer {
+                connection: self.white.clone()?,
+                id: self.white_id,
+                name: self.white_name.clone(),
+                handicap: self.white_handicap,
+                seek: self.seek,
+                color: ColorPreference::Random,
+            },
+            QueuedPlayer {
+                connection: self.black.clone()?,
+                id: self.black_id,
+                name: self.black_name.clone(),
+                handicap: self.black_handicap,
+                seek: self.seek.mirrored(),
+                color: ColorPreference::Random,
+            },
+        ))
+    }
+
+    pub fn player(&self, color: ChessColor) -> PlayerInfo {
+        let bot = self.is_bot(color);
+        match color {
+            ChessColor::White => PlayerInfo {
+                id: self.white_id,
+                name: self.white_name.clone(),
+                // the server's bot has no rating to show
+                rating: Some(self.ratings[0]).filter(|_| self.bot != Some(color)),
+                bot,
+            },
+            ChessColor::Black => PlayerInfo {
+                id: self.black_id,
+                name: self.black_name.clone(),
+                rating: Some(self.ratings[1]).filter(|_| self.bot != Some(color)),
+                bot,
+            },
+        }
+    }
+
+    /// whether the result counts for the ratings, odds would make it meaningless
+    pub fn is_rated(&self) -> bool {
+        self.seek.rated
+            && self.white_handicap == Handicap::None
+            && self.black_handicap == Handicap::None
+    }
+
+    /// whether the server's bot or an engine plays `color`
+    pub fn is_bot(&self, color: ChessColor) -> bool {
+        self.bot == Some(color)
+            || match color {
+                ChessColor::White => self.engines[0],
+                ChessColor::Black => self.engines[1],
+            }
+    }
+
+    /// sends the engines in the game every move so far and the clock
+    pub fn send_engines(&self, id: GameId, board: &Board, clock: Option<Clock>) {
+        for color in [ChessColor::White, ChessColor::Black] {
+            let Some(connection) = self.connection(color).filter(|_| self.is_bot(color)) else {
+                continue;
+            };
+            connection
+                .send(ServerPacket::Position {
+                    game: id,
+                    moves: board.moves.clone(),
+                    clock,
+                })
+                .unwrap_or_else(connection_error);
+        }
+    }
+
+    /// the clock `color` plays with, black's differs from white's with time odds
+    pub fn time_control(&self, color: ChessColor) -> Option<TimeControl> {
+        match color {
+            ChessColor::White => self.seek.time_control,
+            ChessColor::Black => self.seek.mirrored().time_control,
+        }
+    }
+
+    /// correspondence games go on while the players are offline
+    pub fn is_correspondence(&self) -> bool {
+        self.seek
+            .time_control
+            .is_some_and(|time_control| time_control.is_correspondence())
+    }
+
+    pub fn rejoin_key(&self, color: ChessColor) -> u64 {
+        match color {
+            ChessColor::White => self.rejoin_keys[0],
+            ChessColor::Black => self.rejoin_keys[1],
+        }
+    }
+
+    pub fn vacated(&self, color: ChessColor) -> Option<u64> {
+        match color {
+            ChessColor::White => self.vacated[0],
+            ChessColor::Black => self.vacated[1],
+        }
+    }
+
+    pub fn set_vacated(&mut self, color: ChessColor, since: Option<u64>) {
+        match color {
+            ChessColor::White => self.vacated[0] = since,
+            ChessColor::Black => self.vacated[1] = since,
+        }
+    }
+
+    /// the connection in the seat of `color`
+    pub fn connection(&self, color: ChessColor) -> Option<&EcsConnection<ServerPacket>> {
+        match color {
+            ChessColor::White => self.white.as_ref(),
+            ChessColor::Black => self.black.as_ref(),
+        }
+    }
+
+    pub fn color_of(&self, connection_id: ConnectionId) -> Option<ChessColor> {
+        [ChessColor::White, ChessColor::Black]
+            .into_iter()
+            .find(|&color| self.connection(color).map(|c| c.id()) == Some(connection_id))
+    }
+
+    /// sends a packet to the opponent, if they are there
     pub fn send_opponent(&self, connection_id: ConnectionId, packet: ServerPacket) {
-        if self.white.id() == connection_id {
-            &self.black
-        } else if self.black.id() == connection_id {
-            &self.white
-        } else {
+        let Some(color) = self.color_of(connection_id) else {
             return warn!("connection not in this game");
+        };
+        if let Some(opponent) = self.connection(!color) {
+            opponent.send(packet).unwrap_or_else(connection_error);
+        }
+    }
+
+    /// sends a packet to both players
+    pub fn send_players(&self, packet: ServerPacket) {
+        for connection in self.white.iter().chain(&self.black) {
+            connection
+                .send(packet.clone())
+                .unwrap_or_else(connection_error);
         }
-        .send(packet)
-        .unwrap_or_else(connection_error);
     }
 }
 
+impl Board {
+    /// a board nobody moved on yet
+    pub fn new(start: ChessState) -> Self {
+        Self {
+            state: start,
+            start,
+            draw: None,
+            draw_offered: 0,
+            draw_offers: [None; 2],
+            takeback: None,
+            move_history: Vec::new(),
+            moves: Vec::new(),
+            started: timestamp(),
+            last_move: timestamp(),
+        }
+    }
+
+    /// how many moves were played
+    pub fn ply(&self) -> u32 {
+        self.moves.len() as u32
+    }
+
+    /// the moves a client that is sure about the game up to ply `since` needs to catch up
+    pub fn resync(&self, id: GameId, since: u32, clock: Option<Clock>) -> ServerPacket {
+        let since = since.min(self.ply());
+        ServerPacket::Resync {
+            game: id,
+            since,
+            moves: self.moves[since as usize..].to_vec(),
+            clock,
+        }
+    }
+
+    /// what a client that sent a move for ply `ply` needs to catch up, nothing if it sent the
+    /// move the game is waiting for
+    pub fn catch_up(&self, id: GameId, ply: u32, clock: Option<Clock>) -> Option<ServerPacket> {
+        (ply != self.ply()).then(|| self.resync(id, ply, clock))
+    }
+
+    /// takes back every move from ply `ply` on
+    pub fn rewind(&mut self, ply: u32) {
+        self.moves.truncate(ply as usize);
+        self.move_history.truncate(ply as usize);
+        self.last_move = timestamp();
+        self.state = self.start;
+        for &played_move in &self.moves {
+            if self.state.play_move(played_move).is_err() {
+                return warn!("couldn't replay {} while rewinding", played_move);
+            }
+        }
+    }
+
+    /// whether `color` waited long enough since their last draw offer to make another one
+    pub fn can_offer_draw(&self, color: ChessColor) -> bool {
+        let last = match color {
+            ChessColor::White => self.draw_offers[0],
+            ChessColor::Black => self.draw_offers[1],
+        };
+        last.is_none_or(|ply| self.ply() >= ply + DRAW_OFFER_INTERVAL)
+    }
+
+    /// `color` offers their opponent a draw
+    pub fn offer_draw(&mut self, id: GameId, color: ChessColor, players: &Players) {
+        self.draw = Some(color);
+        self.draw_offered = timestamp();
+        match color {
+            ChessColor::White => self.draw_offers[0] = Some(self.ply()),
+            ChessColor::Black => self.draw_offers[1] = Some(self.ply()),
+        }
+        if let Some(opponent) = players.connection(!color) {
+            opponent
+                .send(ServerPacket::DrawRequested(id))
+                .unwrap_or_else(connection_error);
+        }
+    }
+
+    /// takes the draw offer off the table and tells both players
+    pub fn expire_draw(&mut self, id: GameId, players: &Players) {
+        self.draw = None;
+        players.send_players(ServerPacket::DrawExpired(id));
+    }
+
+    /// the ply of the last move `color` played, white plays the even plies
+    pub fn last_move_of(&self, color: ChessColor) -> Option<u32> {
+        (0..self.ply())
+            .rev()
+            .find(|ply| (ply % 2 == 0) == (color == ChessColor::White))
+    }
+}
+
+impl Spectators {
+    pub fn send(&self, packet: ServerPacket) {
+        for spectator in &self.0 {
+            spectator
+                .send(packet.clone())
+                .unwrap_or_else(connection_error);
+        }
+    }
+
+    /// tells the players how many people are watching
+    pub fn send_count(&self, id: GameId, players: &Players) {
+        players.send_players(ServerPacket::Spectators(id, self.0.len() as u32));
+    }
+}
+
+/// the id and name a connection plays with, anonymous players get a new id every time
+fn identify(
+    connection: ConnectionId,
+    accounts: &accounts::Accounts,
+    names: &PlayerNames,
+    next_player: &mut PlayerId,
+) -> (PlayerId, String) {
+    let (player, name) = match accounts.account(connection) {
+        // a registered account keeps its name, even after saying hello with another one
+        Some(account) if account.registered => return (account.id, account.name.clone()),
+        Some(account) => (account.id, account.name.clone()),
+        None => {
+            let player = *next_player;
+            next_player.0 += 1;
+            (player, format!("player {}", player.0))
+        }
+    };
+    (player, names.0.get(&connection).cloned().unwrap_or(name))
+}
+
+/// the name a connection said hello with or logged in as
+fn name_of(
+    connection: ConnectionId,
+    accounts: &accounts::Accounts,
+    names: &PlayerNames,
+) -> Option<String> {
+    let account = accounts.account(connection);
+    if let Some(account) = account.filter(|account| account.registered) {
+        return Some(account.name.clone());
+    }
+    names
+        .0
+        .get(&connection)
+        .cloned()
+        .or_else(|| account.map(|a| a.name.clone()))
+}
+
 fn new_connection_system(
-    mut events: EventReader<NewConnectionEvent<Config>>,
-    mut game_queue: ResMut<GameQueue>,
+    mut events: EventReader<NewConnectionEvent<ServerNetConfig>>,
+    motd: Res<announcements::Motd>,
+    bans: Res<admin::Bans>,
+    connections: Res<ServerConnections<ServerNetConfig>>,
+    rules: Res<config::GameRules>,
 ) {
     for event in events.read() {
-        info!("got a new connection {:?}", event.connection.id());
-        game_queue.0.push(event.connection.clone());
+        if bans.0.contains(&event.address.ip()) {
+            info!(
+                connection = ?event.connection.id(),
+                address = %event.address,
+                "refused a banned address"
+            );
+            event.connection.disconnect();
+            continue;
+        }
+        // the new connection is counted already
+        if rules
+            .max_connections
+            .is_some_and(|max| connections.iter().count() > max as usize)
+        {
+            info!(connection = ?event.connection.id(), "refused, the server is full");
+            event
+                .connection
+                .send(ServerPacket::Error(ServerError::ServerFull))
+                .unwrap_or_else(connection_error);
+            event.connection.disconnect();
+            continue;
+        }
+        info!(connection = ?event.connection.id(), address = %event.address, "connected");
+        if let Some(motd) = &motd.0 {
+            event
+                .connection
+                .send(ServerPacket::Announcement(motd.clone()))
+                .unwrap_or_else(connection_error);
+        }
     }
 }
 
 fn receive_packet(
-    mut event: EventReader<PacketReceiveEvent<Config>>,
-    connection_map: ResMut<ConnectionMap>,
-    mut game_map: ResMut<ChessGameMap>,
+    mut event: EventReader<PacketReceiveEvent<ServerNetConfig>>,
+    mut connection_map: ResMut<ConnectionMap>,
+    mut game_map: ChessGameMap,
     mut writer: EventWriter<EndGameEvent>,
+    puzzle_pool: Res<puzzles::PuzzlePool>,
+    rules: Res<config::GameRules>,
+    mut finished_games: ResMut<rematch::FinishedGames>,
+    mut start_game: EventWriter<StartGameEvent>,
+    mut latencies: ResMut<ping::Latencies>,
+    mut next_player: ResMut<PlayerId>,
+    names: Res<PlayerNames>,
+    accounts: Res<accounts::Accounts>,
+    mut challenges: ResMut<challenge::Challenges>,
+    connections: Res<ServerConnections<ServerNetConfig>>,
+    mut rate_limits: ResMut<ratelimit::RateLimits>,
+    grace: Res<reconnect::GracePeriod>,
 ) {
     for packet in event.read() {
-        let Some(id) = connection_map.0.get(&packet.connection.id()) else {
-            return;
-        };
-        let game = game_map.0.get_mut(id);
+        let _connection = info_span!("connection", id = ?packet.connection.id()).entered();
+        // packets that don't need a game
         match packet.packet {
-            ClientPacket::Move(player_move) => {
-                info!("got a move packet {:?}", player_move);
-                let Some(state) = game else {
-                    return;
+            // answered by stats::send_stats
+            ClientPacket::ServerStats => continue,
+            // answered by history::send_history
+            ClientPacket::RecentGames
+            | ClientPacket::FetchReplay(_)
+            | ClientPacket::History { .. } => continue,
+            // answered by analysis::fetch_analysis
+            ClientPacket::FetchAnalysis(_) => continue,
+            // answered by hints::hints
+            ClientPacket::Hint(_) => continue,
+            // handled by lobby::lobbies
+            ClientPacket::CreateLobby { .. } | ClientPacket::JoinLobby(_) => continue,
+            // handled by queue::join_queue
+            ClientPacket::JoinQueue { .. } | ClientPacket::LeaveQueue => continue,
+            // handled by bots::fill_queue
+            ClientPacket::PlayComputer => continue,
+            // handled by seeks::seeks
+            ClientPacket::CreateSeek { .. }
+            | ClientPacket::ListSeeks
+            | ClientPacket::AcceptSeek(_) => continue,
+            // handled by league::leagues
+            ClientPacket::CreateLeague { .. }
+            | ClientPacket::JoinLeague(_)
+            | ClientPacket::StartLeague(_) => continue,
+            // handled by simul::simuls
+            ClientPacket::CreateSimul { .. }
+            | ClientPacket::JoinSimul(_)
+            | ClientPacket::StartSimul(_) => continue,
+            // handled by accounts::logins, whether it is a bot is kept by bots::engines
+            ClientPacket::Login { .. }
+            | ClientPacket::Register { .. }
+            | ClientPacket::PasswordLogin { .. }
+            | ClientPacket::Hello { .. }
+            | ClientPacket::Rename(_) => continue,
+            ClientPacket::RequestPuzzle => {
+                packet
+                    .connection
+                    .send(ServerPacket::DailyPuzzle(puzzle_pool.daily().cloned()))
+                    .unwrap_or_else(connection_error);
+                continue;
+            }
+            ClientPacket::Challenge {
+                ref target,
+                variant,
+                time_control,
+            } => {
+                let id = packet.connection.id();
+                let target_connection = connections.iter().find(|c| {
+                    c.id() != id
+                        && !connection_map.0.contains_key(&c.id())
+                        && name_of(c.id(), &accounts, &names).as_ref() == Some(target)
+                });
+                let seek = Seek {
+                    variant,
+                    time_control,
+                    time_odds: None,
+                    rated: false,
                 };
-                if packet.connection.id() == state.white.id()
-                    && state.state.turn == ChessColor::White
-                    || packet.connection.id() == state.black.id()
-                        && state.state.turn == ChessColor::Black
-                {
-                    if state.state.move_piece(player_move).is_err() {
-                        packet
-                            .connection
-                            .send(ServerPacket::InvalidMove(state.state))
-                            .unwrap_or_else(connection_error);
-                    } else {
-                        state.draw = None;
-                        state
-                            .send_opponent(packet.connection.id(), ServerPacket::Move(player_move));
-                        state
-                            .move_history
-                            .push(compress_chessboard(&state.state.board));
-                        if !state.state.should_promote {
-                            if let Some(reason) = state.state.check_game_end(&state.move_history) {
-                                writer.send(EndGameEvent(*id, reason));
-                            }
-                        }
-                    }
-                } else {
+                if connection_map.0.contains_key(&id) {
+                    warn!("{:?} challenged someone during a game", id);
+                } else if !seek.is_valid() {
+                    warn!("{:?} challenged someone to a game that can't exist", id);
+                } else if !rules.allows(&seek) {
                     packet
                         .connection
-                        .send(ServerPacket::InvalidMove(state.state))
+                        .send(ServerPacket::Error(ServerError::TimeControlNotAllowed))
                         .unwrap_or_else(connection_error);
-                }
-            }
-            ClientPacket::Reconnect => {
-                if let Some(game) = game {
+                } else if let Some(target_connection) = target_connection {
+                    let (player, name) = identify(id, &accounts, &names, &mut next_player);
+                    challenges.add(
+                        QueuedPlayer {
+                            connection: packet.connection.clone(),
+                            id: player,
+                            name,
+                            handicap: Handicap::None,
+                            seek,
+                            color: ColorPreference::Random,
+                        },
+                        target.clone(),
+                        target_connection,
+                    );
+                } else {
                     packet
                         .connection
-                        .send(ServerPacket::StateReminder(game.state))
+                        .send(ServerPacket::ChallengeDeclined {
+                            target: target.clone(),
+                        })
                         .unwrap_or_else(connection_error);
-                } else {
-                    packet.connection.disconnect();
                 }
+                continue;
+            }
+            ClientPacket::AnswerChallenge(challenge, accept) => {
+                let id = packet.connection.id();
+                let (player, name) = identify(id, &accounts, &names, &mut next_player);
+                challenges.answer(
+                    challenge,
+                    QueuedPlayer {
+                        connection: packet.connection.clone(),
+                        id: player,
+                        name,
+                        handicap: Handicap::None,
+                        seek: Seek::default(),
+                        color: ColorPreference::Random,
+                    },
+                    accept,
+                    &connection_map,
+                    &mut start_game,
+                );
+                continue;
+            }
+            ClientPacket::OfferRematch(game) | ClientPacket::AcceptRematch(game) => {
+                finished_games.rematch(
+                    game,
+                    &packet.connection,
+                    matches!(packet.packet, ClientPacket::AcceptRematch(_)),
+                    &mut start_game,
+                );
+                continue;
+            }
+            ClientPacket::Resume(token) => {
+                reconnect::resume(
+                    &mut game_map,
+                    &mut connection_map,
+                    &packet.connection,
+                    token,
+                    &grace,
+                );
+                continue;
+            }
+            ClientPacket::Spectate(game) => {
+                spectate::spectate(&mut game_map, &packet.connection, game);
+                continue;
+            }
+            ClientPacket::Ping(sent) => {
+                packet
+                    .connection
+                    .send(ServerPacket::Pong(sent))
+                    .unwrap_or_else(connection_error);
+                continue;
             }
-            ClientPacket::RequestDraw => {
-                if let Some(game) = game {
-                    if let Some(draw) = game.draw {
-                        let color = if packet.connection.id() == game.white.id() {
-                            ChessColor::White
-                        } else {
-                            ChessColor::Black
-                        };
-                        if color != draw {
-                            writer.send(EndGameEvent(*id, GameEnd::Draw(EndReason::Agreement)));
+            ClientPacket::Pong(sent) => {
+                latencies.0.insert(packet.connection.id(), round_trip(sent));
+                continue;
+            }
+            ClientPacket::Unsupported => {
+                packet
+                    .connection
+                    .send(ServerPacket::Error(ServerError::Unsupported))
+                    .unwrap_or_else(connection_error);
+                continue;
+            }
+            _ => {}
+        }
+        let Some(id) = packet.packet.game() else {
+            continue;
+        };
+        let _game = info_span!("game", id = id.0).entered();
+        // the connection has to play in the game it names, the game may have ended while
+        // the packet was on its way
+        let Some(mut game) = game_map
+            .get_mut(&id)
+            .filter(|game| game.players.color_of(packet.connection.id()).is_some())
+        else {
+            warn!(connection = ?packet.connection.id(), "packet for a game it doesn't play in");
+            packet
+                .connection
+                .send(ServerPacket::Error(ServerError::NotInGame))
+                .unwrap_or_else(connection_error);
+            continue;
+        };
+        match packet.packet {
+            ClientPacket::Move(_, ply, player_move) => {
+                if let Some(resync) = game.board.catch_up(id, ply, game.clock.0) {
+                    debug!(
+                        ply,
+                        expected = game.board.ply(),
+                        "move rejected, late or duplicated"
+                    );
+                    // a late or duplicated move, the client has to catch up instead, but a client
+                    // sending nothing else is treated like one sending illegal moves
+                    metrics::count(&metrics::REJECTED_MOVES);
+                    if rate_limits.invalid_move(&packet.connection) {
+                        packet
+                            .connection
+                            .send(resync)
+                            .unwrap_or_else(connection_error);
+                    }
+                    continue;
+                }
+                if game.players.color_of(packet.connection.id()) == Some(game.board.state.turn) {
+                    let round_trip = latencies
+                        .0
+                        .get(&packet.connection.id())
+                        .copied()
+                        .unwrap_or_default();
+                    match game.play(player_move, round_trip) {
+                        Err(_) => {
+                            warn!(ply, ?player_move, reason = "illegal", "move rejected");
+                            metrics::count(&metrics::REJECTED_MOVES);
+                            if rate_limits.invalid_move(&packet.connection) {
+                                packet
+                                    .connection
+                                    .send(game.board.resync(id, ply, game.clock.0))
+                                    .unwrap_or_else(connection_error);
+                            }
                         }
-                    } else {
-                        game.draw = Some(if packet.connection.id() == game.white.id() {
-                            ChessColor::White
-                        } else {
-                            ChessColor::Black
-                        });
-                        game.send_opponent(packet.connection.id(), ServerPacket::DrawRequested);
+                        Ok(end) => {
+                            debug!(ply, ?player_move, "move accepted");
+                            metrics::count(&metrics::MOVES);
+                            rate_limits.valid_move(packet.connection.id());
+                            if let Some(clock) = game.clock.0 {
+                                packet
+                                    .connection
+                                    .send(ServerPacket::Clock(id, clock))
+                                    .unwrap_or_else(connection_error);
+                            }
+                            if let Some(reason) = end {
+                                writer.send(EndGameEvent(id, reason));
+                            }
+                        }
+                    }
+                } else {
+                    warn!(ply, ?player_move, reason = "out of turn", "move rejected");
+                    metrics::count(&metrics::REJECTED_MOVES);
+                    if rate_limits.invalid_move(&packet.connection) {
+                        packet
+                            .connection
+                            .send(game.board.resync(id, ply, game.clock.0))
+                            .unwrap_or_else(connection_error);
                     }
                 }
             }
-            ClientPacket::Promotion(piece) => {
-                if let Some(game) = game {
-                    if game.state.promote(piece).is_ok() {
-                        game.send_opponent(packet.connection.id(), ServerPacket::Promotion(piece));
+            ClientPacket::Resync(_, since) => {
+                packet
+                    .connection
+                    .send(game.board.resync(id, since, game.clock.0))
+                    .unwrap_or_else(connection_error);
+            }
+            ClientPacket::RequestDraw(_) => {
+                let color = game.players.color_of(packet.connection.id());
+                if let Some(draw) = game.board.draw {
+                    if color != Some(draw) {
+                        writer.send(EndGameEvent(id, GameEnd::Draw(EndReason::Agreement)));
+                    }
+                } else if let Some(color) = color {
+                    if game.board.can_offer_draw(color) {
+                        game.board.offer_draw(id, color, &game.players);
                     } else {
                         packet
                             .connection
-                            .send(ServerPacket::InvalidMove(game.state))
+                            .send(ServerPacket::Error(ServerError::DrawOfferTooSoon))
                             .unwrap_or_else(connection_error);
                     }
                 }
             }
+            ClientPacket::ClaimDraw(_) => {
+                if let Some(reason) = game.board.state.claimable_draw(&game.board.move_history) {
+                    writer.send(EndGameEvent(id, reason));
+                } else {
+                    warn!("{:?} claimed a draw it can't have", packet.connection.id());
+                }
+            }
+            ClientPacket::DeclineDraw(_) => {
+                // only the player who got the offer can decline it
+                if game.board.draw.is_some()
+                    && game.board.draw != game.players.color_of(packet.connection.id())
+                {
+                    game.board.draw = None;
+                    game.players
+                        .send_opponent(packet.connection.id(), ServerPacket::DrawDeclined(id));
+                }
+            }
+            ClientPacket::Abort(_) => {
+                if game.board.ply() < 2 {
+                    writer.send(EndGameEvent(id, GameEnd::Draw(EndReason::Aborted)));
+                } else {
+                    warn!("{:?} tried to abort a started game", packet.connection.id());
+                }
+            }
+            ClientPacket::RequestTakeback(_) => {
+                let Some(color) = game.players.color_of(packet.connection.id()) else {
+                    continue;
+                };
+                if game.board.takeback.is_none() && game.board.last_move_of(color).is_some() {
+                    game.board.takeback = Some(color);
+                    game.players
+                        .send_opponent(packet.connection.id(), ServerPacket::TakebackRequested(id));
+                }
+            }
+            ClientPacket::RespondTakeback(_, accept) => {
+                // only the opponent of the player asking can answer
+                let Some(asking) = game
+                    .board
+                    .takeback
+                    .filter(|&c| Some(c) != game.players.color_of(packet.connection.id()))
+                else {
+                    continue;
+                };
+                game.board.takeback = None;
+                if !accept {
+                    game.players
+                        .send_opponent(packet.connection.id(), ServerPacket::TakebackDeclined(id));
+                } else if let Some(ply) = game.board.last_move_of(asking) {
+                    game.board.rewind(ply);
+                    game.board.draw = None;
+                    let resync = game.board.resync(id, ply, game.clock.0);
+                    game.players.send_players(resync.clone());
+                    game.spectators.send(resync);
+                    game.players.send_engines(id, &game.board, game.clock.0);
+                }
+            }
+            ClientPacket::RequestPuzzle
+            | ClientPacket::JoinQueue { .. }
+            | ClientPacket::LeaveQueue
+            | ClientPacket::PlayComputer
+            | ClientPacket::Hello { .. }
+            | ClientPacket::Rename(_)
+            | ClientPacket::Login { .. }
+            | ClientPacket::Register { .. }
+            | ClientPacket::PasswordLogin { .. }
+            | ClientPacket::Challenge { .. }
+            | ClientPacket::AnswerChallenge(_, _)
+            | ClientPacket::ServerStats
+            | ClientPacket::RecentGames
+            | ClientPacket::History { .. }
+            | ClientPacket::FetchReplay(_)
+            | ClientPacket::FetchAnalysis(_)
+            | ClientPacket::Hint(_)
+            | ClientPacket::CreateLobby { .. }
+            | ClientPacket::JoinLobby(_)
+            | ClientPacket::CreateSeek { .. }
+            | ClientPacket::ListSeeks
+            | ClientPacket::AcceptSeek(_)
+            | ClientPacket::CreateLeague { .. }
+            | ClientPacket::JoinLeague(_)
+            | ClientPacket::StartLeague(_)
+            | ClientPacket::CreateSimul { .. }
+            | ClientPacket::JoinSimul(_)
+            | ClientPacket::StartSimul(_)
+            | ClientPacket::OfferRematch(_)
+            | ClientPacket::AcceptRematch(_)
+            | ClientPacket::Resume(_)
+            | ClientPacket::Spectate(_)
+            | ClientPacket::Ping(_)
+            | ClientPacket::Pong(_)
+            | ClientPacket::Unsupported => {}
+            ClientPacket::Chat(_, ref message) => {
+                let message = message.trim();
+                if message.is_empty() || message.chars().count() > MAX_CHAT_LENGTH {
+                    warn!("{:?} send an invalid chat message", packet.connection.id());
+                } else if let Some(from) = game.players.color_of(packet.connection.id()) {
+                    game.players.send_opponent(
+                        packet.connection.id(),
+                        ServerPacket::Chat {
+                            game: id,
+                            from,
+                            message: message.to_string(),
+                        },
+                    );
+                }
+            }
+            ClientPacket::Promotion(_, piece) => {
+                if game.board.state.promote(piece).is_ok() {
+                    if let Some(last) = game.board.moves.last_mut() {
+                        last.promotion = Some(piece);
+                    }
+                    game.players
+                        .send_opponent(packet.connection.id(), ServerPacket::Promotion(id, piece));
+                    game.spectators.send(ServerPacket::Promotion(id, piece));
+                    game.players.send_engines(id, &game.board, game.clock.0);
+                } else {
+                    packet
+                        .connection
+                        // undo the promotion by replaying the last move
+                        .send(game.board.resync(
+                            id,
+                            game.board.ply().saturating_sub(1),
+                            game.clock.0,
+                        ))
+                        .unwrap_or_else(connection_error);
+                }
+            }
         }
     }
 }
@@ -192,89 +1338,394 @@ fn receive_packet(
 fn end_game(
     mut event: EventReader<EndGameEvent>,
     mut connection_map: ResMut<ConnectionMap>,
-    mut game_map: ResMut<ChessGameMap>,
+    mut game_map: ChessGameMap,
+    mut puzzle_tasks: ResMut<puzzles::PuzzleTasks>,
+    mut analysis_tasks: ResMut<analysis::AnalysisTasks>,
+    mut opening_stats: ResMut<OpeningStats>,
+    mut finished_games: ResMut<rematch::FinishedGames>,
+    mut store: ResMut<storage::Store>,
+    archive: Res<archive::PgnArchive>,
+    #[cfg(feature = "http")] mut webhooks: Option<ResMut<webhooks::Webhooks>>,
 ) {
+    // the entity of an ended game is only gone after the commands ran
+    let mut ended = Vec::new();
     for e in event.read() {
         let id = e.0;
         let reason = e.1;
-        let Some(game) = game_map.0.get_mut(&id) else {
-            return warn!("no game to end");
+        let _game = info_span!("game", id = id.0).entered();
+        let Some(mut game) = game_map.get_mut(&id).filter(|_| !ended.contains(&id)) else {
+            warn!("no game to end");
+            continue;
         };
-        game.white
-            .send(ServerPacket::EndGame(reason))
-            .unwrap_or_else(connection_error);
-        game.black
-            .send(ServerPacket::EndGame(reason))
-            .unwrap_or_else(connection_error);
-        connection_map.0.remove(&game.white.id());
-        connection_map.0.remove(&game.black.id());
-        game.white.disconnect();
-        game.black.disconnect();
+        ended.push(id);
+        let aborted = matches!(reason, GameEnd::Draw(EndReason::Aborted));
+        let opening = openings::classify(&game.board.moves).filter(|_| !aborted);
+        info!(
+            result = ?reason,
+            moves = game.board.moves.len(),
+            opening = opening.map_or("unknown", |opening| opening.name),
+            "game ended"
+        );
+        if let Some(opening) = opening {
+            *opening_stats.0.entry(opening.name).or_default() += 1;
+        }
+        metrics::record_game(Duration::from_millis(
+            timestamp().saturating_sub(game.board.started),
+        ));
+        let players = &game.players;
+        if players.is_rated() && !aborted {
+            ratings::update(&mut *store.0, players.white_id, players.black_id, reason);
+            info!(
+                "{} is now rated {}, {} is now rated {}",
+                players.white_name,
+                ratings::get(&*store.0, players.white_id),
+                players.black_name,
+                ratings::get(&*store.0, players.black_id)
+            );
+        }
+        let mut replay = None;
+        if !aborted {
+            let ended = timestamp();
+            let record = storage::GameRecord {
+                white: players.white_id,
+                black: players.black_id,
+                white_name: players.white_name.clone(),
+                black_name: players.black_name.clone(),
+                start: game.board.start,
+                moves: game.board.moves.clone(),
+                result: reason,
+                rated: players.is_rated(),
+                ended,
+            };
+            match store.save_game(record) {
+                Ok(id) => replay = Some(id),
+                Err(error) => warn!("couldn't store game {:?}: {}", id, error),
+            }
+            archive.append(id, players, &game.board, reason, ended);
+        }
+        #[cfg(feature = "http")]
+        if let Some(webhooks) = &mut webhooks {
+            webhooks.game_ended(id, players, &game.board, reason);
+        }
+        players.send_players(ServerPacket::EndGame(id, reason));
+        game.spectators.send(ServerPacket::EndGame(id, reason));
+        for connection in players.white.iter().chain(&players.black) {
+            connection_map.remove(connection.id(), id);
+        }
 
-        game_map.0.remove(&id);
+        // a rematch needs both players
+        if let Some((white, black)) = players.players() {
+            finished_games.0.insert(
+                id,
+                rematch::FinishedGame {
+                    white,
+                    black,
+                    rematch: None,
+                },
+            );
+        }
+        // there is nothing to analyze in an aborted game
+        if !aborted {
+            analysis_tasks.analyze_game(
+                id,
+                replay,
+                players
+                    .white
+                    .iter()
+                    .chain(&players.black)
+                    .cloned()
+                    .collect(),
+                game.board.start,
+                game.board.moves.clone(),
+            );
+        }
+        puzzle_tasks.scan_game(game.board.start, std::mem::take(&mut game.board.moves));
+        game_map.remove(&id);
     }
 }
 
 fn create_game(
     mut queue: ResMut<GameQueue>,
-    mut game_map: ResMut<ChessGameMap>,
-    mut id: ResMut<GameId>,
-    mut connection_map: ResMut<ConnectionMap>,
+    mut start_game: EventWriter<StartGameEvent>,
+    game_map: ChessGames,
+    rules: Res<config::GameRules>,
+    recent: Res<queue::RecentOpponents>,
 ) {
-    if !queue.is_changed() || queue.0.len() < 2 {
+    // a full server pairs the queue again once a game ends
+    if !queue.is_changed() && !game_map.is_changed() && !rules.is_changed() {
         return;
     }
     let mut rng = thread_rng();
-    // take 2 random players
-    let mut t = rng.gen_range(0..queue.0.len());
-    let mut white = queue.0.remove(t);
-    t = rng.gen_range(0..queue.0.len());
-    let mut black = queue.0.remove(t);
-    // randomize color
-    if rng.gen_bool(0.5) {
-        std::mem::swap(&mut white, &mut black);
-    }
-
-    white
-        .send(ServerPacket::MatchFound(ChessColor::White))
-        .unwrap_or_else(connection_error);
-    black
-        .send(ServerPacket::MatchFound(ChessColor::Black))
-        .unwrap_or_else(connection_error);
-
-    connection_map.0.insert(white.id(), *id);
-    connection_map.0.insert(black.id(), *id);
-    game_map.0.insert(*id, Game::new(white, black));
+    let mut paired = false;
+    let mut games = game_map.len() as u32;
+    // looking for pairs doesn't change the queue, only finding them does
+    for pool in queue.bypass_change_detection().0.values_mut() {
+        // pair random players looking for the same kind of game until nobody fits
+        while rules.max_games.is_none_or(|max| games < max) {
+            let mut order: Vec<usize> = (0..pool.len()).collect();
+            order.shuffle(&mut rng);
+            let find_pair = |fresh: bool| {
+                order.iter().find_map(|&a| {
+                    order
+                        .iter()
+                        .find(|&&b| {
+                            b != a
+                                && pool[a].seek.compatible(&pool[b].seek)
+                                && pool[a].color.compatible(pool[b].color)
+                                && !(fresh && recent.played(pool[a].id, pool[b].id))
+                        })
+                        .map(|&b| (a, b))
+                })
+            };
+            // players meet someone new when they can, the same opponent again when nobody else fits
+            let Some((a, b)) = find_pair(true).or_else(|| find_pair(false)) else {
+                break;
+            };
+            // remove the later one first so the other index stays valid
+            let first = pool.remove(a.max(b));
+            let second = pool.remove(a.min(b));
+            start_game.send(StartGameEvent::seat(first, second));
+            paired = true;
+            games += 1;
+        }
+    }
+    if paired {
+        queue.0.retain(|_, pool| !pool.is_empty());
+    }
+}
+
+fn start_game(
+    mut start_game: EventReader<StartGameEvent>,
+    mut game_map: ChessGameMap,
+    mut id: ResMut<GameId>,
+    mut connection_map: ResMut<ConnectionMap>,
+    mut game_queue: ResMut<GameQueue>,
+    store: Res<storage::Store>,
+    shutdown: Res<shutdown::Shutdown>,
+    rules: Res<config::GameRules>,
+    engines: Res<bots::Engines>,
+    mut recent: ResMut<queue::RecentOpponents>,
+) {
+    // nothing new starts on a server that is going down
+    if shutdown.is_shutting_down() {
+        return start_game.clear();
+    }
+    // the games started here are only spawned once the commands ran
+    let mut started = 0;
+    for StartGameEvent { white, black } in start_game.read() {
+        // the queue waits for a free game, games from anywhere else are turned down
+        if rules
+            .max_games
+            .is_some_and(|max| game_map.len() + started >= max as usize)
+        {
+            for player in [white, black] {
+                player
+                    .connection
+                    .send(ServerPacket::Error(ServerError::ServerFull))
+                    .unwrap_or_else(connection_error);
+            }
+            continue;
+        }
+        let game = Game::new(
+            white.clone(),
+            black.clone(),
+            [
+                ratings::get(&*store.0, white.id),
+                ratings::get(&*store.0, black.id),
+            ],
+        );
+        let _game = info_span!("game", id = id.0).entered();
+        info!(
+            white = %white.name,
+            black = %black.name,
+            seek = ?white.seek,
+            "match created"
+        );
+        recent.record(white.id, black.id);
+        // someone who accepted a challenge may still have been waiting in the queue
+        game_queue.remove_connection(white.connection.id());
+        game_queue.remove_connection(black.connection.id());
+        open_game(game, &mut id, &mut game_map, &mut connection_map, &engines);
+        started += 1;
+    }
+}
+
+/// tells the players in the game that it started and gives it the next id
+fn open_game(
+    mut game: Game,
+    id: &mut GameId,
+    game_map: &mut ChessGameMap,
+    connection_map: &mut ConnectionMap,
+    engines: &bots::Engines,
+) {
+    let players = &mut game.players;
+    players.engines = [ChessColor::White, ChessColor::Black].map(|color| {
+        players
+            .connection(color)
+            .is_some_and(|c| engines.contains(c.id()))
+    });
+    for color in [ChessColor::White, ChessColor::Black] {
+        let Some(connection) = players.connection(color) else {
+            continue;
+        };
+        connection
+            .send(ServerPacket::MatchFound {
+                game: *id,
+                color,
+                white: players.player(ChessColor::White),
+                black: players.player(ChessColor::Black),
+                rated: players.is_rated(),
+                start: game.board.state,
+                token: GameToken {
+                    game: *id,
+                    key: players.rejoin_key(color),
+                },
+                moves: Vec::new(),
+                clock: game.clock.0,
+            })
+            .unwrap_or_else(connection_error);
+        connection_map.insert(connection.id(), *id);
+    }
+    game_map.insert(*id, game);
     id.0 += 1;
 }
 
 fn disconnect(
-    mut disconnect_event: EventReader<DisconnectionEvent<Config>>,
-    connection_map: Res<ConnectionMap>,
-    mut game_map: ResMut<ChessGameMap>,
+    mut disconnect_event: EventReader<DisconnectionEvent<ServerNetConfig>>,
+    mut connection_map: ResMut<ConnectionMap>,
+    mut game_map: ChessGameMap,
     mut game_queue: ResMut<GameQueue>,
+    mut finished_games: ResMut<rematch::FinishedGames>,
+    mut latencies: ResMut<ping::Latencies>,
+    mut names: ResMut<PlayerNames>,
+    mut accounts: ResMut<accounts::Accounts>,
+    mut challenges: ResMut<challenge::Challenges>,
+    mut lobbies: ResMut<lobby::Lobbies>,
+    mut seeks: ResMut<seeks::Seeks>,
+    mut leagues: ResMut<league::Leagues>,
+    mut simuls: ResMut<simul::Simuls>,
+    mut rate_limits: ResMut<ratelimit::RateLimits>,
+    grace: Res<reconnect::GracePeriod>,
 ) {
     for packet in disconnect_event.read() {
-        let Some(id) = connection_map.0.get(&packet.connection.id()) else {
-            return;
-        };
-        let game = game_map.0.get_mut(id);
-        if let Some(game) = game {
-            game.send_opponent(
-                packet.connection.id(),
-                ServerPacket::EndGame(if packet.connection.id() == game.white.id() {
-                    GameEnd::Black(EndReason::Resignation)
-                } else {
-                    GameEnd::White(EndReason::Resignation)
-                }),
-            );
-        } else {
-            game_queue.0.retain(|x| x.id() != packet.connection.id());
-        }
+        let connection_id = packet.connection.id();
+        info!(connection = ?connection_id, "disconnected");
+        lobbies.remove_connection(connection_id);
+        seeks.remove_connection(connection_id);
+        leagues.remove_connection(connection_id);
+        simuls.remove_connection(connection_id);
+        names.0.remove(&connection_id);
+        rate_limits.remove(connection_id);
+        accounts.logout(connection_id);
+        challenges.remove_connection(connection_id);
+        finished_games.remove_connection(connection_id);
+        latencies.0.remove(&connection_id);
+        game_queue.remove_connection(connection_id);
         packet.connection.disconnect();
+        for id in connection_map.0.remove(&connection_id).unwrap_or_default() {
+            if let Some(mut game) = game_map.get_mut(&id) {
+                reconnect::vacate(&mut game.players, id, connection_id, &grace);
+            }
+        }
     }
 }
 
 fn connection_error(err: impl fmt::Debug) {
+    metrics::count(&metrics::SEND_ERRORS);
     warn!("connection error {:?}", err);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::api::chessmove::ChessboardLocation;
+
+    const GAME: GameId = GameId(1);
+
+    /// e4 e5 Nf3
+    fn opening() -> [ChessMove; 3] {
+        let square = |rank: u8, file: u8| ChessboardLocation::new(rank, file);
+        [
+            ChessMove {
+                from: square(1, 4),
+                to: square(3, 4),
+            },
+            ChessMove {
+                from: square(6, 4),
+                to: square(4, 4),
+            },
+            ChessMove {
+                from: square(0, 6),
+                to: square(2, 5),
+            },
+        ]
+    }
+
+    fn played(moves: &[ChessMove]) -> Board {
+        let mut board = Board::new(ChessState::default());
+        for &chess_move in moves {
+            board.state.move_piece(chess_move).unwrap();
+            board
+                .move_history
+                .push(compress_chessboard(&board.state.board));
+            board.moves.push(chess_move.into());
+        }
+        board
+    }
+
+    fn resynced(packet: Option<ServerPacket>) -> (u32, Vec<PlayedMove>) {
+        match packet {
+            Some(ServerPacket::Resync { since, moves, .. }) => (since, moves),
+            other => panic!("expected a resync, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn the_expected_move_needs_no_catching_up() {
+        let board = played(&opening()[..2]);
+        assert!(board.catch_up(GAME, 2, None).is_none());
+    }
+
+    #[test]
+    fn a_duplicated_move_gets_the_moves_after_it() {
+        let moves = opening();
+        let board = played(&moves);
+        let (since, resent) = resynced(board.catch_up(GAME, 1, None));
+        assert_eq!(since, 1);
+        assert_eq!(resent, [moves[1].into(), moves[2].into()]);
+        let (since, resent) = resynced(board.catch_up(GAME, 2, None));
+        assert_eq!(since, 2);
+        assert_eq!(resent, [moves[2].into()]);
+    }
+
+    #[test]
+    fn a_move_from_the_future_is_resynced_from_the_last_ply() {
+        let board = played(&opening()[..2]);
+        let (since, resent) = resynced(board.catch_up(GAME, 7, None));
+        assert_eq!(since, 2);
+        assert!(resent.is_empty());
+    }
+
+    #[test]
+    fn resync_from_the_start_sends_every_move() {
+        let moves = opening();
+        let board = played(&moves);
+        let (since, resent) = resynced(Some(board.resync(GAME, 0, None)));
+        assert_eq!(since, 0);
+        assert_eq!(resent, moves.map(PlayedMove::from));
+    }
+
+    #[test]
+    fn rewinding_replays_the_moves_before_the_ply() {
+        let moves = opening();
+        let mut board = played(&moves);
+        board.rewind(1);
+        assert_eq!(board.ply(), 1);
+        assert_eq!(board.move_history, played(&moves[..1]).move_history);
+        assert_eq!(
+            compress_chessboard(&board.state.board),
+            board.move_history[0]
+        );
+        assert!(board.catch_up(GAME, 1, None).is_none());
+    }
+}
@@ -0,0 +1,177 @@
+use std::{collections::HashMap, time::Duration};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::{storage::Store, Board, ChessGames, Game, GameClock, Players, Spectators};
+use crate::api::{
+    chessmove::{compress_chessboard, ChessColor, PlayedMove},
+    chessstate::ChessState,
+    clock::Clock,
+    handicap::Handicap,
+    matchmaking::Seek,
+    ping::timestamp,
+    GameId, PlayerId, Rating,
+};
+
+/// how often the running games get saved, a restart loses at most this much of them
+const SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Resource, Debug)]
+pub struct SaveTimer(pub Timer);
+
+impl Default for SaveTimer {
+    fn default() -> Self {
+        Self(Timer::new(SAVE_INTERVAL, TimerMode::Repeating))
+    }
+}
+
+/// everything about a running game that outlives the connections playing it
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SavedGame {
+    pub id: GameId,
+    pub start: ChessState,
+    pub moves: Vec<PlayedMove>,
+    pub white_id: PlayerId,
+    pub black_id: PlayerId,
+    pub white_name: String,
+    pub black_name: String,
+    pub white_handicap: Handicap,
+    pub black_handicap: Handicap,
+    pub seek: Seek,
+    pub rejoin_keys: [u64; 2],
+    pub clock: Option<Clock>,
+    pub ratings: [Rating; 2],
+    pub started: u64,
+    /// when the game was saved, the clocks don't run while the server is down
+    pub saved: u64,
+    /// the seat the bot plays
+    pub bot: Option<ChessColor>,
+    pub engines: [bool; 2],
+}
+
+impl SavedGame {
+    pub fn new(
+        id: GameId,
+        players: &Players,
+        board: &Board,
+        clock: Option<Clock>,
+        now: u64,
+    ) -> Self {
+        Self {
+            id,
+            start: board.start,
+            moves: board.moves.clone(),
+            white_id: players.white_id,
+            black_id: players.black_id,
+            white_name: players.white_name.clone(),
+            black_name: players.black_name.clone(),
+            white_handicap: players.white_handicap,
+            black_handicap: players.black_handicap,
+            seek: players.seek,
+            rejoin_keys: players.rejoin_keys,
+            clock,
+            ratings: players.ratings,
+            started: board.started,
+            saved: now,
+            bot: players.bot,
+            engines: players.engines,
+        }
+    }
+
+    /// the game with the seats of its players empty until they resume it, None if the moves don't replay
+    pub fn restore(self, now: u64) -> Option<Game> {
+        let mut state = self.start;
+        let mut move_history = Vec::new();
+        for &played_move in &self.moves {
+            if state.play_move(played_move).is_err() {
+                warn!(
+                    "couldn't replay {} in saved game {:?}",
+                    played_move, self.id
+                );
+                return None;
+            }
+            move_history.push(compress_chessboard(&state.board));
+        }
+        let clock = self.clock.map(|clock| Clock {
+            server_time: clock.server_time + now.saturating_sub(self.saved),
+            ..clock
+        });
+        Some(Game {
+            players: Players {
+                white: None,
+                black: None,
+                white_id: self.white_id,
+                black_id: self.black_id,
+                white_name: self.white_name,
+                black_name: self.black_name,
+                white_handicap: self.white_handicap,
+                black_handicap: self.black_handicap,
+                seek: self.seek,
+                rejoin_keys: self.rejoin_keys,
+                // the players get as long to come back as after a disconnect, the bot is always there
+                vacated: [
+                    Some(now).filter(|_| self.bot != Some(ChessColor::White)),
+                    Some(now).filter(|_| self.bot != Some(ChessColor::Black)),
+                ],
+                ratings: self.ratings,
+                bot: self.bot,
+                engines: self.engines,
+            },
+            board: Board {
+                state,
+                start: self.start,
+                draw: None,
+                draw_offered: 0,
+                draw_offers: [None; 2],
+                takeback: None,
+                move_history,
+                moves: self.moves,
+                started: self.started,
+                // the time the server was down doesn't count
+                last_move: now,
+            },
+            clock: GameClock(clock),
+            spectators: Spectators::default(),
+        })
+    }
+}
+
+/// the games that were running when the server stopped
+pub fn restore_games(store: &Store) -> HashMap<GameId, Game> {
+    let saved = store.ongoing_games().unwrap_or_else(|error| {
+        warn!("couldn't load the running games: {}", error);
+        Vec::new()
+    });
+    let now = timestamp();
+    let games: HashMap<_, _> = saved
+        .into_iter()
+        .filter_map(|game| Some((game.id, game.restore(now)?)))
+        .collect();
+    info!("restored {} running games", games.len());
+    games
+}
+
+/// saves every running game every few seconds
+pub fn save_games(
+    game_map: ChessGames,
+    mut store: ResMut<Store>,
+    mut timer: ResMut<SaveTimer>,
+    time: Res<Time>,
+) {
+    if timer.0.tick(time.delta()).just_finished() {
+        save(&game_map, &mut store);
+    }
+}
+
+/// replaces the saved games with the running ones
+pub fn save(game_map: &ChessGames, store: &mut Store) {
+    let now = timestamp();
+    let games: Vec<_> = game_map
+        .iter()
+        .map(|game| SavedGame::new(*game.id, game.players, game.board, game.clock.0, now))
+        .collect();
+    if let Err(error) = store.save_ongoing_games(&games) {
+        warn!("couldn't save the running games: {}", error);
+    }
+}
@@ -0,0 +1,77 @@
+use std::{collections::HashMap, time::Duration};
+
+use bevy::prelude::*;
+use bevy_slinet::{
+    connection::ConnectionId,
+    server::{DisconnectionEvent, NewConnectionEvent, PacketReceiveEvent, ServerConnections},
+};
+
+use super::connection_error;
+use crate::api::{
+    ping::{timestamp, PingTimer, PING_INTERVAL},
+    ServerNetConfig, ServerPacket,
+};
+
+/// clients answer every ping, so a connection that stays quiet this long is dead
+const TIMEOUT: Duration = Duration::from_secs(5 * PING_INTERVAL.as_secs());
+
+/// round-trip time of every client that answered a ping
+#[derive(Resource, Default, Debug)]
+pub struct Latencies(pub HashMap<ConnectionId, Duration>);
+
+/// when every connection last send a packet, in time since startup
+#[derive(Resource, Default, Debug)]
+pub struct LastSeen(pub HashMap<ConnectionId, Duration>);
+
+pub fn send_pings(
+    connections: Res<ServerConnections<ServerNetConfig>>,
+    mut timer: ResMut<PingTimer>,
+    time: Res<Time>,
+) {
+    if !timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+    for connection in connections.iter() {
+        connection
+            .send(ServerPacket::Ping(timestamp()))
+            .unwrap_or_else(connection_error);
+    }
+}
+
+pub fn track_activity(
+    mut new_connections: EventReader<NewConnectionEvent<ServerNetConfig>>,
+    mut packets: EventReader<PacketReceiveEvent<ServerNetConfig>>,
+    mut disconnections: EventReader<DisconnectionEvent<ServerNetConfig>>,
+    mut last_seen: ResMut<LastSeen>,
+    time: Res<Time>,
+) {
+    for connection in new_connections
+        .read()
+        .map(|e| &e.connection)
+        .chain(packets.read().map(|e| &e.connection))
+    {
+        last_seen.0.insert(connection.id(), time.elapsed());
+    }
+    for event in disconnections.read() {
+        last_seen.0.remove(&event.connection.id());
+    }
+}
+
+/// half-open sockets never fire a DisconnectionEvent by themselves,
+/// closing them from this side lets the usual disconnect handling take over
+pub fn reap_dead_connections(
+    connections: Res<ServerConnections<ServerNetConfig>>,
+    mut last_seen: ResMut<LastSeen>,
+    time: Res<Time>,
+) {
+    for connection in connections.iter() {
+        let Some(&seen) = last_seen.0.get(&connection.id()) else {
+            continue;
+        };
+        if time.elapsed() - seen > TIMEOUT {
+            warn!("{:?} timed out", connection.id());
+            last_seen.0.remove(&connection.id());
+            connection.disconnect();
+        }
+    }
+}
@@ -0,0 +1,65 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, poll_once, AsyncComputeTaskPool, Task},
+};
+
+use super::storage::Store;
+use crate::api::{
+    chessmove::PlayedMove,
+    chessstate::ChessState,
+    puzzle::{find_puzzles, Puzzle},
+};
+
+/// puzzles found in games played on this server
+#[derive(Resource, Default, Debug)]
+pub struct PuzzlePool(pub Vec<Puzzle>);
+
+impl PuzzlePool {
+    /// the same puzzle for everyone for a whole day
+    pub fn daily(&self) -> Option<&Puzzle> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let day = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            / (24 * 60 * 60);
+        self.0.get(day as usize % self.0.len())
+    }
+}
+
+/// finished games that are still being searched for puzzles
+#[derive(Resource, Default)]
+pub struct PuzzleTasks(pub Vec<Task<Vec<Puzzle>>>);
+
+impl PuzzleTasks {
+    /// searching a game takes a while, so it happens off the main schedule
+    pub fn scan_game(&mut self, start: ChessState, moves: Vec<PlayedMove>) {
+        if moves.is_empty() {
+            return;
+        }
+        self.0
+            .push(AsyncComputeTaskPool::get().spawn(async move { find_puzzles(start, &moves) }));
+    }
+}
+
+pub fn collect_puzzles(
+    mut tasks: ResMut<PuzzleTasks>,
+    mut pool: ResMut<PuzzlePool>,
+    mut store: ResMut<Store>,
+) {
+    tasks.0.retain_mut(|task| {
+        let Some(puzzles) = block_on(poll_once(task)) else {
+            return true;
+        };
+        info!("found {} puzzles", puzzles.len());
+        if let Err(error) = store.add_puzzles(&puzzles) {
+            warn!("couldn't store the puzzles: {}", error);
+        }
+        pool.0.extend(puzzles);
+        false
+    });
+}
@@ -0,0 +1,232 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use bevy_slinet::{connection::ConnectionId, server::PacketReceiveEvent};
+
+use super::{
+    accounts::Accounts, bots::Engines, config::GameRules, connection_error, identify,
+    ConnectionMap, PlayerNames, QueuedPlayer,
+};
+use crate::api::{
+    matchmaking::{Seek, TimeClass, Variant},
+    ClientPacket, PlayerId, ServerError, ServerNetConfig, ServerPacket,
+};
+
+/// how often players in the queue hear how long it still is
+const STATUS_INTERVAL: Duration = Duration::from_secs(2);
+/// how many of their last opponents the queue tries not to pair a player with again
+const RECENT_OPPONENTS: usize = 3;
+
+/// players only get paired with players from the same pool
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Pool {
+    variant: Variant,
+    /// None for games without a clock
+    class: Option<TimeClass>,
+    rated: bool,
+    /// engines only get paired with each other
+    bots: bool,
+}
+
+impl Pool {
+    pub fn of(seek: &Seek, bots: bool) -> Self {
+        // both sides of a game with time odds end up in the pool of the slower clock
+        let class = [seek.time_control, seek.opponent_time_control()]
+            .into_iter()
+            .flatten()
+            .map(|time_control| time_control.class())
+            .max();
+        Self {
+            variant: seek.variant,
+            class,
+            rated: seek.rated,
+            bots,
+        }
+    }
+}
+
+/// the players waiting for a game, by pool, the longest waiting first
+#[derive(Resource, Default, Debug)]
+pub struct GameQueue(pub HashMap<Pool, Vec<QueuedPlayer>>);
+
+impl GameQueue {
+    pub fn join(&mut self, player: QueuedPlayer, bot: bool) {
+        self.0
+            .entry(Pool::of(&player.seek, bot))
+            .or_default()
+            .push(player);
+    }
+
+    pub fn contains(&self, connection_id: ConnectionId) -> bool {
+        self.0
+            .values()
+            .flatten()
+            .any(|player| player.connection.id() == connection_id)
+    }
+
+    pub fn remove_connection(&mut self, connection_id: ConnectionId) {
+        for pool in self.0.values_mut() {
+            pool.retain(|player| player.connection.id() != connection_id);
+        }
+        self.0.retain(|_, pool| !pool.is_empty());
+    }
+
+    /// takes the player out of the queue
+    pub fn take(&mut self, connection_id: ConnectionId) -> Option<QueuedPlayer> {
+        let player = self.0.values_mut().find_map(|pool| {
+            let index = pool
+                .iter()
+                .position(|player| player.connection.id() == connection_id)?;
+            Some(pool.remove(index))
+        });
+        self.0.retain(|_, pool| !pool.is_empty());
+        player
+    }
+
+    /// players waiting in all pools together
+    pub fn players_waiting(&self) -> usize {
+        self.0.values().map(Vec::len).sum()
+    }
+}
+
+/// puts players in the queue and takes them out again
+pub fn join_queue(
+    mut packets: EventReader<PacketReceiveEvent<ServerNetConfig>>,
+    mut game_queue: ResMut<GameQueue>,
+    connection_map: Res<ConnectionMap>,
+    accounts: Res<Accounts>,
+    names: Res<PlayerNames>,
+    mut next_player: ResMut<PlayerId>,
+    rules: Res<GameRules>,
+    open: Res<QueueOpen>,
+    engines: Res<Engines>,
+) {
+    for packet in packets.read() {
+        let id = packet.connection.id();
+        match packet.packet {
+            ClientPacket::JoinQueue {
+                handicap,
+                variant,
+                time_control,
+                time_odds,
+                rated,
+                color,
+            } => {
+                let seek = Seek {
+                    variant,
+                    time_control,
+                    time_odds,
+                    rated,
+                };
+                if connection_map.0.contains_key(&id) || game_queue.contains(id) {
+                    warn!("{:?} tried to join the queue twice", id);
+                } else if !seek.is_valid() {
+                    warn!(
+                        "{:?} asked for time odds in a game that can't have them",
+                        id
+                    );
+                } else if !open.0 {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(ServerError::QueueClosed))
+                        .unwrap_or_else(connection_error);
+                } else if rules
+                    .max_queue
+                    .is_some_and(|max| game_queue.players_waiting() >= max as usize)
+                {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(ServerError::ServerFull))
+                        .unwrap_or_else(connection_error);
+                } else if !rules.allows(&seek) {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(ServerError::TimeControlNotAllowed))
+                        .unwrap_or_else(connection_error);
+                } else if seek.rated && !accounts.registered(id) {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(ServerError::AccountRequired))
+                        .unwrap_or_else(connection_error);
+                } else {
+                    let (player, name) = identify(id, &accounts, &names, &mut next_player);
+                    game_queue.join(
+                        QueuedPlayer {
+                            connection: packet.connection.clone(),
+                            id: player,
+                            name,
+                            handicap,
+                            seek,
+                            color,
+                        },
+                        engines.contains(id),
+                    );
+                }
+            }
+            ClientPacket::LeaveQueue => game_queue.remove_connection(id),
+            _ => {}
+        }
+    }
+}
+
+/// the last opponents of every player, newest last
+#[derive(Resource, Default, Debug)]
+pub struct RecentOpponents(HashMap<PlayerId, VecDeque<PlayerId>>);
+
+impl RecentOpponents {
+    pub fn record(&mut self, a: PlayerId, b: PlayerId) {
+        for (player, opponent) in [(a, b), (b, a)] {
+            let recent = self.0.entry(player).or_default();
+            recent.retain(|&p| p != opponent);
+            recent.push_back(opponent);
+            if recent.len() > RECENT_OPPONENTS {
+                recent.pop_front();
+            }
+        }
+    }
+
+    pub fn played(&self, a: PlayerId, b: PlayerId) -> bool {
+        self.0.get(&a).is_some_and(|recent| recent.contains(&b))
+    }
+}
+
+/// whether players can join the queue, the server console can close it
+#[derive(Resource, Debug)]
+pub struct QueueOpen(pub bool);
+
+impl Default for QueueOpen {
+    fn default() -> Self {
+        Self(true)
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct StatusTimer(pub Timer);
+
+impl Default for StatusTimer {
+    fn default() -> Self {
+        Self(Timer::new(STATUS_INTERVAL, TimerMode::Repeating))
+    }
+}
+
+/// tells everyone in the queue where they are in their pool, whenever it changes and every few seconds
+pub fn send_queue_status(queue: Res<GameQueue>, mut timer: ResMut<StatusTimer>, time: Res<Time>) {
+    if !timer.0.tick(time.delta()).just_finished() && !queue.is_changed() {
+        return;
+    }
+    for pool in queue.0.values() {
+        let players_waiting = pool.len() as u32;
+        for (position, player) in (1..).zip(pool) {
+            player
+                .connection
+                .send(ServerPacket::QueueStatus {
+                    position,
+                    players_waiting,
+                })
+                .unwrap_or_else(connection_error);
+        }
+    }
+}
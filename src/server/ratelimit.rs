@@ -0,0 +1,152 @@
+use std::{
+    collections::HashMap,
+    mem::{discriminant, Discriminant},
+    time::Instant,
+};
+
+use bevy::{ecs::event::ManualEventReader, prelude::*};
+use bevy_slinet::{
+    connection::{ConnectionId, EcsConnection},
+    server::PacketReceiveEvent,
+};
+
+use super::{connection_error, metrics};
+use crate::api::{ClientPacket, ServerError, ServerNetConfig, ServerPacket};
+
+/// dropped packets of one kind in a row before the connection gets kicked
+const STRIKES: u32 = 20;
+/// invalid moves in a row after which the server stops resending the game
+const QUIET_AFTER: u32 = 5;
+/// invalid moves in a row before the connection gets kicked
+const KICK_AFTER: u32 = 30;
+
+/// how many packets of this kind fit in a burst, and how many come back every second
+fn limit(packet: &ClientPacket) -> (f32, f32) {
+    match packet {
+        ClientPacket::Move(..)
+        | ClientPacket::Promotion(..)
+        | ClientPacket::Resync(..)
+        | ClientPacket::Ping(_)
+        | ClientPacket::Pong(_) => (20.0, 10.0),
+        ClientPacket::Chat(..) => (5.0, 1.0),
+        // every password costs a hash, and guessing them shouldn't be quick
+        ClientPacket::Register { .. } | ClientPacket::PasswordLogin { .. } => (3.0, 0.1),
+        // changes the database
+        ClientPacket::Rename(_) => (3.0, 0.1),
+        // counts every connection and game on the server
+        ClientPacket::ServerStats => (3.0, 0.2),
+        // a game nobody asked about before keeps a worker busy
+        ClientPacket::FetchAnalysis(_) => (3.0, 0.1),
+        // a search each, though a game only gets a few
+        ClientPacket::Hint(_) => (3.0, 0.5),
+        _ => (10.0, 2.0),
+    }
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f32,
+    updated: Instant,
+    /// packets of this kind dropped since the last one that got through
+    strikes: u32,
+}
+
+#[derive(Default, Debug)]
+struct Limits {
+    buckets: HashMap<Discriminant<ClientPacket>, Bucket>,
+    /// illegal and out of turn moves since the last legal one
+    invalid_moves: u32,
+}
+
+/// a token bucket for every kind of packet every connection sends
+#[derive(Resource, Default, Debug)]
+pub struct RateLimits(HashMap<ConnectionId, Limits>);
+
+impl RateLimits {
+    /// takes a token for the packet, false if the connection sends this kind of packet too fast,
+    /// the connection hears about it once and gets kicked when it keeps going
+    pub fn allow(
+        &mut self,
+        connection: &EcsConnection<ServerPacket>,
+        packet: &ClientPacket,
+    ) -> bool {
+        let (burst, per_second) = limit(packet);
+        let now = Instant::now();
+        let limits = self.0.entry(connection.id()).or_default();
+        let bucket = limits
+            .buckets
+            .entry(discriminant(packet))
+            .or_insert(Bucket {
+                tokens: burst,
+                updated: now,
+                strikes: 0,
+            });
+        bucket.tokens =
+            (bucket.tokens + (now - bucket.updated).as_secs_f32() * per_second).min(burst);
+        bucket.updated = now;
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            bucket.strikes = 0;
+            return true;
+        }
+        bucket.strikes += 1;
+        metrics::count(&metrics::RATE_LIMITED);
+        if bucket.strikes > STRIKES {
+            warn!("{:?} kept flooding, disconnecting it", connection.id());
+            connection.disconnect();
+        } else if bucket.strikes == 1 {
+            connection
+                .send(ServerPacket::Error(ServerError::RateLimited))
+                .unwrap_or_else(connection_error);
+        }
+        false
+    }
+
+    /// counts an illegal or out of turn move, false once the connection sent so many in a row
+    /// that it doesn't get the game resent anymore, it gets kicked when it keeps going
+    pub fn invalid_move(&mut self, connection: &EcsConnection<ServerPacket>) -> bool {
+        let limits = self.0.entry(connection.id()).or_default();
+        limits.invalid_moves += 1;
+        if limits.invalid_moves > KICK_AFTER {
+            warn!(
+                "{:?} kept sending invalid moves, disconnecting it",
+                connection.id()
+            );
+            connection.disconnect();
+            return false;
+        }
+        limits.invalid_moves <= QUIET_AFTER
+    }
+
+    pub fn valid_move(&mut self, connection_id: ConnectionId) {
+        if let Some(limits) = self.0.get_mut(&connection_id) {
+            limits.invalid_moves = 0;
+        }
+    }
+
+    pub fn remove(&mut self, connection_id: ConnectionId) {
+        self.0.remove(&connection_id);
+    }
+}
+
+/// drops the packets of connections that send them too fast, before any other system reads them.
+/// every system reading packets runs after this one
+pub fn filter_packets(
+    mut packets: ResMut<Events<PacketReceiveEvent<ServerNetConfig>>>,
+    mut reader: Local<ManualEventReader<PacketReceiveEvent<ServerNetConfig>>>,
+    mut rate_limits: ResMut<RateLimits>,
+) {
+    let new = reader.len(&packets);
+    if new == 0 {
+        return;
+    }
+    // the older packets were read last frame already
+    let received: Vec<_> = packets.drain().collect();
+    let skip = received.len() - new;
+    for packet in received.into_iter().skip(skip) {
+        if rate_limits.allow(&packet.connection, &packet.packet) {
+            packets.send(packet);
+        }
+    }
+    reader.clear(&packets);
+}
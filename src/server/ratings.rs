@@ -0,0 +1,88 @@
+use bevy::prelude::*;
+
+use super::storage::Storage;
+use crate::api::{GameEnd, PlayerId, Rating};
+
+/// the rating every player starts with
+const START: f64 = 1500.0;
+
+/// games a rating stays provisional for, it moves faster until then
+const PROVISIONAL_GAMES: u32 = 20;
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlayerRating {
+    pub elo: f64,
+    /// rated games played
+    pub games: u32,
+}
+
+impl Default for PlayerRating {
+    fn default() -> Self {
+        Self {
+            elo: START,
+            games: 0,
+        }
+    }
+}
+
+impl PlayerRating {
+    fn provisional(&self) -> bool {
+        self.games < PROVISIONAL_GAMES
+    }
+
+    /// how much a single game can move the rating
+    fn k_factor(&self) -> f64 {
+        if self.provisional() {
+            40.0
+        } else {
+            20.0
+        }
+    }
+
+    /// the score this player is expected to get against `opponent`, between 0 and 1
+    fn expected(&self, opponent: &PlayerRating) -> f64 {
+        1.0 / (1.0 + 10f64.powf((opponent.elo - self.elo) / 400.0))
+    }
+}
+
+fn load(storage: &dyn Storage, player: PlayerId) -> PlayerRating {
+    storage
+        .rating(player)
+        .unwrap_or_else(|error| {
+            warn!("couldn't load the rating of {:?}: {}", player, error);
+            None
+        })
+        .unwrap_or_default()
+}
+
+/// the rating of a player, players who never played a rated game start at the default
+pub fn get(storage: &dyn Storage, player: PlayerId) -> Rating {
+    let rating = load(storage, player);
+    Rating {
+        elo: rating.elo.round() as u32,
+        provisional: rating.provisional(),
+    }
+}
+
+/// moves both ratings towards the result of a rated game between them
+pub fn update(storage: &mut dyn Storage, white: PlayerId, black: PlayerId, result: GameEnd) {
+    let score = match result {
+        GameEnd::White(_) => 1.0,
+        GameEnd::Black(_) => 0.0,
+        GameEnd::Draw(_) => 0.5,
+    };
+    let old_white = load(storage, white);
+    let old_black = load(storage, black);
+    for (player, old, opponent, score) in [
+        (white, old_white, old_black, score),
+        (black, old_black, old_white, 1.0 - score),
+    ] {
+        let rating = PlayerRating {
+            elo: old.elo + old.k_factor() * (score - old.expected(&opponent)),
+            games: old.games + 1,
+        };
+        if let Err(error) = storage.set_rating(player, rating) {
+            warn!("couldn't store the rating of {:?}: {}", player, error);
+        }
+    }
+}
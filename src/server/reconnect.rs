@@ -0,0 +1,240 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_slinet::connection::{ConnectionId, EcsConnection};
+
+use super::{
+    config::GameRules, connection_error, Board, ChessGameMap, ConnectionMap, EndGameEvent,
+    GameClock, Players,
+};
+use crate::api::{
+    chessmove::ChessColor, ping::timestamp, EndReason, GameEnd, GameId, GameToken, PlayerId,
+    ServerPacket,
+};
+
+/// how long a game waits for a disconnected player to come back, unless the server is told otherwise
+pub const GRACE_PERIOD: Duration = Duration::from_secs(30);
+/// how long a game without a running clock waits for a move before it is called off, unless the
+/// server is told otherwise
+pub const STALE_AFTER: Duration = Duration::from_secs(30 * 60);
+
+/// what happens to a game while one of its players is gone
+#[derive(Resource, Debug, Clone, Copy)]
+pub struct GracePeriod {
+    /// how long the seat stays free before its player loses
+    pub duration: Duration,
+    /// stops the clock of the player that is gone, otherwise it keeps running and they can flag
+    pub pause_clock: bool,
+}
+
+impl GracePeriod {
+    /// whether the clock of `color` is stopped right now
+    pub fn paused(&self, players: &Players, color: ChessColor) -> bool {
+        self.pause_clock && !players.is_correspondence() && players.vacated(color).is_some()
+    }
+}
+
+/// the game end where `color` gave up
+pub fn resignation(color: ChessColor) -> GameEnd {
+    match color {
+        ChessColor::White => GameEnd::Black(EndReason::Resignation),
+        ChessColor::Black => GameEnd::White(EndReason::Resignation),
+    }
+}
+
+/// ends games whose disconnected player didn't come back in time
+pub fn forfeit_abandoned_games(
+    mut games: Query<(&GameId, &mut Players)>,
+    mut writer: EventWriter<EndGameEvent>,
+    grace: Res<GracePeriod>,
+) {
+    let now = timestamp();
+    for (&id, mut players) in &mut games {
+        // the seat stays free until its player resumes the game, however long that takes
+        if players.is_correspondence() {
+            continue;
+        }
+        let expired: Vec<_> = [ChessColor::White, ChessColor::Black]
+            .into_iter()
+            .filter(|&color| {
+                players.vacated(color).is_some_and(|since| {
+                    now.saturating_sub(since) > grace.duration.as_millis() as u64
+                })
+            })
+            .collect();
+        // the player that left first loses, a game restored at startup that nobody came back to is called off
+        let end = match expired[..] {
+            [] => continue,
+            [color] => resignation(color),
+            _ => GameEnd::Draw(EndReason::Aborted),
+        };
+        players.vacated = [None; 2];
+        writer.send(EndGameEvent(id, end));
+    }
+}
+
+/// ends games nobody moved in for too long while no clock was running to end them,
+/// a player who left such a game loses it and otherwise it is called off
+pub fn abort_stale_games(
+    games: Query<(&GameId, &Players, &Board, &GameClock)>,
+    mut writer: EventWriter<EndGameEvent>,
+    rules: Res<GameRules>,
+    grace: Res<GracePeriod>,
+) {
+    let Some(stale_after) = rules.stale_after else {
+        return;
+    };
+    let now = timestamp();
+    for (&id, players, board, clock) in &games {
+        // a running clock flags the player that stopped moving on its own
+        if clock.0.is_some() && !grace.paused(players, board.state.turn) {
+            continue;
+        }
+        if now.saturating_sub(board.last_move) <= stale_after.as_millis() as u64 {
+            continue;
+        }
+        let gone: Vec<_> = [ChessColor::White, ChessColor::Black]
+            .into_iter()
+            .filter(|&color| players.vacated(color).is_some())
+            .collect();
+        let end = match gone[..] {
+            [color] => resignation(color),
+            _ => GameEnd::Draw(EndReason::Aborted),
+        };
+        let _game = info_span!("game", id = id.0).entered();
+        info!(?end, "nobody moved for {:?}", stale_after);
+        writer.send(EndGameEvent(id, end));
+    }
+}
+
+/// frees the seat of the connection that got lost, the game waits for it to come back
+pub fn vacate(players: &mut Players, id: GameId, connection_id: ConnectionId, grace: &GracePeriod) {
+    let Some(color) = players.color_of(connection_id) else {
+        return;
+    };
+    // the opponent is found through this seat, so tell them before it is emptied
+    if !players.is_correspondence() {
+        players.send_opponent(
+            connection_id,
+            ServerPacket::OpponentDisconnected {
+                game: id,
+                grace: grace.duration.as_secs(),
+            },
+        );
+    }
+    match color {
+        ChessColor::White => players.white = None,
+        ChessColor::Black => players.black = None,
+    }
+    players.set_vacated(color, Some(timestamp()));
+}
+
+/// puts a connection that logged into `player` back in its seats, so a player whose address
+/// changed gets back in without the game token even before the server noticed the old connection
+/// is gone, correspondence games wait to be picked
+pub fn resume_account(
+    game_map: &mut ChessGameMap,
+    connection_map: &mut ConnectionMap,
+    connection: &EcsConnection<ServerPacket>,
+    player: PlayerId,
+    grace: &GracePeriod,
+) {
+    let tokens: Vec<_> = game_map
+        .iter()
+        .filter(|game| !game.players.is_correspondence())
+        .flat_map(|game| {
+            let players = game.players;
+            [ChessColor::White, ChessColor::Black]
+                .into_iter()
+                .filter(move |&color| players.player(color).id == player)
+                .map(move |color| GameToken {
+                    game: *game.id,
+                    key: players.rejoin_key(color),
+                })
+        })
+        .collect();
+    for token in tokens {
+        info!(
+            "{:?} logged back into game {:?}",
+            connection.id(),
+            token.game
+        );
+        resume(game_map, connection_map, connection, token, grace);
+    }
+}
+
+/// puts a new connection in the seat `token` belongs to, the old connection is dropped if it is still around
+pub fn resume(
+    game_map: &mut ChessGameMap,
+    connection_map: &mut ConnectionMap,
+    connection: &EcsConnection<ServerPacket>,
+    token: GameToken,
+    grace: &GracePeriod,
+) {
+    let id = token.game;
+    let Some(mut game) = game_map.get_mut(&id) else {
+        warn!(
+            "{:?} tried to resume unknown game {:?}",
+            connection.id(),
+            id
+        );
+        return connection.disconnect();
+    };
+    let Some(color) = [ChessColor::White, ChessColor::Black]
+        .into_iter()
+        .find(|&color| game.players.rejoin_key(color) == token.key)
+    else {
+        warn!(
+            "{:?} tried to resume game {:?} with a wrong token",
+            connection.id(),
+            id
+        );
+        return connection.disconnect();
+    };
+    // logging in put the connection back in its seat already
+    if game.players.connection(color).map(|c| c.id()) == Some(connection.id()) {
+        return;
+    }
+    let seat = match color {
+        ChessColor::White => &mut game.players.white,
+        ChessColor::Black => &mut game.players.black,
+    };
+    // the server may not have noticed the old connection is gone yet
+    if let Some(old) = seat.replace(connection.clone()) {
+        if old.id() != connection.id() {
+            connection_map.remove(old.id(), id);
+            old.disconnect();
+        }
+    }
+    // the clock picks up where it stopped, as if the player never left
+    let paused = grace.paused(&game.players, color);
+    if let (Some(since), Some(clock)) = (game.players.vacated(color), &mut game.clock.0) {
+        if paused && game.board.state.turn == color {
+            clock.server_time = timestamp() - since.saturating_sub(clock.server_time);
+        }
+    }
+    game.players.set_vacated(color, None);
+    connection_map.insert(connection.id(), id);
+
+    // the client rebuilds the board, move list and repetitions from the start
+    connection
+        .send(ServerPacket::MatchFound {
+            game: id,
+            color,
+            white: game.players.player(ChessColor::White),
+            black: game.players.player(ChessColor::Black),
+            rated: game.players.is_rated(),
+            start: game.board.start,
+            token,
+            moves: game.board.moves.clone(),
+            clock: game.clock.0,
+        })
+        .unwrap_or_else(connection_error);
+    if !game.spectators.0.is_empty() {
+        connection
+            .send(ServerPacket::Spectators(id, game.spectators.0.len() as u32))
+            .unwrap_or_else(connection_error);
+    }
+    game.players
+        .send_opponent(connection.id(), ServerPacket::OpponentReconnected(id));
+}
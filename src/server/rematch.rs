@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use bevy_slinet::connection::{ConnectionId, EcsConnection};
+
+use super::{connection_error, QueuedPlayer, StartGameEvent};
+use crate::api::{chessmove::ChessColor, GameId, ServerPacket};
+
+/// games that ended while both players are still connected, so they can play again
+#[derive(Resource, Default, Debug)]
+pub struct FinishedGames(pub HashMap<GameId, FinishedGame>);
+
+#[derive(Debug)]
+pub struct FinishedGame {
+    pub white: QueuedPlayer,
+    pub black: QueuedPlayer,
+    /// the player that offered a rematch
+    pub rematch: Option<ChessColor>,
+}
+
+impl FinishedGame {
+    pub fn color_of(&self, connection_id: ConnectionId) -> Option<ChessColor> {
+        if self.white.connection.id() == connection_id {
+            Some(ChessColor::White)
+        } else if self.black.connection.id() == connection_id {
+            Some(ChessColor::Black)
+        } else {
+            None
+        }
+    }
+}
+
+impl FinishedGames {
+    /// offers (or accepts) a rematch, once both players want one a new game starts with the colors swapped
+    pub fn rematch(
+        &mut self,
+        id: GameId,
+        connection: &EcsConnection<ServerPacket>,
+        accept_only: bool,
+        start_game: &mut EventWriter<StartGameEvent>,
+    ) {
+        let Some(game) = self.0.get_mut(&id) else {
+            return warn!(
+                "{:?} wants a rematch of unknown game {:?}",
+                connection.id(),
+                id
+            );
+        };
+        let Some(color) = game.color_of(connection.id()) else {
+            return warn!("{:?} didn't play in game {:?}", connection.id(), id);
+        };
+        match game.rematch {
+            Some(offered_by) if offered_by != color => {
+                let Some(game) = self.0.remove(&id) else {
+                    return;
+                };
+                start_game.send(StartGameEvent {
+                    white: game.black,
+                    black: game.white,
+                });
+            }
+            Some(_) => {}
+            None if accept_only => {
+                warn!("{:?} accepted a rematch nobody offered", connection.id());
+            }
+            None => {
+                game.rematch = Some(color);
+                match color {
+                    ChessColor::White => &game.black,
+                    ChessColor::Black => &game.white,
+                }
+                .connection
+                .send(ServerPacket::RematchOffered(id))
+                .unwrap_or_else(connection_error);
+            }
+        }
+    }
+
+    /// a rematch is impossible once one of the players left
+    pub fn remove_connection(&mut self, connection_id: ConnectionId) {
+        self.0
+            .retain(|_, game| game.color_of(connection_id).is_none());
+    }
+}
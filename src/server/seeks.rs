@@ -0,0 +1,176 @@
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use bevy_slinet::{connection::ConnectionId, server::PacketReceiveEvent};
+
+use super::{
+    accounts::Accounts, bots::Engines, config::GameRules, connection_error, identify, ratings,
+    storage::Store, ConnectionMap, PlayerNames, QueuedPlayer, StartGameEvent,
+};
+use crate::api::{
+    handicap::Handicap,
+    matchmaking::{ColorPreference, Seek},
+    ClientPacket, OpenSeek, PlayerId, PlayerInfo, SeekId, ServerError, ServerNetConfig,
+    ServerPacket,
+};
+
+/// games players offer to anyone, the oldest first
+#[derive(Resource, Default, Debug)]
+pub struct Seeks {
+    pub open: BTreeMap<SeekId, QueuedPlayer>,
+    next_id: u32,
+}
+
+impl Seeks {
+    /// takes back the seek a connection offered
+    pub fn remove_connection(&mut self, connection_id: ConnectionId) {
+        self.open
+            .retain(|_, player| player.connection.id() != connection_id);
+    }
+}
+
+/// offers seeks, lists them and starts the game of the one that gets accepted
+pub fn seeks(
+    mut packets: EventReader<PacketReceiveEvent<ServerNetConfig>>,
+    mut seeks: ResMut<Seeks>,
+    mut start_game: EventWriter<StartGameEvent>,
+    connection_map: Res<ConnectionMap>,
+    accounts: Res<Accounts>,
+    names: Res<PlayerNames>,
+    mut next_player: ResMut<PlayerId>,
+    store: Res<Store>,
+    rules: Res<GameRules>,
+    engines: Res<Engines>,
+) {
+    // a player that got a game some other way isn't offering one anymore
+    seeks
+        .open
+        .retain(|_, player| !connection_map.0.contains_key(&player.connection.id()));
+    for packet in packets.read() {
+        let id = packet.connection.id();
+        match packet.packet {
+            ClientPacket::CreateSeek {
+                handicap,
+                variant,
+                time_control,
+                time_odds,
+                rated,
+                color,
+            } => {
+                let seek = Seek {
+                    variant,
+                    time_control,
+                    time_odds,
+                    rated,
+                };
+                if connection_map.0.contains_key(&id) {
+                    warn!("{:?} offered a game during a game", id);
+                    continue;
+                }
+                if !seek.is_valid() {
+                    warn!("{:?} offered a game that can't exist", id);
+                    continue;
+                }
+                if !rules.allows(&seek) {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(ServerError::TimeControlNotAllowed))
+                        .unwrap_or_else(connection_error);
+                    continue;
+                }
+                if seek.rated && !accounts.registered(id) {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(ServerError::AccountRequired))
+                        .unwrap_or_else(connection_error);
+                    continue;
+                }
+                // a new seek replaces the one offered before
+                seeks.remove_connection(id);
+                let (player, name) = identify(id, &accounts, &names, &mut next_player);
+                let seek_id = SeekId(seeks.next_id);
+                seeks.next_id += 1;
+                seeks.open.insert(
+                    seek_id,
+                    QueuedPlayer {
+                        connection: packet.connection.clone(),
+                        id: player,
+                        name,
+                        handicap,
+                        seek,
+                        color,
+                    },
+                );
+            }
+            ClientPacket::ListSeeks => {
+                let list = seeks
+                    .open
+                    .iter()
+                    .filter(|(_, player)| player.connection.id() != id)
+                    .map(|(&seek_id, player)| OpenSeek {
+                        id: seek_id,
+                        player: PlayerInfo {
+                            id: player.id,
+                            name: player.name.clone(),
+                            rating: Some(ratings::get(&*store.0, player.id)),
+                            bot: engines.contains(player.connection.id()),
+                        },
+                        handicap: player.handicap,
+                        seek: player.seek,
+                        color: player.color,
+                    })
+                    .collect();
+                packet
+                    .connection
+                    .send(ServerPacket::Seeks(list))
+                    .unwrap_or_else(connection_error);
+            }
+            ClientPacket::AcceptSeek(seek_id) => {
+                if connection_map.0.contains_key(&id) {
+                    warn!("{:?} accepted a seek during a game", id);
+                    continue;
+                }
+                if seeks
+                    .open
+                    .get(&seek_id)
+                    .is_some_and(|player| player.connection.id() == id)
+                {
+                    warn!("{:?} tried to accept their own seek", id);
+                    continue;
+                }
+                if seeks
+                    .open
+                    .get(&seek_id)
+                    .is_some_and(|player| player.seek.rated && !accounts.registered(id))
+                {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(ServerError::AccountRequired))
+                        .unwrap_or_else(connection_error);
+                    continue;
+                }
+                let Some(player) = seeks.open.remove(&seek_id) else {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(ServerError::NoSuchSeek))
+                        .unwrap_or_else(connection_error);
+                    continue;
+                };
+                // someone who accepts can't be offering a game too
+                seeks.remove_connection(id);
+                let (accepter, name) = identify(id, &accounts, &names, &mut next_player);
+                let opponent = QueuedPlayer {
+                    connection: packet.connection.clone(),
+                    id: accepter,
+                    name,
+                    handicap: Handicap::None,
+                    seek: player.seek.mirrored(),
+                    color: ColorPreference::Random,
+                };
+                start_game.send(StartGameEvent::seat(player, opponent));
+            }
+            ClientPacket::LeaveQueue => seeks.remove_connection(id),
+            _ => {}
+        }
+    }
+}
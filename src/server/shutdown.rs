@@ -0,0 +1,119 @@
+//! stops the server on ctrl-c, SIGTERM or /shutdown without losing the running games
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use bevy::{app::AppExit, prelude::*};
+use bevy_slinet::server::{NewConnectionEvent, ServerConnections};
+
+use super::{connection_error, persist, storage::Store, ChessGames, EndGameEvent};
+use crate::api::{EndReason, GameEnd, ServerNetConfig, ServerPacket};
+
+/// how long the players get to receive the last packets before the server exits
+const FLUSH_WAIT: Duration = Duration::from_secs(1);
+
+#[derive(Resource, Debug)]
+pub struct Shutdown {
+    /// set from the signal handler
+    requested: Arc<AtomicBool>,
+    /// whether the running games are saved somewhere they can be resumed from, otherwise
+    /// they get aborted
+    persistent: bool,
+    /// counts down once everyone was told
+    flush: Option<Timer>,
+}
+
+impl Shutdown {
+    /// listens for ctrl-c and SIGTERM, a second one exits right away
+    pub fn new(persistent: bool) -> Self {
+        let requested = Arc::new(AtomicBool::new(false));
+        let flag = requested.clone();
+        if let Err(error) = ctrlc::set_handler(move || {
+            if flag.swap(true, Ordering::SeqCst) {
+                std::process::exit(1);
+            }
+        }) {
+            warn!("couldn't listen for ctrl-c: {}", error);
+        }
+        Self {
+            requested,
+            persistent,
+            flush: None,
+        }
+    }
+
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.flush.is_some()
+    }
+}
+
+/// tells everyone the server is going down, aborts the games that can't be saved
+/// and turns new connections away
+pub fn begin_shutdown(
+    mut shutdown: ResMut<Shutdown>,
+    mut new_connections: EventReader<NewConnectionEvent<ServerNetConfig>>,
+    connections: Res<ServerConnections<ServerNetConfig>>,
+    game_map: ChessGames,
+    mut end_game: EventWriter<EndGameEvent>,
+) {
+    if shutdown.is_shutting_down() {
+        for event in new_connections.read() {
+            event.connection.disconnect();
+        }
+        return;
+    }
+    if !shutdown.requested.load(Ordering::SeqCst) {
+        return;
+    }
+    info!("shutting down");
+    let message = if shutdown.persistent {
+        "the server is shutting down, running games can be resumed once it is back"
+    } else {
+        "the server is shutting down, running games are aborted"
+    };
+    for connection in connections.iter() {
+        connection
+            .send(ServerPacket::Announcement(message.to_string()))
+            .unwrap_or_else(connection_error);
+    }
+    if !shutdown.persistent {
+        for &id in game_map.keys() {
+            end_game.send(EndGameEvent(id, GameEnd::Draw(EndReason::Aborted)));
+        }
+    }
+    shutdown.flush = Some(Timer::new(FLUSH_WAIT, TimerMode::Once));
+}
+
+/// saves the running games, closes every connection and exits
+pub fn finish_shutdown(
+    mut shutdown: ResMut<Shutdown>,
+    time: Res<Time>,
+    game_map: ChessGames,
+    mut store: ResMut<Store>,
+    connections: Res<ServerConnections<ServerNetConfig>>,
+    mut exit: EventWriter<AppExit>,
+) {
+    let Some(flush) = &mut shutdown.flush else {
+        return;
+    };
+    if !flush.tick(time.delta()).just_finished() {
+        return;
+    }
+    if shutdown.persistent {
+        persist::save(&game_map, &mut store);
+        info!("saved {} running games", game_map.len());
+    }
+    for connection in connections.iter() {
+        connection.disconnect();
+    }
+    exit.send(AppExit::Success);
+}
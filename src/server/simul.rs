@@ -0,0 +1,294 @@
+use std::collections::{HashMap, HashSet};
+
+use bevy::prelude::*;
+use bevy_slinet::{connection::ConnectionId, server::PacketReceiveEvent};
+
+use super::{
+    accounts::Accounts, bots::Engines, config::GameRules, connection_error, identify, ratings,
+    storage::Store, ConnectionMap, EndGameEvent, PlayerNames, Players, QueuedPlayer,
+    StartGameEvent,
+};
+use crate::api::{
+    handicap::Handicap,
+    matchmaking::{ColorPreference, Seek, Variant},
+    simul::{SimulId, SimulInfo},
+    ClientPacket, GameId, PlayerId, PlayerInfo, ServerError, ServerNetConfig, ServerPacket,
+};
+
+#[derive(Debug)]
+struct Participant {
+    player: QueuedPlayer,
+    info: PlayerInfo,
+}
+
+impl Participant {
+    fn new(player: QueuedPlayer, store: &Store, engines: &Engines) -> Self {
+        let info = PlayerInfo {
+            id: player.id,
+            name: player.name.clone(),
+            rating: Some(ratings::get(&*store.0, player.id)),
+            bot: engines.contains(player.connection.id()),
+        };
+        Self { player, info }
+    }
+
+    fn connection_id(&self) -> ConnectionId {
+        self.player.connection.id()
+    }
+}
+
+#[derive(Debug)]
+struct Simul {
+    /// plays white on every board
+    host: Participant,
+    players: Vec<Participant>,
+}
+
+impl Simul {
+    fn is_participant(&self, connection_id: ConnectionId) -> bool {
+        self.host.connection_id() == connection_id
+            || self
+                .players
+                .iter()
+                .any(|p| p.connection_id() == connection_id)
+    }
+
+    /// tells the host and everyone who joined who is in
+    fn send_info(&self, id: SimulId) {
+        let simul = SimulInfo {
+            id,
+            host: self.host.info.clone(),
+            time_control: self.host.player.seek.time_control,
+            players: self.players.iter().map(|p| p.info.clone()).collect(),
+        };
+        for participant in std::iter::once(&self.host).chain(&self.players) {
+            participant
+                .player
+                .connection
+                .send(ServerPacket::Simul(simul.clone()))
+                .unwrap_or_else(connection_error);
+        }
+    }
+}
+
+/// a simul whose boards are being played
+#[derive(Debug)]
+struct RunningSimul {
+    host: PlayerId,
+    /// opponents whose game was asked for but hasn't shown up in the game map yet
+    starting: Vec<PlayerId>,
+    /// the boards that are still being played
+    games: HashSet<GameId>,
+}
+
+/// simuls that take players until their host starts them, and the ones being played
+#[derive(Resource, Default, Debug)]
+pub struct Simuls {
+    simuls: HashMap<SimulId, Simul>,
+    running: HashMap<SimulId, RunningSimul>,
+    next_id: u32,
+}
+
+impl Simuls {
+    /// whether `player` hosts the simul `game` is a board of
+    pub fn is_host(&self, game: GameId, player: PlayerId) -> bool {
+        self.running
+            .values()
+            .any(|simul| simul.host == player && simul.games.contains(&game))
+    }
+
+    /// forgets a player who left, and closes the simul when it was its host
+    pub fn remove_connection(&mut self, connection_id: ConnectionId) {
+        self.simuls.retain(|&id, simul| {
+            if !simul.is_participant(connection_id) {
+                return true;
+            }
+            if simul.host.connection_id() != connection_id {
+                simul.players.retain(|p| p.connection_id() != connection_id);
+                simul.send_info(id);
+                return true;
+            }
+            for participant in &simul.players {
+                participant
+                    .player
+                    .connection
+                    .send(ServerPacket::Error(ServerError::NoSuchSimul))
+                    .unwrap_or_else(connection_error);
+            }
+            false
+        });
+    }
+}
+
+/// opens, joins and starts simuls
+pub fn simuls(
+    mut packets: EventReader<PacketReceiveEvent<ServerNetConfig>>,
+    mut simuls: ResMut<Simuls>,
+    mut start_game: EventWriter<StartGameEvent>,
+    connection_map: Res<ConnectionMap>,
+    accounts: Res<Accounts>,
+    names: Res<PlayerNames>,
+    mut next_player: ResMut<PlayerId>,
+    store: Res<Store>,
+    rules: Res<GameRules>,
+    engines: Res<Engines>,
+) {
+    for packet in packets.read() {
+        let connection_id = packet.connection.id();
+        match packet.packet {
+            ClientPacket::CreateSimul { time_control } => {
+                if connection_map.0.contains_key(&connection_id) {
+                    warn!("{:?} opened a simul during a game", connection_id);
+                    continue;
+                }
+                // one player against many isn't a fair fight for the ratings
+                let seek = Seek {
+                    variant: Variant::Standard,
+                    time_control,
+                    time_odds: None,
+                    rated: false,
+                };
+                if !seek.is_valid() {
+                    warn!(
+                        "{:?} opened a simul for games that can't exist",
+                        connection_id
+                    );
+                    continue;
+                }
+                if !rules.allows(&seek) {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(ServerError::TimeControlNotAllowed))
+                        .unwrap_or_else(connection_error);
+                    continue;
+                }
+                // a new simul replaces the one opened or joined before
+                simuls.remove_connection(connection_id);
+                let (player, name) = identify(connection_id, &accounts, &names, &mut next_player);
+                let id = SimulId(simuls.next_id);
+                simuls.next_id += 1;
+                info!("{} opened simul {:?}", name, id);
+                let host = QueuedPlayer {
+                    connection: packet.connection.clone(),
+                    id: player,
+                    name,
+                    handicap: Handicap::None,
+                    seek,
+                    color: ColorPreference::White,
+                };
+                let simul = Simul {
+                    host: Participant::new(host, &store, &engines),
+                    players: Vec::new(),
+                };
+                simul.send_info(id);
+                simuls.simuls.insert(id, simul);
+            }
+            ClientPacket::JoinSimul(id) => {
+                if connection_map.0.contains_key(&connection_id) {
+                    warn!("{:?} joined a simul during a game", connection_id);
+                    continue;
+                }
+                if simuls
+                    .simuls
+                    .get(&id)
+                    .is_some_and(|simul| simul.is_participant(connection_id))
+                {
+                    warn!("{:?} joined simul {:?} twice", connection_id, id);
+                    continue;
+                }
+                if !simuls.simuls.contains_key(&id) {
+                    packet
+                        .connection
+                        .send(ServerPacket::Error(ServerError::NoSuchSimul))
+                        .unwrap_or_else(connection_error);
+                    continue;
+                }
+                simuls.remove_connection(connection_id);
+                let (player, name) = identify(connection_id, &accounts, &names, &mut next_player);
+                let Some(simul) = simuls.simuls.get_mut(&id) else {
+                    continue;
+                };
+                let player = QueuedPlayer {
+                    connection: packet.connection.clone(),
+                    id: player,
+                    name,
+                    handicap: Handicap::None,
+                    seek: simul.host.player.seek,
+                    color: ColorPreference::Black,
+                };
+                simul
+                    .players
+                    .push(Participant::new(player, &store, &engines));
+                simul.send_info(id);
+            }
+            ClientPacket::StartSimul(id) => {
+                if simuls
+                    .simuls
+                    .get(&id)
+                    .is_none_or(|simul| simul.host.connection_id() != connection_id)
+                {
+                    warn!("{:?} can't start simul {:?}", connection_id, id);
+                    continue;
+                }
+                if simuls.simuls[&id].players.is_empty() {
+                    warn!("simul {:?} needs an opponent to start", id);
+                    continue;
+                }
+                let Some(simul) = simuls.simuls.remove(&id) else {
+                    continue;
+                };
+                info!("simul {:?} started with {} boards", id, simul.players.len());
+                let mut running = RunningSimul {
+                    host: simul.host.info.id,
+                    starting: Vec::new(),
+                    games: HashSet::new(),
+                };
+                // players who got a game some other way in the meantime sit this one out
+                for Participant { player, .. } in simul.players {
+                    if connection_map.0.contains_key(&player.connection.id()) {
+                        player
+                            .connection
+                            .send(ServerPacket::Error(ServerError::NoSuchSimul))
+                            .unwrap_or_else(connection_error);
+                        continue;
+                    }
+                    running.starting.push(player.id);
+                    start_game.send(StartGameEvent {
+                        white: simul.host.player.clone(),
+                        black: player,
+                    });
+                }
+                simuls.running.insert(id, running);
+            }
+            ClientPacket::LeaveQueue => simuls.remove_connection(connection_id),
+            _ => {}
+        }
+    }
+}
+
+/// keeps track of the boards of the started simuls until all of them are played
+pub fn run_simuls(
+    mut simuls: ResMut<Simuls>,
+    mut ended: EventReader<EndGameEvent>,
+    games: Query<(&GameId, &Players)>,
+) {
+    let ended: Vec<_> = ended.read().map(|event| event.0).collect();
+    simuls.running.retain(|&id, simul| {
+        for game in &ended {
+            simul.games.remove(game);
+        }
+        // the games asked for last frame are in the game map now
+        for opponent in std::mem::take(&mut simul.starting) {
+            match games
+                .iter()
+                .find(|(_, players)| players.white_id == simul.host && players.black_id == opponent)
+            {
+                Some((&game, _)) => {
+                    simul.games.insert(game);
+                }
+                None => warn!("simul {:?} lost a game it started", id),
+            }
+        }
+        !simul.games.is_empty()
+    });
+}
@@ -0,0 +1,310 @@
+//! load tests a real server with pairs of engines playing random moves over local connections,
+//! and counts every time the server and the rules the engines follow disagree
+
+use std::{collections::HashMap, net::SocketAddr, time::Duration};
+
+use bevy::{app::AppExit, prelude::*};
+use bevy_slinet::{
+    client::{
+        ClientConnections, ClientPlugin, ConnectionEstablishEvent, ConnectionRequestEvent,
+        PacketReceiveEvent,
+    },
+    connection::ConnectionId,
+};
+use rand::prelude::*;
+
+use super::{connection_error, start_server, ServerOptions};
+use crate::api::{
+    chessmove::{compress_chessboard, ChessColor, ChessPieceType, CompressedChessboard},
+    chessstate::ChessState,
+    handicap::Handicap,
+    matchmaking::{ColorPreference, Variant},
+    selfplay::MAX_PLIES,
+    ClientNetConfig, ClientPacket, EndReason, GameEnd, GameId, ServerError, ServerPacket,
+};
+
+/// how often the simulation prints how it is going
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy)]
+pub struct SimulateOptions {
+    /// engines that play each other at the same time, two per game
+    pub pairs: u32,
+    /// the simulation stops after this many finished games
+    pub games: u64,
+    /// how long an engine waits before it moves, the server rate limits faster players
+    pub think: Duration,
+}
+
+/// how long an engine waits before trying a rate limited move again
+const RETRY_AFTER: Duration = Duration::from_secs(1);
+
+/// the game an engine is playing
+#[derive(Debug)]
+struct Seat {
+    game: GameId,
+    color: ChessColor,
+    start: ChessState,
+    state: ChessState,
+    boards: Vec<CompressedChessboard>,
+    /// when to play the next move, in time since startup
+    move_at: Option<Duration>,
+}
+
+#[derive(Resource, Debug)]
+struct Simulation {
+    options: SimulateOptions,
+    seats: HashMap<ConnectionId, Seat>,
+    games: u64,
+    moves: u64,
+    disagreements: u64,
+    rate_limited: u64,
+    report: Timer,
+}
+
+impl Simulation {
+    fn disagree(&mut self, game: GameId, what: &str) {
+        self.disagreements += 1;
+        println!("game {}: {}", game.0, what);
+    }
+
+    fn report(&self, elapsed: Duration) -> String {
+        format!(
+            "{} games and {} moves in {:.0?}, {:.1} moves/second, {} disagreements, {} rate limited packets",
+            self.games,
+            self.moves,
+            elapsed,
+            self.moves as f64 / elapsed.as_secs_f64(),
+            self.disagreements,
+            self.rate_limited
+        )
+    }
+}
+
+/// starts a server on `addr` and plays games against it until enough of them finished,
+/// exits with an error when the server and the engines disagreed on the rules
+pub fn run(addr: SocketAddr, options: SimulateOptions) {
+    // the server's own bot would play the engines too, and its games aren't the ones tested
+    let mut server_options = ServerOptions::default();
+    server_options.rules.bot_after = None;
+    std::thread::spawn(move || start_server(addr, server_options));
+    // gives the server a moment to start listening
+    std::thread::sleep(Duration::from_secs(1));
+    let mut app = App::new();
+    app.add_plugins(MinimalPlugins)
+        .add_plugins(ClientPlugin::<ClientNetConfig>::new())
+        .insert_resource(Simulation {
+            options,
+            seats: HashMap::new(),
+            games: 0,
+            moves: 0,
+            disagreements: 0,
+            rate_limited: 0,
+            report: Timer::new(REPORT_INTERVAL, TimerMode::Repeating),
+        })
+        .add_systems(
+            Startup,
+            move |mut requests: EventWriter<ConnectionRequestEvent<ClientNetConfig>>| {
+                for _ in 0..options.pairs * 2 {
+                    requests.send(ConnectionRequestEvent::new(addr));
+                }
+            },
+        )
+        .add_systems(
+            Update,
+            (join_queue, follow_games, play_moves, report).chain(),
+        );
+    app.run();
+    let simulation = app.world().resource::<Simulation>();
+    std::process::exit(if simulation.disagreements == 0 { 0 } else { 1 });
+}
+
+/// says hello as a bot, so the engines only get paired with each other
+fn join_queue(
+    mut connections: EventReader<ConnectionEstablishEvent<ClientNetConfig>>,
+    mut engines: Local<u32>,
+) {
+    for event in connections.read() {
+        *engines += 1;
+        let packets = [
+            ClientPacket::Hello {
+                name: format!("simulated{}", *engines),
+                bot: true,
+            },
+            queue_packet(),
+        ];
+        for packet in packets {
+            event
+                .connection
+                .send(packet)
+                .unwrap_or_else(connection_error);
+        }
+    }
+}
+
+fn queue_packet() -> ClientPacket {
+    ClientPacket::JoinQueue {
+        handicap: Handicap::None,
+        variant: Variant::Standard,
+        time_control: None,
+        time_odds: None,
+        rated: false,
+        color: ColorPreference::Random,
+    }
+}
+
+/// replays every position the server sends and checks how it ends games
+fn follow_games(
+    mut packets: EventReader<PacketReceiveEvent<ClientNetConfig>>,
+    mut simulation: ResMut<Simulation>,
+    time: Res<Time>,
+) {
+    let think = simulation.options.think;
+    for packet in packets.read() {
+        let id = packet.connection.id();
+        match &packet.packet {
+            ServerPacket::MatchFound {
+                game, color, start, ..
+            } => {
+                simulation.seats.insert(
+                    id,
+                    Seat {
+                        game: *game,
+                        color: *color,
+                        start: *start,
+                        state: *start,
+                        boards: Vec::new(),
+                        move_at: (start.turn == *color).then(|| time.elapsed() + think),
+                    },
+                );
+            }
+            ServerPacket::Position { game, moves, .. } => {
+                let Some(seat) = simulation.seats.get_mut(&id).filter(|s| s.game == *game) else {
+                    continue;
+                };
+                let mut state = seat.start;
+                let mut boards = Vec::new();
+                let mut legal = true;
+                for &played_move in moves {
+                    if state.play_move(played_move).is_err() {
+                        legal = false;
+                        break;
+                    }
+                    boards.push(compress_chessboard(&state.board));
+                }
+                seat.state = state;
+                seat.boards = boards;
+                seat.move_at = (seat.state.turn == seat.color && !seat.state.should_promote)
+                    .then(|| time.elapsed() + think);
+                if seat.color == ChessColor::White {
+                    simulation.moves += 1;
+                }
+                if !legal {
+                    simulation.disagree(*game, "the server accepted a move the rules don't allow");
+                }
+            }
+            // the server only resends the game when it refused a move
+            ServerPacket::Resync { game, .. } => {
+                simulation.disagree(*game, "the server refused a legal move");
+            }
+            ServerPacket::EndGame(game, end) => {
+                let Some(seat) = simulation.seats.remove(&id).filter(|s| s.game == *game) else {
+                    continue;
+                };
+                packet
+                    .connection
+                    .send(queue_packet())
+                    .unwrap_or_else(connection_error);
+                // both engines hear about the end, white counts it
+                if seat.color == ChessColor::Black {
+                    continue;
+                }
+                simulation.games += 1;
+                let reason = match *end {
+                    GameEnd::White(reason) | GameEnd::Black(reason) | GameEnd::Draw(reason) => {
+                        reason
+                    }
+                };
+                let by_rules = matches!(
+                    reason,
+                    EndReason::Checkmate
+                        | EndReason::Stalemate
+                        | EndReason::InsufficientMaterial
+                        | EndReason::RepetitionOfMoves
+                        | EndReason::FiftyMoveRule
+                );
+                if by_rules && seat.state.check_game_end(&seat.boards) != Some(*end) {
+                    simulation
+                        .disagree(*game, &format!("the server ended the game with {:?}", end));
+                }
+            }
+            ServerPacket::Error(ServerError::RateLimited) => {
+                simulation.rate_limited += 1;
+                // the move may have been the packet that got dropped, a move that did get
+                // through brings a position which cancels the retry
+                if let Some(seat) = simulation.seats.get_mut(&id) {
+                    if seat.state.turn == seat.color && !seat.state.should_promote {
+                        seat.move_at = Some(time.elapsed() + think.max(RETRY_AFTER));
+                    }
+                }
+            }
+            ServerPacket::Ping(sent) => packet
+                .connection
+                .send(ClientPacket::Pong(*sent))
+                .unwrap_or_else(connection_error),
+            _ => {}
+        }
+    }
+}
+
+/// plays a random legal move for every engine whose turn it is, long games end in a draw
+fn play_moves(
+    mut simulation: ResMut<Simulation>,
+    connections: Res<ClientConnections<ClientNetConfig>>,
+    time: Res<Time>,
+) {
+    let now = time.elapsed();
+    let mut rng = thread_rng();
+    let mut stuck = Vec::new();
+    for connection in connections.iter() {
+        let Some(seat) = simulation.seats.get_mut(&connection.id()) else {
+            continue;
+        };
+        if seat.move_at.is_none_or(|at| at > now) {
+            continue;
+        }
+        seat.move_at = None;
+        let Some(&chess_move) = seat.state.legal_moves().choose(&mut rng) else {
+            // the game should have ended by now
+            stuck.push(seat.game);
+            continue;
+        };
+        let ply = seat.boards.len() as u32;
+        let mut packets = Vec::new();
+        if ply as usize >= MAX_PLIES {
+            // the other engine accepts by offering too
+            packets.push(ClientPacket::RequestDraw(seat.game));
+        }
+        packets.push(ClientPacket::Move(seat.game, ply, chess_move));
+        let mut after = seat.state;
+        if after.move_piece(chess_move).is_ok() && after.should_promote {
+            packets.push(ClientPacket::Promotion(seat.game, ChessPieceType::Queen));
+        }
+        for packet in packets {
+            connection.send(packet).unwrap_or_else(connection_error);
+        }
+    }
+    for game in stuck {
+        simulation.disagree(game, "the server didn't end a game without legal moves");
+    }
+}
+
+fn report(mut simulation: ResMut<Simulation>, time: Res<Time>, mut exit: EventWriter<AppExit>) {
+    let elapsed = time.elapsed();
+    if simulation.games >= simulation.options.games {
+        println!("{}", simulation.report(elapsed));
+        exit.send(AppExit::Success);
+    } else if simulation.report.tick(time.delta()).just_finished() {
+        println!("{}", simulation.report(elapsed));
+    }
+}
@@ -0,0 +1,51 @@
+use bevy::prelude::*;
+use bevy_slinet::{connection::EcsConnection, server::DisconnectionEvent};
+
+use super::{connection_error, ChessGameMap, Players, Spectators};
+use crate::api::{chessmove::ChessColor, GameId, ServerNetConfig, ServerPacket};
+
+/// adds a connection to the spectators of a game and sends it everything played so far
+pub fn spectate(game_map: &mut ChessGameMap, connection: &EcsConnection<ServerPacket>, id: GameId) {
+    let Some(mut game) = game_map.get_mut(&id) else {
+        return warn!(
+            "{:?} tried to spectate unknown game {:?}",
+            connection.id(),
+            id
+        );
+    };
+    if game.players.color_of(connection.id()).is_some()
+        || game.spectators.0.iter().any(|s| s.id() == connection.id())
+    {
+        return warn!("{:?} is already watching game {:?}", connection.id(), id);
+    }
+    connection
+        .send(ServerPacket::SpectateState {
+            game: id,
+            white: game.players.player(ChessColor::White),
+            black: game.players.player(ChessColor::Black),
+            start: game.board.start,
+            moves: game.board.moves.clone(),
+            clock: game.clock.0,
+        })
+        .unwrap_or_else(connection_error);
+    game.spectators.0.push(connection.clone());
+    game.spectators.send_count(id, &game.players);
+}
+
+/// stops sending connections that left the games they watched, the games go on as if nothing
+/// happened
+pub fn remove_spectators(
+    mut disconnections: EventReader<DisconnectionEvent<ServerNetConfig>>,
+    mut games: Query<(&GameId, &Players, &mut Spectators)>,
+) {
+    for disconnection in disconnections.read() {
+        let connection_id = disconnection.connection.id();
+        for (&id, players, mut spectators) in &mut games {
+            let watching = spectators.0.len();
+            spectators.0.retain(|s| s.id() != connection_id);
+            if spectators.0.len() != watching {
+                spectators.send_count(id, players);
+            }
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use bevy::prelude::*;
+use bevy_slinet::server::{PacketReceiveEvent, ServerConnections};
+
+use super::{connection_error, queue::GameQueue, ChessGames, OpeningStats};
+use crate::api::{ClientPacket, ServerNetConfig, ServerPacket};
+
+/// tells whoever asks how busy the server is, ratelimit::filter_packets drops the asking of
+/// connections that do it too often
+pub fn send_stats(
+    mut packets: EventReader<PacketReceiveEvent<ServerNetConfig>>,
+    connections: Res<ServerConnections<ServerNetConfig>>,
+    game_map: ChessGames,
+    queue: Res<GameQueue>,
+    opening_stats: Res<OpeningStats>,
+    time: Res<Time>,
+) {
+    for packet in packets.read() {
+        if !matches!(packet.packet, ClientPacket::ServerStats) {
+            continue;
+        }
+        packet
+            .connection
+            .send(ServerPacket::ServerStats {
+                games: game_map.len() as u32,
+                players_online: connections.iter().count() as u32,
+                queue_length: queue.players_waiting() as u32,
+                uptime: time.elapsed().as_secs(),
+                opening: opening_stats.most_played().map(str::to_string),
+            })
+            .unwrap_or_else(connection_error);
+    }
+}
@@ -0,0 +1,230 @@
+// accounts are only registered with the accounts feature
+#![cfg_attr(not(all(feature = "accounts", feature = "sqlite")), allow(dead_code))]
+
+use std::{collections::HashMap, error::Error};
+
+use bevy::prelude::*;
+
+#[cfg(feature = "sqlite")]
+use super::database::SqliteStorage;
+use super::{accounts::Account, persist::SavedGame, ratings::PlayerRating};
+use crate::api::{
+    analysis::GameAnalysis, chessmove::PlayedMove, chessstate::ChessState, puzzle::Puzzle, GameEnd,
+    PlayerId, ReplayId, SessionToken,
+};
+
+pub type StorageResult<T> = Result<T, Box<dyn Error + Send + Sync>>;
+
+/// a finished game, kept as the history of its players
+#[derive(Debug, Clone)]
+pub struct GameRecord {
+    pub white: PlayerId,
+    pub black: PlayerId,
+    pub white_name: String,
+    pub black_name: String,
+    /// the position before the first move, games with odds don't start from the usual one
+    pub start: ChessState,
+    pub moves: Vec<PlayedMove>,
+    pub result: GameEnd,
+    pub rated: bool,
+    /// milliseconds since the unix epoch
+    pub ended: u64,
+}
+
+impl GameRecord {
+    pub fn played_by(&self, player: PlayerId) -> bool {
+        self.white == player || self.black == player
+    }
+}
+
+/// everything the server remembers longer than a single game
+pub trait Storage: Send + Sync + 'static {
+    /// the first id no account, rating or finished game has, anonymous players got ids too
+    fn next_player_id(&self) -> StorageResult<PlayerId>;
+    /// false when someone registered the name already
+    fn add_account(&mut self, account: &Account, password_hash: &str) -> StorageResult<bool>;
+    /// the account with this name and the hash of its password
+    fn account_by_name(&self, name: &str) -> StorageResult<Option<(Account, String)>>;
+    /// false when someone registered the name already
+    fn rename_account(&mut self, player: PlayerId, name: &str) -> StorageResult<bool>;
+    fn add_session(&mut self, token: SessionToken, player: PlayerId) -> StorageResult<()>;
+    /// the account a token logs into
+    fn session(&self, token: SessionToken) -> StorageResult<Option<Account>>;
+
+    /// None for players that never finished a rated game
+    fn rating(&self, player: PlayerId) -> StorageResult<Option<PlayerRating>>;
+    fn set_rating(&mut self, player: PlayerId, rating: PlayerRating) -> StorageResult<()>;
+
+    /// the id the game can be fetched with later
+    fn save_game(&mut self, game: GameRecord) -> StorageResult<ReplayId>;
+    /// the last `limit` games the player finished before `before`, the newest first
+    fn recent_games(
+        &self,
+        player: PlayerId,
+        before: Option<ReplayId>,
+        limit: usize,
+    ) -> StorageResult<Vec<(ReplayId, GameRecord)>>;
+    fn game(&self, id: ReplayId) -> StorageResult<Option<GameRecord>>;
+
+    fn save_analysis(&mut self, game: ReplayId, analysis: &GameAnalysis) -> StorageResult<()>;
+    /// None until the game got analyzed
+    fn analysis(&self, game: ReplayId) -> StorageResult<Option<GameAnalysis>>;
+
+    fn add_puzzles(&mut self, puzzles: &[Puzzle]) -> StorageResult<()>;
+    fn puzzles(&self) -> StorageResult<Vec<Puzzle>>;
+
+    /// replaces the saved running games with these
+    fn save_ongoing_games(&mut self, games: &[SavedGame]) -> StorageResult<()>;
+    fn ongoing_games(&self) -> StorageResult<Vec<SavedGame>>;
+}
+
+/// the storage the server runs with
+#[derive(Resource, Deref, DerefMut)]
+pub struct Store(pub Box<dyn Storage>);
+
+impl Store {
+    /// a sqlite database at `database`, or memory that is gone when the server stops
+    pub fn open(database: Option<&str>) -> Self {
+        match database {
+            #[cfg(feature = "sqlite")]
+            Some(path) => Self(Box::new(
+                SqliteStorage::open(path).expect("couldn't open the database"),
+            )),
+            #[cfg(not(feature = "sqlite"))]
+            Some(_) => panic!("the server needs the sqlite feature for a database"),
+            None => Self(Box::<MemoryStorage>::default()),
+        }
+    }
+}
+
+/// keeps everything in memory, for servers that don't need to remember anything
+#[derive(Default, Debug)]
+pub struct MemoryStorage {
+    accounts: HashMap<String, (Account, String)>,
+    sessions: HashMap<SessionToken, PlayerId>,
+    ratings: HashMap<PlayerId, PlayerRating>,
+    games: Vec<GameRecord>,
+    analyses: HashMap<ReplayId, GameAnalysis>,
+    puzzles: Vec<Puzzle>,
+    ongoing_games: Vec<SavedGame>,
+}
+
+impl Storage for MemoryStorage {
+    fn next_player_id(&self) -> StorageResult<PlayerId> {
+        let accounts = self.accounts.values().map(|(account, _)| account.id);
+        let games = self.games.iter().flat_map(|game| [game.white, game.black]);
+        Ok(accounts
+            .chain(self.ratings.keys().copied())
+            .chain(games)
+            .map(|id| PlayerId(id.0 + 1))
+            .max()
+            .unwrap_or_default())
+    }
+
+    fn add_account(&mut self, account: &Account, password_hash: &str) -> StorageResult<bool> {
+        if self.accounts.contains_key(&account.name) {
+            return Ok(false);
+        }
+        self.accounts.insert(
+            account.name.clone(),
+            (account.clone(), password_hash.to_string()),
+        );
+        Ok(true)
+    }
+
+    fn account_by_name(&self, name: &str) -> StorageResult<Option<(Account, String)>> {
+        Ok(self.accounts.get(name).cloned())
+    }
+
+    fn rename_account(&mut self, player: PlayerId, name: &str) -> StorageResult<bool> {
+        if self.accounts.contains_key(name) {
+            return Ok(false);
+        }
+        let (mut account, hash) = self
+            .accounts
+            .values()
+            .find(|(account, _)| account.id == player)
+            .cloned()
+            .ok_or("no account with that id")?;
+        self.accounts.remove(&account.name);
+        account.name = name.to_string();
+        self.accounts.insert(account.name.clone(), (account, hash));
+        Ok(true)
+    }
+
+    fn add_session(&mut self, token: SessionToken, player: PlayerId) -> StorageResult<()> {
+        self.sessions.insert(token, player);
+        Ok(())
+    }
+
+    fn session(&self, token: SessionToken) -> StorageResult<Option<Account>> {
+        Ok(self.sessions.get(&token).and_then(|&player| {
+            self.accounts
+                .values()
+                .find(|(account, _)| account.id == player)
+                .map(|(account, _)| account.clone())
+        }))
+    }
+
+    fn rating(&self, player: PlayerId) -> StorageResult<Option<PlayerRating>> {
+        Ok(self.ratings.get(&player).copied())
+    }
+
+    fn set_rating(&mut self, player: PlayerId, rating: PlayerRating) -> StorageResult<()> {
+        self.ratings.insert(player, rating);
+        Ok(())
+    }
+
+    fn save_game(&mut self, game: GameRecord) -> StorageResult<ReplayId> {
+        self.games.push(game);
+        Ok(ReplayId(self.games.len() as u64 - 1))
+    }
+
+    fn recent_games(
+        &self,
+        player: PlayerId,
+        before: Option<ReplayId>,
+        limit: usize,
+    ) -> StorageResult<Vec<(ReplayId, GameRecord)>> {
+        let end = before.map_or(self.games.len(), |id| (id.0 as usize).min(self.games.len()));
+        Ok(self.games[..end]
+            .iter()
+            .enumerate()
+            .rev()
+            .filter(|(_, game)| game.white == player || game.black == player)
+            .take(limit)
+            .map(|(id, game)| (ReplayId(id as u64), game.clone()))
+            .collect())
+    }
+
+    fn game(&self, id: ReplayId) -> StorageResult<Option<GameRecord>> {
+        Ok(self.games.get(id.0 as usize).cloned())
+    }
+
+    fn save_analysis(&mut self, game: ReplayId, analysis: &GameAnalysis) -> StorageResult<()> {
+        self.analyses.insert(game, analysis.clone());
+        Ok(())
+    }
+
+    fn analysis(&self, game: ReplayId) -> StorageResult<Option<GameAnalysis>> {
+        Ok(self.analyses.get(&game).cloned())
+    }
+
+    fn add_puzzles(&mut self, puzzles: &[Puzzle]) -> StorageResult<()> {
+        self.puzzles.extend_from_slice(puzzles);
+        Ok(())
+    }
+
+    fn puzzles(&self) -> StorageResult<Vec<Puzzle>> {
+        Ok(self.puzzles.clone())
+    }
+
+    fn save_ongoing_games(&mut self, games: &[SavedGame]) -> StorageResult<()> {
+        self.ongoing_games = games.to_vec();
+        Ok(())
+    }
+
+    fn ongoing_games(&self) -> StorageResult<Vec<SavedGame>> {
+        Ok(self.ongoing_games.clone())
+    }
+}
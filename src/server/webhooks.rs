@@ -0,0 +1,153 @@
+//! posts json to urls the server is given whenever a game starts or ends, so bots and websites
+//! can follow the games without speaking the game protocol
+
+use std::{
+    collections::HashSet,
+    io::{self, BufRead, BufReader, Write},
+    net::{TcpStream, ToSocketAddrs},
+    sync::mpsc::{self, Sender},
+    time::Duration,
+};
+
+use bevy::prelude::*;
+use serde::Serialize;
+
+use super::{archive, Board, ChessGames, Players};
+use crate::api::{chessmove::ChessColor, pgn, ping::timestamp, GameEnd, GameId, PlayerInfo};
+
+/// how long posting to a url may take before it is given up on
+const POST_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "lowercase")]
+enum Event {
+    Start {
+        game: u32,
+        white: PlayerInfo,
+        black: PlayerInfo,
+        time_control: Option<String>,
+        rated: bool,
+    },
+    End {
+        game: u32,
+        white: PlayerInfo,
+        black: PlayerInfo,
+        /// 1-0, 0-1 or 1/2-1/2
+        result: &'static str,
+        reason: &'static str,
+        pgn: String,
+    },
+}
+
+/// the games the webhooks were told about, posting happens on a thread of its own
+#[derive(Resource)]
+pub struct Webhooks {
+    posts: Sender<String>,
+    started: HashSet<GameId>,
+    /// the games running when the server started were announced before it stopped
+    ready: bool,
+}
+
+impl Webhooks {
+    pub fn new(urls: Vec<String>) -> Self {
+        let (posts, bodies) = mpsc::channel::<String>();
+        std::thread::spawn(move || {
+            for body in bodies {
+                for url in &urls {
+                    if let Err(error) = post(url, &body) {
+                        warn!("couldn't post to webhook {}: {}", url, error);
+                    }
+                }
+            }
+        });
+        Self {
+            posts,
+            started: HashSet::new(),
+            ready: false,
+        }
+    }
+
+    fn send(&self, event: &Event) {
+        let body = serde_json::to_string(event).unwrap_or_default();
+        self.posts.send(body).ok();
+    }
+
+    /// tells the webhooks a game they were told about ended, end_game calls this for every game
+    /// it ends, whichever system ended it
+    pub fn game_ended(&mut self, id: GameId, players: &Players, board: &Board, end: GameEnd) {
+        if !self.started.remove(&id) {
+            return;
+        }
+        let result = match end {
+            GameEnd::White(_) => "1-0",
+            GameEnd::Black(_) => "0-1",
+            GameEnd::Draw(_) => "1/2-1/2",
+        };
+        let reason = match end {
+            GameEnd::White(reason) | GameEnd::Black(reason) | GameEnd::Draw(reason) => reason,
+        };
+        self.send(&Event::End {
+            game: id.0,
+            white: players.player(ChessColor::White),
+            black: players.player(ChessColor::Black),
+            result,
+            reason: pgn::termination(reason),
+            pgn: archive::pgn(players, board, Some((end, timestamp()))),
+        });
+    }
+}
+
+/// posts `body` to an http url, https needs a proxy in front
+fn post(url: &str, body: &str) -> io::Result<()> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidInput, "only http:// urls work");
+    let rest = url.strip_prefix("http://").ok_or_else(invalid)?;
+    let (host, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+    let address = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{host}:80")
+    };
+    let address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address for the host"))?;
+    let mut stream = TcpStream::connect_timeout(&address, POST_TIMEOUT)?;
+    stream.set_read_timeout(Some(POST_TIMEOUT))?;
+    stream.set_write_timeout(Some(POST_TIMEOUT))?;
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        if path.is_empty() { "/" } else { path },
+        host,
+        body.len(),
+        body
+    )?;
+    let mut status = String::new();
+    BufReader::new(&stream).read_line(&mut status)?;
+    match status.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(()),
+        _ => Err(io::Error::other(format!("answered {}", status.trim()))),
+    }
+}
+
+/// tells the webhooks about every game that started, the ends are posted by end_game
+pub fn post_starts(mut webhooks: ResMut<Webhooks>, game_map: ChessGames) {
+    let new: Vec<_> = game_map
+        .iter()
+        .filter(|game| !webhooks.started.contains(game.id))
+        .collect();
+    for game in new {
+        webhooks.started.insert(*game.id);
+        if webhooks.ready {
+            let players = game.players;
+            webhooks.send(&Event::Start {
+                game: game.id.0,
+                white: players.player(ChessColor::White),
+                black: players.player(ChessColor::Black),
+                time_control: players.seek.time_control.map(|t| t.to_string()),
+                rated: players.is_rated(),
+            });
+        }
+    }
+    webhooks.ready = true;
+}